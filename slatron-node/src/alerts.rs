@@ -0,0 +1,181 @@
+//! CAP alerts crawled along the bottom of the screen.
+//!
+//! The server pushes the alerts covering this node; they crawl over whatever airs
+//! (interrupts included) until cleared or expired. The crawl is a `drawtext` video
+//! filter reading its text from a file, so alerts coming and going only rewrite the
+//! file. Per-file filter options reset the filter chain, so the playback loop puts the
+//! filter back whenever it goes missing.
+
+use crate::NodeState;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use std::path::PathBuf;
+
+/// Label of the crawl filter
+const CRAWL_LABEL: &str = "alert-crawl";
+/// Between alerts on the crawl
+const SEPARATOR: &str = "   •••   ";
+/// How fast the crawl moves, in pixels per second
+const CRAWL_SPEED: u32 = 160;
+
+#[derive(Debug, Clone)]
+struct ShownAlert {
+    id: i32,
+    text: String,
+    severity: String,
+    expires_at: Option<DateTime<Utc>>,
+}
+
+/// The alerts on screen.
+#[derive(Debug, Default)]
+pub struct AlertBoard {
+    alerts: Vec<ShownAlert>,
+    /// Box colour of the filter in place, if it is
+    colour: Option<&'static str>,
+}
+
+impl AlertBoard {
+    fn crawl_text(&self) -> String {
+        self.alerts
+            .iter()
+            .map(|alert| alert.text.as_str())
+            .collect::<Vec<_>>()
+            .join(SEPARATOR)
+    }
+
+    /// Red if any alert is severe or worse, else orange.
+    fn colour(&self) -> &'static str {
+        let severe = self.alerts.iter().any(|alert| {
+            matches!(
+                alert.severity.to_ascii_lowercase().as_str(),
+                "severe" | "extreme"
+            )
+        });
+        if severe {
+            "red"
+        } else {
+            "orange"
+        }
+    }
+}
+
+fn crawl_file() -> PathBuf {
+    PathBuf::from(std::env::var("HOME").unwrap_or_else(|_| ".".to_string()))
+        .join(".slatron/alert_crawl.txt")
+}
+
+/// Replace the file in one go; the filter rereads it every frame.
+fn write_crawl(text: &str) -> Result<()> {
+    let path = crawl_file();
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, text)?;
+    std::fs::rename(&tmp, &path)?;
+    Ok(())
+}
+
+fn crawl_graph(colour: &str) -> String {
+    format!(
+        "drawtext=textfile='{}':reload=1:expansion=none:fontsize=h/18:fontcolor=white:box=1:boxcolor={}@0.85:boxborderw=12:y=h-th-40:x=w-mod(t*{}\\,w+tw)",
+        crawl_file().to_string_lossy(),
+        colour,
+        CRAWL_SPEED
+    )
+}
+
+pub async fn show(
+    state: &NodeState,
+    id: i32,
+    text: String,
+    severity: String,
+    expires_at: Option<DateTime<Utc>>,
+) {
+    tracing::warn!("Alert {} ({}): {}", id, severity, text);
+    {
+        let mut board = state.alerts.write().await;
+        board.alerts.retain(|alert| alert.id != id);
+        board.alerts.push(ShownAlert {
+            id,
+            text,
+            severity,
+            expires_at,
+        });
+        if let Err(e) = write_crawl(&board.crawl_text()) {
+            tracing::error!("Failed to write alert crawl: {}", e);
+        }
+    }
+    refresh(state).await;
+}
+
+pub async fn clear(state: &NodeState, id: i32) {
+    {
+        let mut board = state.alerts.write().await;
+        let before = board.alerts.len();
+        board.alerts.retain(|alert| alert.id != id);
+        if board.alerts.len() == before {
+            return;
+        }
+        tracing::info!("Alert {} cleared", id);
+        if let Err(e) = write_crawl(&board.crawl_text()) {
+            tracing::error!("Failed to write alert crawl: {}", e);
+        }
+    }
+    refresh(state).await;
+}
+
+/// Drop expired alerts and keep the crawl filter in place. Run every playback loop tick.
+pub async fn tick(state: &NodeState) {
+    {
+        let mut board = state.alerts.write().await;
+        if board.alerts.is_empty() && board.colour.is_none() {
+            return;
+        }
+        let now = Utc::now();
+        let before = board.alerts.len();
+        board
+            .alerts
+            .retain(|alert| alert.expires_at.is_none_or(|expires| expires > now));
+        if board.alerts.len() != before {
+            tracing::info!("Alert expired");
+            if let Err(e) = write_crawl(&board.crawl_text()) {
+                tracing::error!("Failed to write alert crawl: {}", e);
+            }
+        }
+    }
+    refresh(state).await;
+}
+
+/// Add, recolour or remove the crawl filter to match the board.
+async fn refresh(state: &NodeState) {
+    let mut board = state.alerts.write().await;
+    let wanted = (!board.alerts.is_empty()).then(|| board.colour());
+
+    let present =
+        board.colour.is_some() && matches!(state.mpv.has_filter("vf", CRAWL_LABEL), Ok(true));
+    if present && board.colour == wanted {
+        return;
+    }
+
+    if board.colour.is_some() {
+        if let Err(e) = state.mpv.remove_filter("vf", CRAWL_LABEL) {
+            tracing::warn!("Failed to remove alert crawl: {}", e);
+        }
+    }
+    board.colour = None;
+
+    if let Some(colour) = wanted {
+        // Nothing to draw over while idle; retried next tick
+        if matches!(state.mpv.is_idle(), Ok(true)) {
+            return;
+        }
+        match state
+            .mpv
+            .add_filter("vf", CRAWL_LABEL, &crawl_graph(colour))
+        {
+            Ok(()) => board.colour = Some(colour),
+            Err(e) => tracing::warn!("Failed to show alert crawl: {}", e),
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use crate::websocket_client::{NodeMessage, PlaybackPhase};
+use crate::NodeState;
+
+/// Events kept while disconnected; the oldest are dropped beyond this
+const MAX_PENDING_EVENTS: usize = 1000;
+
+/// Report that something started airing. `kind` is one of `content`, `bumper`,
+/// `dj_voice`, `spot_reel_item`, `break` or `interrupt`.
+pub fn report_start(
+    state: &NodeState,
+    kind: &str,
+    content_id: Option<i32>,
+    title: Option<String>,
+    path: Option<String>,
+) {
+    send(
+        state,
+        NodeMessage::PlaybackEvent {
+            kind: kind.to_string(),
+            phase: PlaybackPhase::Start,
+            content_id,
+            title,
+            path,
+            at: chrono::Utc::now(),
+        },
+    );
+}
+
+/// Report that whatever of `kind` was airing (only `content_id`, if given) has ended.
+pub fn report_end(state: &NodeState, kind: &str, content_id: Option<i32>) {
+    send(
+        state,
+        NodeMessage::PlaybackEvent {
+            kind: kind.to_string(),
+            phase: PlaybackPhase::End,
+            content_id,
+            title: None,
+            path: None,
+            at: chrono::Utc::now(),
+        },
+    );
+}
+
+/// Send events held back while the server was unreachable.
+pub fn flush(state: &NodeState) {
+    let sender = state.log_sender.lock().ok().and_then(|s| s.clone());
+    let Some(sender) = sender else {
+        return;
+    };
+
+    let Ok(mut pending) = state.pending_as_run.lock() else {
+        return;
+    };
+    while let Some(msg) = pending.pop_front() {
+        if let Err(e) = sender.send(msg) {
+            pending.push_front(e.0);
+            break;
+        }
+    }
+}
+
+fn send(state: &NodeState, msg: NodeMessage) {
+    // Keep event order: anything still pending goes first
+    flush(state);
+
+    let sender = state.log_sender.lock().ok().and_then(|s| s.clone());
+    let unsent = match sender {
+        Some(sender) => sender.send(msg).err().map(|e| e.0),
+        None => Some(msg),
+    };
+
+    if let Some(msg) = unsent {
+        if let Ok(mut pending) = state.pending_as_run.lock() {
+            if pending.len() >= MAX_PENDING_EVENTS {
+                pending.pop_front();
+            }
+            pending.push_back(msg);
+        }
+    }
+}
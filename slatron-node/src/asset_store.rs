@@ -0,0 +1,482 @@
+//! Local copies of remote media, so upcoming content doesn't stream from the network.
+//!
+//! Files are stored by their SHA-256 (`<asset_dir>/<hash>`), with `index.json` mapping
+//! each source URL to its hash. Downloads are checked against the checksum the server
+//! supplies, and the least recently used files are evicted to stay under the disk budget.
+//! The prefetcher keeps the next `prefetch_hours` of the schedule downloaded.
+
+use crate::NodeState;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const INDEX_FILE: &str = "index.json";
+const PREFETCH_INTERVAL: Duration = Duration::from_secs(60);
+/// Failed downloads aren't retried sooner than this
+const RETRY_AFTER_MINUTES: i64 = 15;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Asset {
+    sha256: String,
+    size: u64,
+    last_used: DateTime<Utc>,
+}
+
+/// Reported to the server with each heartbeat.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CacheStatus {
+    pub items: usize,
+    pub used_bytes: u64,
+    pub budget_bytes: u64,
+    /// Upcoming items not downloaded yet
+    pub pending: usize,
+    /// Upcoming items whose download or checksum verification failed
+    pub failed: usize,
+}
+
+pub struct AssetStore {
+    dir: PathBuf,
+    budget_bytes: u64,
+    /// Source URL -> stored file
+    assets: HashMap<String, Asset>,
+    /// Source URL -> when its last download failed
+    failures: HashMap<String, DateTime<Utc>>,
+    pending: usize,
+    failed: usize,
+}
+
+impl AssetStore {
+    /// Open the store in `dir`. A missing or unreadable index starts an empty store.
+    pub fn open(dir: PathBuf, budget_bytes: u64) -> Self {
+        let assets = std::fs::read(dir.join(INDEX_FILE))
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<HashMap<String, Asset>>(&bytes).ok())
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|(_, asset)| dir.join(&asset.sha256).exists())
+            .collect();
+
+        Self {
+            dir,
+            budget_bytes,
+            assets,
+            failures: HashMap::new(),
+            pending: 0,
+            failed: 0,
+        }
+    }
+
+    /// Local copy of `url`, if stored. Marks it as recently used.
+    pub fn local_path(&mut self, url: &str) -> Option<PathBuf> {
+        let asset = self.assets.get_mut(url)?;
+        let path = self.dir.join(&asset.sha256);
+        if !path.exists() {
+            self.assets.remove(url);
+            return None;
+        }
+        asset.last_used = Utc::now();
+        Some(path)
+    }
+
+    /// The URL a stored file was downloaded from, for mapping the player's path back
+    /// to a content item.
+    pub fn source_url(&self, path: &str) -> Option<String> {
+        let path = Path::new(path);
+        if path.parent() != Some(self.dir.as_path()) {
+            return None;
+        }
+        let hash = path.file_name()?.to_str()?;
+        self.assets
+            .iter()
+            .find(|(_, asset)| asset.sha256 == hash)
+            .map(|(url, _)| url.clone())
+    }
+
+    pub fn status(&self) -> CacheStatus {
+        CacheStatus {
+            items: self.assets.len(),
+            used_bytes: self.used_bytes(),
+            budget_bytes: self.budget_bytes,
+            pending: self.pending,
+            failed: self.failed,
+        }
+    }
+
+    fn used_bytes(&self) -> u64 {
+        // Identical files downloaded from several URLs are stored once
+        let mut seen = HashSet::new();
+        self.assets
+            .values()
+            .filter(|asset| seen.insert(asset.sha256.as_str()))
+            .map(|asset| asset.size)
+            .sum()
+    }
+
+    fn insert(&mut self, url: String, sha256: String, size: u64) {
+        let replaced = self.assets.insert(
+            url,
+            Asset {
+                sha256,
+                size,
+                last_used: Utc::now(),
+            },
+        );
+        // The media behind the URL changed; drop the old file unless shared
+        if let Some(old) = replaced {
+            if !self.assets.values().any(|a| a.sha256 == old.sha256) {
+                let _ = std::fs::remove_file(self.dir.join(&old.sha256));
+            }
+        }
+    }
+
+    /// Evict least recently used files until a file of `size` bytes hashing to `sha256`
+    /// fits in the budget; one already stored under another URL takes no extra space.
+    /// URLs in `keep` are never evicted. Returns whether the space was found.
+    fn make_room(&mut self, sha256: &str, size: u64, keep: &HashSet<String>) -> bool {
+        let extra = if self.assets.values().any(|a| a.sha256 == sha256) {
+            0
+        } else {
+            size
+        };
+        while self.used_bytes() + extra > self.budget_bytes {
+            let oldest = self
+                .assets
+                .iter()
+                .filter(|(url, _)| !keep.contains(*url))
+                .min_by_key(|(_, asset)| asset.last_used)
+                .map(|(url, _)| url.clone());
+            let Some(url) = oldest else {
+                return false;
+            };
+
+            if let Some(asset) = self.remove(&url) {
+                tracing::info!("Evicted cached asset {} ({} bytes)", url, asset.size);
+            }
+        }
+        true
+    }
+
+    /// Forget `url`, deleting its file unless another URL shares it.
+    fn remove(&mut self, url: &str) -> Option<Asset> {
+        let asset = self.assets.remove(url)?;
+        if !self.assets.values().any(|a| a.sha256 == asset.sha256) {
+            let _ = std::fs::remove_file(self.dir.join(&asset.sha256));
+        }
+        Some(asset)
+    }
+
+    fn save_index(&self) -> Result<()> {
+        let tmp_path = self.dir.join(format!("{}.tmp", INDEX_FILE));
+        std::fs::write(&tmp_path, serde_json::to_vec(&self.assets)?)?;
+        std::fs::rename(&tmp_path, self.dir.join(INDEX_FILE))?;
+        Ok(())
+    }
+}
+
+/// The local copy of `path` if it is cached, else `path` itself.
+pub async fn resolve(state: &NodeState, path: String) -> String {
+    match state.assets.write().await.local_path(&path) {
+        Some(local) => {
+            tracing::info!("Playing cached copy of {}", path);
+            local.to_string_lossy().to_string()
+        }
+        None => path,
+    }
+}
+
+fn is_remote(path: &str) -> bool {
+    path.starts_with("http://") || path.starts_with("https://")
+}
+
+/// Keep upcoming remote media downloaded.
+pub async fn run_prefetcher(state: NodeState) {
+    let client = reqwest::Client::new();
+    loop {
+        if let Err(e) = prefetch_upcoming(&client, &state).await {
+            tracing::error!("Prefetch failed: {}", e);
+        }
+        tokio::time::sleep(PREFETCH_INTERVAL).await;
+    }
+}
+
+async fn prefetch_upcoming(client: &reqwest::Client, state: &NodeState) -> Result<()> {
+    let wanted = upcoming_assets(client, state).await;
+    let keep: HashSet<String> = wanted.iter().map(|(url, _)| url.clone()).collect();
+
+    let mut missing: Vec<(String, Option<String>)> = Vec::new();
+    {
+        let mut store = state.assets.write().await;
+        let retry_cutoff = Utc::now() - chrono::Duration::minutes(RETRY_AFTER_MINUTES);
+        store.failures.retain(|_, at| *at > retry_cutoff);
+        store.failed = 0;
+
+        for (url, sha256) in wanted {
+            if store.failures.contains_key(&url) {
+                store.failed += 1;
+                continue;
+            }
+
+            match store.assets.get_mut(&url) {
+                // A changed checksum on the server means the media changed
+                Some(asset)
+                    if sha256
+                        .as_ref()
+                        .is_some_and(|expected| &asset.sha256 != expected) =>
+                {
+                    store.remove(&url);
+                    missing.push((url, sha256));
+                }
+                // Upcoming items count as in use, so they're evicted last
+                Some(asset) => asset.last_used = Utc::now(),
+                None => missing.push((url, sha256)),
+            }
+        }
+        store.pending = missing.len();
+    }
+
+    for (url, sha256) in missing {
+        match download(client, state, &url, sha256.as_deref(), &keep).await {
+            Ok(()) => state.assets.write().await.pending -= 1,
+            Err(e) => {
+                tracing::warn!("Failed to prefetch {}: {}", url, e);
+                let mut store = state.assets.write().await;
+                store.pending -= 1;
+                store.failed += 1;
+                store.failures.insert(url, Utc::now());
+            }
+        }
+    }
+
+    state.assets.read().await.save_index()
+}
+
+/// Remote media (with expected checksum) airing in the next `prefetch_hours`, including
+/// the fallback rotation and the items of any spot reels, break reels among them.
+async fn upcoming_assets(
+    client: &reqwest::Client,
+    state: &NodeState,
+) -> Vec<(String, Option<String>)> {
+    let now = Utc::now();
+    let horizon = now + chrono::Duration::hours(state.config.prefetch_hours as i64);
+
+    let mut reel_ids = Vec::new();
+    let mut content_ids = Vec::new();
+    for block in state
+        .schedule_cache
+        .read()
+        .await
+        .blocks_between(now, horizon)
+    {
+        content_ids.extend(block.content_id);
+        content_ids.extend(block.items.iter().map(|item| item.content_id));
+        if let Some(policy) = &block.break_policy {
+            if !reel_ids.contains(&policy.spot_reel_id) {
+                reel_ids.push(policy.spot_reel_id);
+            }
+        }
+    }
+    content_ids.extend(state.fallback.read().await.content_ids.iter().copied());
+
+    let mut seen = HashSet::new();
+    let mut wanted = Vec::new();
+    {
+        let cache = state.content_cache.read().await;
+        for content_id in content_ids {
+            let Some(item) = cache.get(&content_id) else {
+                continue;
+            };
+            if let Some(reel_id) = item.spot_reel_id {
+                if !reel_ids.contains(&reel_id) {
+                    reel_ids.push(reel_id);
+                }
+            } else if is_remote(&item.content_path) && seen.insert(item.content_path.clone()) {
+                wanted.push((item.content_path.clone(), item.sha256.clone()));
+            }
+        }
+    }
+
+    for reel_id in reel_ids {
+        match crate::spot_reel_player::fetch_reel(client, state, reel_id).await {
+            Ok(reel) => {
+                for item in reel.items {
+                    if matches!(item.item_type.as_str(), "image" | "video")
+                        && is_remote(&item.item_path)
+                        && seen.insert(item.item_path.clone())
+                    {
+                        wanted.push((item.item_path, None));
+                    }
+                }
+            }
+            Err(e) => tracing::debug!("Skipping spot reel {} prefetch: {}", reel_id, e),
+        }
+    }
+
+    wanted
+}
+
+/// A download in progress, deleted when dropped unless it was moved into the store.
+struct PartialDownload(PathBuf);
+
+impl Drop for PartialDownload {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// Download `url` into the store, hashing as it streams to disk.
+async fn download(
+    client: &reqwest::Client,
+    state: &NodeState,
+    url: &str,
+    expected: Option<&str>,
+    keep: &HashSet<String>,
+) -> Result<()> {
+    let mut resp = client.get(url).send().await?.error_for_status()?;
+
+    // Pages (e.g. video sites resolved by mpv's ytdl hook) aren't media we can store
+    let content_type = resp
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    if content_type.starts_with("text/") {
+        return Err(anyhow!("not a media file ({})", content_type));
+    }
+
+    let (dir, budget) = {
+        let store = state.assets.read().await;
+        (store.dir.clone(), store.budget_bytes)
+    };
+    if resp.content_length().is_some_and(|len| len > budget) {
+        return Err(anyhow!("larger than the cache budget"));
+    }
+
+    std::fs::create_dir_all(&dir)?;
+    let tmp = PartialDownload(dir.join(format!("download-{}.tmp", std::process::id())));
+    let mut file = std::fs::File::create(&tmp.0)?;
+    let mut hasher = Sha256::new();
+    let mut size: u64 = 0;
+    while let Some(chunk) = resp.chunk().await? {
+        hasher.update(&chunk);
+        file.write_all(&chunk)?;
+        size += chunk.len() as u64;
+        if size > budget {
+            return Err(anyhow!("larger than the cache budget"));
+        }
+    }
+    file.sync_all()?;
+    drop(file);
+
+    let sha256 = format!("{:x}", hasher.finalize());
+    if let Some(expected) = expected {
+        if !sha256.eq_ignore_ascii_case(expected) {
+            return Err(anyhow!(
+                "checksum mismatch (expected {}, got {})",
+                expected,
+                sha256
+            ));
+        }
+    }
+
+    let mut store = state.assets.write().await;
+    if !store.make_room(&sha256, size, keep) {
+        return Err(anyhow!("no room left in the cache budget"));
+    }
+    std::fs::rename(&tmp.0, dir.join(&sha256))?;
+    store.insert(url.to_string(), sha256, size);
+    tracing::info!("Prefetched {} ({} bytes)", url, size);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A store in a fresh directory, with files for `hashes` already on disk.
+    fn store(name: &str, budget_bytes: u64, hashes: &[&str]) -> AssetStore {
+        let dir =
+            std::env::temp_dir().join(format!("slatron-assets-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        for hash in hashes {
+            std::fs::write(dir.join(hash), hash).unwrap();
+        }
+        AssetStore::open(dir, budget_bytes)
+    }
+
+    fn add(store: &mut AssetStore, url: &str, sha256: &str, size: u64, minutes_ago: i64) {
+        store.insert(url.to_string(), sha256.to_string(), size);
+        store.assets.get_mut(url).unwrap().last_used =
+            Utc::now() - chrono::Duration::minutes(minutes_ago);
+    }
+
+    #[test]
+    fn test_make_room_evicts_least_recently_used() {
+        let mut store = store("evict", 100, &["a", "b", "c"]);
+        add(&mut store, "http://x/a", "a", 40, 30);
+        add(&mut store, "http://x/b", "b", 40, 20);
+        add(&mut store, "http://x/c", "c", 30, 10);
+
+        // The oldest upcoming item is kept, so the next oldest goes instead
+        let keep = HashSet::from(["http://x/a".to_string()]);
+        assert!(store.make_room("d", 30, &keep));
+        assert!(!store.assets.contains_key("http://x/b"));
+        assert!(!store.dir.join("b").exists());
+        assert_eq!(store.used_bytes(), 70);
+
+        // A file already stored under another URL takes no extra space, so nothing goes
+        assert!(store.make_room("a", 40, &HashSet::new()));
+        assert_eq!(store.assets.len(), 2);
+
+        // Nothing left to evict but kept items
+        let keep = HashSet::from(["http://x/a".to_string(), "http://x/c".to_string()]);
+        assert!(!store.make_room("e", 50, &keep));
+
+        let _ = std::fs::remove_dir_all(&store.dir);
+    }
+
+    #[test]
+    fn test_shared_files_are_stored_once() {
+        let mut store = store("shared", 1000, &["a", "b"]);
+        add(&mut store, "http://x/one", "a", 100, 0);
+        add(&mut store, "http://mirror/one", "a", 100, 0);
+        assert_eq!(store.used_bytes(), 100);
+
+        // Re-pointing one URL keeps the file the other still uses
+        store.insert("http://x/one".to_string(), "b".to_string(), 50);
+        assert!(store.dir.join("a").exists());
+        assert_eq!(store.used_bytes(), 150);
+
+        // Removing the last URL of a file deletes it
+        store.remove("http://mirror/one");
+        assert!(!store.dir.join("a").exists());
+        assert_eq!(store.used_bytes(), 50);
+
+        let _ = std::fs::remove_dir_all(&store.dir);
+    }
+
+    #[test]
+    fn test_source_url() {
+        let mut store = store("source", 1000, &["a"]);
+        add(&mut store, "http://x/a.mp4", "a", 10, 0);
+
+        let local = store.dir.join("a");
+        assert_eq!(
+            store.source_url(local.to_str().unwrap()),
+            Some("http://x/a.mp4".to_string())
+        );
+        // Only files directly in the store map back
+        assert_eq!(store.source_url("/elsewhere/a"), None);
+        assert_eq!(
+            store.source_url(store.dir.join("b").to_str().unwrap()),
+            None
+        );
+
+        let _ = std::fs::remove_dir_all(&store.dir);
+    }
+}
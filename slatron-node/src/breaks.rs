@@ -0,0 +1,195 @@
+//! Mid-programme breaks inside long-form content.
+//!
+//! When a block has a break policy, the node watches the player's position and, at each
+//! cue point of the airing content, plays the policy's spot reel (cut off after
+//! `max_secs`) before resuming the content at the cue.
+
+use crate::NodeState;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BreakPolicy {
+    pub spot_reel_id: i32,
+    #[serde(default = "default_max_secs")]
+    pub max_secs: u32,
+    /// Breaks per airing of a content item at most; every cue point if unset
+    #[serde(default)]
+    pub max_breaks: Option<u32>,
+}
+
+fn default_max_secs() -> u32 {
+    180
+}
+
+impl BreakPolicy {
+    /// Cue points a break is taken at when the content starts `from_secs` in.
+    fn breaks_from(&self, cues: &[f64], from_secs: f64) -> VecDeque<f64> {
+        cues.iter()
+            .copied()
+            .filter(|at| *at > from_secs)
+            .take(self.max_breaks.map_or(usize::MAX, |n| n as usize))
+            .collect()
+    }
+}
+
+#[derive(Deserialize)]
+struct CuePoint {
+    at_secs: f64,
+}
+
+/// Positions of a content item's cue points, sorted. Unparseable cue points are ignored.
+fn cue_positions(json: Option<&str>) -> Vec<f64> {
+    let mut cues: Vec<f64> = json
+        .and_then(|json| serde_json::from_str::<Vec<CuePoint>>(json).ok())
+        .unwrap_or_default()
+        .into_iter()
+        .map(|c| c.at_secs)
+        .collect();
+    cues.sort_by(f64::total_cmp);
+    cues
+}
+
+/// Breaks due in the content airing now.
+#[derive(Default)]
+pub struct BreakTracker {
+    content_id: Option<i32>,
+    policy: Option<BreakPolicy>,
+    cues: Vec<f64>,
+    /// Cues still to break at; planned from the first position the player reports, so
+    /// cues before a join offset or a script's `start_time` are skipped
+    pending: Option<VecDeque<f64>>,
+    running: Option<JoinHandle<()>>,
+    /// Whether a break has run, leaving the content behind the schedule
+    delayed: bool,
+}
+
+impl BreakTracker {
+    /// Track breaks for `content_id`, which just started playing in a block with `policy`.
+    pub async fn start(&mut self, state: &NodeState, content_id: i32, policy: Option<BreakPolicy>) {
+        self.stop();
+        let Some(policy) = policy else {
+            return;
+        };
+
+        let cues = {
+            let cache = state.content_cache.read().await;
+            cue_positions(
+                cache
+                    .get(&content_id)
+                    .and_then(|item| item.cue_points.as_deref()),
+            )
+        };
+        if !cues.is_empty() {
+            self.content_id = Some(content_id);
+            self.policy = Some(policy);
+            self.cues = cues;
+        }
+    }
+
+    /// Stop tracking. A break in progress is cancelled by whatever plays next.
+    pub fn stop(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Whether the content now trails the schedule by the breaks taken in it.
+    pub fn delayed(&self) -> bool {
+        self.delayed
+    }
+
+    /// Start a break if the content has reached its next cue point.
+    pub async fn tick(&mut self, state: &NodeState) {
+        let (Some(content_id), Some(policy)) = (self.content_id, self.policy.as_ref()) else {
+            return;
+        };
+        if self
+            .running
+            .as_ref()
+            .is_some_and(|task| !task.is_finished())
+        {
+            return;
+        }
+        self.running = None;
+
+        // Content loaded by a command isn't the scheduled content
+        if *state.current_content_id.read().await != Some(content_id) {
+            return;
+        }
+        let Ok(position) = state.mpv.get_position() else {
+            return;
+        };
+        let pending = self
+            .pending
+            .get_or_insert_with(|| policy.breaks_from(&self.cues, position));
+
+        // After a seek past several cues, break once at the last of them
+        let mut due = None;
+        while pending.front().is_some_and(|at| *at <= position) {
+            due = pending.pop_front();
+        }
+        let Some(cue) = due else {
+            return;
+        };
+
+        tracing::info!("Break at {:.1}s in content {}", cue, content_id);
+        self.delayed = true;
+        let cancel = CancellationToken::new();
+        *state.spot_reel_cancel.write().await = Some(cancel.clone());
+
+        let state = state.clone();
+        let policy = policy.clone();
+        self.running = Some(tokio::spawn(async move {
+            run_break(&state, content_id, &policy, cue, cancel).await;
+        }));
+    }
+}
+
+/// Play the break's spot reel, then resume the content at `cue` unless something else
+/// started playing meanwhile.
+async fn run_break(
+    state: &NodeState,
+    content_id: i32,
+    policy: &BreakPolicy,
+    cue: f64,
+    cancel: CancellationToken,
+) {
+    let client = reqwest::Client::new();
+    let reel = match crate::spot_reel_player::fetch_reel(&client, state, policy.spot_reel_id).await
+    {
+        Ok(reel) => reel,
+        Err(e) => {
+            tracing::warn!("Skipping break: {}", e);
+            return;
+        }
+    };
+    let Ok(resume_path) = state.mpv.get_path() else {
+        return;
+    };
+
+    crate::as_run::report_start(
+        state,
+        "break",
+        Some(content_id),
+        Some(reel.title.clone()),
+        None,
+    );
+    let max = Duration::from_secs(policy.max_secs as u64);
+    if let Err(e) = crate::spot_reel_player::play_break(state, &reel, max, &cancel).await {
+        tracing::error!("Break failed: {}", e);
+    }
+    crate::as_run::report_end(state, "break", Some(content_id));
+
+    if cancel.is_cancelled() {
+        return;
+    }
+    state.spot_reel_cancel.write().await.take();
+
+    tracing::info!("Resuming {} at {:.1}s after break", resume_path, cue);
+    crate::playback::apply_content_gain(state, content_id).await;
+    if let Err(e) = state.mpv.play(&resume_path, Some(cue), None) {
+        tracing::error!("Failed to resume content after break: {}", e);
+    }
+}
@@ -13,12 +13,42 @@ pub struct Config {
     #[serde(default = "default_voice_socket")]
     pub voice_mpv_socket_path: String,
     pub offline_mode_warning_hours: u64,
+    /// Days of schedule fetched ahead, so a node can ride out longer outages
+    #[serde(default = "default_prefetch_days")]
+    pub prefetch_days: u32,
+    /// Where caches are saved for offline restarts; defaults to `~/.slatron/state`
+    #[serde(default)]
+    pub state_dir: Option<String>,
+    /// Hours of upcoming remote media downloaded ahead of airing
+    #[serde(default = "default_prefetch_hours")]
+    pub prefetch_hours: u32,
+    /// Disk budget for downloaded media
+    #[serde(default = "default_asset_cache_max_mb")]
+    pub asset_cache_max_mb: u64,
+    /// Where downloaded media is stored; defaults to `~/.slatron/assets`
+    #[serde(default)]
+    pub asset_dir: Option<String>,
+    /// Folders of local media scanned into the server's content library
+    #[serde(default)]
+    pub media_roots: Vec<String>,
 }
 
 fn default_voice_socket() -> String {
     "/tmp/mpv-socket-voice".to_string()
 }
 
+fn default_prefetch_days() -> u32 {
+    3
+}
+
+fn default_prefetch_hours() -> u32 {
+    24
+}
+
+fn default_asset_cache_max_mb() -> u64 {
+    10_240
+}
+
 impl Config {
     pub fn load(path: &str) -> Result<Self> {
         let content = fs::read_to_string(path)?;
@@ -35,6 +65,10 @@ schedule_poll_interval_secs = 60
 mpv_socket_path = "/tmp/mpv-socket"
 voice_mpv_socket_path = "/tmp/mpv-socket-voice"
 offline_mode_warning_hours = 24
+prefetch_days = 3
+prefetch_hours = 24
+asset_cache_max_mb = 10240
+media_roots = []
 "#
     }
 }
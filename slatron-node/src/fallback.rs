@@ -0,0 +1,154 @@
+//! Playback while no block covers the current time.
+//!
+//! The server resolves the node's fallback policy and sends it with the schedule. It is
+//! kept in `NodeState` (and the slate downloaded up front), so gaps are still filled
+//! while the server is unreachable.
+
+use crate::NodeState;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// Time a freshly loaded item gets before an idle player counts as finished
+const LOAD_GRACE: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Fallback {
+    /// `silence`, `playlist`, `spot_reel` or `slate`
+    pub mode: String,
+    #[serde(default)]
+    pub content_ids: Vec<i32>,
+    /// Rendered slate, relative to the server URL
+    #[serde(default)]
+    pub slate_path: Option<String>,
+    /// Local copy of the slate
+    #[serde(default)]
+    pub slate_file: Option<PathBuf>,
+    /// Loudness normalisation gain (dB) for the slate
+    #[serde(default)]
+    pub slate_gain_db: Option<f64>,
+}
+
+/// Download the slate (if any) so it can be looped offline later.
+pub async fn prepare(
+    client: &reqwest::Client,
+    http_base: &str,
+    mut fallback: Fallback,
+) -> Fallback {
+    if let Some(rendered_path) = fallback.slate_path.clone() {
+        match cache_slate(client, http_base, &rendered_path).await {
+            Ok(file) => fallback.slate_file = Some(file),
+            Err(e) => tracing::error!("Failed to cache fallback slate: {}", e),
+        }
+    }
+    fallback
+}
+
+/// Download a rendered slate into the bumper cache, unless it is there already.
+pub async fn cache_slate(
+    client: &reqwest::Client,
+    http_base: &str,
+    rendered_path: &str,
+) -> Result<PathBuf> {
+    let cache_dir = PathBuf::from(std::env::var("HOME").unwrap_or_else(|_| ".".to_string()))
+        .join(".slatron/bumper_cache");
+    std::fs::create_dir_all(&cache_dir)?;
+
+    let file_name = rendered_path.rsplit('/').next().unwrap_or("slate.mp4");
+    let local_path = cache_dir.join(file_name);
+    if !local_path.exists() {
+        let url = format!("{}/{}", http_base, rendered_path);
+        tracing::info!("Downloading fallback slate: {}", url);
+        let resp = client.get(&url).send().await?.error_for_status()?;
+        std::fs::write(&local_path, resp.bytes().await?)?;
+    }
+    Ok(local_path)
+}
+
+/// Where the node is in its fallback rotation.
+#[derive(Default)]
+pub struct FallbackCursor {
+    /// Mode being played; `None` while a block is airing
+    mode: Option<String>,
+    next_index: usize,
+    started_at: Option<Instant>,
+}
+
+impl FallbackCursor {
+    /// Stop filling because a block started. Returns whether fallback was playing.
+    /// The rotation carries on where it left off at the next gap.
+    pub fn leave(&mut self, state: &NodeState) -> bool {
+        let Some(mode) = self.mode.take() else {
+            return false;
+        };
+        if mode == "slate" {
+            crate::as_run::report_end(state, "bumper", None);
+        }
+        self.started_at = None;
+        true
+    }
+}
+
+/// Keep fallback playback going. Called every playback loop tick while nothing is
+/// scheduled.
+pub async fn tick(state: &NodeState, cursor: &mut FallbackCursor) {
+    let fallback = state.fallback.read().await.clone();
+
+    let mode = match fallback.mode.as_str() {
+        "playlist" | "spot_reel" if !fallback.content_ids.is_empty() => fallback.mode.as_str(),
+        "slate" if fallback.slate_file.is_some() => "slate",
+        _ => "silence",
+    };
+
+    if cursor.mode.as_deref() != Some(mode) {
+        cursor.leave(state);
+        tracing::info!("Nothing scheduled, fallback: {}", mode);
+        match mode {
+            "slate" => {
+                crate::playback::stop_playback(state).await;
+                if let Some(file) = &fallback.slate_file {
+                    let path = file.to_string_lossy().to_string();
+                    if let Err(e) = state.mpv.set_gain(fallback.slate_gain_db) {
+                        tracing::warn!("Failed to set slate gain: {}", e);
+                    }
+                    match state.mpv.play(&path, None, Some(true)) {
+                        Ok(()) => {
+                            crate::as_run::report_start(state, "bumper", None, None, Some(path))
+                        }
+                        Err(e) => tracing::error!("Failed to play fallback slate: {}", e),
+                    }
+                }
+            }
+            "silence" => crate::playback::stop_playback(state).await,
+            _ => play_next(state, cursor, &fallback.content_ids).await,
+        }
+        cursor.mode = Some(mode.to_string());
+        return;
+    }
+
+    // Slates loop and spot reels run until cancelled; playlists advance when an item ends
+    if mode == "playlist" && item_finished(state, cursor).await {
+        play_next(state, cursor, &fallback.content_ids).await;
+    }
+}
+
+async fn item_finished(state: &NodeState, cursor: &FallbackCursor) -> bool {
+    if cursor.started_at.is_some_and(|t| t.elapsed() < LOAD_GRACE) {
+        return false;
+    }
+    if state.spot_reel_cancel.read().await.is_some() {
+        return false;
+    }
+    matches!(state.mpv.is_idle(), Ok(true))
+}
+
+async fn play_next(state: &NodeState, cursor: &mut FallbackCursor, content_ids: &[i32]) {
+    let content_id = content_ids[cursor.next_index % content_ids.len()];
+    cursor.next_index = (cursor.next_index + 1) % content_ids.len();
+    cursor.started_at = Some(Instant::now());
+
+    if let Err(e) = crate::playback::play_content(state, content_id, None, None, None).await {
+        tracing::error!("Failed to play fallback content {}: {}", content_id, e);
+    }
+}
@@ -56,11 +56,21 @@ impl HeartbeatManager {
             if current_content_id.is_some() {
                 tracing::info!("MPV is idle, clearing content ID");
                 *self.state.current_content_id.write().await = None;
+                crate::as_run::report_end(&self.state, "content", current_content_id);
                 current_content_id = None;
             }
         }
         // If not idle, double check what is ACTUALLY playing to catch Queued content
-        else if let Ok(path) = self.state.mpv.get_path() {
+        else if let Ok(mpv_path) = self.state.mpv.get_path() {
+            // Prefetched media plays from the asset store; match on the original URL
+            let path = self
+                .state
+                .assets
+                .read()
+                .await
+                .source_url(&mpv_path)
+                .unwrap_or(mpv_path);
+
             // Path from MPV might differ from cache path logic (e.g. absolute vs relative)
             // We need a way to map MPV Path back to ID.
             // We have content_cache: Map<ID, ServerContentItem>.
@@ -98,6 +108,13 @@ impl HeartbeatManager {
                     // Update State!
                     // tracing::info!("Resolved Path {} to ID {}", path, id);
                     *self.state.current_content_id.write().await = Some(id);
+                    crate::as_run::report_start(
+                        &self.state,
+                        "content",
+                        Some(id),
+                        None,
+                        Some(path.clone()),
+                    );
                     current_content_id = Some(id);
                 } else {
                     tracing::warn!(
@@ -116,6 +133,7 @@ impl HeartbeatManager {
             // If we thought we were playing, but MPV says "no path", we stopped.
             if current_content_id.is_some() {
                 *self.state.current_content_id.write().await = None;
+                crate::as_run::report_end(&self.state, "content", current_content_id);
                 current_content_id = None;
             }
         }
@@ -143,6 +161,10 @@ impl HeartbeatManager {
             cpu_usage_percent: cpu_usage,
             memory_usage_mb: memory_usage,
             errors: vec![],
+            cache: Some(self.state.assets.read().await.status()),
+            interrupt: self.state.interrupt.read().await.clone(),
+            sync: Some(crate::sync_group::status(&self.state).await),
+            sent_at: chrono::Utc::now(),
         }
     }
 }
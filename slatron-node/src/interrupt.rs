@@ -0,0 +1,121 @@
+//! Emergency interrupts.
+//!
+//! An interrupt command preempts everything: the playback loop stops following the
+//! schedule, spot reels, breaks and queued bumpers are dropped, and DJ injections and
+//! content commands are ignored. The slate (or black) loops with the message on screen
+//! until the interrupt is cleared or times out; the playback loop then rejoins the
+//! schedule where it would be by then.
+
+use crate::NodeState;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Shown when an interrupt has no slate
+const BLACK: &str = "av://lavfi:color=c=black:s=1920x1080";
+/// The message stays on screen for the interrupt; it is cleared when it ends
+const MESSAGE_DURATION_MS: u32 = 24 * 60 * 60 * 1000;
+
+/// An interrupt in progress, as reported in heartbeats.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interrupt {
+    pub message: Option<String>,
+    pub started_at: DateTime<Utc>,
+    /// When the schedule resumes by itself, if the interrupt has a timeout
+    pub until: Option<DateTime<Utc>>,
+}
+
+/// Break into playback, replacing any interrupt already in progress.
+pub async fn start(
+    state: &NodeState,
+    message: Option<String>,
+    slate_path: Option<String>,
+    slate_gain_db: Option<f64>,
+    timeout_secs: Option<u32>,
+) {
+    let now = Utc::now();
+    tracing::warn!(
+        "Interrupt: {} (until {})",
+        message.as_deref().unwrap_or("no message"),
+        timeout_secs.map_or("cleared".to_string(), |secs| format!("{}s", secs))
+    );
+
+    // Set first, so the playback loop doesn't restart anything meanwhile
+    let previous = state.interrupt.write().await.replace(Interrupt {
+        message: message.clone(),
+        started_at: now,
+        until: timeout_secs.map(|secs| now + chrono::Duration::seconds(secs as i64)),
+    });
+    if previous.is_some() {
+        crate::as_run::report_end(state, "interrupt", None);
+    }
+
+    crate::playback::stop_playback(state).await;
+    state.bumper_queue.write().await.clear();
+    if let Err(e) = state.mpv_voice.stop() {
+        tracing::warn!("Failed to stop voice track: {}", e);
+    }
+
+    let path = match slate_path {
+        Some(rendered_path) => slate_file(state, &rendered_path).await,
+        None => BLACK.to_string(),
+    };
+    if let Err(e) = state.mpv.set_gain(slate_gain_db) {
+        tracing::warn!("Failed to set slate gain: {}", e);
+    }
+    if let Err(e) = state.mpv.play(&path, None, Some(true)) {
+        tracing::error!("Failed to play interrupt slate: {}", e);
+    }
+    if let Some(message) = &message {
+        if let Err(e) = state.mpv.show_text(message, MESSAGE_DURATION_MS) {
+            tracing::error!("Failed to show interrupt message: {}", e);
+        }
+    }
+
+    crate::as_run::report_start(state, "interrupt", None, message, Some(path));
+}
+
+/// A local copy of the slate, else its URL to stream from.
+async fn slate_file(state: &NodeState, rendered_path: &str) -> String {
+    let http_base = state
+        .config
+        .server_url
+        .replace("ws://", "http://")
+        .replace("/ws", "");
+
+    let client = reqwest::Client::new();
+    match crate::fallback::cache_slate(&client, &http_base, rendered_path).await {
+        Ok(file) => file.to_string_lossy().to_string(),
+        Err(e) => {
+            tracing::warn!("Failed to download interrupt slate, streaming it: {}", e);
+            format!("{}/{}", http_base, rendered_path)
+        }
+    }
+}
+
+/// End the interrupt, if there is one. The playback loop resumes the schedule.
+pub async fn clear(state: &NodeState) {
+    if state.interrupt.write().await.take().is_none() {
+        return;
+    }
+    tracing::info!("Interrupt cleared");
+
+    if let Err(e) = state.mpv.show_text("", 0) {
+        tracing::warn!("Failed to clear interrupt message: {}", e);
+    }
+    crate::playback::stop_playback(state).await;
+    crate::as_run::report_end(state, "interrupt", None);
+}
+
+/// Whether an interrupt is in progress, ending it if it has timed out.
+pub async fn active(state: &NodeState) -> bool {
+    let until = match &*state.interrupt.read().await {
+        Some(interrupt) => interrupt.until,
+        None => return false,
+    };
+    if until.is_some_and(|until| Utc::now() >= until) {
+        tracing::info!("Interrupt timed out");
+        clear(state).await;
+        return false;
+    }
+    true
+}
@@ -0,0 +1,189 @@
+//! Media library scans.
+//!
+//! Walks the configured `media_roots` and reports every media file to the server, which
+//! turns them into content items. Files are probed with ffprobe for their duration and
+//! tags; probes are remembered by size and modification time, so a rescan only probes
+//! what changed. A scan runs after connecting and whenever the server asks for one.
+
+use crate::websocket_client::NodeMessage;
+use crate::NodeState;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// File extensions scanned, lowercase
+const MEDIA_EXTENSIONS: &[&str] = &[
+    "mp4", "mkv", "mov", "avi", "webm", "m4v", "ts", "mpg", "mpeg", "wmv", "mp3", "flac", "wav",
+    "ogg", "opus", "m4a", "aac",
+];
+
+/// A media file as reported to the server.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScannedFile {
+    pub path: String,
+    pub size_bytes: i64,
+    pub modified_at: DateTime<Utc>,
+    pub duration_secs: Option<f64>,
+    /// Container tags, keys lowercased
+    pub tags: HashMap<String, String>,
+}
+
+/// Files found by the last scan, by path.
+#[derive(Debug, Default)]
+pub struct Library {
+    files: HashMap<String, ScannedFile>,
+}
+
+fn is_media(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| MEDIA_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+/// Every media file under `roots` with its size and modification time. Hidden files and
+/// folders are skipped, and symlinked folders aren't followed.
+fn walk(roots: &[String]) -> Vec<(PathBuf, i64, DateTime<Utc>)> {
+    let mut found = Vec::new();
+    let mut dirs: Vec<PathBuf> = roots.iter().map(PathBuf::from).collect();
+    while let Some(dir) = dirs.pop() {
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                tracing::warn!("Can't scan {}: {}", dir.display(), e);
+                continue;
+            }
+        };
+        for entry in entries.flatten() {
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            let path = entry.path();
+            if entry.file_type().is_ok_and(|t| t.is_dir()) {
+                dirs.push(path);
+            } else if is_media(&path) {
+                if let Ok(meta) = std::fs::metadata(&path) {
+                    let modified = meta.modified().map(DateTime::<Utc>::from);
+                    if let (true, Ok(modified)) = (meta.is_file(), modified) {
+                        found.push((path, meta.len() as i64, modified));
+                    }
+                }
+            }
+        }
+    }
+    found.sort();
+    found
+}
+
+#[derive(Deserialize)]
+struct Probe {
+    format: ProbeFormat,
+}
+
+#[derive(Deserialize)]
+struct ProbeFormat {
+    duration: Option<String>,
+    #[serde(default)]
+    tags: HashMap<String, String>,
+}
+
+/// Duration and container tags of a file.
+async fn probe(path: &Path) -> Result<(Option<f64>, HashMap<String, String>)> {
+    let output = tokio::process::Command::new("ffprobe")
+        .args(["-v", "error", "-show_format", "-of", "json"])
+        .arg(path)
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "ffprobe failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    let probe: Probe = serde_json::from_slice(&output.stdout)?;
+    let duration = probe
+        .format
+        .duration
+        .and_then(|d| d.parse::<f64>().ok())
+        .filter(|d| d.is_finite() && *d > 0.0);
+    let tags = probe
+        .format
+        .tags
+        .into_iter()
+        .map(|(key, value)| (key.to_lowercase(), value))
+        .collect();
+    Ok((duration, tags))
+}
+
+/// Scan the media roots and send what's there to the server.
+pub async fn scan(state: &NodeState) -> Result<()> {
+    let roots = state.config.media_roots.clone();
+    if roots.is_empty() {
+        tracing::info!("No media roots configured, skipping library scan");
+        return Ok(());
+    }
+    let sender = state
+        .log_sender
+        .lock()
+        .ok()
+        .and_then(|s| s.clone())
+        .ok_or_else(|| anyhow!("Not connected to the server"))?;
+
+    // One scan at a time
+    let mut library = state.library.lock().await;
+    tracing::info!("Scanning media library: {}", roots.join(", "));
+
+    let walk_roots = roots.clone();
+    let found = tokio::task::spawn_blocking(move || walk(&walk_roots)).await?;
+
+    let mut files = HashMap::with_capacity(found.len());
+    let mut probed = 0;
+    for (path, size_bytes, modified_at) in found {
+        let key = path.to_string_lossy().to_string();
+        let unchanged = library
+            .files
+            .remove(&key)
+            .filter(|f| f.size_bytes == size_bytes && f.modified_at == modified_at);
+        let file = match unchanged {
+            Some(file) => file,
+            None => {
+                probed += 1;
+                let (duration_secs, tags) = probe(&path).await.unwrap_or_else(|e| {
+                    tracing::warn!("Can't probe {}: {}", key, e);
+                    (None, HashMap::new())
+                });
+                ScannedFile {
+                    path: key.clone(),
+                    size_bytes,
+                    modified_at,
+                    duration_secs,
+                    tags,
+                }
+            }
+        };
+        files.insert(key, file);
+    }
+    library.files = files;
+    tracing::info!(
+        "Library scan found {} files ({} probed)",
+        library.files.len(),
+        probed
+    );
+
+    sender
+        .send(NodeMessage::LibraryScan {
+            roots,
+            files: library.files.values().cloned().collect(),
+        })
+        .map_err(|_| anyhow!("Connection closed before the scan was sent"))?;
+    Ok(())
+}
+
+/// `scan`, logging failures; for running in the background.
+pub async fn run(state: NodeState) {
+    if let Err(e) = scan(&state).await {
+        tracing::error!("Library scan failed: {}", e);
+    }
+}
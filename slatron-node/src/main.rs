@@ -1,18 +1,29 @@
+mod alerts;
+mod as_run;
+mod asset_store;
+mod breaks;
 mod config;
+mod fallback;
 mod heartbeat;
+mod interrupt;
+mod library;
 mod mpv_client;
 mod playback;
 mod rhai_engine;
 mod schedule;
 mod screenshot;
 mod spot_reel_player;
+mod state_store;
+mod sync_group;
+mod transition;
 mod web_capture;
 mod websocket_client;
 
 use anyhow::Result;
-use chrono::{Datelike, NaiveDate, NaiveTime};
-use serde::Deserialize;
+use chrono::{DateTime, Datelike, NaiveDate, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
 use std::process::Child;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
@@ -25,6 +36,7 @@ use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, Layer};
 
 use crate::config::Config;
 
+use crate::asset_store::AssetStore;
 use crate::mpv_client::MpvClient;
 use crate::schedule::ScheduleCache;
 use crate::websocket_client::WebSocketClient;
@@ -50,6 +62,13 @@ pub struct NodeState {
     pub schedule_dirty: Arc<AtomicBool>,
     pub bumper_queue: Arc<RwLock<VecDeque<String>>>, // Queue of bumper names/IDs to play
     pub spot_reel_cancel: Arc<RwLock<Option<CancellationToken>>>, // Cancel token for active spot reel
+    pub pending_as_run: Arc<Mutex<VecDeque<crate::websocket_client::NodeMessage>>>, // As-run events not yet sent
+    pub fallback: Arc<RwLock<crate::fallback::Fallback>>, // What to play when nothing is scheduled
+    pub assets: Arc<RwLock<crate::asset_store::AssetStore>>, // Prefetched remote media
+    pub interrupt: Arc<RwLock<Option<crate::interrupt::Interrupt>>>, // Emergency interrupt in progress
+    pub alerts: Arc<RwLock<crate::alerts::AlertBoard>>, // CAP alerts crawling on screen
+    pub sync_group: Arc<RwLock<crate::sync_group::SyncGroup>>, // Server clock offset and drift
+    pub library: Arc<tokio::sync::Mutex<crate::library::Library>>, // Media found by the last library scan
 }
 
 // Log Visitor to extract message
@@ -112,6 +131,17 @@ struct ServerScheduleResponse {
     blocks: Vec<ServerScheduleBlock>,
     content: Vec<ServerContentItem>,
     scripts: Vec<ServerScript>,
+    // UTC bounds of the node's local day the blocks cover (absent on older servers)
+    #[serde(default)]
+    window_start: Option<DateTime<Utc>>,
+    #[serde(default)]
+    window_end: Option<DateTime<Utc>>,
+    #[serde(default)]
+    fallback: crate::fallback::Fallback,
+    #[serde(default)]
+    sync_playback: bool,
+    #[serde(default)]
+    server_time: bool,
 }
 
 #[derive(Deserialize)]
@@ -123,16 +153,43 @@ struct ServerScheduleBlock {
     specific_date: Option<NaiveDate>,
     start_time: NaiveTime,
     duration_minutes: i32,
+    #[serde(default)]
+    duration_secs: Option<i32>,
     script_id: Option<i32>,
+    #[serde(default)]
+    offset_secs: i32,
+    #[serde(default)]
+    items: Vec<ServerRundownItem>,
+    #[serde(default)]
+    break_policy: Option<crate::breaks::BreakPolicy>,
+    #[serde(default)]
+    transition: Option<crate::transition::Transition>,
 }
 
 #[derive(Deserialize)]
+struct ServerRundownItem {
+    content_id: i32,
+    start_at: DateTime<Utc>,
+    duration_secs: i32,
+    #[serde(default)]
+    offset_secs: i32,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct ServerContentItem {
     pub id: i32,
     pub content_path: String,
     pub content_type: Option<String>,
     pub transformer_scripts: Option<String>,
     pub spot_reel_id: Option<i32>,
+    #[serde(default)]
+    pub sha256: Option<String>,
+    /// JSON list of `{at_secs, ...}` positions where breaks may be taken
+    #[serde(default)]
+    pub cue_points: Option<String>,
+    /// Loudness normalisation gain (dB) from the server's analysis
+    #[serde(default)]
+    pub gain_db: Option<f64>,
 }
 
 #[derive(Deserialize)]
@@ -190,6 +247,10 @@ heartbeat_interval_secs = 5
 schedule_poll_interval_secs = 60
 mpv_socket_path = "/tmp/mpv-socket"
 offline_mode_warning_hours = 24
+prefetch_days = 3
+prefetch_hours = 24
+asset_cache_max_mb = 10240
+media_roots = []
 "#,
         node_name, server_url, secret_key
     );
@@ -358,6 +419,16 @@ async fn main() -> Result<()> {
         }
     }
 
+    let asset_dir = config
+        .asset_dir
+        .clone()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| {
+            PathBuf::from(std::env::var("HOME").unwrap_or_else(|_| ".".to_string()))
+                .join(".slatron/assets")
+        });
+    let assets = AssetStore::open(asset_dir, config.asset_cache_max_mb * 1024 * 1024);
+
     // Create node state
     let state = NodeState {
         config: Arc::new(config.clone()),
@@ -379,8 +450,22 @@ async fn main() -> Result<()> {
         schedule_dirty: Arc::new(AtomicBool::new(false)),
         bumper_queue: Arc::new(RwLock::new(VecDeque::new())),
         spot_reel_cancel: Arc::new(RwLock::new(None)),
+        pending_as_run: Arc::new(Mutex::new(VecDeque::new())),
+        fallback: Arc::new(RwLock::new(crate::fallback::Fallback::default())),
+        assets: Arc::new(RwLock::new(assets)),
+        interrupt: Arc::new(RwLock::new(None)),
+        alerts: Arc::new(RwLock::new(crate::alerts::AlertBoard::default())),
+        sync_group: Arc::new(RwLock::new(crate::sync_group::SyncGroup::default())),
+        library: Arc::new(tokio::sync::Mutex::new(crate::library::Library::default())),
     };
 
+    // Restore caches from the last run, so playback can start without the server
+    match crate::state_store::load(&state).await {
+        Ok(true) => {}
+        Ok(false) => tracing::info!("No saved state found, waiting for the server"),
+        Err(e) => tracing::error!("Failed to load saved state: {}", e),
+    }
+
     // Start WebSocket client
     let mut ws_client = WebSocketClient::new(state.clone());
 
@@ -397,6 +482,12 @@ async fn main() -> Result<()> {
         poll_schedule(state_clone_poll).await;
     });
 
+    // Start content prefetcher
+    let state_clone_prefetch = state.clone();
+    tokio::spawn(async move {
+        crate::asset_store::run_prefetcher(state_clone_prefetch).await;
+    });
+
     // Start playback loop with shutdown signal handling
     tokio::select! {
         _ = playback_loop(state.clone()) => {},
@@ -508,12 +599,23 @@ async fn fetch_and_update_schedule(client: &reqwest::Client, state: &NodeState,
         .replace("/ws", "");
 
     // Fetch Schedule
-    let url = format!("{}/api/nodes/{}/schedule", http_base, node_id);
+    let url = format!(
+        "{}/api/nodes/{}/schedule?days={}",
+        http_base, node_id, state.config.prefetch_days
+    );
     let today = chrono::Utc::now().date_naive();
+    let mut schedule_updated = false;
 
     if let Ok(res) = client.get(&url).send().await {
         if res.status().is_success() {
             if let Ok(response) = res.json::<ServerScheduleResponse>().await {
+                schedule_updated = true;
+                let fallback =
+                    crate::fallback::prepare(client, &http_base, response.fallback).await;
+                *state.fallback.write().await = fallback;
+                crate::sync_group::set_enabled(state, response.sync_playback).await;
+                crate::sync_group::set_server_time(state, response.server_time).await;
+
                 // Update cache
                 let mut cache = state.schedule_cache.write().await;
                 let mut script_cache = state.script_cache.write().await;
@@ -542,9 +644,24 @@ async fn fetch_and_update_schedule(client: &reqwest::Client, state: &NodeState,
                     let block = crate::schedule::ScheduleBlock {
                         start_time: server_block.start_time,
                         duration_minutes: server_block.duration_minutes,
+                        duration_secs: server_block.duration_secs,
                         content_id: server_block.content_id,
                         content_path,
                         script_id: server_block.script_id,
+                        offset_secs: server_block.offset_secs,
+                        items: server_block
+                            .items
+                            .iter()
+                            .map(|item| crate::schedule::RundownItem {
+                                content_id: item.content_id,
+                                content_path: content_map.get(&item.content_id).cloned(),
+                                start_at: item.start_at,
+                                duration_secs: item.duration_secs,
+                                offset_secs: item.offset_secs,
+                            })
+                            .collect(),
+                        break_policy: server_block.break_policy,
+                        transition: server_block.transition,
                     };
 
                     if let Some(date) = server_block.specific_date {
@@ -559,8 +676,14 @@ async fn fetch_and_update_schedule(client: &reqwest::Client, state: &NodeState,
                     }
                 }
 
-                for (date, blocks) in blocks_by_date {
-                    cache.update(date, blocks);
+                if let (Some(start), Some(end)) = (response.window_start, response.window_end) {
+                    // The node's local day can straddle two UTC dates, so only replace
+                    // blocks inside the window the server resolved.
+                    cache.replace_window(start.naive_utc(), end.naive_utc(), blocks_by_date);
+                } else {
+                    for (date, blocks) in blocks_by_date {
+                        cache.update(date, blocks);
+                    }
                 }
                 tracing::info!("Schedule updated from server");
             }
@@ -586,13 +709,81 @@ async fn fetch_and_update_schedule(client: &reqwest::Client, state: &NodeState,
             }
         }
     }
+
+    if schedule_updated {
+        if let Err(e) = crate::state_store::save(state).await {
+            tracing::error!("Failed to save state: {}", e);
+        }
+    }
+}
+
+/// Below this, content starts from the beginning rather than seeking to catch up
+const MIN_JOIN_OFFSET_SECS: f64 = 2.0;
+
+/// What the schedule airs at a moment.
+struct Airing {
+    date: NaiveDate,
+    block: crate::schedule::ScheduleBlock,
+    content_id: Option<i32>,
+    content_path: Option<String>,
+    /// Start of the run-down item, in fill blocks
+    item_start: Option<DateTime<Utc>>,
+}
+
+impl Airing {
+    async fn at(state: &NodeState, at: DateTime<Utc>) -> Option<Self> {
+        let cache = state.schedule_cache.read().await;
+        let (date, block) = cache.get_current_block(at.date_naive(), at.time())?;
+
+        // Fill blocks switch content at run-down item boundaries
+        let (content_id, content_path, item_start) = match block.item_at(at) {
+            Some(item) => (
+                Some(item.content_id),
+                item.content_path.clone(),
+                Some(item.start_at),
+            ),
+            None => (block.content_id, block.content_path.clone(), None),
+        };
+        Some(Self {
+            date,
+            block: block.clone(),
+            content_id,
+            content_path,
+            item_start,
+        })
+    }
 }
 
 async fn playback_loop(state: NodeState) {
     let mut last_content_id: Option<i32> = None;
+    let mut last_item_start: Option<DateTime<Utc>> = None;
+    let mut fallback = crate::fallback::FallbackCursor::default();
+    let mut breaks = crate::breaks::BreakTracker::default();
+    // When the outgoing item started fading to black
+    let mut fading_since: Option<DateTime<Utc>> = None;
+    let mut interrupted = false;
     let loop_interval = Duration::from_secs(1);
 
     loop {
+        crate::alerts::tick(&state).await;
+
+        // An interrupt preempts everything until it's cleared or times out
+        if crate::interrupt::active(&state).await {
+            interrupted = true;
+            crate::sync_group::release(&state).await;
+            tokio::time::sleep(loop_interval).await;
+            continue;
+        }
+        if interrupted {
+            // Rejoin the schedule (or fallback) where it is now
+            interrupted = false;
+            last_content_id = None;
+            last_item_start = None;
+            fading_since = None;
+            breaks.stop();
+            fallback.leave(&state);
+        }
+
         // Check for bumpers
         if let Err(e) = crate::playback::play_queued_bumpers(&state).await {
             tracing::error!("Error playing queued bumpers: {}", e);
@@ -600,43 +791,114 @@ async fn playback_loop(state: NodeState) {
 
         tokio::time::sleep(loop_interval).await;
 
-        let now = chrono::Utc::now();
-        let today = now.date_naive();
-        let time = now.time();
-
-        let block_opt = {
-            let cache = state.schedule_cache.read().await;
-            cache.get_current_block(today, time).cloned()
+        // In a sync group (or if the station says so), the server's clock
+        let now = crate::sync_group::now(&state).await;
+
+        // A segue starts the next item early, by the lead of the transition out of
+        // what's on air
+        let on_air = Airing::at(&state, now).await;
+        let transition =
+            crate::transition::for_block(&state, on_air.as_ref().map(|a| &a.block)).await;
+        let lead = transition.lead();
+        let airing = if lead.is_zero() {
+            on_air
+        } else {
+            Airing::at(&state, now + lead).await
         };
 
-        if let Some(block) = block_opt {
-            if block.content_id != last_content_id {
-                if block.content_id.is_none() {
+        // The outgoing item fades to black ahead of the switch
+        if transition.video_fade_secs > 0.0 && last_content_id.is_some() {
+            match fading_since {
+                None => {
+                    let ahead = Airing::at(&state, now + transition.fade_out_lead()).await;
+                    let next = ahead.map_or((None, None), |a| (a.content_id, a.item_start));
+                    if next != (last_content_id, last_item_start) {
+                        crate::transition::fade_out(&state, &transition);
+                        fading_since = Some(now);
+                    }
+                }
+                // The switch didn't come (the schedule changed): fade back up
+                Some(since) if transition.fade_out_overdue(since, now) => {
+                    crate::transition::clear_fade_out(&state);
+                    fading_since = None;
+                }
+                Some(_) => {}
+            }
+        }
+
+        if let Some(airing) = airing {
+            let content_id = airing.content_id;
+            let item_start = airing.item_start;
+
+            let left_fallback = fallback.leave(&state);
+            if left_fallback || content_id != last_content_id || item_start != last_item_start {
+                if content_id.is_none() {
                     tracing::info!("Status: Entering DJ Block (Waiting for Dynamic Content)");
                 } else {
-                    tracing::info!("Content changed to {:?}", block.content_id);
+                    tracing::info!("Content changed to {:?}", content_id);
                 }
-                last_content_id = block.content_id;
+                // Segue only from scheduled content into scheduled content
+                let segue = (!left_fallback && last_content_id.is_some() && content_id.is_some())
+                    .then_some(transition);
+                last_content_id = content_id;
+                last_item_start = item_start;
+                fading_since = None;
+
+                if let Some(content_id) = content_id {
+                    // Join in progress, like linear TV: after a boot or reconnect the content
+                    // starts where the published schedule says it is now
+                    let offset = (airing.block.content_offset_at(airing.date, now + lead)
+                        - transition.audio_crossfade_secs)
+                        .max(0.0);
+                    let join_offset = (offset >= MIN_JOIN_OFFSET_SECS).then_some(offset);
+
+                    if let Some(segue) = &segue {
+                        crate::transition::hand_off_audio(&state, segue);
+                    }
 
-                if let Some(content_id) = block.content_id {
                     // Pass the block's content path (which might be None, play_content resolves it)
-                    if let Err(e) =
-                        crate::playback::play_content(&state, content_id, block.content_path).await
+                    if let Err(e) = crate::playback::play_content(
+                        &state,
+                        content_id,
+                        airing.content_path,
+                        join_offset,
+                        segue,
+                    )
+                    .await
                     {
                         tracing::error!("Failed to play content: {}", e);
                     }
+                    breaks
+                        .start(&state, content_id, airing.block.break_policy.clone())
+                        .await;
                 } else {
                     // Content ID is None but there is a block? Stop.
                     crate::playback::stop_playback(&state).await;
+                    breaks.stop();
+                }
+            }
+            breaks.tick(&state).await;
+
+            // A sync group holds the content to the schedule, except mid-fade or once a
+            // break has pushed it back
+            match content_id.filter(|_| fading_since.is_none() && !breaks.delayed()) {
+                Some(content_id) => {
+                    let expected = airing.block.content_offset_at(airing.date, now + lead);
+                    crate::sync_group::correct(&state, content_id, expected).await;
                 }
+                None => crate::sync_group::release(&state).await,
             }
         } else {
-            // Nothing scheduled
+            // Nothing scheduled: fill the gap per the fallback policy
             if last_content_id.is_some() {
-                tracing::info!("Schedule ended, stopping playback");
-                crate::playback::stop_playback(&state).await;
+                tracing::info!("Schedule ended");
                 last_content_id = None;
+                last_item_start = None;
+                fading_since = None;
+                breaks.stop();
+                crate::sync_group::release(&state).await;
             }
+            crate::fallback::tick(&state, &mut fallback).await;
         }
     }
 }
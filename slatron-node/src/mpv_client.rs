@@ -51,6 +51,17 @@ impl MpvClient {
         path: &str,
         start_time: Option<f64>,
         loop_enabled: Option<bool>,
+    ) -> Result<()> {
+        self.play_with_options(path, start_time, loop_enabled, Vec::new())
+    }
+
+    /// `play`, with extra per-file options (`key=value`) that only last for this file.
+    pub fn play_with_options(
+        &self,
+        path: &str,
+        start_time: Option<f64>,
+        loop_enabled: Option<bool>,
+        mut options: Vec<String>,
     ) -> Result<()> {
         let mut args = vec![
             "loadfile".to_string(),
@@ -58,7 +69,6 @@ impl MpvClient {
             "replace".to_string(),
         ];
 
-        let mut options = Vec::new();
         if let Some(start) = start_time {
             options.push(format!("start={}", start));
         }
@@ -121,6 +131,70 @@ impl MpvClient {
         Ok(())
     }
 
+    /// Apply a loudness normalisation gain (dB) as a labelled audio filter, leaving
+    /// `volume` to scripts and ducking. `None` removes it.
+    pub fn set_gain(&self, gain_db: Option<f64>) -> Result<()> {
+        self.remove_filter("af", "gain")?;
+        if let Some(gain) = gain_db.filter(|g| *g != 0.0) {
+            self.add_filter("af", "gain", &format!("volume={}dB", gain))?;
+        }
+        Ok(())
+    }
+
+    /// Add a lavfi `graph` to the `af` or `vf` chain under `label`.
+    pub fn add_filter(&self, chain: &str, label: &str, graph: &str) -> Result<()> {
+        self.send_command(json!({
+            "command": [chain, "add", format!("@{}:lavfi=[{}]", label, graph)]
+        }))?;
+        Ok(())
+    }
+
+    /// Remove the filter labelled `label`. Removing one that isn't there is a harmless
+    /// error reply.
+    pub fn remove_filter(&self, chain: &str, label: &str) -> Result<()> {
+        self.send_command(json!({
+            "command": [chain, "remove", format!("@{}", label)]
+        }))?;
+        Ok(())
+    }
+
+    /// Whether a filter labelled `label` is on the `af` or `vf` chain.
+    pub fn has_filter(&self, chain: &str, label: &str) -> Result<bool> {
+        let response = self.send_command(json!({
+            "command": ["get_property", chain]
+        }))?;
+
+        let filters = response["data"]
+            .as_array()
+            .ok_or_else(|| anyhow::anyhow!("Invalid response"))?;
+        Ok(filters
+            .iter()
+            .any(|filter| filter["label"].as_str() == Some(label)))
+    }
+
+    /// Play `secs` of `path`'s audio from `start`, fading out, e.g. the tail of an item
+    /// handed over from another player during a crossfade.
+    pub fn play_audio_tail(&self, path: &str, start: f64, secs: f64) -> Result<()> {
+        self.play_with_options(
+            path,
+            Some(start),
+            None,
+            vec![
+                "vid=no".to_string(),
+                format!("end={}", start + secs),
+                format!("af=lavfi=[afade=t=out:st={}:d={}]", start, secs),
+            ],
+        )
+    }
+
+    /// Playback speed, 1.0 being normal. Audio keeps its pitch.
+    pub fn set_speed(&self, speed: f64) -> Result<()> {
+        self.send_command(json!({
+            "command": ["set_property", "speed", speed]
+        }))?;
+        Ok(())
+    }
+
     pub fn pause(&self) -> Result<()> {
         self.send_command(json!({
             "command": ["set_property", "pause", true]
@@ -156,6 +230,14 @@ impl MpvClient {
         Ok(())
     }
 
+    /// Show `text` on the OSD for `duration_ms`. Empty text clears it.
+    pub fn show_text(&self, text: &str, duration_ms: u32) -> Result<()> {
+        self.send_command(json!({
+            "command": ["show-text", text, duration_ms]
+        }))?;
+        Ok(())
+    }
+
     pub fn screenshot(&self, path: &str) -> Result<()> {
         // "screenshot-to-file" "<filename>" "<mode>"
         // mode: "video" (no subtitles/osd), "window" (with osd)
@@ -2,13 +2,18 @@ use crate::NodeState;
 use anyhow::{anyhow, Result};
 use tokio_util::sync::CancellationToken;
 
+/// Play a content item. `join_offset` is how far into the content the schedule already
+/// is, for joining a block in progress.
 pub async fn play_content(
     state: &NodeState,
     content_id: i32,
     path_override: Option<String>,
+    join_offset: Option<f64>,
+    transition: Option<crate::transition::Transition>,
 ) -> Result<()> {
     // Cancel any active spot reel first
     cancel_active_spot_reel(state).await;
+    crate::transition::clear_fade_out(state);
 
     // Check if this content is a spot reel
     {
@@ -24,6 +29,13 @@ pub async fn play_content(
 
                     // Update current content ID
                     *state.current_content_id.write().await = Some(content_id);
+                    crate::as_run::report_start(
+                        state,
+                        "content",
+                        Some(content_id),
+                        None,
+                        Some(item.content_path.clone()),
+                    );
 
                     // Create cancellation token and store it
                     let cancel = CancellationToken::new();
@@ -203,11 +215,28 @@ pub async fn play_content(
         }
     }
 
-    // Pass start_secs to mpv.play
-    state.mpv.play(&content_path, start_secs, loop_enabled)?;
+    // Looping content has no meaningful position to join at
+    if let Some(offset) = join_offset.filter(|_| loop_enabled != Some(true)) {
+        tracing::info!("Joining content {} {:.1}s in", content_id, offset);
+        start_secs = Some(start_secs.unwrap_or(0.0) + offset);
+    }
+
+    apply_content_gain(state, content_id).await;
+
+    // A segue fades the content in from where it starts
+    let options = transition
+        .map(|t| t.fade_in_options(start_secs.unwrap_or(0.0)))
+        .unwrap_or_default();
+
+    // Pass start_secs to mpv.play, preferring a prefetched copy of remote media
+    let play_path = crate::asset_store::resolve(state, content_path.clone()).await;
+    state
+        .mpv
+        .play_with_options(&play_path, start_secs, loop_enabled, options)?;
 
     // Update Current Content ID
     *state.current_content_id.write().await = Some(content_id);
+    crate::as_run::report_start(state, "content", Some(content_id), None, Some(content_path));
 
     // 6. Exec 'on_load'
     for (content, args) in &current_scripts_to_run {
@@ -231,17 +260,33 @@ pub async fn play_content(
     Ok(())
 }
 
+/// Set the player's loudness gain to that of `content_id`.
+pub async fn apply_content_gain(state: &NodeState, content_id: i32) {
+    let gain = state
+        .content_cache
+        .read()
+        .await
+        .get(&content_id)
+        .and_then(|item| item.gain_db);
+    if let Err(e) = state.mpv.set_gain(gain) {
+        tracing::warn!("Failed to set loudness gain: {}", e);
+    }
+}
+
 pub async fn stop_playback(state: &NodeState) {
     // Cancel any active spot reel
     cancel_active_spot_reel(state).await;
 
     unload_active_scripts(state).await;
+    crate::transition::clear_fade_out(state);
 
     if let Err(e) = state.mpv.stop() {
         tracing::error!("Failed to stop playback: {}", e);
     }
 
-    *state.current_content_id.write().await = None;
+    if state.current_content_id.write().await.take().is_some() {
+        crate::as_run::report_end(state, "content", None);
+    }
 }
 
 /// Cancel any actively running spot reel
@@ -250,6 +295,7 @@ async fn cancel_active_spot_reel(state: &NodeState) {
     if let Some(cancel) = cancel_guard.take() {
         tracing::info!("Cancelling active spot reel");
         cancel.cancel();
+        crate::as_run::report_end(state, "spot_reel_item", None);
     }
 }
 
@@ -388,6 +434,11 @@ pub async fn play_queued_bumpers(state: &NodeState) -> Result<()> {
                                         }
                                     }
 
+                                    let gain = bumper.get("gain_db").and_then(|g| g.as_f64());
+                                    if let Err(e) = state.mpv.set_gain(gain) {
+                                        tracing::warn!("Failed to set bumper gain: {}", e);
+                                    }
+
                                     if let Err(e) =
                                         state
                                             .mpv
@@ -395,6 +446,14 @@ pub async fn play_queued_bumpers(state: &NodeState) -> Result<()> {
                                     {
                                         tracing::error!("Failed to play bumper: {}", e);
                                     } else {
+                                        crate::as_run::report_start(
+                                            state,
+                                            "bumper",
+                                            None,
+                                            Some(bumper_name_or_id.clone()),
+                                            Some(local_path.to_string_lossy().to_string()),
+                                        );
+
                                         // Wait for bumper to finish
                                         if let Some(duration) =
                                             bumper.get("duration_ms").and_then(|d| d.as_i64())
@@ -407,8 +466,14 @@ pub async fn play_queued_bumpers(state: &NodeState) -> Result<()> {
                                             tokio::time::sleep(std::time::Duration::from_secs(3))
                                                 .await;
                                         }
+                                        crate::as_run::report_end(state, "bumper", None);
 
                                         // Resume previous content if applicable
+                                        if let Some(content_id) =
+                                            *state.current_content_id.read().await
+                                        {
+                                            apply_content_gain(state, content_id).await;
+                                        }
                                         if let Some(path) = resume_path {
                                             tracing::info!(
                                                 "Resuming content: {} at {:.2}s",
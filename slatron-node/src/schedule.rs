@@ -1,8 +1,10 @@
-use chrono::{NaiveDate, NaiveTime, Timelike};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Timelike, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use tracing::{debug, info};
 
+const SECS_PER_DAY: u32 = 86_400;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ScheduleCache {
     pub schedules: HashMap<NaiveDate, Vec<ScheduleBlock>>,
@@ -12,9 +14,62 @@ pub struct ScheduleCache {
 pub struct ScheduleBlock {
     pub start_time: NaiveTime,
     pub duration_minutes: i32,
+    /// Exact duration from the server; preferred over `duration_minutes` when present
+    #[serde(default)]
+    pub duration_secs: Option<i32>,
     pub content_id: Option<i32>,
     pub content_path: Option<String>,
     pub script_id: Option<i32>,
+    /// Seconds into the content at `start_time`; non-zero when the block resumes after
+    /// a higher priority block cut into it
+    #[serde(default)]
+    pub offset_secs: i32,
+    /// Run-down of a fill block; empty for single-content blocks
+    #[serde(default)]
+    pub items: Vec<RundownItem>,
+    /// Spot breaks taken at the content's cue points
+    #[serde(default)]
+    pub break_policy: Option<crate::breaks::BreakPolicy>,
+    /// Segue out of the block's items; the global `transition` setting if unset
+    #[serde(default)]
+    pub transition: Option<crate::transition::Transition>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RundownItem {
+    pub content_id: i32,
+    pub content_path: Option<String>,
+    pub start_at: DateTime<Utc>,
+    pub duration_secs: i32,
+    #[serde(default)]
+    pub offset_secs: i32,
+}
+
+impl ScheduleBlock {
+    pub fn total_duration_secs(&self) -> u32 {
+        self.duration_secs
+            .map(|secs| secs.max(0) as u32)
+            .unwrap_or(self.duration_minutes.max(0) as u32 * 60)
+    }
+
+    /// Where in its content the block (or its current run-down item) is at `now`, given
+    /// the date its `start_time` falls on. Nodes joining mid-block start playback here.
+    pub fn content_offset_at(&self, date: NaiveDate, now: DateTime<Utc>) -> f64 {
+        let (start, offset) = match self.item_at(now) {
+            Some(item) => (item.start_at, item.offset_secs),
+            None => (date.and_time(self.start_time).and_utc(), self.offset_secs),
+        };
+        let elapsed = (now - start).num_milliseconds().max(0) as f64 / 1000.0;
+        offset.max(0) as f64 + elapsed
+    }
+
+    /// Run-down item airing at `now`, for fill blocks.
+    pub fn item_at(&self, now: DateTime<Utc>) -> Option<&RundownItem> {
+        self.items.iter().find(|item| {
+            now >= item.start_at
+                && now < item.start_at + chrono::Duration::seconds(item.duration_secs as i64)
+        })
+    }
 }
 
 impl ScheduleCache {
@@ -28,43 +83,202 @@ impl ScheduleCache {
         self.schedules.insert(date, blocks);
     }
 
+    /// Replace every cached block starting within `[start, end)` (UTC) with `blocks`,
+    /// leaving blocks outside the window untouched.
+    pub fn replace_window(
+        &mut self,
+        start: NaiveDateTime,
+        end: NaiveDateTime,
+        blocks: HashMap<NaiveDate, Vec<ScheduleBlock>>,
+    ) {
+        for (date, cached) in self.schedules.iter_mut() {
+            cached.retain(|b| {
+                let block_start = date.and_time(b.start_time);
+                block_start < start || block_start >= end
+            });
+        }
+
+        for (date, new_blocks) in blocks {
+            let entry = self.schedules.entry(date).or_default();
+            entry.extend(new_blocks);
+            entry.sort_by_key(|b| b.start_time);
+        }
+
+        self.schedules.retain(|_, blocks| !blocks.is_empty());
+    }
+
+    /// Blocks airing at any point in `[start, end)`.
+    pub fn blocks_between(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Vec<&ScheduleBlock> {
+        self.schedules
+            .iter()
+            .flat_map(|(date, blocks)| blocks.iter().map(move |b| (date, b)))
+            .filter(|(date, block)| {
+                let block_start = date.and_time(block.start_time).and_utc();
+                let block_end =
+                    block_start + chrono::Duration::seconds(block.total_duration_secs() as i64);
+                block_start < end && block_end > start
+            })
+            .map(|(_, block)| block)
+            .collect()
+    }
+
+    /// Drop blocks for dates before `date`.
+    pub fn prune_before(&mut self, date: NaiveDate) {
+        self.schedules.retain(|d, _| *d >= date);
+    }
+
     pub fn get_blocks_for_date(&self, date: NaiveDate) -> Option<&Vec<ScheduleBlock>> {
         self.schedules.get(&date)
     }
 
-    pub fn get_current_block(&self, date: NaiveDate, time: NaiveTime) -> Option<&ScheduleBlock> {
+    /// The block airing at `date` `time`, and the date its `start_time` falls on.
+    pub fn get_current_block(
+        &self,
+        date: NaiveDate,
+        time: NaiveTime,
+    ) -> Option<(NaiveDate, &ScheduleBlock)> {
         debug!("Checking schedule for Date: {:?}, Time: {:?}", date, time);
 
-        let blocks = match self.get_blocks_for_date(date) {
-            Some(b) => b,
-            None => {
-                debug!("No schedule blocks found for date {:?}", date);
-                debug!("Available dates in cache: {:?}", self.schedules.keys());
-                return None;
+        let current_secs = time.hour() * 3600 + time.minute() * 60 + time.second();
+
+        if let Some(blocks) = self.get_blocks_for_date(date) {
+            debug!("Found {} blocks for date {:?}", blocks.len(), date);
+            if let Some(block) = Self::find_block_at(blocks, current_secs) {
+                info!("  -> MATCH! Playing block content {:?}", block.content_id);
+                return Some((date, block));
             }
-        };
+        } else {
+            debug!("No schedule blocks found for date {:?}", date);
+            debug!("Available dates in cache: {:?}", self.schedules.keys());
+        }
 
-        debug!("Found {} blocks for date {:?}", blocks.len(), date);
-        let current_secs = time.hour() * 3600 + time.minute() * 60 + time.second();
+        // A block from the previous day may run past midnight into today
+        if let Some(prev) = date.pred_opt() {
+            if let Some(blocks) = self.get_blocks_for_date(prev) {
+                if let Some(block) = Self::find_block_at(blocks, current_secs + SECS_PER_DAY) {
+                    info!(
+                        "  -> MATCH! Playing block content {:?} (carried over from previous day)",
+                        block.content_id
+                    );
+                    return Some((prev, block));
+                }
+            }
+        }
+
+        debug!("  -> No matching block found for time {:?}", time);
+
+        None
+    }
 
-        for block in blocks {
+    fn find_block_at(blocks: &[ScheduleBlock], current_secs: u32) -> Option<&ScheduleBlock> {
+        blocks.iter().find(|block| {
             let start = block.start_time;
             let start_secs = start.hour() * 3600 + start.minute() * 60 + start.second();
-            let end_secs = start_secs + (block.duration_minutes as u32 * 60);
+            let end_secs = start_secs + block.total_duration_secs();
 
             debug!(
-                "  Checking Block: Start {:?} ({}s), Dur {}m, End {}s. Current: {}s",
-                start, start_secs, block.duration_minutes, end_secs, current_secs
+                "  Checking Block: Start {:?} ({}s), Dur {}s, End {}s. Current: {}s",
+                start,
+                start_secs,
+                block.total_duration_secs(),
+                end_secs,
+                current_secs
             );
 
-            if current_secs >= start_secs && current_secs < end_secs {
-                info!("  -> MATCH! Playing block content {:?}", block.content_id);
-                return Some(block);
-            }
+            current_secs >= start_secs && current_secs < end_secs
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(start: &str, minutes: i32, content_id: i32) -> ScheduleBlock {
+        ScheduleBlock {
+            start_time: NaiveTime::parse_from_str(start, "%H:%M").unwrap(),
+            duration_minutes: minutes,
+            duration_secs: None,
+            content_id: Some(content_id),
+            content_path: None,
+            script_id: None,
+            offset_secs: 0,
+            items: Vec::new(),
+            break_policy: None,
+            transition: None,
         }
+    }
 
-        debug!("  -> No matching block found for time {:?}", time);
+    fn at(date: NaiveDate, time: &str) -> DateTime<Utc> {
+        date.and_time(NaiveTime::parse_from_str(time, "%H:%M:%S").unwrap())
+            .and_utc()
+    }
 
-        None
+    #[test]
+    fn test_join_mid_block() {
+        let date = NaiveDate::from_ymd_opt(2026, 3, 2).unwrap();
+        let mut cache = ScheduleCache::new();
+        cache.update(date, vec![block("09:00", 60, 1), block("10:00", 30, 2)]);
+
+        let now = at(date, "09:20:30");
+        let (start_date, current) = cache.get_current_block(date, now.time()).unwrap();
+        assert_eq!((start_date, current.content_id), (date, Some(1)));
+        assert_eq!(current.content_offset_at(start_date, now), 1230.0);
+
+        // A block resumed after a higher priority one cut into it
+        let mut resumed = block("10:00", 30, 2);
+        resumed.offset_secs = 600;
+        assert_eq!(resumed.content_offset_at(date, at(date, "10:05:00")), 900.0);
+
+        assert!(cache
+            .get_current_block(date, at(date, "10:30:00").time())
+            .is_none());
+    }
+
+    #[test]
+    fn test_join_block_carried_over_from_previous_day() {
+        let yesterday = NaiveDate::from_ymd_opt(2026, 3, 1).unwrap();
+        let today = yesterday.succ_opt().unwrap();
+        let mut cache = ScheduleCache::new();
+        cache.update(yesterday, vec![block("23:00", 120, 7)]);
+        cache.update(today, vec![block("06:00", 60, 8)]);
+
+        let now = at(today, "00:15:00");
+        let (start_date, current) = cache.get_current_block(today, now.time()).unwrap();
+        assert_eq!((start_date, current.content_id), (yesterday, Some(7)));
+        assert_eq!(current.content_offset_at(start_date, now), 4500.0);
+
+        assert!(cache
+            .get_current_block(today, at(today, "01:00:00").time())
+            .is_none());
+    }
+
+    #[test]
+    fn test_join_mid_item_in_fill_block() {
+        let date = NaiveDate::from_ymd_opt(2026, 3, 2).unwrap();
+        let mut fill = block("12:00", 30, 0);
+        fill.content_id = None;
+        fill.items = vec![
+            RundownItem {
+                content_id: 1,
+                content_path: None,
+                start_at: at(date, "12:00:00"),
+                duration_secs: 600,
+                offset_secs: 0,
+            },
+            RundownItem {
+                content_id: 2,
+                content_path: None,
+                start_at: at(date, "12:10:00"),
+                duration_secs: 1200,
+                offset_secs: 90,
+            },
+        ];
+
+        let now = at(date, "12:14:00");
+        assert_eq!(fill.item_at(now).map(|item| item.content_id), Some(2));
+        // Offset into the second item, not the block: its own offset plus four minutes
+        assert_eq!(fill.content_offset_at(date, now), 330.0);
+        assert!(fill.item_at(at(date, "12:30:00")).is_none());
     }
 }
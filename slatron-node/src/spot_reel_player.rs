@@ -35,16 +35,8 @@ pub async fn play_spot_reel(
         .replace("wss://", "https://");
     let api_url = base_url.split("/ws").next().unwrap_or(&base_url);
 
-    let url = format!("{}/api/spot-reels/{}", api_url, reel_id);
     let client = reqwest::Client::new();
-
-    let reel: SpotReelResponse = client
-        .get(&url)
-        .send()
-        .await?
-        .json()
-        .await
-        .map_err(|e| anyhow!("Failed to fetch spot reel {}: {}", reel_id, e))?;
+    let reel = fetch_reel(&client, state, reel_id).await?;
 
     if reel.items.is_empty() {
         tracing::warn!("Spot reel '{}' has no items, nothing to play", reel.title);
@@ -71,32 +63,7 @@ pub async fn play_spot_reel(
                 return Ok(());
             }
 
-            let item_title = item
-                .title
-                .as_deref()
-                .unwrap_or(&item.item_path);
-
-            tracing::info!(
-                "Spot reel item: {} (type: {}, duration: {}s)",
-                item_title,
-                item.item_type,
-                item.display_duration_secs
-            );
-
-            match item.item_type.as_str() {
-                "image" => {
-                    play_image_item(state, item, &cancel).await?;
-                }
-                "video" => {
-                    play_video_item(state, item, &cancel).await?;
-                }
-                "web" => {
-                    play_web_item(state, item, &cancel, api_url).await?;
-                }
-                other => {
-                    tracing::warn!("Unknown spot reel item type: {}", other);
-                }
-            }
+            play_item(state, item, &cancel, api_url).await?;
 
             if cancel.is_cancelled() {
                 return Ok(());
@@ -105,6 +72,110 @@ pub async fn play_spot_reel(
     }
 }
 
+/// Play a reel's items once, as a break inside other content, stopping after `max`
+/// or when cancelled.
+pub async fn play_break(
+    state: &NodeState,
+    reel: &SpotReelResponse,
+    max: Duration,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    let server_url = &state.config.server_url;
+    let base_url = server_url
+        .replace("ws://", "http://")
+        .replace("wss://", "https://");
+    let api_url = base_url.split("/ws").next().unwrap_or(&base_url);
+
+    let mut items = reel.items.clone();
+    items.sort_by_key(|i| i.position);
+
+    let play_items = async {
+        for item in &items {
+            if cancel.is_cancelled() {
+                break;
+            }
+            play_item(state, item, cancel, api_url).await?;
+        }
+        Ok::<(), anyhow::Error>(())
+    };
+
+    tokio::select! {
+        result = play_items => result,
+        _ = tokio::time::sleep(max) => {
+            tracing::info!("Break '{}' reached its {}s limit", reel.title, max.as_secs());
+            Ok(())
+        },
+    }
+}
+
+async fn play_item(
+    state: &NodeState,
+    item: &SpotReelItemResponse,
+    cancel: &CancellationToken,
+    api_url: &str,
+) -> Result<()> {
+    let item_title = item.title.as_deref().unwrap_or(&item.item_path);
+
+    tracing::info!(
+        "Spot reel item: {} (type: {}, duration: {}s)",
+        item_title,
+        item.item_type,
+        item.display_duration_secs
+    );
+
+    crate::as_run::report_start(
+        state,
+        "spot_reel_item",
+        None,
+        Some(item_title.to_string()),
+        Some(item.item_path.clone()),
+    );
+
+    // Reel items aren't analysed for loudness
+    if let Err(e) = state.mpv.set_gain(None) {
+        tracing::warn!("Failed to clear loudness gain: {}", e);
+    }
+
+    match item.item_type.as_str() {
+        "image" => {
+            play_image_item(state, item, cancel).await?;
+        }
+        "video" => {
+            play_video_item(state, item, cancel).await?;
+        }
+        "web" => {
+            play_web_item(state, item, cancel, api_url).await?;
+        }
+        other => {
+            tracing::warn!("Unknown spot reel item type: {}", other);
+        }
+    }
+
+    Ok(())
+}
+
+/// Fetch a reel and its items from the server.
+pub async fn fetch_reel(
+    client: &reqwest::Client,
+    state: &NodeState,
+    reel_id: i32,
+) -> Result<SpotReelResponse> {
+    let server_url = &state.config.server_url;
+    let base_url = server_url
+        .replace("ws://", "http://")
+        .replace("wss://", "https://");
+    let api_url = base_url.split("/ws").next().unwrap_or(&base_url);
+
+    let url = format!("{}/api/spot-reels/{}", api_url, reel_id);
+    client
+        .get(&url)
+        .send()
+        .await?
+        .json()
+        .await
+        .map_err(|e| anyhow!("Failed to fetch spot reel {}: {}", reel_id, e))
+}
+
 /// Play an image item - load in MPV and wait for display_duration_secs
 async fn play_image_item(
     state: &NodeState,
@@ -112,7 +183,8 @@ async fn play_image_item(
     cancel: &CancellationToken,
 ) -> Result<()> {
     // MPV can display images as stills
-    state.mpv.play(&item.item_path, None, Some(false))?;
+    let path = crate::asset_store::resolve(state, item.item_path.clone()).await;
+    state.mpv.play(&path, None, Some(false))?;
 
     // Wait for display duration or cancellation
     let duration = Duration::from_secs(item.display_duration_secs as u64);
@@ -130,7 +202,8 @@ async fn play_video_item(
     item: &SpotReelItemResponse,
     cancel: &CancellationToken,
 ) -> Result<()> {
-    state.mpv.play(&item.item_path, None, Some(false))?;
+    let path = crate::asset_store::resolve(state, item.item_path.clone()).await;
+    state.mpv.play(&path, None, Some(false))?;
 
     let duration = Duration::from_secs(item.display_duration_secs as u64);
 
@@ -0,0 +1,101 @@
+//! Persists the node's caches so a restart without a server still has something to play.
+//!
+//! Everything fetched from the server is written to `node_state.json` in the state
+//! directory after each successful schedule fetch and loaded again at startup.
+
+use crate::fallback::Fallback;
+use crate::schedule::ScheduleCache;
+use crate::{NodeState, ServerContentItem};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+const STATE_FILE: &str = "node_state.json";
+
+#[derive(Serialize, Deserialize)]
+struct SavedState {
+    saved_at: DateTime<Utc>,
+    schedule: ScheduleCache,
+    content: HashMap<i32, ServerContentItem>,
+    scripts: HashMap<i32, String>,
+    script_names: HashMap<String, i32>,
+    global_settings: HashMap<String, String>,
+    #[serde(default)]
+    fallback: Fallback,
+}
+
+fn state_dir(state: &NodeState) -> PathBuf {
+    match &state.config.state_dir {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(std::env::var("HOME").unwrap_or_else(|_| ".".to_string()))
+            .join(".slatron/state"),
+    }
+}
+
+/// Write the current caches to disk. Blocks from before yesterday are dropped first.
+pub async fn save(state: &NodeState) -> Result<()> {
+    let yesterday = Utc::now().date_naive().pred_opt().unwrap_or_default();
+    let schedule = {
+        let mut cache = state.schedule_cache.write().await;
+        cache.prune_before(yesterday);
+        cache.clone()
+    };
+
+    let saved = SavedState {
+        saved_at: Utc::now(),
+        schedule,
+        content: state.content_cache.read().await.clone(),
+        scripts: state.script_cache.read().await.clone(),
+        script_names: state.script_name_cache.read().await.clone(),
+        global_settings: state.global_settings.read().await.clone(),
+        fallback: state.fallback.read().await.clone(),
+    };
+
+    let dir = state_dir(state);
+    std::fs::create_dir_all(&dir)?;
+
+    // Write then rename, so a power cut never leaves a half-written file behind
+    let tmp_path = dir.join(format!("{}.tmp", STATE_FILE));
+    std::fs::write(&tmp_path, serde_json::to_vec(&saved)?)?;
+    std::fs::rename(&tmp_path, dir.join(STATE_FILE))?;
+    Ok(())
+}
+
+/// Restore caches saved by a previous run. Returns `false` if there was nothing to load.
+pub async fn load(state: &NodeState) -> Result<bool> {
+    let path = state_dir(state).join(STATE_FILE);
+    if !path.exists() {
+        return Ok(false);
+    }
+
+    let saved: SavedState = serde_json::from_slice(&std::fs::read(&path)?)?;
+
+    let age = Utc::now() - saved.saved_at;
+    if age < chrono::Duration::zero() {
+        // Typical of a board without an RTC that booted without a network
+        tracing::warn!(
+            "System clock is behind the saved state ({}); it may be wrong until the server corrects it",
+            saved.saved_at
+        );
+    } else if age.num_hours() >= state.config.offline_mode_warning_hours as i64 {
+        tracing::warn!(
+            "Saved state is {} hours old; the cached schedule may be out of date",
+            age.num_hours()
+        );
+    }
+    tracing::info!(
+        "Loaded saved state from {} ({} days of schedule)",
+        saved.saved_at,
+        saved.schedule.schedules.len()
+    );
+
+    *state.schedule_cache.write().await = saved.schedule;
+    *state.content_cache.write().await = saved.content;
+    *state.script_cache.write().await = saved.scripts;
+    *state.script_name_cache.write().await = saved.script_names;
+    *state.global_settings.write().await = saved.global_settings;
+    *state.fallback.write().await = saved.fallback;
+    Ok(true)
+}
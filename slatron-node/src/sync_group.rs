@@ -0,0 +1,312 @@
+//! Synchronised playback for sync groups.
+//!
+//! Every node measures its clock against the server's with an NTP-style exchange over
+//! the websocket; of the recent samples, the one with the shortest round trip is
+//! trusted. Nodes in a sync group channel run the schedule on the server's clock rather
+//! than their own, so they switch items at the same moment, and hold the player to where
+//! the schedule has the content: small drift is closed by nudging mpv's `speed`, larger
+//! drift by seeking. The station can also have every node run its schedule on the
+//! server's clock, for nodes whose own can't be trusted (no RTC, booted offline).
+
+use crate::websocket_client::NodeMessage;
+use crate::NodeState;
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedSender;
+
+/// Clock samples kept; the best of them sets the offset
+const SAMPLES: usize = 8;
+/// Between clock exchanges
+const EXCHANGE_INTERVAL: Duration = Duration::from_secs(5);
+/// Drift left alone, about a frame
+const DEADBAND_SECS: f64 = 0.04;
+/// Drift beyond this is seeked away rather than nudged
+const SEEK_THRESHOLD_SECS: f64 = 1.0;
+/// Largest change to the playback speed while catching up
+const MAX_NUDGE: f64 = 0.02;
+/// How long a nudge aims to take to close the drift
+const CATCH_UP_SECS: f64 = 5.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Sample {
+    /// Server clock minus ours
+    offset: ChronoDuration,
+    rtt: ChronoDuration,
+}
+
+impl Sample {
+    /// From the four timestamps of an exchange. `None` if the round trip comes out
+    /// negative, as when our clock steps mid-exchange.
+    fn measure(
+        client_sent: DateTime<Utc>,
+        server_received: DateTime<Utc>,
+        server_sent: DateTime<Utc>,
+        client_received: DateTime<Utc>,
+    ) -> Option<Self> {
+        let rtt = (client_received - client_sent) - (server_sent - server_received);
+        if rtt < ChronoDuration::zero() {
+            return None;
+        }
+        let offset = ((server_received - client_sent) + (server_sent - client_received)) / 2;
+        Some(Self { offset, rtt })
+    }
+}
+
+/// What to do about the player being `drift` seconds off the schedule.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Correction {
+    Seek,
+    Speed(f64),
+    /// Leave the player as it is
+    Hold,
+}
+
+impl Correction {
+    fn for_drift(drift: f64, nudged: bool) -> Self {
+        if drift.abs() > SEEK_THRESHOLD_SECS {
+            Self::Seek
+        } else if drift.abs() > DEADBAND_SECS {
+            // Ahead slows down, behind speeds up
+            Self::Speed(1.0 - (drift / CATCH_UP_SECS).clamp(-MAX_NUDGE, MAX_NUDGE))
+        } else if nudged {
+            Self::Speed(1.0)
+        } else {
+            Self::Hold
+        }
+    }
+}
+
+/// Clock and drift state.
+#[derive(Debug, Default)]
+pub struct SyncGroup {
+    /// Whether the node's channel plays in lockstep
+    enabled: bool,
+    /// Whether to run the schedule on the server's clock outside a sync group too
+    server_time: bool,
+    samples: VecDeque<Sample>,
+    /// Content position minus where the schedule has it, while correcting
+    drift_secs: Option<f64>,
+    /// Whether the player's speed is off 1.0
+    nudged: bool,
+}
+
+impl SyncGroup {
+    fn best(&self) -> Option<Sample> {
+        self.samples.iter().min_by_key(|sample| sample.rtt).copied()
+    }
+}
+
+/// Clock and drift as reported in heartbeats.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncStatus {
+    pub enabled: bool,
+    pub clock_offset_ms: Option<i64>,
+    pub rtt_ms: Option<i64>,
+    pub drift_ms: Option<i64>,
+}
+
+/// Ask the server for its time every few seconds until the connection drops.
+pub async fn run_exchange(sender: UnboundedSender<NodeMessage>) {
+    let mut tick = tokio::time::interval(EXCHANGE_INTERVAL);
+    loop {
+        tick.tick().await;
+        let request = NodeMessage::TimeSync {
+            client_sent: Utc::now(),
+        };
+        if sender.send(request).is_err() {
+            break;
+        }
+    }
+}
+
+/// Take the server's reply to a clock exchange.
+pub async fn record(
+    state: &NodeState,
+    client_sent: DateTime<Utc>,
+    server_received: DateTime<Utc>,
+    server_sent: DateTime<Utc>,
+) {
+    let Some(sample) = Sample::measure(client_sent, server_received, server_sent, Utc::now())
+    else {
+        return;
+    };
+
+    let mut group = state.sync_group.write().await;
+    group.samples.push_back(sample);
+    if group.samples.len() > SAMPLES {
+        group.samples.pop_front();
+    }
+}
+
+/// Join or leave the sync group, per the schedule the server sent.
+pub async fn set_enabled(state: &NodeState, enabled: bool) {
+    let changed = {
+        let mut group = state.sync_group.write().await;
+        std::mem::replace(&mut group.enabled, enabled) != enabled
+    };
+    if changed {
+        tracing::info!("{} sync group", if enabled { "Joined" } else { "Left" });
+        if !enabled {
+            release(state).await;
+        }
+    }
+}
+
+/// Run the schedule on the server's clock, per the station's `clock` setting.
+pub async fn set_server_time(state: &NodeState, server_time: bool) {
+    let mut group = state.sync_group.write().await;
+    if std::mem::replace(&mut group.server_time, server_time) != server_time {
+        tracing::info!(
+            "Scheduling against the {} clock",
+            if server_time { "server's" } else { "system" }
+        );
+    }
+}
+
+/// The time to run the schedule on: the server's in a sync group or when told to (once
+/// measured), else the node's own.
+pub async fn now(state: &NodeState) -> DateTime<Utc> {
+    let group = state.sync_group.read().await;
+    match group.best().filter(|_| group.enabled || group.server_time) {
+        Some(sample) => Utc::now() + sample.offset,
+        None => Utc::now(),
+    }
+}
+
+/// Hold `content_id` to `expected`, the position the schedule has it at now. Run every
+/// playback loop tick while scheduled content airs.
+pub async fn correct(state: &NodeState, content_id: i32, expected: f64) {
+    if !state.sync_group.read().await.enabled {
+        return;
+    }
+    // Content loaded by a command, a break or a spot reel isn't held to the schedule
+    if *state.current_content_id.read().await != Some(content_id)
+        || state.spot_reel_cancel.read().await.is_some()
+    {
+        release(state).await;
+        return;
+    }
+
+    // A script's start point shifts the content; looping content has no position to hold
+    let (looping, start) = {
+        let settings = state.active_settings.read().await;
+        let looping = settings
+            .get("loop")
+            .and_then(|v| v.as_bool().ok())
+            .unwrap_or(false);
+        let start = settings.get("start_time").and_then(|v| {
+            v.as_float()
+                .ok()
+                .or_else(|| v.as_int().ok().map(|secs| secs as f64))
+        });
+        (looping, start.unwrap_or(0.0))
+    };
+    let target = start + expected;
+    let past_end = state
+        .mpv
+        .get_duration()
+        .is_ok_and(|duration| target >= duration);
+    if looping || past_end {
+        release(state).await;
+        return;
+    }
+    let Ok(position) = state.mpv.get_position() else {
+        return;
+    };
+
+    let drift = position - target;
+    let mut group = state.sync_group.write().await;
+    group.drift_secs = Some(drift);
+
+    match Correction::for_drift(drift, group.nudged) {
+        Correction::Seek => {
+            tracing::info!("Drifted {:.2}s from the sync group, seeking", drift);
+            if let Err(e) = state.mpv.seek(target) {
+                tracing::warn!("Failed to seek back into sync: {}", e);
+            }
+            set_speed(state, &mut group, 1.0);
+        }
+        Correction::Speed(speed) => set_speed(state, &mut group, speed),
+        Correction::Hold => {}
+    }
+}
+
+/// Stop correcting drift, putting the player back to normal speed.
+pub async fn release(state: &NodeState) {
+    let mut group = state.sync_group.write().await;
+    group.drift_secs = None;
+    if group.nudged {
+        set_speed(state, &mut group, 1.0);
+    }
+}
+
+fn set_speed(state: &NodeState, group: &mut SyncGroup, speed: f64) {
+    match state.mpv.set_speed(speed) {
+        Ok(()) => group.nudged = speed != 1.0,
+        Err(e) => tracing::warn!("Failed to set playback speed: {}", e),
+    }
+}
+
+pub async fn status(state: &NodeState) -> SyncStatus {
+    let group = state.sync_group.read().await;
+    let best = group.best();
+    SyncStatus {
+        enabled: group.enabled,
+        clock_offset_ms: best.map(|sample| sample.offset.num_milliseconds()),
+        rtt_ms: best.map(|sample| sample.rtt.num_milliseconds()),
+        drift_ms: group
+            .drift_secs
+            .map(|drift| (drift * 1000.0).round() as i64),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clock_sample() {
+        let t0 = Utc::now();
+        let ms = ChronoDuration::milliseconds;
+
+        // Server a second ahead, 50ms each way, 10ms to answer
+        let sample = Sample::measure(t0, t0 + ms(1050), t0 + ms(1060), t0 + ms(110)).unwrap();
+        assert_eq!(
+            sample,
+            Sample {
+                offset: ms(1000),
+                rtt: ms(100)
+            }
+        );
+
+        // Asymmetric paths skew the offset by half the difference
+        let sample = Sample::measure(t0, t0 + ms(1080), t0 + ms(1080), t0 + ms(100)).unwrap();
+        assert_eq!(sample.offset, ms(1030));
+
+        // The server took longer to answer than the whole exchange: our clock stepped back
+        assert_eq!(
+            Sample::measure(t0, t0 + ms(1000), t0 + ms(1200), t0 + ms(100)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_correction_for_drift() {
+        assert_eq!(Correction::for_drift(2.5, false), Correction::Seek);
+        assert_eq!(Correction::for_drift(-1.2, true), Correction::Seek);
+
+        // Nudges close the drift over a few seconds, capped at 2%
+        let Correction::Speed(speed) = Correction::for_drift(-0.05, false) else {
+            panic!("expected a nudge");
+        };
+        assert!((speed - 1.01).abs() < 1e-9);
+        assert_eq!(Correction::for_drift(0.5, false), Correction::Speed(0.98));
+        assert_eq!(Correction::for_drift(-0.9, false), Correction::Speed(1.02));
+
+        // Within the deadband: back to normal speed if nudged, else left alone
+        assert_eq!(Correction::for_drift(0.02, true), Correction::Speed(1.0));
+        assert_eq!(Correction::for_drift(-0.02, false), Correction::Hold);
+    }
+}
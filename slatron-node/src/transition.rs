@@ -0,0 +1,203 @@
+//! Segues between items: audio crossfades and video fades through black.
+//!
+//! With an audio crossfade the node switches to the next item `audio_crossfade_secs`
+//! early. The outgoing item's audio is handed to the secondary (voice) player, where it
+//! plays out and fades under the incoming item as that fades in. With a video fade the
+//! outgoing picture fades to black just before the switch and the next item fades up
+//! from black; without a crossfade the sound dips with it.
+
+use crate::schedule::ScheduleBlock;
+use crate::NodeState;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Label of the filters fading the outgoing item
+const FADE_OUT_LABEL: &str = "fade-out";
+/// How late the switch may come after a fade to black before the picture comes back
+const FADE_OUT_GRACE_SECS: i64 = 2;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Transition {
+    #[serde(default)]
+    pub audio_crossfade_secs: f64,
+    #[serde(default)]
+    pub video_fade_secs: f64,
+}
+
+impl Transition {
+    /// How long before its scheduled start the next item starts playing.
+    pub fn lead(&self) -> chrono::Duration {
+        secs(self.audio_crossfade_secs)
+    }
+
+    /// How long before its scheduled start the outgoing item starts fading to black.
+    pub fn fade_out_lead(&self) -> chrono::Duration {
+        secs(self.audio_crossfade_secs + self.video_fade_secs)
+    }
+
+    /// Whether a fade to black that began at `since` has outlasted its switch, which
+    /// then isn't coming (the schedule changed).
+    pub fn fade_out_overdue(&self, since: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+        now - since > self.fade_out_lead() + chrono::Duration::seconds(FADE_OUT_GRACE_SECS)
+    }
+
+    /// Per-file player options fading the incoming item in, given the position it starts at.
+    pub fn fade_in_options(&self, start_secs: f64) -> Vec<String> {
+        let mut options = Vec::new();
+        let audio_secs = if self.audio_crossfade_secs > 0.0 {
+            self.audio_crossfade_secs
+        } else {
+            self.video_fade_secs
+        };
+        if audio_secs > 0.0 {
+            options.push(format!(
+                "af-append=lavfi=[afade=t=in:st={}:d={}]",
+                start_secs, audio_secs
+            ));
+        }
+        if self.video_fade_secs > 0.0 {
+            options.push(format!(
+                "vf-append=lavfi=[fade=t=in:st={}:d={}]",
+                start_secs, self.video_fade_secs
+            ));
+        }
+        options
+    }
+}
+
+fn secs(secs: f64) -> chrono::Duration {
+    chrono::Duration::milliseconds((secs.max(0.0) * 1000.0) as i64)
+}
+
+/// The transition out of `block`'s items: the block's own, else the global `transition`
+/// setting, else a hard cut.
+pub async fn for_block(state: &NodeState, block: Option<&ScheduleBlock>) -> Transition {
+    if let Some(transition) = block.and_then(|b| b.transition) {
+        return transition;
+    }
+    state
+        .global_settings
+        .read()
+        .await
+        .get("transition")
+        .and_then(|json| serde_json::from_str(json).ok())
+        .unwrap_or_default()
+}
+
+/// Start fading the outgoing item to black ahead of a switch.
+pub fn fade_out(state: &NodeState, transition: &Transition) {
+    if transition.video_fade_secs <= 0.0 {
+        return;
+    }
+    let Ok(position) = state.mpv.get_position() else {
+        return;
+    };
+
+    let secs = transition.video_fade_secs;
+    let mut result = state.mpv.add_filter(
+        "vf",
+        FADE_OUT_LABEL,
+        &format!("fade=t=out:st={}:d={}", position, secs),
+    );
+    // A crossfade hands the sound over instead
+    if result.is_ok() && transition.audio_crossfade_secs <= 0.0 {
+        result = state.mpv.add_filter(
+            "af",
+            FADE_OUT_LABEL,
+            &format!("afade=t=out:st={}:d={}", position, secs),
+        );
+    }
+    if let Err(e) = result {
+        tracing::warn!("Failed to fade out: {}", e);
+    }
+}
+
+/// Remove the outgoing item's fade, so it doesn't carry over to what plays next.
+pub fn clear_fade_out(state: &NodeState) {
+    for chain in ["vf", "af"] {
+        if let Err(e) = state.mpv.remove_filter(chain, FADE_OUT_LABEL) {
+            tracing::warn!("Failed to clear fade out: {}", e);
+        }
+    }
+}
+
+/// Hand the outgoing item's audio to the secondary player to play out under the next
+/// item. Skipped while the secondary player is busy with a DJ voice track.
+pub fn hand_off_audio(state: &NodeState, transition: &Transition) {
+    if transition.audio_crossfade_secs <= 0.0
+        || !matches!(state.mpv_voice.is_idle(), Ok(true))
+        || matches!(state.mpv.is_idle(), Ok(true))
+    {
+        return;
+    }
+    let (Ok(path), Ok(position)) = (state.mpv.get_path(), state.mpv.get_position()) else {
+        return;
+    };
+
+    if let Err(e) =
+        state
+            .mpv_voice
+            .play_audio_tail(&path, position, transition.audio_crossfade_secs)
+    {
+        tracing::warn!("Failed to crossfade: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_leads() {
+        let ms = chrono::Duration::milliseconds;
+        let transition = Transition {
+            audio_crossfade_secs: 2.5,
+            video_fade_secs: 1.0,
+        };
+        // The next item starts at the crossfade, the picture fades out before that
+        assert_eq!(transition.lead(), ms(2500));
+        assert_eq!(transition.fade_out_lead(), ms(3500));
+        assert_eq!(Transition::default().lead(), ms(0));
+
+        // Negative durations from a bad setting count as none
+        let bad = Transition {
+            audio_crossfade_secs: -1.0,
+            video_fade_secs: 0.5,
+        };
+        assert_eq!(bad.lead(), ms(0));
+        assert_eq!(bad.fade_out_lead(), ms(0));
+
+        let since = Utc::now();
+        assert!(!transition.fade_out_overdue(since, since + ms(5500)));
+        assert!(transition.fade_out_overdue(since, since + ms(5501)));
+    }
+
+    #[test]
+    fn test_fade_in_options() {
+        let crossfade = Transition {
+            audio_crossfade_secs: 2.0,
+            video_fade_secs: 1.0,
+        };
+        assert_eq!(
+            crossfade.fade_in_options(30.0),
+            vec![
+                "af-append=lavfi=[afade=t=in:st=30:d=2]".to_string(),
+                "vf-append=lavfi=[fade=t=in:st=30:d=1]".to_string(),
+            ]
+        );
+
+        // Without a crossfade the sound comes up with the picture
+        let fade = Transition {
+            audio_crossfade_secs: 0.0,
+            video_fade_secs: 1.5,
+        };
+        assert_eq!(
+            fade.fade_in_options(0.0),
+            vec![
+                "af-append=lavfi=[afade=t=in:st=0:d=1.5]".to_string(),
+                "vf-append=lavfi=[fade=t=in:st=0:d=1.5]".to_string(),
+            ]
+        );
+        assert!(Transition::default().fade_in_options(0.0).is_empty());
+    }
+}
@@ -25,6 +25,12 @@ pub enum ServerMessage {
     Command { command: NodeCommand },
     #[serde(rename = "heartbeat_ack")]
     HeartbeatAck,
+    #[serde(rename = "time_sync")]
+    TimeSync {
+        client_sent: chrono::DateTime<chrono::Utc>,
+        server_received: chrono::DateTime<chrono::Utc>,
+        server_sent: chrono::DateTime<chrono::Utc>,
+    },
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -54,6 +60,26 @@ pub enum NodeCommand {
     Shutdown,
     #[serde(rename = "inject_audio")]
     InjectAudio { url: String, mix: bool },
+    #[serde(rename = "interrupt")]
+    Interrupt {
+        message: Option<String>,
+        slate_path: Option<String>,
+        slate_gain_db: Option<f64>,
+        timeout_secs: Option<u32>,
+    },
+    #[serde(rename = "clear_interrupt")]
+    ClearInterrupt,
+    #[serde(rename = "show_alert")]
+    ShowAlert {
+        alert_id: i32,
+        text: String,
+        severity: String,
+        expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    },
+    #[serde(rename = "clear_alert")]
+    ClearAlert { alert_id: i32 },
+    #[serde(rename = "scan_library")]
+    ScanLibrary,
 }
 
 // Node → Server messages
@@ -74,11 +100,20 @@ pub enum NodeMessage {
         cpu_usage_percent: f64,
         memory_usage_mb: f64,
         errors: Vec<String>,
+        cache: Option<crate::asset_store::CacheStatus>,
+        interrupt: Option<crate::interrupt::Interrupt>,
+        sync: Option<crate::sync_group::SyncStatus>,
+        sent_at: chrono::DateTime<chrono::Utc>,
     },
     #[serde(rename = "request_schedule")]
     RequestSchedule,
     #[serde(rename = "report_paths")]
     ReportPaths { available_paths: Vec<String> },
+    #[serde(rename = "library_scan")]
+    LibraryScan {
+        roots: Vec<String>,
+        files: Vec<crate::library::ScannedFile>,
+    },
     #[serde(rename = "content_error")]
     ContentError { content_id: i32, error: String },
     #[serde(rename = "log")]
@@ -90,6 +125,26 @@ pub enum NodeMessage {
     },
     #[serde(rename = "screenshot")]
     Screenshot { image_base64: String },
+    #[serde(rename = "time_sync")]
+    TimeSync {
+        client_sent: chrono::DateTime<chrono::Utc>,
+    },
+    #[serde(rename = "playback_event")]
+    PlaybackEvent {
+        kind: String,
+        phase: PlaybackPhase,
+        content_id: Option<i32>,
+        title: Option<String>,
+        path: Option<String>,
+        at: chrono::DateTime<chrono::Utc>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlaybackPhase {
+    Start,
+    End,
 }
 
 pub struct WebSocketClient {
@@ -168,6 +223,7 @@ impl WebSocketClient {
         if let Ok(mut sender_guard) = self.state.log_sender.lock() {
             *sender_guard = Some(msg_tx.clone());
         }
+        crate::as_run::flush(&self.state);
 
         // Spawn write task
         let write_task = tokio::spawn(async move {
@@ -187,6 +243,12 @@ impl WebSocketClient {
             heartbeat_manager.start().await;
         });
 
+        // Measure the clock against the server's
+        tokio::spawn(crate::sync_group::run_exchange(msg_tx.clone()));
+
+        // Bring the server's content library up to date
+        tokio::spawn(crate::library::run(self.state.clone()));
+
         // Start Screenshot manager
         let screenshot_manager =
             crate::screenshot::ScreenshotManager::new(self.state.clone(), msg_tx.clone());
@@ -247,12 +309,36 @@ impl WebSocketClient {
             ServerMessage::HeartbeatAck => {
                 // Heartbeat acknowledged
             }
+            ServerMessage::TimeSync {
+                client_sent,
+                server_received,
+                server_sent,
+            } => {
+                crate::sync_group::record(&self.state, client_sent, server_received, server_sent)
+                    .await;
+            }
         }
 
         Ok(())
     }
 
     async fn handle_command(&self, command: NodeCommand) -> Result<()> {
+        // Nothing but the interrupt itself (and alerts over it) airs during an interrupt
+        let playback_command = !matches!(
+            command,
+            NodeCommand::ReloadSchedule
+                | NodeCommand::Shutdown
+                | NodeCommand::ScanLibrary
+                | NodeCommand::Interrupt { .. }
+                | NodeCommand::ClearInterrupt
+                | NodeCommand::ShowAlert { .. }
+                | NodeCommand::ClearAlert { .. }
+        );
+        if playback_command && self.state.interrupt.read().await.is_some() {
+            tracing::warn!("Ignoring command during interrupt: {:?}", command);
+            return Ok(());
+        }
+
         match command {
             NodeCommand::Play => {
                 tracing::info!("Command: Play (Resume)");
@@ -292,11 +378,16 @@ impl WebSocketClient {
                             transformer_scripts: None,
                             content_type: None,
                             spot_reel_id: None,
+                            sha256: None,
+                            cue_points: None,
+                            gain_db: None,
                         },
                     );
                 }
 
-                if let Err(e) = crate::playback::play_content(&self.state, content_id, path).await {
+                if let Err(e) =
+                    crate::playback::play_content(&self.state, content_id, path, None, None).await
+                {
                     tracing::error!("Failed to play content via command: {}", e);
                 }
             }
@@ -315,6 +406,9 @@ impl WebSocketClient {
                                 transformer_scripts: None,
                                 content_type: None,
                                 spot_reel_id: None,
+                                sha256: None,
+                                cue_points: None,
+                                gain_db: None,
                             },
                         );
                         Some(p.clone())
@@ -376,6 +470,9 @@ impl WebSocketClient {
                     // If failed to play, restore volume immediately
                     let _ = main_mpv.set_volume(current_vol);
                 } else {
+                    crate::as_run::report_start(&self.state, "dj_voice", None, None, Some(url));
+                    let state = self.state.clone();
+
                     // 3. Monitor for Completion (Async)
                     tokio::spawn(async move {
                         // Wait for start (give it up to 2s to become active)
@@ -406,6 +503,8 @@ impl WebSocketClient {
                             tracing::warn!("Voice track never started (idle timeout). Restoring.");
                         }
 
+                        crate::as_run::report_end(&state, "dj_voice", None);
+
                         tracing::info!("Voice track finished. Restoring volume to {}", current_vol);
                         if let Err(e) = main_mpv.set_volume(current_vol) {
                             tracing::error!("Failed to restore volume: {}", e);
@@ -413,6 +512,40 @@ impl WebSocketClient {
                     });
                 }
             }
+            NodeCommand::Interrupt {
+                message,
+                slate_path,
+                slate_gain_db,
+                timeout_secs,
+            } => {
+                crate::interrupt::start(
+                    &self.state,
+                    message,
+                    slate_path,
+                    slate_gain_db,
+                    timeout_secs,
+                )
+                .await;
+            }
+            NodeCommand::ClearInterrupt => {
+                tracing::info!("Command: Clear interrupt");
+                crate::interrupt::clear(&self.state).await;
+            }
+            NodeCommand::ShowAlert {
+                alert_id,
+                text,
+                severity,
+                expires_at,
+            } => {
+                crate::alerts::show(&self.state, alert_id, text, severity, expires_at).await;
+            }
+            NodeCommand::ClearAlert { alert_id } => {
+                crate::alerts::clear(&self.state, alert_id).await;
+            }
+            NodeCommand::ScanLibrary => {
+                tracing::info!("Command: Scan library");
+                tokio::spawn(crate::library::run(self.state.clone()));
+            }
         }
 
         Ok(())
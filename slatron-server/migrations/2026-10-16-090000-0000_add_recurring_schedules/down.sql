@@ -0,0 +1,32 @@
+-- Revert: recreate schedules with the original CHECK constraint (without 'recurring')
+PRAGMA foreign_keys=off;
+
+DELETE FROM schedule_blocks WHERE schedule_id IN (SELECT id FROM schedules WHERE schedule_type = 'recurring');
+DELETE FROM node_schedules WHERE schedule_id IN (SELECT id FROM schedules WHERE schedule_type = 'recurring');
+DELETE FROM schedules WHERE schedule_type = 'recurring';
+
+ALTER TABLE schedule_blocks DROP COLUMN rrule;
+
+CREATE TABLE schedules_old (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name VARCHAR(255) NOT NULL,
+    description TEXT,
+    schedule_type VARCHAR(50) NOT NULL CHECK(schedule_type IN ('weekly', 'one_off')),
+    priority INTEGER NOT NULL,
+    is_active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    dj_id INTEGER REFERENCES dj_profiles(id)
+);
+
+INSERT INTO schedules_old (id, name, description, schedule_type, priority, is_active, created_at, updated_at, dj_id)
+    SELECT id, name, description, schedule_type, priority, is_active, created_at, updated_at, dj_id
+    FROM schedules;
+
+DROP TABLE schedules;
+
+ALTER TABLE schedules_old RENAME TO schedules;
+
+CREATE INDEX idx_schedules_priority ON schedules(priority);
+
+PRAGMA foreign_keys=on;
@@ -0,0 +1,30 @@
+-- SQLite does not support altering CHECK constraints, so we must recreate the table.
+-- This migration adds 'recurring' to the schedule_type CHECK constraint and an
+-- RFC 5545 RRULE column on schedule_blocks (anchored on specific_date).
+PRAGMA foreign_keys=off;
+
+CREATE TABLE schedules_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name VARCHAR(255) NOT NULL,
+    description TEXT,
+    schedule_type VARCHAR(50) NOT NULL CHECK(schedule_type IN ('weekly', 'one_off', 'recurring')),
+    priority INTEGER NOT NULL,
+    is_active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    dj_id INTEGER REFERENCES dj_profiles(id)
+);
+
+INSERT INTO schedules_new (id, name, description, schedule_type, priority, is_active, created_at, updated_at, dj_id)
+    SELECT id, name, description, schedule_type, priority, is_active, created_at, updated_at, dj_id
+    FROM schedules;
+
+DROP TABLE schedules;
+
+ALTER TABLE schedules_new RENAME TO schedules;
+
+CREATE INDEX idx_schedules_priority ON schedules(priority);
+
+ALTER TABLE schedule_blocks ADD COLUMN rrule TEXT;
+
+PRAGMA foreign_keys=on;
@@ -0,0 +1,2 @@
+-- IANA timezone the node's schedule is laid out in; NULL falls back to the global 'timezone' setting
+ALTER TABLE nodes ADD COLUMN timezone TEXT;
@@ -0,0 +1 @@
+ALTER TABLE schedule_blocks DROP COLUMN duration_secs;
@@ -0,0 +1,2 @@
+-- Optional second-precision duration; overrides duration_minutes when set (e.g. short interstitials)
+ALTER TABLE schedule_blocks ADD COLUMN duration_secs INTEGER;
@@ -0,0 +1,3 @@
+DROP TABLE block_rundowns;
+ALTER TABLE content_items DROP COLUMN last_played_at;
+ALTER TABLE schedule_blocks DROP COLUMN fill_rule;
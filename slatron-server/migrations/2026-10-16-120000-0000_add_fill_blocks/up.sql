@@ -0,0 +1,17 @@
+-- Smart fill blocks: a JSON content query plus rotation policy instead of a single content_id
+ALTER TABLE schedule_blocks ADD COLUMN fill_rule TEXT;
+
+-- Used by the least-recently-played rotation
+ALTER TABLE content_items ADD COLUMN last_played_at TIMESTAMP;
+
+-- Concrete run-down generated for each airing of a fill block, so it stays stable between polls
+CREATE TABLE block_rundowns (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    block_id INTEGER NOT NULL REFERENCES schedule_blocks(id) ON DELETE CASCADE,
+    occurrence_date DATE NOT NULL,
+    fill_rule TEXT NOT NULL,
+    duration_secs INTEGER NOT NULL,
+    items TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(block_id, occurrence_date)
+);
@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_as_run_node_started;
+DROP TABLE IF EXISTS as_run;
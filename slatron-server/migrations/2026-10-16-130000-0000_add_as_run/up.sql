@@ -0,0 +1,14 @@
+-- What each node actually aired, one row per item from start to end
+CREATE TABLE as_run (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    node_id INTEGER NOT NULL REFERENCES nodes(id) ON DELETE CASCADE,
+    kind TEXT NOT NULL CHECK(kind IN ('content', 'bumper', 'dj_voice', 'spot_reel_item')),
+    -- Not a foreign key: the log must outlive deleted content
+    content_id INTEGER,
+    title TEXT,
+    path TEXT,
+    started_at TIMESTAMP NOT NULL,
+    ended_at TIMESTAMP
+);
+
+CREATE INDEX idx_as_run_node_started ON as_run(node_id, started_at);
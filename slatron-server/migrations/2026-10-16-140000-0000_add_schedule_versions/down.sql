@@ -0,0 +1,2 @@
+ALTER TABLE schedules DROP COLUMN published_version_id;
+DROP TABLE IF EXISTS schedule_versions;
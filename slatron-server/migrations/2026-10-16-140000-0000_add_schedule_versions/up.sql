@@ -0,0 +1,42 @@
+-- Published, immutable snapshots of a schedule's blocks. `schedule_blocks` becomes the
+-- editable draft; nodes only ever see the version a schedule points at.
+CREATE TABLE schedule_versions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    schedule_id INTEGER NOT NULL REFERENCES schedules(id) ON DELETE CASCADE,
+    version INTEGER NOT NULL,
+    -- JSON array of the schedule's blocks at publish time
+    blocks TEXT NOT NULL,
+    note TEXT,
+    published_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    published_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(schedule_id, version)
+);
+
+ALTER TABLE schedules ADD COLUMN published_version_id INTEGER REFERENCES schedule_versions(id);
+
+-- Existing schedules keep airing: their current blocks become version 1
+INSERT INTO schedule_versions (schedule_id, version, blocks, note)
+SELECT s.id, 1, (
+    SELECT json_group_array(json_object(
+        'id', b.id,
+        'schedule_id', b.schedule_id,
+        'content_id', b.content_id,
+        'day_of_week', b.day_of_week,
+        'specific_date', b.specific_date,
+        'start_time', b.start_time,
+        'duration_minutes', b.duration_minutes,
+        'script_id', b.script_id,
+        'created_at', replace(b.created_at, ' ', 'T'),
+        'updated_at', replace(b.updated_at, ' ', 'T'),
+        'dj_id', b.dj_id,
+        'rrule', b.rrule,
+        'duration_secs', b.duration_secs,
+        'fill_rule', b.fill_rule
+    ))
+    FROM (SELECT * FROM schedule_blocks WHERE schedule_id = s.id ORDER BY id) b
+), 'Initial version'
+FROM schedules s;
+
+UPDATE schedules SET published_version_id = (
+    SELECT v.id FROM schedule_versions v WHERE v.schedule_id = schedules.id AND v.version = 1
+);
@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_schedule_exceptions_schedule_dates;
+DROP TABLE IF EXISTS schedule_exceptions;
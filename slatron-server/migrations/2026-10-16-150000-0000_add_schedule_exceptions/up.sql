@@ -0,0 +1,16 @@
+-- Holiday and blackout dates: on these days a schedule is skipped or another one airs in its place
+CREATE TABLE schedule_exceptions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    schedule_id INTEGER NOT NULL REFERENCES schedules(id) ON DELETE CASCADE,
+    start_date DATE NOT NULL,
+    -- Inclusive
+    end_date DATE NOT NULL,
+    action TEXT NOT NULL CHECK(action IN ('skip', 'replace')),
+    replacement_schedule_id INTEGER REFERENCES schedules(id) ON DELETE CASCADE,
+    note TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CHECK(end_date >= start_date),
+    CHECK((action = 'replace') = (replacement_schedule_id IS NOT NULL))
+);
+
+CREATE INDEX idx_schedule_exceptions_schedule_dates ON schedule_exceptions(schedule_id, start_date, end_date);
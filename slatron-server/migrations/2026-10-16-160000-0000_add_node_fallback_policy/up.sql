@@ -0,0 +1,2 @@
+-- JSON fallback policy for when nothing is scheduled; NULL falls back to the global 'fallback_policy' setting
+ALTER TABLE nodes ADD COLUMN fallback_policy TEXT;
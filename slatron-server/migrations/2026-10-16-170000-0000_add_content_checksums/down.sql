@@ -0,0 +1,2 @@
+ALTER TABLE nodes DROP COLUMN cache_status;
+ALTER TABLE content_items DROP COLUMN sha256;
@@ -0,0 +1,4 @@
+-- Hex SHA-256 of the media behind content_path; nodes verify prefetched copies against it
+ALTER TABLE content_items ADD COLUMN sha256 TEXT;
+-- JSON asset cache status from the node's last heartbeat
+ALTER TABLE nodes ADD COLUMN cache_status TEXT;
@@ -0,0 +1,2 @@
+ALTER TABLE schedule_blocks DROP COLUMN break_policy;
+ALTER TABLE content_items DROP COLUMN cue_points;
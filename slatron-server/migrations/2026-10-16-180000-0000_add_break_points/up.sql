@@ -0,0 +1,4 @@
+-- JSON array of cue points ({"at_secs", "label", "source"}) where breaks may be taken
+ALTER TABLE content_items ADD COLUMN cue_points TEXT;
+-- JSON BreakPolicy; when set, the block's content breaks for a spot reel at each cue point
+ALTER TABLE schedule_blocks ADD COLUMN break_policy TEXT;
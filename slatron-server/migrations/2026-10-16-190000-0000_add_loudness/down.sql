@@ -0,0 +1,6 @@
+ALTER TABLE bumpers DROP COLUMN loudness_analyzed_at;
+ALTER TABLE bumpers DROP COLUMN true_peak_dbtp;
+ALTER TABLE bumpers DROP COLUMN loudness_lufs;
+ALTER TABLE content_items DROP COLUMN loudness_analyzed_at;
+ALTER TABLE content_items DROP COLUMN true_peak_dbtp;
+ALTER TABLE content_items DROP COLUMN loudness_lufs;
@@ -0,0 +1,8 @@
+-- EBU R128 measurements from the loudness analysis job; analyzed_at is set even when
+-- measuring fails, so failures aren't retried until the media changes
+ALTER TABLE content_items ADD COLUMN loudness_lufs REAL;
+ALTER TABLE content_items ADD COLUMN true_peak_dbtp REAL;
+ALTER TABLE content_items ADD COLUMN loudness_analyzed_at TIMESTAMP;
+ALTER TABLE bumpers ADD COLUMN loudness_lufs REAL;
+ALTER TABLE bumpers ADD COLUMN true_peak_dbtp REAL;
+ALTER TABLE bumpers ADD COLUMN loudness_analyzed_at TIMESTAMP;
@@ -0,0 +1,2 @@
+ALTER TABLE schedule_blocks DROP COLUMN transition;
+ALTER TABLE content_items DROP COLUMN cue_out_secs;
@@ -0,0 +1,4 @@
+-- Seconds into the media where the next item may start, leaving the outro to overlap it
+ALTER TABLE content_items ADD COLUMN cue_out_secs REAL;
+-- JSON Transition; overrides the global `transition` setting for segues out of the block's items
+ALTER TABLE schedule_blocks ADD COLUMN transition TEXT;
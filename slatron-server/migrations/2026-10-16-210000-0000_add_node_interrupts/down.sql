@@ -0,0 +1,21 @@
+CREATE TABLE as_run_old (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    node_id INTEGER NOT NULL REFERENCES nodes(id) ON DELETE CASCADE,
+    kind TEXT NOT NULL CHECK(kind IN ('content', 'bumper', 'dj_voice', 'spot_reel_item', 'break')),
+    content_id INTEGER,
+    title TEXT,
+    path TEXT,
+    started_at TIMESTAMP NOT NULL,
+    ended_at TIMESTAMP
+);
+
+INSERT INTO as_run_old
+    SELECT id, node_id, kind, content_id, title, path, started_at, ended_at FROM as_run
+    WHERE kind IN ('content', 'bumper', 'dj_voice', 'spot_reel_item', 'break');
+DROP INDEX IF EXISTS idx_as_run_node_started;
+DROP TABLE as_run;
+ALTER TABLE as_run_old RENAME TO as_run;
+
+CREATE INDEX idx_as_run_node_started ON as_run(node_id, started_at);
+
+ALTER TABLE nodes DROP COLUMN interrupt_status;
@@ -0,0 +1,22 @@
+-- JSON interrupt state from the node's last heartbeat; NULL while it isn't interrupted
+ALTER TABLE nodes ADD COLUMN interrupt_status TEXT;
+
+-- Interrupts are logged too. SQLite can't alter a CHECK, so rebuild the table
+CREATE TABLE as_run_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    node_id INTEGER NOT NULL REFERENCES nodes(id) ON DELETE CASCADE,
+    kind TEXT NOT NULL CHECK(kind IN ('content', 'bumper', 'dj_voice', 'spot_reel_item', 'break', 'interrupt')),
+    -- Not a foreign key: the log must outlive deleted content
+    content_id INTEGER,
+    title TEXT,
+    path TEXT,
+    started_at TIMESTAMP NOT NULL,
+    ended_at TIMESTAMP
+);
+
+INSERT INTO as_run_new SELECT id, node_id, kind, content_id, title, path, started_at, ended_at FROM as_run;
+DROP INDEX IF EXISTS idx_as_run_node_started;
+DROP TABLE as_run;
+ALTER TABLE as_run_new RENAME TO as_run;
+
+CREATE INDEX idx_as_run_node_started ON as_run(node_id, started_at);
@@ -0,0 +1,3 @@
+ALTER TABLE nodes DROP COLUMN alert_areas;
+DROP INDEX IF EXISTS idx_alerts_cleared;
+DROP TABLE IF EXISTS alerts;
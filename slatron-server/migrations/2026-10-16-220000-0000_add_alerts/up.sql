@@ -0,0 +1,27 @@
+-- CAP alerts received from feeds or pushed to the server
+CREATE TABLE alerts (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    -- CAP identifier, unique per sender
+    identifier TEXT NOT NULL,
+    sender TEXT NOT NULL,
+    sent_at TIMESTAMP NOT NULL,
+    event TEXT NOT NULL,
+    severity TEXT NOT NULL,
+    urgency TEXT,
+    headline TEXT,
+    description TEXT,
+    instruction TEXT,
+    -- JSON array of areas ({"desc", "geocodes"})
+    areas TEXT NOT NULL DEFAULT '[]',
+    expires_at TIMESTAMP,
+    received_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    -- Set when the alert expires or is cancelled or updated; nodes stop showing it
+    cleared_at TIMESTAMP,
+    UNIQUE(sender, identifier)
+);
+
+CREATE INDEX idx_alerts_cleared ON alerts(cleared_at);
+
+-- JSON array of CAP area codes ("SAME=048439") or area names the node shows alerts for;
+-- NULL uses the areas of the global cap_alerts setting
+ALTER TABLE nodes ADD COLUMN alert_areas TEXT;
@@ -0,0 +1,6 @@
+DELETE FROM node_schedules WHERE excluded = 1;
+ALTER TABLE node_schedules DROP COLUMN excluded;
+ALTER TABLE nodes DROP COLUMN channel_id;
+DROP INDEX IF EXISTS idx_channel_schedules_channel_id;
+DROP TABLE IF EXISTS channel_schedules;
+DROP TABLE IF EXISTS channels;
@@ -0,0 +1,32 @@
+-- Channels: groups of nodes sharing a schedule lineup, fallback and timezone
+CREATE TABLE channels (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE,
+    description TEXT,
+    -- Member nodes without their own timezone use this one
+    timezone TEXT,
+    -- JSON FallbackPolicy for member nodes without their own
+    fallback_policy TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Channel Schedule Assignments Table
+CREATE TABLE channel_schedules (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    channel_id INTEGER NOT NULL,
+    schedule_id INTEGER NOT NULL,
+    priority INTEGER,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (channel_id) REFERENCES channels(id) ON DELETE CASCADE,
+    FOREIGN KEY (schedule_id) REFERENCES schedules(id) ON DELETE CASCADE,
+    UNIQUE(channel_id, schedule_id)
+);
+
+CREATE INDEX idx_channel_schedules_channel_id ON channel_schedules(channel_id);
+
+-- Nodes inherit their channel's lineup; their own assignments override it
+ALTER TABLE nodes ADD COLUMN channel_id INTEGER REFERENCES channels(id) ON DELETE SET NULL;
+
+-- Drops a schedule the node's channel assigns from this node's lineup
+ALTER TABLE node_schedules ADD COLUMN excluded BOOLEAN NOT NULL DEFAULT 0;
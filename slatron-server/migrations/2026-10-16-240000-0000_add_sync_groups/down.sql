@@ -0,0 +1,2 @@
+ALTER TABLE nodes DROP COLUMN sync_status;
+ALTER TABLE channels DROP COLUMN sync_playback;
@@ -0,0 +1,5 @@
+-- Members of a sync group channel play in lockstep against the server's clock
+ALTER TABLE channels ADD COLUMN sync_playback BOOLEAN NOT NULL DEFAULT 0;
+
+-- JSON clock offset and playback drift from the last heartbeat
+ALTER TABLE nodes ADD COLUMN sync_status TEXT;
@@ -0,0 +1,3 @@
+ALTER TABLE nodes DROP COLUMN clock_warning;
+ALTER TABLE nodes DROP COLUMN clock_rtt_ms;
+ALTER TABLE nodes DROP COLUMN clock_offset_ms;
@@ -0,0 +1,6 @@
+-- Node clock offset (server minus node) and round trip from the last heartbeat
+ALTER TABLE nodes ADD COLUMN clock_offset_ms INTEGER;
+ALTER TABLE nodes ADD COLUMN clock_rtt_ms INTEGER;
+
+-- Set while the offset exceeds the clock setting's warn_ms
+ALTER TABLE nodes ADD COLUMN clock_warning BOOLEAN NOT NULL DEFAULT 0;
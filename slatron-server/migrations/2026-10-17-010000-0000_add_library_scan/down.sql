@@ -0,0 +1,4 @@
+ALTER TABLE nodes DROP COLUMN library_scan;
+ALTER TABLE content_items DROP COLUMN missing_since;
+ALTER TABLE content_items DROP COLUMN file_modified_at;
+ALTER TABLE content_items DROP COLUMN file_size_bytes;
@@ -0,0 +1,7 @@
+-- Library scans: the file as last reported by a node, and when it went missing
+ALTER TABLE content_items ADD COLUMN file_size_bytes BIGINT;
+ALTER TABLE content_items ADD COLUMN file_modified_at TIMESTAMP;
+ALTER TABLE content_items ADD COLUMN missing_since TIMESTAMP;
+
+-- JSON report of the node's last library scan
+ALTER TABLE nodes ADD COLUMN library_scan TEXT;
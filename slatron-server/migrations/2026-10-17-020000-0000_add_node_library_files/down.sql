@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_node_library_files_content_id;
+DROP TABLE IF EXISTS node_library_files;
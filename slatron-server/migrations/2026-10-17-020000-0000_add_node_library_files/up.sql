@@ -0,0 +1,13 @@
+-- Which nodes' library scans found each content item's file. An item only goes missing
+-- once no node that scans its folder has it.
+CREATE TABLE node_library_files (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    node_id INTEGER NOT NULL,
+    content_id INTEGER NOT NULL,
+    seen_at TIMESTAMP NOT NULL,
+    FOREIGN KEY (node_id) REFERENCES nodes(id) ON DELETE CASCADE,
+    FOREIGN KEY (content_id) REFERENCES content_items(id) ON DELETE CASCADE,
+    UNIQUE(node_id, content_id)
+);
+
+CREATE INDEX idx_node_library_files_content_id ON node_library_files(content_id);
@@ -0,0 +1,110 @@
+use crate::models::{Alert, User};
+use crate::services::alert_service::{self, CapSettings, Ingested};
+use crate::AppState;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use chrono::Utc;
+use diesel::prelude::*;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct AlertsQuery {
+    /// Include cleared alerts
+    #[serde(default)]
+    pub all: bool,
+}
+
+pub async fn list_alerts(
+    State(state): State<AppState>,
+    Query(params): Query<AlertsQuery>,
+) -> Result<Json<Vec<Alert>>, StatusCode> {
+    use crate::schema::alerts::dsl::*;
+
+    let mut conn = state
+        .db
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let mut query = alerts.order(id.desc()).into_boxed();
+    if !params.all {
+        query = query.filter(cleared_at.is_null());
+    }
+    let results = query
+        .select(Alert::as_select())
+        .load(&mut conn)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(results))
+}
+
+/// Take a CAP 1.2 document (or feed of them) pushed by an alerting system.
+pub async fn receive_cap(
+    State(state): State<AppState>,
+    Extension(user): Extension<User>,
+    body: String,
+) -> Result<Json<Ingested>, StatusCode> {
+    if !user.is_editor() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let cap_alerts = alert_service::parse_alerts(&body).map_err(|e| {
+        tracing::warn!("Rejected CAP document: {}", e);
+        StatusCode::BAD_REQUEST
+    })?;
+
+    let mut ingested = Ingested::default();
+    let settings = {
+        let mut conn = state
+            .db
+            .get()
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        let settings = CapSettings::load(&mut conn);
+        let now = Utc::now();
+        for alert in &cap_alerts {
+            alert_service::ingest(&mut conn, alert, &settings, now, &mut ingested).map_err(
+                |e| {
+                    tracing::error!("Failed to store CAP alert {}: {}", alert.identifier, e);
+                    StatusCode::INTERNAL_SERVER_ERROR
+                },
+            )?;
+        }
+        settings
+    };
+
+    if let Err(e) = alert_service::dispatch(&state, &ingested, &settings).await {
+        tracing::error!("Failed to push CAP alerts: {}", e);
+    }
+    Ok(Json(ingested))
+}
+
+pub async fn clear_alert(
+    State(state): State<AppState>,
+    Extension(user): Extension<User>,
+    Path(alert_id): Path<i32>,
+) -> Result<StatusCode, StatusCode> {
+    if !user.is_editor() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let cleared = {
+        let mut conn = state
+            .db
+            .get()
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        alert_service::clear(&mut conn, alert_id).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    };
+    if !cleared {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    tracing::info!("Alert {} cleared (by {})", alert_id, user.username);
+
+    let ingested = Ingested {
+        cleared: vec![alert_id],
+        ..Default::default()
+    };
+    alert_service::dispatch(&state, &ingested, &CapSettings::default())
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(StatusCode::NO_CONTENT)
+}
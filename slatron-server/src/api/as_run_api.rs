@@ -0,0 +1,106 @@
+use crate::models::AsRunEntry;
+use crate::services::as_run_service::{self, AsRunDiff};
+use crate::AppState;
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct AsRunQuery {
+    /// Defaults to 24 hours before `to`
+    pub from: Option<DateTime<Utc>>,
+    /// Defaults to now
+    pub to: Option<DateTime<Utc>>,
+    pub kind: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct AsRunDiffQuery {
+    /// Local date on the node; defaults to today
+    pub date: Option<NaiveDate>,
+}
+
+fn load_entries(
+    state: &AppState,
+    node_id: i32,
+    params: &AsRunQuery,
+) -> Result<Vec<AsRunEntry>, StatusCode> {
+    let to = params.to.unwrap_or_else(Utc::now);
+    let from = params.from.unwrap_or(to - Duration::hours(24));
+    if from >= to {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if let Some(k) = params.kind.as_deref() {
+        if !as_run_service::KINDS.contains(&k) {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+    }
+
+    let mut conn = state
+        .db
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    as_run_service::entries_between(
+        &mut conn,
+        node_id,
+        from.naive_utc(),
+        to.naive_utc(),
+        params.kind.as_deref(),
+    )
+    .map_err(|e| {
+        tracing::error!("Failed to load as-run log: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+pub async fn list_as_run(
+    State(state): State<AppState>,
+    Path(node_id): Path<i32>,
+    Query(params): Query<AsRunQuery>,
+) -> Result<Json<Vec<AsRunEntry>>, StatusCode> {
+    Ok(Json(load_entries(&state, node_id, &params)?))
+}
+
+pub async fn export_as_run_csv(
+    State(state): State<AppState>,
+    Path(node_id): Path<i32>,
+    Query(params): Query<AsRunQuery>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let entries = load_entries(&state, node_id, &params)?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"as-run-node-{}.csv\"", node_id),
+            ),
+        ],
+        as_run_service::to_csv(&entries),
+    ))
+}
+
+pub async fn get_as_run_diff(
+    State(state): State<AppState>,
+    Path(node_id): Path<i32>,
+    Query(params): Query<AsRunDiffQuery>,
+) -> Result<Json<AsRunDiff>, StatusCode> {
+    let mut conn = state
+        .db
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let date = params.date.unwrap_or_else(|| Utc::now().date_naive());
+    let diff = as_run_service::planned_vs_aired(&mut conn, node_id, date).map_err(|e| {
+        tracing::error!("Failed to diff as-run log: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(diff))
+}
@@ -1,6 +1,7 @@
 use crate::models::{
     Bumper, BumperBack, NewBumper, NewBumperBack, UpdateBumper, UpdateBumperBack, User,
 };
+use crate::services::loudness_service;
 use crate::AppState;
 use axum::extract::Multipart;
 use axum::{
@@ -29,13 +30,36 @@ pub struct RenderAllResponse {
     pub errors: Vec<String>,
 }
 
+/// A bumper with the gain nodes apply to bring it to the station's loudness target.
+#[derive(Serialize)]
+pub struct BumperWithGain {
+    #[serde(flatten)]
+    pub bumper: Bumper,
+    pub gain_db: Option<f64>,
+}
+
+impl BumperWithGain {
+    fn new(bumper: Bumper, target_lufs: Option<f64>) -> Self {
+        Self {
+            gain_db: loudness_service::stored_gain_db(
+                bumper.loudness_lufs,
+                bumper.true_peak_dbtp,
+                target_lufs,
+            ),
+            bumper,
+        }
+    }
+}
+
 #[derive(Deserialize)]
 pub struct FetchBackRequest {
     pub url: String,
     pub name: String,
 }
 
-pub async fn list_bumpers(State(state): State<AppState>) -> Result<Json<Vec<Bumper>>, StatusCode> {
+pub async fn list_bumpers(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<BumperWithGain>>, StatusCode> {
     use crate::schema::bumpers::dsl::*;
 
     let mut conn = state
@@ -43,18 +67,24 @@ pub async fn list_bumpers(State(state): State<AppState>) -> Result<Json<Vec<Bump
         .get()
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    let results = bumpers
+    let results: Vec<Bumper> = bumpers
         .select(Bumper::as_select())
         .load(&mut conn)
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    Ok(Json(results))
+    let target_lufs = loudness_service::target_lufs(&mut conn);
+    Ok(Json(
+        results
+            .into_iter()
+            .map(|bumper| BumperWithGain::new(bumper, target_lufs))
+            .collect(),
+    ))
 }
 
 pub async fn get_bumper(
     State(state): State<AppState>,
     Path(bumper_id): Path<i32>,
-) -> Result<Json<Bumper>, StatusCode> {
+) -> Result<Json<BumperWithGain>, StatusCode> {
     use crate::schema::bumpers::dsl::*;
 
     let mut conn = state
@@ -67,7 +97,8 @@ pub async fn get_bumper(
         .first(&mut conn)
         .map_err(|_| StatusCode::NOT_FOUND)?;
 
-    Ok(Json(bumper))
+    let target_lufs = loudness_service::target_lufs(&mut conn);
+    Ok(Json(BumperWithGain::new(bumper, target_lufs)))
 }
 
 pub async fn create_bumper(
@@ -138,6 +169,19 @@ pub async fn update_bumper(
         }
     }
 
+    // A new rendering needs measuring again
+    if let Some(new_path) = &updates.rendered_path {
+        let current_path: Option<String> = bumpers
+            .filter(id.eq(bumper_id))
+            .select(rendered_path)
+            .first(&mut conn)
+            .map_err(|_| StatusCode::NOT_FOUND)?;
+        if &current_path != new_path {
+            loudness_service::reset_bumper(&mut conn, bumper_id)
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        }
+    }
+
     let bumper = diesel::update(bumpers.filter(id.eq(bumper_id)))
         .set(&updates)
         .returning(Bumper::as_select())
@@ -0,0 +1,332 @@
+use crate::api::nodes_api::{assign_schedules, UpdateNodeSchedulesRequest};
+use crate::models::{Channel, NewChannel, Schedule, UpdateChannel, User};
+use crate::services::channel_service::{self, Target};
+use crate::services::fallback_service::FallbackPolicy;
+use crate::services::interrupt_service;
+use crate::websocket::NodeCommand;
+use crate::AppState;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize)]
+pub struct ChannelDetail {
+    #[serde(flatten)]
+    pub channel: Channel,
+    pub node_ids: Vec<i32>,
+    /// The channel's lineup, highest priority first
+    pub schedules: Vec<Schedule>,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateChannelRequest {
+    pub name: Option<String>,
+    /// Empty string clears it, as do `timezone` and `fallback_policy`
+    pub description: Option<String>,
+    pub timezone: Option<String>,
+    pub fallback_policy: Option<String>,
+    pub sync_playback: Option<bool>,
+}
+
+#[derive(Deserialize)]
+pub struct ChannelNodesRequest {
+    /// The channel's members; nodes left out that were in it leave it
+    pub node_ids: Vec<i32>,
+}
+
+#[derive(Serialize)]
+pub struct ChannelCommandResponse {
+    /// Connected member nodes the command was sent to
+    pub node_ids: Vec<i32>,
+}
+
+fn validate(timezone: Option<&str>, fallback_policy: Option<&str>) -> Result<(), StatusCode> {
+    if let Some(tz_name) = timezone.filter(|t| !t.is_empty()) {
+        if tz_name.parse::<chrono_tz::Tz>().is_err() {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+    }
+    if let Some(policy) = fallback_policy.filter(|p| !p.is_empty()) {
+        if FallbackPolicy::parse(policy).is_err() {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+    }
+    Ok(())
+}
+
+fn load_detail(
+    conn: &mut crate::db::DbConnection,
+    channel: Channel,
+) -> Result<ChannelDetail, StatusCode> {
+    let channel_id = channel.id.unwrap_or_default();
+    let node_ids = channel_service::members(conn, channel_id)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let schedules = channel_service::lineup(conn, Target::Channel(channel_id))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .into_iter()
+        .map(|a| a.schedule)
+        .collect();
+
+    Ok(ChannelDetail {
+        channel,
+        node_ids,
+        schedules,
+    })
+}
+
+/// Have a channel's members refetch their schedule.
+async fn notify_members(state: &AppState, channel_id: i32) -> Result<(), StatusCode> {
+    let node_ids = {
+        let mut conn = state
+            .db
+            .get()
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        channel_service::members(&mut conn, channel_id)
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    };
+    channel_service::notify(state, &node_ids).await;
+    Ok(())
+}
+
+pub async fn list_channels(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<ChannelDetail>>, StatusCode> {
+    use crate::schema::channels::dsl::*;
+
+    let mut conn = state
+        .db
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let results: Vec<Channel> = channels
+        .order(name.asc())
+        .select(Channel::as_select())
+        .load(&mut conn)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let details = results
+        .into_iter()
+        .map(|channel| load_detail(&mut conn, channel))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Json(details))
+}
+
+pub async fn get_channel(
+    State(state): State<AppState>,
+    Path(channel_id): Path<i32>,
+) -> Result<Json<ChannelDetail>, StatusCode> {
+    use crate::schema::channels::dsl::*;
+
+    let mut conn = state
+        .db
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let channel: Channel = channels
+        .filter(id.eq(channel_id))
+        .select(Channel::as_select())
+        .first(&mut conn)
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+
+    Ok(Json(load_detail(&mut conn, channel)?))
+}
+
+pub async fn create_channel(
+    State(state): State<AppState>,
+    Extension(user): Extension<User>,
+    Json(new_channel): Json<NewChannel>,
+) -> Result<Json<Channel>, StatusCode> {
+    if !user.is_editor() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    validate(
+        new_channel.timezone.as_deref(),
+        new_channel.fallback_policy.as_deref(),
+    )?;
+    use crate::schema::channels::dsl::*;
+
+    let mut conn = state
+        .db
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let channel = diesel::insert_into(channels)
+        .values(&new_channel)
+        .returning(Channel::as_select())
+        .get_result(&mut conn)
+        .map_err(|e| {
+            tracing::error!("Failed to create channel: {}", e);
+            StatusCode::CONFLICT
+        })?;
+
+    Ok(Json(channel))
+}
+
+pub async fn update_channel(
+    State(state): State<AppState>,
+    Extension(user): Extension<User>,
+    Path(channel_id): Path<i32>,
+    Json(req): Json<UpdateChannelRequest>,
+) -> Result<Json<Channel>, StatusCode> {
+    if !user.is_editor() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    validate(req.timezone.as_deref(), req.fallback_policy.as_deref())?;
+    use crate::schema::channels::dsl::*;
+
+    let mut conn = state
+        .db
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let clearable = |v: Option<String>| v.map(|v| Some(v).filter(|v| !v.is_empty()));
+    let members_affected =
+        req.timezone.is_some() || req.fallback_policy.is_some() || req.sync_playback.is_some();
+    let changes = UpdateChannel {
+        name: req.name,
+        description: clearable(req.description),
+        timezone: clearable(req.timezone),
+        fallback_policy: clearable(req.fallback_policy),
+        sync_playback: req.sync_playback,
+    };
+
+    let channel = diesel::update(channels.filter(id.eq(channel_id)))
+        .set((&changes, updated_at.eq(chrono::Utc::now().naive_utc())))
+        .returning(Channel::as_select())
+        .get_result(&mut conn)
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+
+    // Members' local day, fallback or sync may have changed
+    if members_affected {
+        notify_members(&state, channel_id).await?;
+    }
+
+    Ok(Json(channel))
+}
+
+pub async fn delete_channel(
+    State(state): State<AppState>,
+    Extension(user): Extension<User>,
+    Path(channel_id): Path<i32>,
+) -> Result<StatusCode, StatusCode> {
+    if !user.is_editor() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    use crate::schema::{channel_schedules, channels, nodes};
+
+    let mut conn = state
+        .db
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let node_ids = channel_service::members(&mut conn, channel_id)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        diesel::update(nodes::table.filter(nodes::channel_id.eq(channel_id)))
+            .set(nodes::channel_id.eq(None::<i32>))
+            .execute(conn)?;
+        diesel::delete(
+            channel_schedules::table.filter(channel_schedules::channel_id.eq(channel_id)),
+        )
+        .execute(conn)?;
+        diesel::delete(channels::table.filter(channels::id.eq(channel_id))).execute(conn)?;
+        Ok(())
+    })
+    .map_err(|e| {
+        tracing::error!("Failed to delete channel: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    // Former members lose the channel's lineup
+    channel_service::notify(&state, &node_ids).await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Set which nodes are in a channel
+pub async fn update_channel_nodes(
+    State(state): State<AppState>,
+    Extension(user): Extension<User>,
+    Path(path_channel_id): Path<i32>,
+    Json(req): Json<ChannelNodesRequest>,
+) -> Result<StatusCode, StatusCode> {
+    if !user.is_editor() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    use crate::schema::nodes::dsl::*;
+
+    let mut conn = state
+        .db
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let mut affected = channel_service::members(&mut conn, path_channel_id)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        diesel::update(nodes.filter(channel_id.eq(path_channel_id)))
+            .set(channel_id.eq(None::<i32>))
+            .execute(conn)?;
+        diesel::update(nodes.filter(id.eq_any(&req.node_ids)))
+            .set(channel_id.eq(Some(path_channel_id)))
+            .execute(conn)?;
+        Ok(())
+    })
+    .map_err(|e| {
+        tracing::error!("Failed to update channel nodes: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    affected.extend(&req.node_ids);
+    affected.sort_unstable();
+    affected.dedup();
+    channel_service::notify(&state, &affected).await;
+
+    Ok(StatusCode::OK)
+}
+
+pub async fn update_channel_schedules(
+    State(state): State<AppState>,
+    Extension(user): Extension<User>,
+    Path(channel_id): Path<i32>,
+    Json(req): Json<UpdateNodeSchedulesRequest>,
+) -> Result<StatusCode, StatusCode> {
+    if !user.is_editor() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    assign_schedules(&state, Target::Channel(channel_id), &req).await
+}
+
+/// Send a playback command to every connected node in a channel
+pub async fn send_channel_command(
+    State(state): State<AppState>,
+    Extension(user): Extension<User>,
+    Path(channel_id): Path<i32>,
+    Json(command): Json<NodeCommand>,
+) -> Result<Json<ChannelCommandResponse>, StatusCode> {
+    if !user.is_editor() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let members = {
+        let mut conn = state
+            .db
+            .get()
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        channel_service::members(&mut conn, channel_id)
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    };
+    tracing::info!(
+        "Sending {:?} to channel {} (by {})",
+        command,
+        channel_id,
+        user.username
+    );
+
+    let node_ids = interrupt_service::send(&state, Some(&members), command).await;
+    Ok(Json(ChannelCommandResponse { node_ids }))
+}
@@ -1,4 +1,5 @@
 use crate::models::{ContentItem, NewContentItem, UpdateContentItem, User};
+use crate::services::{break_service, loudness_service};
 use crate::AppState;
 use axum::{
     extract::{Path, State},
@@ -28,11 +29,18 @@ pub async fn list_content(
 pub async fn create_content(
     State(state): State<AppState>,
     Extension(user): Extension<User>,
-    Json(new_item): Json<NewContentItem>,
+    Json(mut new_item): Json<NewContentItem>,
 ) -> Result<Json<ContentItem>, StatusCode> {
     if !user.is_editor() {
         return Err(StatusCode::FORBIDDEN);
     }
+    if let Some(sum) = &mut new_item.sha256 {
+        normalize_sha256(sum)?;
+    }
+    if let Some(cues) = &mut new_item.cue_points {
+        normalize_cue_points(cues)?;
+    }
+    validate_cue_out(new_item.cue_out_secs)?;
     use crate::schema::content_items;
 
     let mut conn = state
@@ -53,11 +61,18 @@ pub async fn update_content(
     State(state): State<AppState>,
     Extension(user): Extension<User>,
     Path(item_id): Path<i32>,
-    Json(updates): Json<UpdateContentItem>,
+    Json(mut updates): Json<UpdateContentItem>,
 ) -> Result<Json<ContentItem>, StatusCode> {
     if !user.is_editor() {
         return Err(StatusCode::FORBIDDEN);
     }
+    if let Some(Some(sum)) = &mut updates.sha256 {
+        normalize_sha256(sum)?;
+    }
+    if let Some(Some(cues)) = &mut updates.cue_points {
+        normalize_cue_points(cues)?;
+    }
+    validate_cue_out(updates.cue_out_secs.flatten())?;
     use crate::schema::content_items::dsl::*;
 
     let mut conn = state
@@ -65,6 +80,19 @@ pub async fn update_content(
         .get()
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
+    // New media needs measuring again
+    if let Some(new_path) = &updates.content_path {
+        let current_path: String = content_items
+            .filter(id.eq(item_id))
+            .select(content_path)
+            .first(&mut conn)
+            .map_err(|_| StatusCode::NOT_FOUND)?;
+        if &current_path != new_path {
+            loudness_service::reset_content(&mut conn, item_id)
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        }
+    }
+
     let item = diesel::update(content_items.filter(id.eq(item_id)))
         .set(&updates)
         .returning(ContentItem::as_select())
@@ -74,6 +102,57 @@ pub async fn update_content(
     Ok(Json(item))
 }
 
+/// Replace an item's chapter cue points with the chapters of its media. Manual cue
+/// points are kept.
+pub async fn detect_cue_points(
+    State(state): State<AppState>,
+    Extension(user): Extension<User>,
+    Path(item_id): Path<i32>,
+) -> Result<Json<ContentItem>, StatusCode> {
+    if !user.is_editor() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    use crate::schema::content_items::dsl::*;
+
+    let mut conn = state
+        .db
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let item: ContentItem = content_items
+        .filter(id.eq(item_id))
+        .select(ContentItem::as_select())
+        .first(&mut conn)
+        .optional()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let path = item.content_path.clone();
+    let chapters = tokio::task::spawn_blocking(move || break_service::detect_chapters(&path))
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .map_err(|e| {
+            tracing::warn!("Chapter detection failed for content {}: {}", item_id, e);
+            StatusCode::UNPROCESSABLE_ENTITY
+        })?;
+
+    let existing = item
+        .cue_points
+        .as_deref()
+        .and_then(|json| break_service::parse_cue_points(json).ok())
+        .unwrap_or_default();
+    let merged = serde_json::to_string(&break_service::merge_chapters(existing, chapters))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let item = diesel::update(content_items.filter(id.eq(item_id)))
+        .set(cue_points.eq(merged))
+        .returning(ContentItem::as_select())
+        .get_result(&mut conn)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(item))
+}
+
 pub async fn delete_content(
     State(state): State<AppState>,
     Extension(user): Extension<User>,
@@ -95,3 +174,31 @@ pub async fn delete_content(
 
     Ok(StatusCode::NO_CONTENT)
 }
+
+/// Checksums are stored as lowercase hex so nodes can compare them directly.
+fn normalize_sha256(sum: &mut String) -> Result<(), StatusCode> {
+    if sum.len() != 64 || !sum.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    *sum = sum.to_ascii_lowercase();
+    Ok(())
+}
+
+/// Cue points are stored sorted, so nodes can walk them in order.
+fn normalize_cue_points(cues: &mut String) -> Result<(), StatusCode> {
+    *cues = break_service::normalize_cue_points(cues).map_err(|e| {
+        tracing::warn!("Rejected cue points: {}", e);
+        StatusCode::BAD_REQUEST
+    })?;
+    Ok(())
+}
+
+fn validate_cue_out(secs: Option<f32>) -> Result<(), StatusCode> {
+    match secs {
+        Some(secs) if !secs.is_finite() || secs < 0.0 => {
+            tracing::warn!("Rejected cue-out point {}", secs);
+            Err(StatusCode::BAD_REQUEST)
+        }
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,136 @@
+use crate::api::versions_api;
+use crate::models::{NewScheduleException, ScheduleException, User};
+use crate::AppState;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use chrono::NaiveDate;
+use diesel::prelude::*;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct ExceptionRequest {
+    pub start_date: NaiveDate,
+    /// Inclusive; defaults to `start_date`
+    pub end_date: Option<NaiveDate>,
+    /// `skip` or `replace`
+    pub action: String,
+    pub replacement_schedule_id: Option<i32>,
+    pub note: Option<String>,
+}
+
+pub async fn list_exceptions(
+    State(state): State<AppState>,
+    Path(sched_id): Path<i32>,
+) -> Result<Json<Vec<ScheduleException>>, StatusCode> {
+    use crate::schema::schedule_exceptions::dsl::*;
+
+    let mut conn = state
+        .db
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let exceptions = schedule_exceptions
+        .filter(schedule_id.eq(sched_id))
+        .order(start_date.asc())
+        .select(ScheduleException::as_select())
+        .load(&mut conn)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(exceptions))
+}
+
+/// Add a skip or replace exception. Unlike block edits these take effect immediately,
+/// without publishing.
+pub async fn create_exception(
+    State(state): State<AppState>,
+    Extension(user): Extension<User>,
+    Path(sched_id): Path<i32>,
+    Json(req): Json<ExceptionRequest>,
+) -> Result<Json<ScheduleException>, StatusCode> {
+    if !user.is_editor() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let end = req.end_date.unwrap_or(req.start_date);
+    if end < req.start_date {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    match (req.action.as_str(), req.replacement_schedule_id) {
+        ("skip", None) => {}
+        ("replace", Some(rid)) if rid != sched_id => {}
+        _ => return Err(StatusCode::BAD_REQUEST),
+    }
+
+    let mut conn = state
+        .db
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    // Both the schedule and its replacement must exist
+    let wanted: Vec<i32> = std::iter::once(sched_id)
+        .chain(req.replacement_schedule_id)
+        .collect();
+    let found: i64 = {
+        use crate::schema::schedules::dsl::*;
+        schedules
+            .filter(id.eq_any(&wanted))
+            .count()
+            .get_result(&mut conn)
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    };
+    if found != wanted.len() as i64 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let exception = diesel::insert_into(crate::schema::schedule_exceptions::table)
+        .values(&NewScheduleException {
+            schedule_id: sched_id,
+            start_date: req.start_date,
+            end_date: end,
+            action: req.action,
+            replacement_schedule_id: req.replacement_schedule_id,
+            note: req.note,
+        })
+        .returning(ScheduleException::as_select())
+        .get_result(&mut conn)
+        .map_err(|e| {
+            tracing::error!("Failed to create schedule exception: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    versions_api::notify_nodes(&state, &mut conn, sched_id).await;
+    Ok(Json(exception))
+}
+
+pub async fn delete_exception(
+    State(state): State<AppState>,
+    Extension(user): Extension<User>,
+    Path((sched_id, exception_id)): Path<(i32, i32)>,
+) -> Result<StatusCode, StatusCode> {
+    if !user.is_editor() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    use crate::schema::schedule_exceptions::dsl::*;
+
+    let mut conn = state
+        .db
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let deleted = diesel::delete(
+        schedule_exceptions
+            .filter(id.eq(exception_id))
+            .filter(schedule_id.eq(sched_id)),
+    )
+    .execute(&mut conn)
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if deleted == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    versions_api::notify_nodes(&state, &mut conn, sched_id).await;
+    Ok(StatusCode::NO_CONTENT)
+}
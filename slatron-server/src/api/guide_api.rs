@@ -0,0 +1,85 @@
+use crate::services::xmltv_service::{self, MAX_GUIDE_DAYS};
+use crate::AppState;
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct GuideQuery {
+    /// Days to cover, starting today; defaults to 7
+    pub days: Option<i64>,
+    /// Comma-separated node IDs (combined guide only)
+    pub nodes: Option<String>,
+}
+
+fn render_guide(
+    state: &AppState,
+    node_ids: &[i32],
+    days: Option<i64>,
+) -> Result<String, StatusCode> {
+    let days = days.unwrap_or(7);
+    if !(1..=MAX_GUIDE_DAYS).contains(&days) || node_ids.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let mut conn = state
+        .db
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let guides = node_ids
+        .iter()
+        .map(|nid| xmltv_service::node_guide(&mut conn, *nid, days))
+        .collect::<anyhow::Result<Vec<_>>>()
+        .map_err(|e| match e.downcast_ref::<diesel::result::Error>() {
+            Some(diesel::result::Error::NotFound) => StatusCode::NOT_FOUND,
+            _ => {
+                tracing::error!("Failed to build guide: {:?}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        })?;
+
+    xmltv_service::render(&guides).map_err(|e| {
+        tracing::error!("Failed to render XMLTV: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+fn xml_response(xml: String) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "application/xml; charset=utf-8")],
+        xml,
+    )
+}
+
+/// XMLTV guide for a single node
+pub async fn get_node_xmltv(
+    State(state): State<AppState>,
+    Path(node_id): Path<i32>,
+    Query(params): Query<GuideQuery>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let xml = render_guide(&state, &[node_id], params.days)?;
+    Ok(xml_response(xml))
+}
+
+/// XMLTV guide with one channel per node in `nodes`
+pub async fn get_xmltv(
+    State(state): State<AppState>,
+    Query(params): Query<GuideQuery>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let node_ids = params
+        .nodes
+        .as_deref()
+        .unwrap_or_default()
+        .split(',')
+        .filter(|s| !s.trim().is_empty())
+        .map(|s| s.trim().parse::<i32>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let xml = render_guide(&state, &node_ids, params.days)?;
+    Ok(xml_response(xml))
+}
@@ -1,7 +1,12 @@
+pub mod alerts_api;
+pub mod as_run_api;
 pub mod auth_api;
 pub mod bumper_api;
+pub mod channels_api;
 pub mod content_api;
 pub mod dj_api;
+pub mod exceptions_api;
+pub mod guide_api;
 pub mod nodes_api;
 pub mod permissions_api;
 pub mod schedules_api;
@@ -9,6 +14,7 @@ pub mod scripts_api;
 pub mod settings_api;
 pub mod spot_reel_api;
 pub mod users_api;
+pub mod versions_api;
 
 use crate::AppState;
 use axum::{
@@ -22,6 +28,10 @@ pub fn routes(state: AppState) -> Router<AppState> {
         // Protected routes
         .route("/schedules", get(schedules_api::list_schedules))
         .route("/schedules", post(schedules_api::create_schedule))
+        .route(
+            "/schedules/import",
+            post(schedules_api::import_schedule_ics),
+        )
         .route("/schedules/:id", put(schedules_api::update_schedule))
         .route("/schedules/:id", delete(schedules_api::delete_schedule))
         .route(
@@ -44,11 +54,41 @@ pub fn routes(state: AppState) -> Router<AppState> {
             "/schedules/collapsed",
             get(schedules_api::get_collapsed_schedule),
         )
+        .route(
+            "/schedules/:id/validate",
+            get(schedules_api::validate_schedule),
+        )
+        .route("/schedules/:id/versions", get(versions_api::list_versions))
+        .route(
+            "/schedules/:id/versions/:version",
+            get(versions_api::get_version),
+        )
+        .route(
+            "/schedules/:id/versions/:version/rollback",
+            post(versions_api::rollback_schedule),
+        )
+        .route("/schedules/:id/diff", get(versions_api::get_diff))
+        .route(
+            "/schedules/:id/exceptions",
+            get(exceptions_api::list_exceptions).post(exceptions_api::create_exception),
+        )
+        .route(
+            "/schedules/:schedule_id/exceptions/:exception_id",
+            delete(exceptions_api::delete_exception),
+        )
+        .route(
+            "/schedules/:id/publish",
+            post(versions_api::publish_schedule),
+        )
         // Content
         .route("/content", get(content_api::list_content))
         .route("/content", post(content_api::create_content))
         .route("/content/:id", put(content_api::update_content))
         .route("/content/:id", delete(content_api::delete_content))
+        .route(
+            "/content/:id/cue-points/detect",
+            post(content_api::detect_cue_points),
+        )
         // Nodes
         .route("/nodes", get(nodes_api::list_nodes))
         .route("/nodes", post(nodes_api::create_node))
@@ -62,6 +102,51 @@ pub fn routes(state: AppState) -> Router<AppState> {
             put(nodes_api::update_node_schedules),
         )
         .route("/nodes/:id/logs", get(nodes_api::get_node_logs))
+        .route(
+            "/nodes/:id/validate",
+            get(nodes_api::validate_node_schedule),
+        )
+        .route(
+            "/nodes/:id/simulate",
+            get(nodes_api::simulate_node_schedule),
+        )
+        .route(
+            "/nodes/:id/interrupt",
+            post(nodes_api::interrupt_node).delete(nodes_api::clear_node_interrupt),
+        )
+        .route("/interrupt", post(nodes_api::interrupt_station))
+        .route("/interrupt/clear", post(nodes_api::clear_station_interrupt))
+        // CAP alerts
+        .route("/alerts", get(alerts_api::list_alerts))
+        .route("/alerts/cap", post(alerts_api::receive_cap))
+        .route("/alerts/:id", delete(alerts_api::clear_alert))
+        // Channels (node groups)
+        .route(
+            "/channels",
+            get(channels_api::list_channels).post(channels_api::create_channel),
+        )
+        .route(
+            "/channels/:id",
+            get(channels_api::get_channel)
+                .put(channels_api::update_channel)
+                .delete(channels_api::delete_channel),
+        )
+        .route(
+            "/channels/:id/nodes",
+            put(channels_api::update_channel_nodes),
+        )
+        .route(
+            "/channels/:id/schedules",
+            put(channels_api::update_channel_schedules),
+        )
+        .route(
+            "/channels/:id/command",
+            post(channels_api::send_channel_command),
+        )
+        // As-run log
+        .route("/nodes/:id/as-run", get(as_run_api::list_as_run))
+        .route("/nodes/:id/as-run/csv", get(as_run_api::export_as_run_csv))
+        .route("/nodes/:id/as-run/diff", get(as_run_api::get_as_run_diff))
         // Scripts
         .route("/scripts", get(scripts_api::list_scripts))
         .route("/scripts", post(scripts_api::create_script))
@@ -150,6 +235,13 @@ pub fn routes(state: AppState) -> Router<AppState> {
         .route("/auth/login", post(auth_api::login))
         .route("/auth/logout", post(auth_api::logout))
         .route("/nodes/:id/schedule", get(nodes_api::get_node_schedule))
+        // Programme guide (Public for IPTV middleware)
+        .route("/nodes/:id/xmltv", get(guide_api::get_node_xmltv))
+        .route("/xmltv", get(guide_api::get_xmltv))
+        .route(
+            "/schedules/:id/ics",
+            get(schedules_api::export_schedule_ics),
+        )
         .route("/settings", get(settings_api::list_settings))
         .route(
             "/system/capabilities",
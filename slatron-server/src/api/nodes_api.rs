@@ -1,7 +1,17 @@
+use crate::api::schedules_api::{validation_error, ValidationQuery};
 use crate::models::{NewNode, Node, User};
+use crate::services::alert_service;
+use crate::services::channel_service::{self, Target};
+use crate::services::clock_service;
+use crate::services::fallback_service::{self, FallbackPolicy};
+use crate::services::interrupt_service::{self, InterruptRequest};
+use crate::services::loudness_service;
+use crate::services::simulation_service::{self, Simulation};
+use crate::services::validation_service::{self, ValidationReport};
+use crate::websocket::{NodeCommand as WsCommand, ServerMessage};
 use crate::AppState;
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     Extension, Json,
 };
@@ -20,13 +30,6 @@ pub struct CreateNodeResponse {
     pub secret_key: String,
 }
 
-#[derive(Deserialize)]
-pub struct NodeCommand {
-    pub _action: String,
-    pub _position_secs: Option<i32>,
-    pub _content_id: Option<i32>,
-}
-
 #[derive(Serialize)]
 pub struct EffectiveBlock {
     pub id: Option<i32>,
@@ -35,10 +38,36 @@ pub struct EffectiveBlock {
     pub specific_date: Option<chrono::NaiveDate>,
     pub start_time: chrono::NaiveTime,
     pub duration_minutes: i32,
+    pub duration_secs: i32,
     pub script_id: Option<i32>,
     pub source_schedule_name: String,
     pub dj_id: Option<i32>,
     pub dj_name: Option<String>,
+    /// Seconds into the content at the block start (see `CollapsedBlock::offset_secs`)
+    pub offset_secs: i32,
+    /// Fill block run-down; the node plays whichever item covers the current time
+    pub items: Vec<crate::api::schedules_api::CollapsedItem>,
+    /// Breaks the node takes at the content's cue points
+    pub break_policy: Option<crate::services::break_service::BreakPolicy>,
+    /// Segue out of the block's items; absent means the global `transition` setting
+    pub transition: Option<crate::services::transition_service::Transition>,
+}
+
+/// Upper bound on `days`, to keep schedule responses (and run-down generation) bounded
+const MAX_SCHEDULE_DAYS: u32 = 14;
+
+#[derive(Deserialize)]
+pub struct NodeScheduleQuery {
+    /// Local days to return, starting today (default 1)
+    pub days: Option<u32>,
+}
+
+#[derive(Serialize)]
+pub struct NodeContentItem {
+    #[serde(flatten)]
+    pub item: crate::models::ContentItem,
+    /// Gain (dB) the node applies to bring the item to the station's loudness target
+    pub gain_db: Option<f64>,
 }
 
 #[derive(Serialize)]
@@ -46,8 +75,19 @@ pub struct NodeScheduleResponse {
     pub schedule: Option<crate::models::Schedule>,
     pub assigned_schedules: Vec<crate::models::Schedule>,
     pub blocks: Vec<EffectiveBlock>, // Changed from ScheduleBlock
-    pub content: Vec<crate::models::ContentItem>,
+    pub content: Vec<NodeContentItem>,
     pub scripts: Vec<crate::models::Script>,
+    /// IANA timezone the blocks were resolved in
+    pub timezone: String,
+    /// UTC bounds of the local days the blocks cover; nodes replace cached blocks in this window
+    pub window_start: chrono::DateTime<chrono::Utc>,
+    pub window_end: chrono::DateTime<chrono::Utc>,
+    /// What to play when no block covers the current time
+    pub fallback: crate::services::fallback_service::ResolvedFallback,
+    /// Play in lockstep with the rest of the node's channel, against the server's clock
+    pub sync_playback: bool,
+    /// Run the schedule on the server's clock rather than the node's own
+    pub server_time: bool,
 }
 
 pub async fn list_nodes(State(state): State<AppState>) -> Result<Json<Vec<Node>>, StatusCode> {
@@ -103,6 +143,12 @@ pub async fn create_node(
 #[derive(Deserialize)]
 pub struct UpdateNodeRequest {
     pub name: Option<String>,
+    pub timezone: Option<String>,
+    /// JSON `FallbackPolicy`; empty string clears it
+    pub fallback_policy: Option<String>,
+    /// JSON array of CAP area codes or names the node shows alerts for; empty string
+    /// shows every alert the station takes
+    pub alert_areas: Option<String>,
 }
 
 pub async fn delete_node(
@@ -143,99 +189,162 @@ pub async fn update_node(
         .get()
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    let target = diesel::update(nodes.filter(id.eq(node_id)));
+    // Empty string clears the node timezone (falls back to the global setting)
+    if let Some(tz_name) = req.timezone.as_deref().filter(|t| !t.is_empty()) {
+        if tz_name.parse::<chrono_tz::Tz>().is_err() {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+    }
+
+    if let Some(policy) = req.fallback_policy.as_deref().filter(|p| !p.is_empty()) {
+        if FallbackPolicy::parse(policy).is_err() {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+    }
+
+    if let Some(areas) = req.alert_areas.as_deref().filter(|a| !a.is_empty()) {
+        if alert_service::parse_areas(areas).is_err() {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+    }
 
     if let Some(new_name) = &req.name {
-        let updated_node = target
+        diesel::update(nodes.filter(id.eq(node_id)))
             .set(name.eq(new_name))
-            .returning(Node::as_select())
-            .get_result(&mut conn)
+            .execute(&mut conn)
             .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-        Ok(Json(updated_node))
-    } else {
-        // No changes, fetch and return
-        let node = nodes
-            .filter(id.eq(node_id))
-            .select(Node::as_select())
-            .first(&mut conn)
-            .map_err(|_| StatusCode::NOT_FOUND)?;
-        Ok(Json(node))
     }
+
+    if let Some(tz_name) = &req.timezone {
+        let tz_value = Some(tz_name.as_str()).filter(|t| !t.is_empty());
+        diesel::update(nodes.filter(id.eq(node_id)))
+            .set(timezone.eq(tz_value))
+            .execute(&mut conn)
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        // The node's local day moved, so have it refetch its schedule
+        if let Some(tx) = state.connected_nodes.read().await.get(&node_id) {
+            let _ = tx.send(ServerMessage::ScheduleUpdated {
+                timestamp: chrono::Utc::now().to_rfc3339(),
+            });
+        }
+    }
+
+    if let Some(policy) = &req.fallback_policy {
+        let policy_value = Some(policy.as_str()).filter(|p| !p.is_empty());
+        diesel::update(nodes.filter(id.eq(node_id)))
+            .set(fallback_policy.eq(policy_value))
+            .execute(&mut conn)
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        // The fallback travels with the schedule
+        if let Some(tx) = state.connected_nodes.read().await.get(&node_id) {
+            let _ = tx.send(ServerMessage::ScheduleUpdated {
+                timestamp: chrono::Utc::now().to_rfc3339(),
+            });
+        }
+    }
+
+    if let Some(areas) = &req.alert_areas {
+        let areas_value = Some(areas.as_str()).filter(|a| !a.is_empty());
+        diesel::update(nodes.filter(id.eq(node_id)))
+            .set(alert_areas.eq(areas_value))
+            .execute(&mut conn)
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    }
+
+    let node = nodes
+        .filter(id.eq(node_id))
+        .select(Node::as_select())
+        .first(&mut conn)
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    Ok(Json(node))
 }
 
+/// Send a playback command to a connected node
 pub async fn send_command(
-    State(_state): State<AppState>,
+    State(state): State<AppState>,
     Extension(user): Extension<User>,
-    Path(_node_id): Path<i32>,
-    Json(_command): Json<NodeCommand>,
+    Path(node_id): Path<i32>,
+    Json(command): Json<WsCommand>,
 ) -> Result<StatusCode, StatusCode> {
     if !user.is_editor() {
         return Err(StatusCode::FORBIDDEN);
     }
-    // This will send commands via WebSocket to the node
-    // Implementation will be in the WebSocket module
-    // For now, just acknowledge the command
+    tracing::info!(
+        "Sending {:?} to node {} (by {})",
+        command,
+        node_id,
+        user.username
+    );
+
+    let reached = interrupt_service::send(&state, Some(&[node_id]), command).await;
+    if reached.is_empty() {
+        return Err(StatusCode::CONFLICT);
+    }
     Ok(StatusCode::ACCEPTED)
 }
 
 #[derive(Deserialize)]
 pub struct UpdateNodeSchedulesRequest {
     pub schedule_ids: Vec<i32>,
+    /// Schedules the node's channel assigns that this node doesn't air
+    #[serde(default)]
+    pub excluded_schedule_ids: Vec<i32>,
 }
 
-pub async fn update_node_schedules(
-    State(state): State<AppState>,
-    Extension(user): Extension<User>,
-    Path(path_node_id): Path<i32>,
-    Json(req): Json<UpdateNodeSchedulesRequest>,
+/// Replace a node's or channel's schedule assignments and have the affected nodes
+/// refetch their schedule.
+pub(crate) async fn assign_schedules(
+    state: &AppState,
+    target: Target,
+    req: &UpdateNodeSchedulesRequest,
 ) -> Result<StatusCode, StatusCode> {
-    if !user.is_editor() {
-        return Err(StatusCode::FORBIDDEN);
-    }
-    use crate::schema::node_schedules::dsl::*;
-
     let mut conn = state
         .db
         .get()
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    conn.transaction::<_, diesel::result::Error, _>(|conn| {
-        // 1. Delete all existing schedules for this node
-        diesel::delete(node_schedules.filter(node_id.eq(path_node_id))).execute(conn)?;
-
-        // 2. Insert new schedules with priority based on order
-        // Index 0 (Top) gets highest priority.
-        let count = req.schedule_ids.len();
-        for (i, s_id) in req.schedule_ids.iter().enumerate() {
-            let priority_val = (count - i) as i32;
-
-            let new_assignment = crate::models::NewNodeSchedule {
-                node_id: path_node_id,
-                schedule_id: *s_id,
-                priority: Some(priority_val),
-            };
-
-            diesel::insert_into(node_schedules)
-                .values(&new_assignment)
-                .execute(conn)?;
-        }
-
-        Ok(())
-    })
+    channel_service::assign(
+        &mut conn,
+        target,
+        &req.schedule_ids,
+        &req.excluded_schedule_ids,
+    )
     .map_err(|e| {
-        tracing::error!("Failed to update node schedules: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
+        if e.downcast_ref::<diesel::result::Error>().is_some() {
+            tracing::error!("Failed to update {:?} schedules: {}", target, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        } else {
+            StatusCode::BAD_REQUEST
+        }
     })?;
 
+    let node_ids =
+        channel_service::nodes(&mut conn, target).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    channel_service::notify(state, &node_ids).await;
+
     Ok(StatusCode::OK)
 }
 
+pub async fn update_node_schedules(
+    State(state): State<AppState>,
+    Extension(user): Extension<User>,
+    Path(path_node_id): Path<i32>,
+    Json(req): Json<UpdateNodeSchedulesRequest>,
+) -> Result<StatusCode, StatusCode> {
+    if !user.is_editor() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    assign_schedules(&state, Target::Node(path_node_id), &req).await
+}
+
 pub async fn get_node_schedule(
     State(state): State<AppState>,
     Path(query_node_id): Path<i32>,
+    Query(range): Query<NodeScheduleQuery>,
 ) -> Result<Json<NodeScheduleResponse>, StatusCode> {
     use crate::schema::content_items::dsl::{content_items, id as content_item_id};
-    use crate::schema::schedules::dsl::{is_active, priority, schedules};
     use crate::schema::scripts::dsl::{id as script_id_col, scripts};
     use crate::services::schedule_service;
 
@@ -244,73 +353,79 @@ pub async fn get_node_schedule(
         .get()
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    // 1. Fetch Global Timezone Setting
-    use crate::schema::global_settings::dsl::{global_settings, key, value};
-    let timezone_setting: Option<String> = global_settings
-        .filter(key.eq("timezone"))
-        .select(value)
-        .first(&mut conn)
-        .optional()
-        .unwrap_or(None);
-
-    // 2. Parse Timezone
-    let tz: chrono_tz::Tz = timezone_setting
-        .as_deref()
-        .and_then(|s| s.parse().ok())
-        .unwrap_or(chrono_tz::UTC);
+    // 1. Resolve the node's timezone (falls back to the global setting)
+    let tz = schedule_service::node_timezone(&mut conn, query_node_id).map_err(|e| {
+        tracing::error!("Failed to resolve node timezone: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
 
-    // 3. Calculate Local Date for Facility
+    // 2. Calculate Local Date for the node
     let now_utc = chrono::Utc::now();
     let now_local = now_utc.with_timezone(&tz);
     let local_today = now_local.date_naive();
+    let days = range.days.unwrap_or(1).clamp(1, MAX_SCHEDULE_DAYS);
+    let last_day = local_today + chrono::Duration::days(days as i64 - 1);
+    let (window_start, _) = schedule_service::local_day_bounds(&tz, local_today);
+    let (_, window_end) = schedule_service::local_day_bounds(&tz, last_day);
+
+    // 3. Calculate Collapsed Schedule for each LOCAL day, starting today
+    let mut collapsed_blocks = Vec::new();
+    for date in local_today.iter_days().take(days as usize) {
+        let day_blocks = schedule_service::calculate_collapsed_schedule(
+            &mut conn,
+            Target::Node(query_node_id),
+            date,
+            Some(tz.name().to_string()),
+        )
+        .map_err(|e| {
+            tracing::error!("Failed to calculate collapsed schedule: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+        collapsed_blocks.extend(day_blocks);
+    }
 
-    // 4. Calculate Collapsed Schedule for TODAY (LOCAL)
-    // The service now returns blocks in LOCAL time (HH:MM:SS) for the given date.
-    let collapsed_blocks = schedule_service::calculate_collapsed_schedule(
-        &mut conn,
-        query_node_id,
-        local_today,
-        timezone_setting,
-    )
-    .map_err(|e| {
-        tracing::error!("Failed to calculate collapsed schedule: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
-
-    // 5. Convert CollapsedBlocks to EffectiveBlocks for the response
-    // We map the Local Time blocks back to absolute UTC time for the Node.
+    // 4. Convert CollapsedBlocks to EffectiveBlocks for the response
+    // Collapsed blocks carry their absolute start, so DST gaps/overlaps are already resolved.
     let blocks: Vec<EffectiveBlock> = collapsed_blocks
         .iter()
         .enumerate()
-        .map(|(idx, cb)| {
-            // Parse Local Start Time
-            let local_start =
-                chrono::NaiveTime::parse_from_str(&cb.start_time, "%H:%M:%S").unwrap_or_default();
-
-            // Construct Local DateTime
-            let local_dt = local_today.and_time(local_start);
-
-            // Convert to UTC
-            // Note: This matches the "Facility Day" concept, but timestamps will be UTC.
-            let utc_dt = local_dt.and_local_timezone(tz).unwrap().to_utc();
-
-            EffectiveBlock {
-                id: Some(idx as i32 + 1), // unique ID for frontend keys
-                schedule_id: cb.schedule_id,
-                content_id: cb.content_id,
-                specific_date: Some(utc_dt.date_naive()), // UTC Date
-                start_time: utc_dt.time(),                // UTC Time
-                duration_minutes: cb.duration_minutes,
-                script_id: cb.script_id,
-                source_schedule_name: cb.schedule_name.clone(), // Populate from collapsed block
-                dj_id: cb.dj_id,                                // Added mapping
-                dj_name: cb.dj_name.clone(),
-            }
+        .map(|(idx, cb)| EffectiveBlock {
+            id: Some(idx as i32 + 1), // unique ID for frontend keys
+            schedule_id: cb.schedule_id,
+            content_id: cb.content_id,
+            specific_date: Some(cb.start_at.date_naive()), // UTC Date
+            start_time: cb.start_at.time(),                // UTC Time
+            duration_minutes: cb.duration_minutes,
+            duration_secs: cb.duration_secs,
+            script_id: cb.script_id,
+            source_schedule_name: cb.schedule_name.clone(), // Populate from collapsed block
+            dj_id: cb.dj_id,                                // Added mapping
+            dj_name: cb.dj_name.clone(),
+            offset_secs: cb.offset_secs,
+            items: cb.items.clone(),
+            break_policy: cb.break_policy.clone(),
+            transition: cb.transition,
         })
         .collect();
 
-    // 3. Fetch Content Items referenced by the blocks
-    let content_ids: Vec<i32> = blocks.iter().filter_map(|b| b.content_id).collect();
+    // Resolve what to play between blocks
+    let fallback = fallback_service::node_policy(&mut conn, query_node_id)
+        .and_then(|policy| fallback_service::resolve(&mut conn, &policy, local_today))
+        .map_err(|e| {
+            tracing::error!("Failed to resolve fallback policy: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    // 3. Fetch Content Items referenced by the blocks and the fallback
+    let content_ids: Vec<i32> = blocks
+        .iter()
+        .flat_map(|b| {
+            b.content_id
+                .into_iter()
+                .chain(b.items.iter().map(|i| i.content_id))
+        })
+        .chain(fallback.content_ids.iter().copied())
+        .collect();
 
     let content_list = content_items
         .filter(content_item_id.eq_any(content_ids))
@@ -340,6 +455,7 @@ pub async fn get_node_schedule(
     }
 
     // Fetch Global Active Scripts
+    use crate::schema::global_settings::dsl::{global_settings, key, value};
     let global_script_json: Option<String> = global_settings
         .filter(key.eq("global_active_scripts"))
         .select(value)
@@ -373,68 +489,249 @@ pub async fn get_node_schedule(
         .load::<crate::models::Script>(&mut conn)
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    // 6. determine "effective_schedule" metadata if needed (optional)
-    // We can just pick the highest priority one to show name, or leave None.
-    // The previous logic returned the "effective schedule" object.
-    // We can try to fetch the highest priority *active* schedule just for metadata.
-    use crate::schema::node_schedules::dsl::{node_id, node_schedules}; // Added import
-
-    let primary_schedule: Option<crate::models::Schedule> = node_schedules
-        .filter(node_id.eq(query_node_id))
-        .inner_join(schedules)
-        .select(crate::models::Schedule::as_select())
-        .filter(is_active.eq(true))
-        .order(priority.desc())
-        .first(&mut conn)
-        .optional()
-        .unwrap_or(None);
-
-    // 7. Get all assigned schedules for management UI
-    // Sorted by effective priority (Override or Default) DESC
-    // We reuse logic similar to schedule_service but returning just the schedules
-    let assigned_data: Vec<(crate::models::Schedule, Option<i32>)> =
-        crate::schema::node_schedules::dsl::node_schedules
-            .inner_join(crate::schema::schedules::dsl::schedules)
-            .filter(crate::schema::node_schedules::dsl::node_id.eq(query_node_id))
-            .select((
-                crate::models::Schedule::as_select(),
-                crate::schema::node_schedules::dsl::priority,
-            ))
-            .load(&mut conn)
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-    struct EffectiveSchedule {
-        schedule: crate::models::Schedule,
-        effective_priority: i32,
-    }
+    // 6. The node's lineup (its channel's and its own), sorted by effective priority,
+    //    for the management UI; the highest priority active schedule is the primary one
+    let assigned_schedules_list: Vec<crate::models::Schedule> =
+        channel_service::lineup(&mut conn, Target::Node(query_node_id))
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+            .into_iter()
+            .map(|a| a.schedule)
+            .collect();
 
-    let mut effective_schedules: Vec<EffectiveSchedule> = assigned_data
-        .into_iter()
-        .map(|(s, p_override)| {
-            let eff = p_override.unwrap_or(s.priority);
-            EffectiveSchedule {
-                schedule: s,
-                effective_priority: eff,
-            }
-        })
-        .collect();
-
-    effective_schedules.sort_by(|a, b| b.effective_priority.cmp(&a.effective_priority));
+    let primary_schedule = assigned_schedules_list
+        .iter()
+        .find(|s| s.is_active)
+        .cloned();
 
-    let assigned_schedules_list: Vec<crate::models::Schedule> = effective_schedules
-        .into_iter()
-        .map(|es| es.schedule)
-        .collect();
+    let target_lufs = loudness_service::target_lufs(&mut conn);
+    let sync_playback = channel_service::syncs_playback(&mut conn, query_node_id)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let server_time = clock_service::ClockSettings::load(&mut conn).server_time;
 
     Ok(Json(NodeScheduleResponse {
         schedule: primary_schedule,
         assigned_schedules: assigned_schedules_list,
         blocks,
-        content: content_list,
+        content: content_list
+            .into_iter()
+            .map(|item| NodeContentItem {
+                gain_db: loudness_service::stored_gain_db(
+                    item.loudness_lufs,
+                    item.true_peak_dbtp,
+                    target_lufs,
+                ),
+                item,
+            })
+            .collect(),
         scripts: fetched_scripts,
+        timezone: tz.name().to_string(),
+        window_start: window_start.with_timezone(&chrono::Utc),
+        window_end: window_end.with_timezone(&chrono::Utc),
+        fallback,
+        sync_playback,
+        server_time,
     }))
 }
 
+/// Validate the schedules a node will air, including dead air in its effective schedule
+pub async fn validate_node_schedule(
+    State(state): State<AppState>,
+    Extension(user): Extension<User>,
+    Path(node_id): Path<i32>,
+    Query(params): Query<ValidationQuery>,
+) -> Result<Json<ValidationReport>, StatusCode> {
+    if !user.is_editor() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let (from, to) = validation_service::resolve_range(params.from, params.to)
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let mut conn = state
+        .db
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let report = validation_service::validate_node(&mut conn, node_id, from, to)
+        .map_err(validation_error)?;
+
+    Ok(Json(report))
+}
+
+#[derive(Deserialize)]
+pub struct SimulationQuery {
+    /// Defaults to now
+    pub from: Option<chrono::DateTime<chrono::Utc>>,
+    /// Defaults to 24 hours after `from`
+    pub to: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// What the node will air between `from` and `to`, without changing anything
+pub async fn simulate_node_schedule(
+    State(state): State<AppState>,
+    Path(node_id): Path<i32>,
+    Query(params): Query<SimulationQuery>,
+) -> Result<Json<Simulation>, StatusCode> {
+    let from = params.from.unwrap_or_else(chrono::Utc::now);
+    let to = params.to.unwrap_or(from + chrono::Duration::hours(24));
+    if from >= to || to - from > chrono::Duration::days(simulation_service::MAX_SIMULATION_DAYS) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let mut conn = state
+        .db
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let simulation = simulation_service::simulate(&mut conn, node_id, from, to).map_err(|e| {
+        tracing::error!("Failed to simulate node {}: {}", node_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(simulation))
+}
+
+#[derive(Deserialize)]
+pub struct StationInterruptRequest {
+    /// Nodes to interrupt; every connected node if unset (and no channel is given)
+    #[serde(default)]
+    pub node_ids: Option<Vec<i32>>,
+    /// Interrupt this channel's nodes (as well as `node_ids`)
+    #[serde(default)]
+    pub channel_id: Option<i32>,
+    #[serde(flatten)]
+    pub interrupt: InterruptRequest,
+}
+
+#[derive(Deserialize)]
+pub struct ClearInterruptRequest {
+    /// Nodes to clear; every connected node if unset (and no channel is given)
+    #[serde(default)]
+    pub node_ids: Option<Vec<i32>>,
+    /// Clear this channel's nodes (as well as `node_ids`)
+    #[serde(default)]
+    pub channel_id: Option<i32>,
+}
+
+#[derive(Serialize)]
+pub struct InterruptResponse {
+    /// Connected nodes the command was sent to
+    pub node_ids: Vec<i32>,
+}
+
+/// `node_ids` plus the members of `channel_id`; `None` (every node) if neither is given.
+fn station_nodes(
+    state: &AppState,
+    node_ids: Option<&[i32]>,
+    channel_id: Option<i32>,
+) -> Result<Option<Vec<i32>>, StatusCode> {
+    let Some(channel) = channel_id else {
+        return Ok(node_ids.map(<[i32]>::to_vec));
+    };
+    let mut conn = state
+        .db
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let mut targets = channel_service::members(&mut conn, channel)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    targets.extend(node_ids.unwrap_or_default());
+    targets.sort_unstable();
+    targets.dedup();
+    Ok(Some(targets))
+}
+
+fn interrupt_command(state: &AppState, req: &InterruptRequest) -> Result<WsCommand, StatusCode> {
+    let mut conn = state
+        .db
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    interrupt_service::command(&mut conn, req).map_err(|e| {
+        if e.downcast_ref::<diesel::result::Error>().is_some() {
+            tracing::error!("Failed to prepare interrupt: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        } else {
+            tracing::warn!("Rejected interrupt: {}", e);
+            StatusCode::BAD_REQUEST
+        }
+    })
+}
+
+/// Break into a node's playback until cleared or the interrupt times out
+pub async fn interrupt_node(
+    State(state): State<AppState>,
+    Extension(user): Extension<User>,
+    Path(node_id): Path<i32>,
+    Json(req): Json<InterruptRequest>,
+) -> Result<Json<InterruptResponse>, StatusCode> {
+    if !user.is_editor() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let command = interrupt_command(&state, &req)?;
+    tracing::warn!("Interrupting node {} (by {})", node_id, user.username);
+
+    let node_ids = interrupt_service::send(&state, Some(&[node_id]), command).await;
+    Ok(Json(InterruptResponse { node_ids }))
+}
+
+/// End a node's interrupt; it resumes its schedule
+pub async fn clear_node_interrupt(
+    State(state): State<AppState>,
+    Extension(user): Extension<User>,
+    Path(node_id): Path<i32>,
+) -> Result<Json<InterruptResponse>, StatusCode> {
+    if !user.is_editor() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    tracing::info!(
+        "Clearing interrupt on node {} (by {})",
+        node_id,
+        user.username
+    );
+
+    let node_ids =
+        interrupt_service::send(&state, Some(&[node_id]), WsCommand::ClearInterrupt).await;
+    Ok(Json(InterruptResponse { node_ids }))
+}
+
+/// Break into several nodes at once, or every connected node
+pub async fn interrupt_station(
+    State(state): State<AppState>,
+    Extension(user): Extension<User>,
+    Json(req): Json<StationInterruptRequest>,
+) -> Result<Json<InterruptResponse>, StatusCode> {
+    if !user.is_editor() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let command = interrupt_command(&state, &req.interrupt)?;
+    let targets = station_nodes(&state, req.node_ids.as_deref(), req.channel_id)?;
+    tracing::warn!(
+        "Interrupting nodes {:?} (by {})",
+        targets.as_deref().unwrap_or_default(),
+        user.username
+    );
+
+    let node_ids = interrupt_service::send(&state, targets.as_deref(), command).await;
+    Ok(Json(InterruptResponse { node_ids }))
+}
+
+pub async fn clear_station_interrupt(
+    State(state): State<AppState>,
+    Extension(user): Extension<User>,
+    Json(req): Json<ClearInterruptRequest>,
+) -> Result<Json<InterruptResponse>, StatusCode> {
+    if !user.is_editor() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let targets = station_nodes(&state, req.node_ids.as_deref(), req.channel_id)?;
+    tracing::info!("Clearing interrupts (by {})", user.username);
+
+    let node_ids =
+        interrupt_service::send(&state, targets.as_deref(), WsCommand::ClearInterrupt).await;
+    Ok(Json(InterruptResponse { node_ids }))
+}
+
 pub async fn get_node_logs(
     State(state): State<AppState>,
     Path(query_node_id): Path<i32>,
@@ -1,17 +1,30 @@
 use crate::models::{NewSchedule, NewScheduleBlock, Schedule, ScheduleBlock, UpdateSchedule, User};
+use crate::services::break_service::BreakPolicy;
+use crate::services::channel_service::Target;
+use crate::services::ical_service::{self, SkippedEvent};
+use crate::services::recurrence::RecurrenceRule;
+use crate::services::rundown_service::FillRule;
+use crate::services::schedule_service;
+use crate::services::transition_service::Transition;
+use crate::services::validation_service::{self, ValidationReport};
+use crate::services::version_service;
 use crate::AppState;
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
+    http::{header, StatusCode},
+    response::IntoResponse,
     Extension, Json,
 };
 use chrono::{NaiveDate, Timelike};
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 
 #[derive(Deserialize)]
 pub struct CollapsedScheduleQuery {
-    pub node_id: i32,
+    /// Exactly one of `node_id` and `channel_id`
+    pub node_id: Option<i32>,
+    pub channel_id: Option<i32>,
     pub date: NaiveDate,
 }
 
@@ -22,15 +35,40 @@ pub struct CollapsedScheduleResponse {
 
 #[derive(Serialize, Clone)]
 pub struct CollapsedBlock {
+    /// Local wall-clock start (HH:MM:SS)
     pub start_time: String,
+    /// Absolute start; unambiguous even when `start_time` repeats on a DST fall-back day
+    pub start_at: chrono::DateTime<chrono::Utc>,
     pub duration_minutes: i32,
+    /// Exact duration; `duration_minutes` is this rounded down
+    pub duration_secs: i32,
     pub content_id: Option<i32>,
     pub script_id: Option<i32>,
     pub priority: i32,
     pub schedule_name: String,
     pub schedule_id: i32,
+    pub block_id: i32,
+    /// The block's DJ, else that of the schedule airing it
     pub dj_id: Option<i32>,
     pub dj_name: Option<String>,
+    /// Seconds into the block's content at `start_at`; non-zero where this is the rest of a
+    /// block that a higher priority block cut into
+    pub offset_secs: i32,
+    /// Run-down of a fill block, clipped to this block; empty for single-content blocks
+    pub items: Vec<CollapsedItem>,
+    /// Breaks to take at the content's cue points
+    pub break_policy: Option<BreakPolicy>,
+    /// Segue out of the block's items; the global setting applies if unset
+    pub transition: Option<Transition>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct CollapsedItem {
+    pub content_id: i32,
+    pub start_at: chrono::DateTime<chrono::Utc>,
+    pub duration_secs: i32,
+    /// Seconds into the item where this entry starts (non-zero if its start was cut off)
+    pub offset_secs: i32,
 }
 
 pub async fn list_schedules(
@@ -84,23 +122,30 @@ pub async fn update_schedule(
     if !user.is_editor() {
         return Err(StatusCode::FORBIDDEN);
     }
-    use crate::schema::node_schedules;
     use crate::schema::schedules::dsl::*;
+    use crate::schema::{channel_schedules, node_schedules};
 
     let mut conn = state
         .db
         .get()
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    // Enforce activation logic: A schedule cannot be active if it isn't assigned to a node.
+    // Enforce activation logic: A schedule cannot be active if it isn't assigned to a node
+    // or channel.
     if let Some(true) = updates.is_active {
-        let count: i64 = node_schedules::table
+        let node_count: i64 = node_schedules::table
             .filter(node_schedules::schedule_id.eq(schedule_id))
+            .filter(node_schedules::excluded.eq(false))
+            .count()
+            .get_result(&mut conn)
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        let channel_count: i64 = channel_schedules::table
+            .filter(channel_schedules::schedule_id.eq(schedule_id))
             .count()
             .get_result(&mut conn)
             .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-        if count == 0 {
+        if node_count + channel_count == 0 {
             return Err(StatusCode::BAD_REQUEST);
         }
     }
@@ -156,38 +201,43 @@ pub async fn get_schedule_blocks(
     Ok(Json(blocks))
 }
 
+// How far ahead a recurring block's occurrences are compared when checking overlaps
+const RECURRENCE_OVERLAP_HORIZON_DAYS: i64 = 366;
+
 // Helper to check for overlaps
+#[allow(clippy::too_many_arguments)]
 fn check_overlap(
     conn: &mut SqliteConnection,
     sched_id: i32,
     day: Option<i32>,
     date: Option<NaiveDate>, // Added date parameter
+    rrule_str: Option<&str>,
     start: chrono::NaiveTime,
-    duration_mins: i32,
+    total_secs: i64,
     exclude_block_id: Option<i32>,
 ) -> Result<bool, diesel::result::Error> {
     use crate::schema::schedule_blocks::dsl::*;
 
-    let mut query = schedule_blocks
+    let blocks = schedule_blocks
         .filter(schedule_id.eq(sched_id))
-        .into_boxed();
-
-    if let Some(d) = day {
-        query = query.filter(day_of_week.eq(d));
-    } else if let Some(dt) = date {
-        query = query.filter(specific_date.eq(dt));
-    } else {
-        // If neither, fallback or logic needs refinement?
-        // For now, if neither is set (shouldn't happen in strict mode), maybe return false?
-        // But let's assume one is always set.
-    }
-
-    let blocks = query
         .select(ScheduleBlock::as_select())
         .load::<ScheduleBlock>(conn)?;
 
-    let new_start_mins = start.num_seconds_from_midnight() as i32 / 60;
-    let new_end_mins = new_start_mins + duration_mins;
+    // Dates the candidate airs on. Recurring blocks may share a date with any other
+    // block in the schedule, so compare actual airings over the next year.
+    let airing_dates: Vec<NaiveDate> = match (date, rrule_str.map(RecurrenceRule::parse)) {
+        (Some(dt), Some(Ok(rule))) => rule.occurrences_between(
+            dt,
+            dt,
+            dt + chrono::Duration::days(RECURRENCE_OVERLAP_HORIZON_DAYS),
+        ),
+        (Some(_), Some(Err(_))) => vec![],
+        (Some(dt), None) => vec![dt],
+        (None, _) => vec![],
+    };
+
+    let new_start_secs = start.num_seconds_from_midnight() as i64;
+    let new_end_secs = new_start_secs + total_secs;
 
     for b in blocks {
         if let Some(id_to_exclude) = exclude_block_id {
@@ -196,18 +246,94 @@ fn check_overlap(
             }
         }
 
-        let b_start_mins = b.start_time.num_seconds_from_midnight() as i32 / 60;
-        let b_end_mins = b_start_mins + b.duration_minutes;
+        // Expand the block's own airings once over the candidate's, plus a day either side
+        let b_airings = match (airing_dates.first(), airing_dates.last()) {
+            (Some(first), Some(last)) if day.is_none() => schedule_service::block_airings_between(
+                b.day_of_week,
+                b.specific_date,
+                b.rrule.as_deref(),
+                *first - chrono::Duration::days(1),
+                *last + chrono::Duration::days(1),
+            ),
+            _ => HashSet::new(),
+        };
+
+        // Blocks may run past midnight, so also compare against the neighbouring days.
+        for day_offset in -1..=1 {
+            let airs_on_offset_day = if let Some(d) = day {
+                b.day_of_week == Some((d + day_offset).rem_euclid(7))
+            } else if date.is_some() {
+                airing_dates.iter().any(|dt| {
+                    b_airings.contains(&(*dt + chrono::Duration::days(day_offset as i64)))
+                })
+            } else {
+                // Neither day nor date set (shouldn't happen in strict mode): compare times only.
+                day_offset == 0
+            };
+
+            if !airs_on_offset_day {
+                continue;
+            }
 
-        // Check intersection
-        if new_start_mins < b_end_mins && new_end_mins > b_start_mins {
-            return Ok(true);
+            let b_start_secs =
+                b.start_time.num_seconds_from_midnight() as i64 + day_offset as i64 * 86400;
+            let b_end_secs = b_start_secs + b.total_duration_secs();
+
+            // Check intersection
+            if new_start_secs < b_end_secs && new_end_secs > b_start_secs {
+                return Ok(true);
+            }
         }
     }
 
     Ok(false)
 }
 
+// Recurring blocks need a parseable RRULE and a series start date
+fn validate_rrule(rule: Option<&str>, dtstart: Option<NaiveDate>) -> Result<(), StatusCode> {
+    let Some(rule) = rule else {
+        return Ok(());
+    };
+    if dtstart.is_none() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    RecurrenceRule::parse(rule).map_err(|e| {
+        tracing::warn!("Rejected invalid RRULE '{}': {}", rule, e);
+        StatusCode::BAD_REQUEST
+    })?;
+    Ok(())
+}
+
+fn validate_fill_rule(rule: Option<&str>) -> Result<(), StatusCode> {
+    if let Some(rule) = rule {
+        FillRule::parse(rule).map_err(|e| {
+            tracing::warn!("Rejected fill rule '{}': {}", rule, e);
+            StatusCode::BAD_REQUEST
+        })?;
+    }
+    Ok(())
+}
+
+fn validate_break_policy(policy: Option<&str>) -> Result<(), StatusCode> {
+    if let Some(policy) = policy {
+        BreakPolicy::parse(policy).map_err(|e| {
+            tracing::warn!("Rejected break policy '{}': {}", policy, e);
+            StatusCode::BAD_REQUEST
+        })?;
+    }
+    Ok(())
+}
+
+fn validate_transition(transition: Option<&str>) -> Result<(), StatusCode> {
+    if let Some(transition) = transition {
+        Transition::parse(transition).map_err(|e| {
+            tracing::warn!("Rejected transition '{}': {}", transition, e);
+            StatusCode::BAD_REQUEST
+        })?;
+    }
+    Ok(())
+}
+
 pub async fn create_schedule_block(
     State(state): State<AppState>,
     Extension(user): Extension<User>,
@@ -224,14 +350,15 @@ pub async fn create_schedule_block(
         .get()
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    // Validate duration and end of day
-    if new_block.duration_minutes <= 0 {
-        return Err(StatusCode::BAD_REQUEST);
-    }
-    let start_mins = new_block.start_time.num_seconds_from_midnight() as i32 / 60;
-    if start_mins + new_block.duration_minutes > 1440 {
+    // Validate duration. Blocks may run past midnight, but for at most one day.
+    let total_secs = new_block.total_duration_secs();
+    if total_secs <= 0 || total_secs > 86400 {
         return Err(StatusCode::BAD_REQUEST);
     }
+    validate_rrule(new_block.rrule.as_deref(), new_block.specific_date)?;
+    validate_fill_rule(new_block.fill_rule.as_deref())?;
+    validate_break_policy(new_block.break_policy.as_deref())?;
+    validate_transition(new_block.transition.as_deref())?;
 
     // Check overlap
     let has_overlap = check_overlap(
@@ -239,8 +366,9 @@ pub async fn create_schedule_block(
         new_block.schedule_id,
         new_block.day_of_week,
         new_block.specific_date,
+        new_block.rrule.as_deref(),
         new_block.start_time,
-        new_block.duration_minutes,
+        total_secs,
         None,
     )
     .map_err(|e| {
@@ -277,14 +405,15 @@ pub async fn update_schedule_block(
         .get()
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    // Validate duration and end of day
-    if updates.duration_minutes <= 0 {
-        return Err(StatusCode::BAD_REQUEST);
-    }
-    let start_mins = updates.start_time.num_seconds_from_midnight() as i32 / 60;
-    if start_mins + updates.duration_minutes > 1440 {
+    // Validate duration. Blocks may run past midnight, but for at most one day.
+    let total_secs = updates.total_duration_secs();
+    if total_secs <= 0 || total_secs > 86400 {
         return Err(StatusCode::BAD_REQUEST);
     }
+    validate_rrule(updates.rrule.as_deref(), updates.specific_date)?;
+    validate_fill_rule(updates.fill_rule.as_deref())?;
+    validate_break_policy(updates.break_policy.as_deref())?;
+    validate_transition(updates.transition.as_deref())?;
 
     // Check overlap
     let has_overlap = check_overlap(
@@ -292,8 +421,9 @@ pub async fn update_schedule_block(
         updates.schedule_id,
         updates.day_of_week,
         updates.specific_date,
+        updates.rrule.as_deref(),
         updates.start_time,
-        updates.duration_minutes,
+        total_secs,
         Some(block_id),
     )
     .map_err(|e| {
@@ -314,6 +444,11 @@ pub async fn update_schedule_block(
             duration_minutes.eq(updates.duration_minutes),
             script_id.eq(updates.script_id),
             dj_id.eq(updates.dj_id), // Added missing field
+            rrule.eq(&updates.rrule),
+            duration_secs.eq(updates.duration_secs),
+            fill_rule.eq(&updates.fill_rule),
+            break_policy.eq(&updates.break_policy),
+            transition.eq(&updates.transition),
         ))
         .returning(ScheduleBlock::as_select())
         .get_result(&mut conn)
@@ -348,20 +483,252 @@ pub async fn get_collapsed_schedule(
     State(state): State<AppState>,
     Query(params): Query<CollapsedScheduleQuery>,
 ) -> Result<Json<CollapsedScheduleResponse>, StatusCode> {
-    use crate::services::schedule_service;
-
     let mut conn = state
         .db
         .get()
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    let blocks = schedule_service::calculate_collapsed_schedule(
-        &mut conn,
-        params.node_id,
-        params.date,
-        None,
-    )
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let target = match (params.node_id, params.channel_id) {
+        (Some(node_id), None) => Target::Node(node_id),
+        (None, Some(channel_id)) => Target::Channel(channel_id),
+        _ => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    let blocks = schedule_service::preview_collapsed_schedule(&mut conn, target, params.date, None)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
     Ok(Json(CollapsedScheduleResponse { blocks }))
 }
+
+#[derive(Deserialize)]
+pub struct ValidationQuery {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    /// Also check content availability against this node
+    pub node_id: Option<i32>,
+}
+
+pub(crate) fn validation_error(e: anyhow::Error) -> StatusCode {
+    match e.downcast_ref::<diesel::result::Error>() {
+        Some(diesel::result::Error::NotFound) => StatusCode::NOT_FOUND,
+        _ => {
+            tracing::error!("Schedule validation failed: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+pub async fn validate_schedule(
+    State(state): State<AppState>,
+    Extension(user): Extension<User>,
+    Path(schedule_id): Path<i32>,
+    Query(params): Query<ValidationQuery>,
+) -> Result<Json<ValidationReport>, StatusCode> {
+    if !user.is_editor() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let (from, to) = validation_service::resolve_range(params.from, params.to)
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let mut conn = state
+        .db
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let report =
+        validation_service::validate_schedule(&mut conn, schedule_id, from, to, params.node_id)
+            .map_err(validation_error)?;
+
+    Ok(Json(report))
+}
+
+/// Schedule as a subscribable iCalendar feed
+pub async fn export_schedule_ics(
+    State(state): State<AppState>,
+    Path(schedule_id): Path<i32>,
+) -> Result<impl IntoResponse, StatusCode> {
+    use crate::schema::schedules::dsl::*;
+
+    let mut conn = state
+        .db
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let schedule = schedules
+        .filter(id.eq(schedule_id))
+        .select(Schedule::as_select())
+        .first(&mut conn)
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+
+    let ics = ical_service::export_schedule(&mut conn, &schedule).map_err(|e| {
+        tracing::error!("Failed to export schedule {}: {:?}", schedule_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok((
+        [
+            (
+                header::CONTENT_TYPE,
+                "text/calendar; charset=utf-8".to_string(),
+            ),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("inline; filename=\"schedule-{}.ics\"", schedule_id),
+            ),
+        ],
+        ics,
+    ))
+}
+
+#[derive(Deserialize)]
+pub struct ImportIcsRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub priority: Option<i32>,
+    /// The iCalendar document
+    pub ics: String,
+}
+
+#[derive(Serialize)]
+pub struct ImportIcsResponse {
+    pub schedule: Schedule,
+    pub imported: usize,
+    /// Event summaries that matched no content title; their blocks have no content
+    pub unmatched: Vec<String>,
+    pub skipped: Vec<SkippedEvent>,
+}
+
+/// Create a `recurring` schedule from an iCalendar file, one block per event, published
+/// as its first version. Event summaries are matched to content items by title
+/// (case-insensitive).
+pub async fn import_schedule_ics(
+    State(state): State<AppState>,
+    Extension(user): Extension<User>,
+    Json(req): Json<ImportIcsRequest>,
+) -> Result<Json<ImportIcsResponse>, StatusCode> {
+    if !user.is_editor() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let mut conn = state
+        .db
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let tz = schedule_service::global_timezone(&mut conn)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let (events, mut skipped) = ical_service::parse_events(&req.ics, &tz);
+    if events.is_empty() && skipped.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let titles: HashMap<String, i32> = {
+        use crate::schema::content_items::dsl::*;
+        content_items
+            .select((id, title))
+            .load::<(Option<i32>, String)>(&mut conn)
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+            .into_iter()
+            .rev()
+            .filter_map(|(cid, t)| cid.map(|c| (t.trim().to_lowercase(), c)))
+            .collect()
+    };
+
+    let result = conn.transaction::<_, anyhow::Error, _>(|conn| {
+        let mut schedule = diesel::insert_into(crate::schema::schedules::table)
+            .values(&NewSchedule {
+                name: req.name.clone(),
+                description: req.description.clone(),
+                schedule_type: "recurring".to_string(),
+                priority: req.priority.unwrap_or(0),
+                is_active: true,
+                dj_id: None,
+            })
+            .returning(Schedule::as_select())
+            .get_result(conn)?;
+        let new_schedule_id = schedule
+            .id
+            .ok_or_else(|| anyhow::anyhow!("Schedule ID missing"))?;
+
+        let mut imported = 0;
+        let mut unmatched = Vec::new();
+        for event in events {
+            let skip = |reason: &str| SkippedEvent {
+                summary: event.summary.clone(),
+                reason: reason.to_string(),
+            };
+
+            if let Some(rule) = &event.rrule {
+                if let Err(e) = RecurrenceRule::parse(rule) {
+                    skipped.push(skip(&e.to_string()));
+                    continue;
+                }
+            }
+
+            let date = event.start.date();
+            let start = event.start.time();
+            if check_overlap(
+                conn,
+                new_schedule_id,
+                None,
+                Some(date),
+                event.rrule.as_deref(),
+                start,
+                event.duration_secs,
+                None,
+            )? {
+                skipped.push(skip("Overlaps an earlier event"));
+                continue;
+            }
+
+            let matched = titles.get(&event.summary.trim().to_lowercase()).copied();
+            if matched.is_none() {
+                unmatched.push(event.summary.clone());
+            }
+
+            diesel::insert_into(crate::schema::schedule_blocks::table)
+                .values(&NewScheduleBlock {
+                    schedule_id: new_schedule_id,
+                    content_id: matched,
+                    day_of_week: None,
+                    specific_date: Some(date),
+                    start_time: start,
+                    duration_minutes: (event.duration_secs / 60) as i32,
+                    script_id: None,
+                    dj_id: None,
+                    rrule: event.rrule.clone(),
+                    duration_secs: Some(event.duration_secs as i32),
+                    fill_rule: None,
+                    break_policy: None,
+                    transition: None,
+                })
+                .execute(conn)?;
+            imported += 1;
+        }
+
+        // The schedule is created active, so the imported blocks air as version 1
+        let version = version_service::publish(
+            conn,
+            new_schedule_id,
+            user.id,
+            Some("Imported from iCalendar".to_string()),
+        )?;
+        schedule.published_version_id = version.id;
+
+        Ok(ImportIcsResponse {
+            schedule,
+            imported,
+            unmatched,
+            skipped: Vec::new(),
+        })
+    });
+
+    let mut response = result.map_err(|e| {
+        tracing::error!("Failed to import iCalendar: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    response.skipped = skipped;
+
+    Ok(Json(response))
+}
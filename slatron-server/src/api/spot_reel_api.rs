@@ -201,6 +201,9 @@ pub async fn create_spot_reel(
         transformer_scripts: None,
         is_dj_accessible: false,
         spot_reel_id: Some(reel_id_val),
+        sha256: None,
+        cue_points: None,
+        cue_out_secs: None,
     };
 
     let content: ContentItem = diesel::insert_into(content_items::table)
@@ -259,6 +262,9 @@ pub async fn update_spot_reel(
             transformer_scripts: None,
             is_dj_accessible: None,
             spot_reel_id: None,
+            sha256: None,
+            cue_points: None,
+            cue_out_secs: None,
         };
 
         let _ = diesel::update(
@@ -0,0 +1,211 @@
+use crate::db::DbConnection;
+use crate::models::{Schedule, ScheduleBlock, ScheduleVersion, User};
+use crate::services::channel_service;
+use crate::services::version_service::{self, VersionDiff};
+use crate::AppState;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize)]
+pub struct PublishRequest {
+    pub note: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct VersionDetail {
+    #[serde(flatten)]
+    pub version: ScheduleVersion,
+    pub blocks: Vec<ScheduleBlock>,
+}
+
+#[derive(Deserialize)]
+pub struct DiffQuery {
+    /// Version number; defaults to the published version
+    pub from: Option<i32>,
+    /// Version number; defaults to the draft
+    pub to: Option<i32>,
+}
+
+#[derive(Serialize)]
+pub struct DiffResponse {
+    /// `None` when the schedule has never been published
+    pub from: Option<i32>,
+    /// `None` means the draft
+    pub to: Option<i32>,
+    #[serde(flatten)]
+    pub diff: VersionDiff,
+}
+
+fn version_error(e: anyhow::Error) -> StatusCode {
+    match e.downcast_ref::<diesel::result::Error>() {
+        Some(diesel::result::Error::NotFound) => StatusCode::NOT_FOUND,
+        _ => {
+            tracing::error!("Schedule version operation failed: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+fn load_schedule(conn: &mut DbConnection, schedule_id: i32) -> Result<Schedule, StatusCode> {
+    use crate::schema::schedules::dsl::*;
+
+    schedules
+        .filter(id.eq(schedule_id))
+        .select(Schedule::as_select())
+        .first(conn)
+        .map_err(|_| StatusCode::NOT_FOUND)
+}
+
+/// Version number a schedule currently airs, if it has been published.
+fn published_version_number(
+    conn: &mut DbConnection,
+    schedule: &Schedule,
+) -> Result<Option<i32>, StatusCode> {
+    use crate::schema::schedule_versions::dsl::*;
+
+    let Some(version_id) = schedule.published_version_id else {
+        return Ok(None);
+    };
+    schedule_versions
+        .filter(id.eq(version_id))
+        .select(version)
+        .first(conn)
+        .map(Some)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+/// Have the nodes airing a schedule (directly or through their channel) refetch their
+/// schedule.
+pub(crate) async fn notify_nodes(state: &AppState, conn: &mut DbConnection, sched_id: i32) {
+    let assigned = channel_service::schedule_nodes(conn, sched_id).unwrap_or_default();
+    channel_service::notify(state, &assigned).await;
+}
+
+pub async fn list_versions(
+    State(state): State<AppState>,
+    Path(schedule_id): Path<i32>,
+) -> Result<Json<Vec<ScheduleVersion>>, StatusCode> {
+    let mut conn = state
+        .db
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let versions = version_service::list_versions(&mut conn, schedule_id).map_err(version_error)?;
+    Ok(Json(versions))
+}
+
+pub async fn get_version(
+    State(state): State<AppState>,
+    Path((schedule_id, version)): Path<(i32, i32)>,
+) -> Result<Json<VersionDetail>, StatusCode> {
+    let mut conn = state
+        .db
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let version =
+        version_service::get_version(&mut conn, schedule_id, version).map_err(version_error)?;
+    let blocks = version_service::version_blocks(&version).map_err(version_error)?;
+    Ok(Json(VersionDetail { version, blocks }))
+}
+
+/// Diff two versions of a schedule, by default the published version against the draft.
+pub async fn get_diff(
+    State(state): State<AppState>,
+    Path(schedule_id): Path<i32>,
+    Query(params): Query<DiffQuery>,
+) -> Result<Json<DiffResponse>, StatusCode> {
+    let mut conn = state
+        .db
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let schedule = load_schedule(&mut conn, schedule_id)?;
+    let from = match params.from {
+        Some(v) => Some(v),
+        None => published_version_number(&mut conn, &schedule)?,
+    };
+
+    let mut blocks_of = |version: Option<i32>| -> Result<Vec<ScheduleBlock>, StatusCode> {
+        let Some(v) = version else {
+            return Ok(Vec::new());
+        };
+        let version =
+            version_service::get_version(&mut conn, schedule_id, v).map_err(version_error)?;
+        version_service::version_blocks(&version).map_err(version_error)
+    };
+    let before = blocks_of(from)?;
+    let after = match params.to {
+        Some(v) => blocks_of(Some(v))?,
+        None => version_service::draft_blocks(&mut conn, schedule_id).map_err(version_error)?,
+    };
+
+    Ok(Json(DiffResponse {
+        from,
+        to: params.to,
+        diff: version_service::diff_blocks(&before, &after),
+    }))
+}
+
+/// Publish the draft as a new version and push it to the schedule's nodes.
+/// Publishing a draft that matches the published version is a conflict.
+pub async fn publish_schedule(
+    State(state): State<AppState>,
+    Extension(user): Extension<User>,
+    Path(schedule_id): Path<i32>,
+    Json(req): Json<PublishRequest>,
+) -> Result<Json<ScheduleVersion>, StatusCode> {
+    if !user.is_editor() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let mut conn = state
+        .db
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let schedule = load_schedule(&mut conn, schedule_id)?;
+    if schedule.published_version_id.is_some() {
+        let published =
+            version_service::published_blocks(&mut conn, &schedule).map_err(version_error)?;
+        let draft = version_service::draft_blocks(&mut conn, schedule_id).map_err(version_error)?;
+        if version_service::diff_blocks(&published, &draft).is_empty() {
+            return Err(StatusCode::CONFLICT);
+        }
+    }
+
+    let note = req.note.filter(|n| !n.trim().is_empty());
+    let version =
+        version_service::publish(&mut conn, schedule_id, user.id, note).map_err(version_error)?;
+
+    notify_nodes(&state, &mut conn, schedule_id).await;
+    Ok(Json(version))
+}
+
+/// Make an earlier version live again. The draft is reset to it and it is published
+/// as a new version.
+pub async fn rollback_schedule(
+    State(state): State<AppState>,
+    Extension(user): Extension<User>,
+    Path((schedule_id, version)): Path<(i32, i32)>,
+) -> Result<Json<ScheduleVersion>, StatusCode> {
+    if !user.is_editor() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let mut conn = state
+        .db
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let version = version_service::rollback(&mut conn, schedule_id, version, user.id)
+        .map_err(version_error)?;
+
+    notify_nodes(&state, &mut conn, schedule_id).await;
+    Ok(Json(version))
+}
@@ -21,3 +21,16 @@ pub fn run_migrations(conn: &mut DbConnection) -> Result<()> {
         .map(|_| ())
         .map_err(|e| anyhow::anyhow!("Migration error: {}", e))
 }
+
+/// A fresh in-memory database with every migration applied.
+#[cfg(test)]
+pub fn test_connection() -> DbConnection {
+    // Each connection to `:memory:` is its own database, so the pool holds just one
+    let pool = r2d2::Pool::builder()
+        .max_size(1)
+        .build(ConnectionManager::<SqliteConnection>::new(":memory:"))
+        .expect("in-memory database");
+    let mut conn = pool.get().expect("in-memory connection");
+    run_migrations(&mut conn).expect("migrations");
+    conn
+}
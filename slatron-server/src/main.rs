@@ -240,6 +240,12 @@ async fn main() -> Result<()> {
     // Spawn Cleanup task (TTS & Bumper Backs)
     tokio::spawn(services::cleaning_service::run(state.db.clone()));
 
+    // Spawn loudness analysis of content and bumpers
+    tokio::spawn(services::loudness_service::run(state.db.clone()));
+
+    // Spawn CAP alert feed polling and expiry
+    tokio::spawn(services::alert_service::run(state.clone()));
+
     // Get address before moving state
     let addr = format!("{}:{}", state.config.server.host, state.config.server.port);
 
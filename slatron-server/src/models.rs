@@ -57,6 +57,28 @@ pub struct Node {
     pub playback_position_secs: Option<f32>,
     pub playback_duration_secs: Option<f32>,
     pub script_context: Option<String>,
+    /// IANA timezone name; `None` uses the global `timezone` setting.
+    pub timezone: Option<String>,
+    /// JSON `FallbackPolicy`; `None` uses the global `fallback_policy` setting.
+    pub fallback_policy: Option<String>,
+    /// JSON asset cache status from the last heartbeat
+    pub cache_status: Option<String>,
+    /// JSON interrupt state from the last heartbeat; `None` while not interrupted
+    pub interrupt_status: Option<String>,
+    /// JSON array of CAP area codes or names; `None` uses the `cap_alerts` setting's areas
+    pub alert_areas: Option<String>,
+    /// Channel whose lineup, timezone and fallback the node inherits
+    pub channel_id: Option<i32>,
+    /// JSON clock offset and playback drift from the last heartbeat
+    pub sync_status: Option<String>,
+    /// Server clock minus the node's, from the last heartbeat
+    pub clock_offset_ms: Option<i32>,
+    /// Round trip of the exchange that measured the offset; `None` if estimated
+    pub clock_rtt_ms: Option<i32>,
+    /// Whether the offset exceeds the `clock` setting's `warn_ms`
+    pub clock_warning: bool,
+    /// JSON report of the node's last library scan
+    pub library_scan: Option<String>,
 }
 
 mod ts_seconds {
@@ -136,6 +158,8 @@ pub struct Schedule {
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
     pub dj_id: Option<i32>,
+    /// The `ScheduleVersion` nodes air; `None` until the schedule is first published
+    pub published_version_id: Option<i32>,
 }
 
 #[derive(Debug, Insertable, Deserialize)]
@@ -170,7 +194,7 @@ pub struct UpdateSchedule {
 }
 
 // Schedule Block models
-#[derive(Debug, Clone, Queryable, Selectable, Serialize, Deserialize)]
+#[derive(Debug, Clone, Queryable, Selectable, Insertable, Serialize, Deserialize)]
 #[diesel(table_name = crate::schema::schedule_blocks)]
 
 pub struct ScheduleBlock {
@@ -185,6 +209,24 @@ pub struct ScheduleBlock {
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
     pub dj_id: Option<i32>,
+    /// RFC 5545 RRULE for blocks of `recurring` schedules; `specific_date` is the series start.
+    pub rrule: Option<String>,
+    /// Second-precision duration; overrides `duration_minutes` when set.
+    pub duration_secs: Option<i32>,
+    /// JSON `FillRule`; when set the block is filled from a content query instead of `content_id`.
+    pub fill_rule: Option<String>,
+    /// JSON `BreakPolicy`; when set the content breaks for a spot reel at its cue points.
+    pub break_policy: Option<String>,
+    /// JSON `Transition` for segues out of the block's items; the global setting if unset.
+    pub transition: Option<String>,
+}
+
+impl ScheduleBlock {
+    pub fn total_duration_secs(&self) -> i64 {
+        self.duration_secs
+            .map(i64::from)
+            .unwrap_or(self.duration_minutes as i64 * 60)
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -206,6 +248,19 @@ pub struct NewScheduleBlock {
     pub duration_minutes: i32,
     pub script_id: Option<i32>,
     pub dj_id: Option<i32>,
+    pub rrule: Option<String>,
+    pub duration_secs: Option<i32>,
+    pub fill_rule: Option<String>,
+    pub break_policy: Option<String>,
+    pub transition: Option<String>,
+}
+
+impl NewScheduleBlock {
+    pub fn total_duration_secs(&self) -> i64 {
+        self.duration_secs
+            .map(i64::from)
+            .unwrap_or(self.duration_minutes as i64 * 60)
+    }
 }
 
 // Content Item models
@@ -227,6 +282,23 @@ pub struct ContentItem {
     pub transformer_scripts: Option<String>,
     pub is_dj_accessible: bool,
     pub spot_reel_id: Option<i32>,
+    pub last_played_at: Option<NaiveDateTime>,
+    /// Hex SHA-256 of the media, for nodes to verify downloaded copies
+    pub sha256: Option<String>,
+    /// JSON list of `CuePoint`s where scheduled breaks may be taken
+    pub cue_points: Option<String>,
+    /// Integrated loudness (LUFS), measured by the loudness analysis job
+    pub loudness_lufs: Option<f32>,
+    /// True peak (dBTP)
+    pub true_peak_dbtp: Option<f32>,
+    pub loudness_analyzed_at: Option<NaiveDateTime>,
+    /// Seconds in where the next item starts, leaving the outro to overlap it
+    pub cue_out_secs: Option<f32>,
+    /// Size and modification time of the file as last scanned by a node
+    pub file_size_bytes: Option<i64>,
+    pub file_modified_at: Option<NaiveDateTime>,
+    /// When a library scan last found the file gone; `None` while it's there
+    pub missing_since: Option<NaiveDateTime>,
 }
 
 #[derive(Debug, Insertable, Deserialize)]
@@ -243,6 +315,12 @@ pub struct NewContentItem {
     pub transformer_scripts: Option<String>,
     pub is_dj_accessible: bool,
     pub spot_reel_id: Option<i32>,
+    #[serde(default)]
+    pub sha256: Option<String>,
+    #[serde(default)]
+    pub cue_points: Option<String>,
+    #[serde(default)]
+    pub cue_out_secs: Option<f32>,
 }
 
 #[derive(Debug, AsChangeset, Deserialize)]
@@ -259,6 +337,9 @@ pub struct UpdateContentItem {
     pub transformer_scripts: Option<Option<String>>,
     pub is_dj_accessible: Option<bool>,
     pub spot_reel_id: Option<Option<i32>>,
+    pub sha256: Option<Option<String>>,
+    pub cue_points: Option<Option<String>>,
+    pub cue_out_secs: Option<Option<f32>>,
 }
 
 // AI Provider models
@@ -386,6 +467,7 @@ pub struct NodeSchedule {
     pub node_id: i32,
     pub schedule_id: i32,
     pub priority: Option<i32>,
+    pub excluded: bool,
 }
 
 #[derive(Debug, Insertable, Deserialize)]
@@ -394,6 +476,55 @@ pub struct NewNodeSchedule {
     pub node_id: i32,
     pub schedule_id: i32,
     pub priority: Option<i32>,
+    /// Drops the schedule from the lineup the node inherits from its channel
+    #[serde(default)]
+    pub excluded: bool,
+}
+
+// Channel models
+#[derive(Debug, Clone, Queryable, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = crate::schema::channels)]
+pub struct Channel {
+    pub id: Option<i32>,
+    pub name: String,
+    pub description: Option<String>,
+    /// IANA timezone for member nodes without their own
+    pub timezone: Option<String>,
+    /// JSON `FallbackPolicy` for member nodes without their own
+    pub fallback_policy: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+    /// Members play in lockstep against the server's clock
+    pub sync_playback: bool,
+}
+
+#[derive(Debug, Insertable, Deserialize)]
+#[diesel(table_name = crate::schema::channels)]
+pub struct NewChannel {
+    pub name: String,
+    pub description: Option<String>,
+    pub timezone: Option<String>,
+    pub fallback_policy: Option<String>,
+    #[serde(default)]
+    pub sync_playback: bool,
+}
+
+#[derive(Debug, AsChangeset, Deserialize)]
+#[diesel(table_name = crate::schema::channels)]
+pub struct UpdateChannel {
+    pub name: Option<String>,
+    pub description: Option<Option<String>>,
+    pub timezone: Option<Option<String>>,
+    pub fallback_policy: Option<Option<String>>,
+    pub sync_playback: Option<bool>,
+}
+
+#[derive(Debug, Insertable, Deserialize)]
+#[diesel(table_name = crate::schema::channel_schedules)]
+pub struct NewChannelSchedule {
+    pub channel_id: i32,
+    pub schedule_id: i32,
+    pub priority: Option<i32>,
 }
 
 // Permission models
@@ -489,6 +620,11 @@ pub struct Bumper {
     pub updated_at: NaiveDateTime,
     pub last_rendered_at: Option<NaiveDateTime>,
     pub bumper_back_id: Option<i32>,
+    /// Integrated loudness (LUFS) of the rendered bumper
+    pub loudness_lufs: Option<f32>,
+    /// True peak (dBTP) of the rendered bumper
+    pub true_peak_dbtp: Option<f32>,
+    pub loudness_analyzed_at: Option<NaiveDateTime>,
 }
 
 #[derive(Debug, Insertable, Deserialize)]
@@ -578,3 +714,144 @@ pub struct UpdateSpotReelItem {
     pub position: Option<i32>,
     pub title: Option<Option<String>>,
 }
+
+// Block Rundown models
+#[derive(Debug, Clone, Queryable, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = crate::schema::block_rundowns)]
+pub struct BlockRundown {
+    pub id: Option<i32>,
+    pub block_id: i32,
+    pub occurrence_date: NaiveDate,
+    pub fill_rule: String,
+    pub duration_secs: i32,
+    pub items: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::block_rundowns)]
+pub struct NewBlockRundown {
+    pub block_id: i32,
+    pub occurrence_date: NaiveDate,
+    pub fill_rule: String,
+    pub duration_secs: i32,
+    pub items: String,
+}
+
+// As-run log models
+#[derive(Debug, Clone, Queryable, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = crate::schema::as_run)]
+pub struct AsRunEntry {
+    pub id: Option<i32>,
+    pub node_id: i32,
+    pub kind: String,
+    pub content_id: Option<i32>,
+    pub title: Option<String>,
+    pub path: Option<String>,
+    pub started_at: NaiveDateTime,
+    pub ended_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::as_run)]
+pub struct NewAsRunEntry {
+    pub node_id: i32,
+    pub kind: String,
+    pub content_id: Option<i32>,
+    pub title: Option<String>,
+    pub path: Option<String>,
+    pub started_at: NaiveDateTime,
+}
+
+// Schedule version models
+#[derive(Debug, Clone, Queryable, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = crate::schema::schedule_versions)]
+pub struct ScheduleVersion {
+    pub id: Option<i32>,
+    pub schedule_id: i32,
+    /// 1-based, counting up per schedule
+    pub version: i32,
+    /// JSON array of the `ScheduleBlock`s published in this version
+    #[serde(skip_serializing)]
+    pub blocks: String,
+    pub note: Option<String>,
+    pub published_by: Option<i32>,
+    pub published_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::schedule_versions)]
+pub struct NewScheduleVersion {
+    pub schedule_id: i32,
+    pub version: i32,
+    pub blocks: String,
+    pub note: Option<String>,
+    pub published_by: Option<i32>,
+}
+
+// Schedule exception models
+#[derive(Debug, Clone, Queryable, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = crate::schema::schedule_exceptions)]
+pub struct ScheduleException {
+    pub id: Option<i32>,
+    pub schedule_id: i32,
+    pub start_date: NaiveDate,
+    /// Inclusive
+    pub end_date: NaiveDate,
+    /// `skip` or `replace`
+    pub action: String,
+    /// Schedule that airs instead when `action` is `replace`
+    pub replacement_schedule_id: Option<i32>,
+    pub note: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::schedule_exceptions)]
+pub struct NewScheduleException {
+    pub schedule_id: i32,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub action: String,
+    pub replacement_schedule_id: Option<i32>,
+    pub note: Option<String>,
+}
+
+// CAP alert models
+#[derive(Debug, Clone, Queryable, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = crate::schema::alerts)]
+pub struct Alert {
+    pub id: Option<i32>,
+    /// CAP identifier, unique per sender
+    pub identifier: String,
+    pub sender: String,
+    pub sent_at: NaiveDateTime,
+    pub event: String,
+    pub severity: String,
+    pub urgency: Option<String>,
+    pub headline: Option<String>,
+    pub description: Option<String>,
+    pub instruction: Option<String>,
+    /// JSON array of `CapArea`s
+    pub areas: String,
+    pub expires_at: Option<NaiveDateTime>,
+    pub received_at: NaiveDateTime,
+    /// When nodes stopped showing it: expired, cancelled or superseded
+    pub cleared_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::alerts)]
+pub struct NewAlert {
+    pub identifier: String,
+    pub sender: String,
+    pub sent_at: NaiveDateTime,
+    pub event: String,
+    pub severity: String,
+    pub urgency: Option<String>,
+    pub headline: Option<String>,
+    pub description: Option<String>,
+    pub instruction: Option<String>,
+    pub areas: String,
+    pub expires_at: Option<NaiveDateTime>,
+}
@@ -305,6 +305,118 @@ pub fn execute_script(
     Ok((result, cmds))
 }
 
+/// Operation budget for simulated scripts, so a runaway loop can't stall a preview
+const SIMULATION_MAX_OPERATIONS: u64 = 1_000_000;
+
+/// Run a node script's `transform`, `on_load` or `on_unload` the way the node does, for
+/// schedule simulation. Player, shell and download functions do nothing, the clock reads
+/// `at`, and `inject_bumper` calls are collected and returned.
+pub fn simulate_script_function(
+    script_content: &str,
+    fn_name: &str,
+    settings: &mut rhai::Map,
+    args: rhai::Map,
+    at: chrono::DateTime<chrono_tz::Tz>,
+) -> Result<Vec<String>, String> {
+    let mut engine = create_engine("global");
+    engine.set_max_operations(SIMULATION_MAX_OPERATIONS);
+
+    let injected = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    register_simulation_functions(&mut engine, at, injected.clone());
+
+    let ast = engine
+        .compile(script_content)
+        .map_err(|e| format!("Compilation error: {}", e))?;
+
+    let mut scope = Scope::new();
+    scope.push("args", args);
+    scope.push("settings", settings.clone());
+    engine
+        .run_ast_with_scope(&mut scope, &ast)
+        .map_err(|e| format!("Execution error: {}", e))?;
+
+    if fn_name == "transform" {
+        // Legacy scripts set globals instead of returning settings
+        for name in ["loop", "volume", "start_time", "end_time"] {
+            if let Some(val) = scope.get_value::<rhai::Dynamic>(name) {
+                settings.insert(name.into(), val);
+            }
+        }
+        match engine.call_fn::<rhai::Map>(&mut scope, &ast, fn_name, (rhai::Map::new(),)) {
+            Ok(returned) => settings.extend(returned),
+            Err(e) if e.to_string().contains("not found") => {}
+            Err(e) => return Err(format!("'{}' failed: {}", fn_name, e)),
+        }
+    } else {
+        let result = engine.call_fn::<()>(&mut scope, &ast, fn_name, (settings.clone(),));
+        let result = match result {
+            Err(e) if e.to_string().contains("not found") => {
+                engine.call_fn::<()>(&mut scope, &ast, fn_name, ())
+            }
+            other => other,
+        };
+        match result {
+            Ok(()) => {}
+            Err(e) if e.to_string().contains("not found") => {}
+            Err(e) => return Err(format!("'{}' failed: {}", fn_name, e)),
+        }
+    }
+
+    let bumpers = injected.lock().map(|b| b.clone()).unwrap_or_default();
+    Ok(bumpers)
+}
+
+fn register_simulation_functions(
+    engine: &mut Engine,
+    at: chrono::DateTime<chrono_tz::Tz>,
+    injected: std::sync::Arc<std::sync::Mutex<Vec<String>>>,
+) {
+    use chrono::Timelike;
+
+    engine.register_fn("inject_bumper", move |name_or_id: String| {
+        if let Ok(mut bumpers) = injected.lock() {
+            bumpers.push(name_or_id);
+        }
+    });
+    engine.register_fn("is_top_of_hour", move || -> bool { at.minute() == 0 });
+    engine.register_fn("get_current_hour", move || -> i64 { at.hour() as i64 });
+
+    engine.register_fn("set_loop", |ctx: &mut rhai::Map, enabled: bool| {
+        ctx.insert("loop".into(), rhai::Dynamic::from(enabled));
+    });
+    engine.register_fn("set_volume", |ctx: &mut rhai::Map, volume: i64| {
+        ctx.insert("volume".into(), rhai::Dynamic::from(volume));
+    });
+    engine.register_fn("set_start_time", |ctx: &mut rhai::Map, seconds: f64| {
+        ctx.insert("start_time".into(), rhai::Dynamic::from(seconds));
+    });
+    engine.register_fn("set_end_time", |ctx: &mut rhai::Map, seconds: f64| {
+        ctx.insert("end_time".into(), rhai::Dynamic::from(seconds));
+    });
+
+    // Transformers may fetch content on the node; a simulation never does
+    engine.register_fn(
+        "shell_execute",
+        |_cmd: String, _args: Vec<rhai::Dynamic>| -> rhai::Map { simulated_shell_result() },
+    );
+    engine.register_fn("shell_execute", |_cmd: String| -> rhai::Map {
+        simulated_shell_result()
+    });
+    engine.register_fn(
+        "download_file",
+        |_url: String, _output_path: String| -> bool { false },
+    );
+    engine.register_fn("get_env", |_key: String| -> String { String::new() });
+}
+
+fn simulated_shell_result() -> rhai::Map {
+    let mut map = rhai::Map::new();
+    map.insert("code".into(), (-1_i64).into());
+    map.insert("stdout".into(), "".into());
+    map.insert("stderr".into(), "not run in simulation".into());
+    map
+}
+
 #[cfg(test)]
 mod path_test;
 
@@ -15,6 +15,50 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    alerts (id) {
+        id -> Nullable<Integer>,
+        identifier -> Text,
+        sender -> Text,
+        sent_at -> Timestamp,
+        event -> Text,
+        severity -> Text,
+        urgency -> Nullable<Text>,
+        headline -> Nullable<Text>,
+        description -> Nullable<Text>,
+        instruction -> Nullable<Text>,
+        areas -> Text,
+        expires_at -> Nullable<Timestamp>,
+        received_at -> Timestamp,
+        cleared_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    as_run (id) {
+        id -> Nullable<Integer>,
+        node_id -> Integer,
+        kind -> Text,
+        content_id -> Nullable<Integer>,
+        title -> Nullable<Text>,
+        path -> Nullable<Text>,
+        started_at -> Timestamp,
+        ended_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    block_rundowns (id) {
+        id -> Nullable<Integer>,
+        block_id -> Integer,
+        occurrence_date -> Date,
+        fill_rule -> Text,
+        duration_secs -> Integer,
+        items -> Text,
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     bumper_backs (id) {
         id -> Nullable<Integer>,
@@ -43,6 +87,32 @@ diesel::table! {
         updated_at -> Timestamp,
         last_rendered_at -> Nullable<Timestamp>,
         bumper_back_id -> Nullable<Integer>,
+        loudness_lufs -> Nullable<Float>,
+        true_peak_dbtp -> Nullable<Float>,
+        loudness_analyzed_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    channel_schedules (id) {
+        id -> Nullable<Integer>,
+        channel_id -> Integer,
+        schedule_id -> Integer,
+        priority -> Nullable<Integer>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    channels (id) {
+        id -> Nullable<Integer>,
+        name -> Text,
+        description -> Nullable<Text>,
+        timezone -> Nullable<Text>,
+        fallback_policy -> Nullable<Text>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        sync_playback -> Bool,
     }
 }
 
@@ -62,6 +132,16 @@ diesel::table! {
         transformer_scripts -> Nullable<Text>,
         is_dj_accessible -> Bool,
         spot_reel_id -> Nullable<Integer>,
+        last_played_at -> Nullable<Timestamp>,
+        sha256 -> Nullable<Text>,
+        cue_points -> Nullable<Text>,
+        loudness_lufs -> Nullable<Float>,
+        true_peak_dbtp -> Nullable<Float>,
+        loudness_analyzed_at -> Nullable<Timestamp>,
+        cue_out_secs -> Nullable<Float>,
+        file_size_bytes -> Nullable<BigInt>,
+        file_modified_at -> Nullable<Timestamp>,
+        missing_since -> Nullable<Timestamp>,
     }
 }
 
@@ -103,6 +183,15 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    node_library_files (id) {
+        id -> Nullable<Integer>,
+        node_id -> Integer,
+        content_id -> Integer,
+        seen_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     node_schedules (id) {
         id -> Nullable<Integer>,
@@ -110,6 +199,7 @@ diesel::table! {
         schedule_id -> Integer,
         created_at -> Timestamp,
         priority -> Nullable<Integer>,
+        excluded -> Bool,
     }
 }
 
@@ -128,6 +218,17 @@ diesel::table! {
         playback_position_secs -> Nullable<Float>,
         playback_duration_secs -> Nullable<Float>,
         script_context -> Nullable<Text>,
+        timezone -> Nullable<Text>,
+        fallback_policy -> Nullable<Text>,
+        cache_status -> Nullable<Text>,
+        interrupt_status -> Nullable<Text>,
+        alert_areas -> Nullable<Text>,
+        channel_id -> Nullable<Integer>,
+        sync_status -> Nullable<Text>,
+        clock_offset_ms -> Nullable<Integer>,
+        clock_rtt_ms -> Nullable<Integer>,
+        clock_warning -> Bool,
+        library_scan -> Nullable<Text>,
     }
 }
 
@@ -155,6 +256,36 @@ diesel::table! {
         created_at -> Timestamp,
         updated_at -> Timestamp,
         dj_id -> Nullable<Integer>,
+        rrule -> Nullable<Text>,
+        duration_secs -> Nullable<Integer>,
+        fill_rule -> Nullable<Text>,
+        break_policy -> Nullable<Text>,
+        transition -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    schedule_exceptions (id) {
+        id -> Nullable<Integer>,
+        schedule_id -> Integer,
+        start_date -> Date,
+        end_date -> Date,
+        action -> Text,
+        replacement_schedule_id -> Nullable<Integer>,
+        note -> Nullable<Text>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    schedule_versions (id) {
+        id -> Nullable<Integer>,
+        schedule_id -> Integer,
+        version -> Integer,
+        blocks -> Text,
+        note -> Nullable<Text>,
+        published_by -> Nullable<Integer>,
+        published_at -> Timestamp,
     }
 }
 
@@ -169,6 +300,7 @@ diesel::table! {
         created_at -> Timestamp,
         updated_at -> Timestamp,
         dj_id -> Nullable<Integer>,
+        published_version_id -> Nullable<Integer>,
     }
 }
 
@@ -221,33 +353,50 @@ diesel::table! {
     }
 }
 
+diesel::joinable!(as_run -> nodes (node_id));
+diesel::joinable!(block_rundowns -> schedule_blocks (block_id));
 diesel::joinable!(bumpers -> bumper_backs (bumper_back_id));
+diesel::joinable!(channel_schedules -> channels (channel_id));
+diesel::joinable!(channel_schedules -> schedules (schedule_id));
 diesel::joinable!(content_items -> scripts (adapter_id));
 diesel::joinable!(content_items -> spot_reels (spot_reel_id));
 diesel::joinable!(dj_memories -> dj_profiles (dj_id));
+diesel::joinable!(node_library_files -> content_items (content_id));
+diesel::joinable!(node_library_files -> nodes (node_id));
 diesel::joinable!(node_schedules -> nodes (node_id));
 diesel::joinable!(node_schedules -> schedules (schedule_id));
+diesel::joinable!(nodes -> channels (channel_id));
 diesel::joinable!(nodes -> content_items (current_content_id));
 diesel::joinable!(permissions -> users (user_id));
 diesel::joinable!(schedule_blocks -> content_items (content_id));
 diesel::joinable!(schedule_blocks -> dj_profiles (dj_id));
 diesel::joinable!(schedule_blocks -> schedules (schedule_id));
 diesel::joinable!(schedule_blocks -> scripts (script_id));
+diesel::joinable!(schedule_versions -> schedules (schedule_id));
+diesel::joinable!(schedule_versions -> users (published_by));
 diesel::joinable!(schedules -> dj_profiles (dj_id));
 diesel::joinable!(spot_reel_items -> spot_reels (spot_reel_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
     ai_providers,
+    alerts,
+    as_run,
+    block_rundowns,
     bumper_backs,
     bumpers,
+    channel_schedules,
+    channels,
     content_items,
     dj_memories,
     dj_profiles,
     global_settings,
+    node_library_files,
     node_schedules,
     nodes,
     permissions,
     schedule_blocks,
+    schedule_exceptions,
+    schedule_versions,
     schedules,
     scripts,
     spot_reels,
@@ -34,6 +34,31 @@ const DEFAULT_SETTINGS: &[(&str, &str, &str)] = &[
         "Whether the initial onboarding wizard has been completed.",
     ),
     ("timezone", "UTC", "Global timezone for the station"),
+    (
+        "fallback_policy",
+        "{\"mode\":\"silence\"}",
+        "JSON policy for what nodes play when nothing is scheduled.",
+    ),
+    (
+        "loudness_target_lufs",
+        "-23",
+        "Integrated loudness (LUFS) nodes normalise content and bumpers to. Empty disables normalisation.",
+    ),
+    (
+        "transition",
+        "{\"audio_crossfade_secs\":0,\"video_fade_secs\":0}",
+        "JSON transition nodes use between items unless a block sets its own; zero is a hard cut.",
+    ),
+    (
+        "cap_alerts",
+        "{\"feeds\":[],\"poll_secs\":60,\"min_severity\":\"severe\",\"areas\":[],\"read_aloud\":false}",
+        "JSON CAP alert feeds (URLs or files) polled for alerts shown on nodes, with the minimum severity, station areas and TTS read-out.",
+    ),
+    (
+        "clock",
+        "{\"warn_ms\":2000,\"server_time\":false}",
+        "JSON node clock checks: the offset from the server's clock that raises a warning, and whether every node runs its schedule on server time.",
+    ),
 ];
 
 // Define default scripts
@@ -0,0 +1,872 @@
+//! CAP 1.2 (Common Alerting Protocol) alerts shown on nodes.
+//!
+//! Alerts arrive from the feeds in the `cap_alerts` setting, polled in the background
+//! (HTTP URLs or local files), or are pushed to `POST /api/alerts/cap`. Actual alerts at
+//! or above the minimum severity that cover the station's areas are stored and pushed
+//! to every connected node whose own areas they cover, as a crawl along the bottom of
+//! the screen and optionally read out over a TTS voice. Cancelled, superseded and
+//! expired alerts are cleared from the nodes again.
+
+use crate::db::DbConnection;
+use crate::models::{AiProvider, Alert, NewAlert};
+use crate::websocket::{NodeCommand, ServerMessage};
+use crate::AppState;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use diesel::prelude::*;
+use quick_xml::events::Event;
+use quick_xml::reader::Reader;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// How often expiries are checked; feeds are polled every `poll_secs`
+const TICK: Duration = Duration::from_secs(10);
+/// Feeds are never polled more often than this
+const MIN_POLL_SECS: u64 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Unknown,
+    Minor,
+    Moderate,
+    Severe,
+    Extreme,
+}
+
+impl std::str::FromStr for Severity {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "unknown" => Ok(Severity::Unknown),
+            "minor" => Ok(Severity::Minor),
+            "moderate" => Ok(Severity::Moderate),
+            "severe" => Ok(Severity::Severe),
+            "extreme" => Ok(Severity::Extreme),
+            other => Err(anyhow!("Unknown CAP severity '{}'", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CapArea {
+    pub desc: String,
+    /// `valueName=value`, e.g. `SAME=048439` or `UGC=TXC439`
+    #[serde(default)]
+    pub geocodes: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CapAlert {
+    pub identifier: String,
+    pub sender: String,
+    pub sent: DateTime<Utc>,
+    /// `Actual`, `Exercise`, `System`, `Test` or `Draft`
+    pub status: String,
+    /// `Alert`, `Update`, `Cancel`, `Ack` or `Error`
+    pub msg_type: String,
+    /// (sender, identifier) of the alerts an update or cancellation refers to
+    pub references: Vec<(String, String)>,
+    /// The English (or else the first) info block
+    pub info: Option<CapInfo>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CapInfo {
+    pub event: String,
+    pub severity: Severity,
+    pub urgency: Option<String>,
+    pub headline: Option<String>,
+    pub description: Option<String>,
+    pub instruction: Option<String>,
+    pub expires: Option<DateTime<Utc>>,
+    pub areas: Vec<CapArea>,
+}
+
+/// Parse every `<alert>` in `xml`: a single CAP message or a feed wrapping several
+/// (namespace prefixes are ignored). Malformed alerts are logged and skipped.
+pub fn parse_alerts(xml: &str) -> Result<Vec<CapAlert>> {
+    let mut reader = Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+
+    let mut alerts = Vec::new();
+    // Element path below the current <alert>, if inside one
+    let mut path: Option<Vec<String>> = None;
+    let mut raw = RawAlert::default();
+    let mut text = String::new();
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) => {
+                let name = String::from_utf8_lossy(e.local_name().as_ref()).into_owned();
+                match path.as_mut() {
+                    Some(path) => {
+                        raw.open(path, &name);
+                        path.push(name);
+                    }
+                    None if name == "alert" => {
+                        path = Some(Vec::new());
+                        raw = RawAlert::default();
+                    }
+                    None => {}
+                }
+                text.clear();
+            }
+            Event::Text(t) => text.push_str(&t.unescape()?),
+            Event::CData(t) => text.push_str(&String::from_utf8_lossy(&t)),
+            Event::End(_) => {
+                if let Some(elements) = path.as_mut() {
+                    if elements.is_empty() {
+                        match std::mem::take(&mut raw).finish() {
+                            Ok(alert) => alerts.push(alert),
+                            Err(e) => tracing::warn!("Skipping CAP alert: {}", e),
+                        }
+                        path = None;
+                    } else {
+                        raw.close(elements, text.trim());
+                        elements.pop();
+                    }
+                }
+                text.clear();
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(alerts)
+}
+
+#[derive(Default)]
+struct RawAlert {
+    identifier: Option<String>,
+    sender: Option<String>,
+    sent: Option<String>,
+    status: Option<String>,
+    msg_type: Option<String>,
+    references: Option<String>,
+    infos: Vec<RawInfo>,
+}
+
+#[derive(Default)]
+struct RawInfo {
+    language: Option<String>,
+    event: Option<String>,
+    severity: Option<String>,
+    urgency: Option<String>,
+    headline: Option<String>,
+    description: Option<String>,
+    instruction: Option<String>,
+    expires: Option<String>,
+    areas: Vec<CapArea>,
+    /// valueName and value of the geocode being read
+    geocode: (String, String),
+}
+
+impl RawAlert {
+    /// An element opened at `path` (below <alert>).
+    fn open(&mut self, path: &[String], name: &str) {
+        match (path, name) {
+            ([], "info") => self.infos.push(RawInfo::default()),
+            ([info], "area") if info == "info" => {
+                if let Some(info) = self.infos.last_mut() {
+                    info.areas.push(CapArea::default());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// The element at `path` closed with `text`.
+    fn close(&mut self, path: &[String], text: &str) {
+        let path: Vec<&str> = path.iter().map(String::as_str).collect();
+        let value = Some(text.to_string()).filter(|t| !t.is_empty());
+        if let [field] = path.as_slice() {
+            match *field {
+                "identifier" => self.identifier = value,
+                "sender" => self.sender = value,
+                "sent" => self.sent = value,
+                "status" => self.status = value,
+                "msgType" => self.msg_type = value,
+                "references" => self.references = value,
+                _ => {}
+            }
+            return;
+        }
+
+        let Some(info) = self.infos.last_mut() else {
+            return;
+        };
+        match path.as_slice() {
+            ["info", "language"] => info.language = value,
+            ["info", "event"] => info.event = value,
+            ["info", "severity"] => info.severity = value,
+            ["info", "urgency"] => info.urgency = value,
+            ["info", "headline"] => info.headline = value,
+            ["info", "description"] => info.description = value,
+            ["info", "instruction"] => info.instruction = value,
+            ["info", "expires"] => info.expires = value,
+            ["info", "area", "areaDesc"] => {
+                if let Some(area) = info.areas.last_mut() {
+                    area.desc = text.to_string();
+                }
+            }
+            ["info", "area", "geocode", "valueName"] => info.geocode.0 = text.to_string(),
+            ["info", "area", "geocode", "value"] => info.geocode.1 = text.to_string(),
+            ["info", "area", "geocode"] => {
+                let (name, code) = std::mem::take(&mut info.geocode);
+                if let Some(area) = info.areas.last_mut() {
+                    area.geocodes.push(format!("{}={}", name, code));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn finish(self) -> Result<CapAlert> {
+        let identifier = self
+            .identifier
+            .ok_or_else(|| anyhow!("Alert without identifier"))?;
+        let field = |value: Option<String>, name: &str| {
+            value.ok_or_else(|| anyhow!("Alert {} has no {}", identifier, name))
+        };
+        let sender = field(self.sender, "sender")?;
+        let sent = parse_time(&field(self.sent, "sent time")?)?;
+        let status = field(self.status, "status")?;
+        let msg_type = field(self.msg_type, "msgType")?;
+
+        // "sender,identifier,sent" triples, space separated
+        let references = self
+            .references
+            .unwrap_or_default()
+            .split_whitespace()
+            .filter_map(|reference| {
+                let mut parts = reference.split(',');
+                Some((parts.next()?.to_string(), parts.next()?.to_string()))
+            })
+            .collect();
+
+        let mut infos = self.infos;
+        let english = infos.iter().position(|info| {
+            info.language
+                .as_deref()
+                .is_none_or(|l| l.to_ascii_lowercase().starts_with("en"))
+        });
+        let info = match english {
+            Some(index) => Some(infos.swap_remove(index)),
+            None => infos.into_iter().next(),
+        };
+        let info = match info {
+            Some(info) => Some(CapInfo {
+                event: info
+                    .event
+                    .ok_or_else(|| anyhow!("Alert {} has no event", identifier))?,
+                severity: info.severity.as_deref().unwrap_or("Unknown").parse()?,
+                urgency: info.urgency,
+                headline: info.headline,
+                description: info.description,
+                instruction: info.instruction,
+                expires: info.expires.as_deref().map(parse_time).transpose()?,
+                areas: info.areas,
+            }),
+            None => None,
+        };
+
+        Ok(CapAlert {
+            identifier,
+            sender,
+            sent,
+            status,
+            msg_type,
+            references,
+            info,
+        })
+    }
+}
+
+fn parse_time(value: &str) -> Result<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|t| t.with_timezone(&Utc))
+        .map_err(|e| anyhow!("Invalid CAP time '{}': {}", value, e))
+}
+
+/// The `cap_alerts` setting.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapSettings {
+    /// HTTP(S) URLs or local file paths of CAP documents or feeds
+    #[serde(default)]
+    pub feeds: Vec<String>,
+    #[serde(default = "default_poll_secs")]
+    pub poll_secs: u64,
+    #[serde(default = "default_min_severity")]
+    pub min_severity: String,
+    /// Area codes or names alerts must cover to be shown at all; any area if empty
+    #[serde(default)]
+    pub areas: Vec<String>,
+    /// Read each new alert out on the nodes through TTS
+    #[serde(default)]
+    pub read_aloud: bool,
+    /// TTS provider for read-outs; the first active TTS provider if unset
+    #[serde(default)]
+    pub tts_provider_id: Option<i32>,
+}
+
+fn default_poll_secs() -> u64 {
+    60
+}
+
+fn default_min_severity() -> String {
+    "severe".to_string()
+}
+
+impl Default for CapSettings {
+    fn default() -> Self {
+        Self {
+            feeds: Vec::new(),
+            poll_secs: default_poll_secs(),
+            min_severity: default_min_severity(),
+            areas: Vec::new(),
+            read_aloud: false,
+            tts_provider_id: None,
+        }
+    }
+}
+
+impl CapSettings {
+    pub fn parse(json: &str) -> Result<Self> {
+        let settings: Self =
+            serde_json::from_str(json).map_err(|e| anyhow!("Invalid cap_alerts setting: {}", e))?;
+        settings.min_severity()?;
+        Ok(settings)
+    }
+
+    pub fn min_severity(&self) -> Result<Severity> {
+        self.min_severity.parse()
+    }
+
+    /// The setting, or the defaults (no feeds) if unset or invalid.
+    pub fn load(conn: &mut DbConnection) -> Self {
+        use crate::schema::global_settings::dsl::{global_settings, key, value};
+
+        let json: Option<String> = global_settings
+            .filter(key.eq("cap_alerts"))
+            .select(value)
+            .first(conn)
+            .optional()
+            .ok()
+            .flatten();
+
+        match json.as_deref().map(Self::parse) {
+            Some(Ok(settings)) => settings,
+            Some(Err(e)) => {
+                tracing::warn!("{}; CAP alerts use the defaults", e);
+                Self::default()
+            }
+            None => Self::default(),
+        }
+    }
+}
+
+/// Whether `filter` (area codes such as `SAME=048439`, bare code values, or names
+/// matched against the area description) covers any of `areas`. An empty filter
+/// covers everything.
+pub fn covers(filter: &[String], areas: &[CapArea]) -> bool {
+    filter.is_empty()
+        || filter.iter().any(|wanted| {
+            let wanted = wanted.trim();
+            areas.iter().any(|area| {
+                area.geocodes.iter().any(|code| {
+                    code.eq_ignore_ascii_case(wanted)
+                        || code
+                            .split_once('=')
+                            .is_some_and(|(_, value)| value.eq_ignore_ascii_case(wanted))
+                }) || area.desc.to_lowercase().contains(&wanted.to_lowercase())
+            })
+        })
+}
+
+/// Parse a node's `alert_areas`.
+pub fn parse_areas(json: &str) -> Result<Vec<String>> {
+    serde_json::from_str(json).map_err(|e| anyhow!("Invalid alert areas: {}", e))
+}
+
+/// What ingesting alerts changed.
+#[derive(Debug, Default, Serialize)]
+pub struct Ingested {
+    /// Newly stored alerts to show
+    pub shown: Vec<Alert>,
+    /// Alerts cancelled or superseded
+    pub cleared: Vec<i32>,
+}
+
+/// Store `alert` if it should be shown, and clear whatever it cancels or updates.
+pub fn ingest(
+    conn: &mut DbConnection,
+    alert: &CapAlert,
+    settings: &CapSettings,
+    now: DateTime<Utc>,
+    ingested: &mut Ingested,
+) -> Result<()> {
+    if alert.status != "Actual" {
+        tracing::debug!("Ignoring {} CAP alert {}", alert.status, alert.identifier);
+        return Ok(());
+    }
+
+    match alert.msg_type.as_str() {
+        "Cancel" => {
+            ingested.cleared.extend(clear_references(conn, alert, now)?);
+            return Ok(());
+        }
+        "Update" => ingested.cleared.extend(clear_references(conn, alert, now)?),
+        "Alert" => {}
+        _ => return Ok(()),
+    }
+
+    let Some(info) = &alert.info else {
+        return Ok(());
+    };
+    if info.severity < settings.min_severity()?
+        || info.expires.is_some_and(|expires| expires <= now)
+        || !covers(&settings.areas, &info.areas)
+    {
+        return Ok(());
+    }
+
+    use crate::schema::alerts::dsl::*;
+    let inserted = diesel::insert_into(alerts)
+        .values(&NewAlert {
+            identifier: alert.identifier.clone(),
+            sender: alert.sender.clone(),
+            sent_at: alert.sent.naive_utc(),
+            event: info.event.clone(),
+            severity: format!("{:?}", info.severity),
+            urgency: info.urgency.clone(),
+            headline: info.headline.clone(),
+            description: info.description.clone(),
+            instruction: info.instruction.clone(),
+            areas: serde_json::to_string(&info.areas)?,
+            expires_at: info.expires.map(|t| t.naive_utc()),
+        })
+        // Feeds repeat alerts on every poll
+        .on_conflict((sender, identifier))
+        .do_nothing()
+        .returning(Alert::as_returning())
+        .get_result(conn)
+        .optional()?;
+
+    if let Some(row) = inserted {
+        tracing::warn!("CAP alert {}: {}", row.identifier, row.event);
+        ingested.shown.push(row);
+    }
+    Ok(())
+}
+
+fn clear_references(
+    conn: &mut DbConnection,
+    alert: &CapAlert,
+    now: DateTime<Utc>,
+) -> Result<Vec<i32>> {
+    use crate::schema::alerts::dsl::*;
+
+    let mut ids = Vec::new();
+    for (ref_sender, ref_identifier) in &alert.references {
+        let cleared: Vec<Option<i32>> = diesel::update(
+            alerts
+                .filter(sender.eq(ref_sender))
+                .filter(identifier.eq(ref_identifier))
+                .filter(cleared_at.is_null()),
+        )
+        .set(cleared_at.eq(Some(now.naive_utc())))
+        .returning(id)
+        .get_results(conn)?;
+        ids.extend(cleared.into_iter().flatten());
+    }
+    Ok(ids)
+}
+
+/// Clear alerts that expired by `now`.
+pub fn expire(conn: &mut DbConnection, now: NaiveDateTime) -> Result<Vec<i32>> {
+    use crate::schema::alerts::dsl::*;
+
+    let cleared: Vec<Option<i32>> = diesel::update(
+        alerts
+            .filter(cleared_at.is_null())
+            .filter(expires_at.le(now)),
+    )
+    .set(cleared_at.eq(Some(now)))
+    .returning(id)
+    .get_results(conn)?;
+    Ok(cleared.into_iter().flatten().collect())
+}
+
+/// Clear an alert by hand. Returns whether it was showing.
+pub fn clear(conn: &mut DbConnection, alert_id: i32) -> Result<bool> {
+    use crate::schema::alerts::dsl::*;
+
+    let updated = diesel::update(alerts.filter(id.eq(alert_id)).filter(cleared_at.is_null()))
+        .set(cleared_at.eq(Some(Utc::now().naive_utc())))
+        .execute(conn)?;
+    Ok(updated > 0)
+}
+
+/// The line crawled across the screen for an alert.
+pub fn crawl_text(alert: &Alert) -> String {
+    let parts = [
+        Some(alert.event.to_uppercase()),
+        alert.headline.clone(),
+        alert.description.clone(),
+        alert.instruction.clone(),
+    ];
+    let text = parts.into_iter().flatten().collect::<Vec<_>>().join(" — ");
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn spoken_text(alert: &Alert) -> String {
+    let parts = [
+        Some(format!("Emergency alert. {}.", alert.event)),
+        alert.headline.clone(),
+        alert.instruction.clone(),
+    ];
+    parts.into_iter().flatten().collect::<Vec<_>>().join(" ")
+}
+
+fn show_command(alert: &Alert) -> NodeCommand {
+    NodeCommand::ShowAlert {
+        alert_id: alert.id.unwrap_or_default(),
+        text: crawl_text(alert),
+        severity: alert.severity.clone(),
+        expires_at: alert.expires_at.map(|t| t.and_utc()),
+    }
+}
+
+/// Whether a node with `alert_areas` should show `alert`.
+fn node_covers(node_areas: Option<&str>, alert: &Alert) -> bool {
+    let Some(json) = node_areas else {
+        return true;
+    };
+    let areas: Vec<CapArea> = serde_json::from_str(&alert.areas).unwrap_or_default();
+    match parse_areas(json) {
+        Ok(filter) => covers(&filter, &areas),
+        Err(e) => {
+            tracing::warn!("{}; showing alert {:?} anyway", e, alert.id);
+            true
+        }
+    }
+}
+
+/// Commands showing the active alerts that cover `node_id`, for a node that just
+/// connected.
+pub fn active_commands(conn: &mut DbConnection, node_id: i32) -> Result<Vec<NodeCommand>> {
+    use crate::schema::alerts::dsl::{alerts, cleared_at, id};
+    use crate::schema::nodes::dsl as n_dsl;
+
+    let node_areas: Option<String> = n_dsl::nodes
+        .filter(n_dsl::id.eq(node_id))
+        .select(n_dsl::alert_areas)
+        .first(conn)?;
+
+    let active: Vec<Alert> = alerts
+        .filter(cleared_at.is_null())
+        .order(id.asc())
+        .select(Alert::as_select())
+        .load(conn)?;
+
+    let now = Utc::now().naive_utc();
+    Ok(active
+        .iter()
+        .filter(|alert| alert.expires_at.is_none_or(|t| t > now))
+        .filter(|alert| node_covers(node_areas.as_deref(), alert))
+        .map(show_command)
+        .collect())
+}
+
+/// Push what `ingested` changed to the connected nodes.
+pub async fn dispatch(state: &AppState, ingested: &Ingested, settings: &CapSettings) -> Result<()> {
+    let connected = state.connected_nodes.read().await.clone();
+    if connected.is_empty() {
+        return Ok(());
+    }
+
+    for alert_id in &ingested.cleared {
+        for tx in connected.values() {
+            let _ = tx.send(ServerMessage::Command {
+                command: NodeCommand::ClearAlert {
+                    alert_id: *alert_id,
+                },
+            });
+        }
+    }
+    if ingested.shown.is_empty() {
+        return Ok(());
+    }
+
+    let node_areas: Vec<(Option<i32>, Option<String>)> = {
+        use crate::schema::nodes::dsl::*;
+        let mut conn = state.db.get()?;
+        nodes
+            .filter(id.eq_any(connected.keys().copied().collect::<Vec<_>>()))
+            .select((id, alert_areas))
+            .load(&mut conn)?
+    };
+
+    for alert in &ingested.shown {
+        let targets: Vec<i32> = node_areas
+            .iter()
+            .filter(|(_, areas)| node_covers(areas.as_deref(), alert))
+            .filter_map(|(node_id, _)| *node_id)
+            .collect();
+        let voice_url = if settings.read_aloud && !targets.is_empty() {
+            read_aloud(state, alert, settings).await
+        } else {
+            None
+        };
+
+        for node_id in targets {
+            let Some(tx) = connected.get(&node_id) else {
+                continue;
+            };
+            let _ = tx.send(ServerMessage::Command {
+                command: show_command(alert),
+            });
+            if let Some(url) = &voice_url {
+                let _ = tx.send(ServerMessage::Command {
+                    command: NodeCommand::InjectAudio {
+                        url: url.clone(),
+                        mix: true,
+                    },
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Generate the alert's read-out; the URL nodes fetch it from.
+async fn read_aloud(state: &AppState, alert: &Alert, settings: &CapSettings) -> Option<String> {
+    let provider = match tts_provider(state, settings.tts_provider_id) {
+        Ok(Some(provider)) => provider,
+        Ok(None) => {
+            tracing::warn!("No active TTS provider to read alerts out");
+            return None;
+        }
+        Err(e) => {
+            tracing::error!("Failed to load TTS provider: {}", e);
+            return None;
+        }
+    };
+
+    let output_dir = std::path::PathBuf::from("static/tts");
+    match state
+        .tts_service
+        .generate_speech(
+            &spoken_text(alert),
+            None,
+            None,
+            None,
+            &output_dir,
+            &provider,
+        )
+        .await
+    {
+        Ok(path) => {
+            let filename = path.file_name()?.to_string_lossy().into_owned();
+            Some(format!(
+                "http://{}:{}/tts/{}",
+                state.config.server.host, state.config.server.port, filename
+            ))
+        }
+        Err(e) => {
+            tracing::error!("Alert TTS generation failed: {}", e);
+            None
+        }
+    }
+}
+
+fn tts_provider(state: &AppState, provider_id: Option<i32>) -> Result<Option<AiProvider>> {
+    use crate::schema::ai_providers::dsl::*;
+
+    let mut conn = state.db.get()?;
+    let mut query = ai_providers
+        .filter(is_active.eq(true))
+        .filter(provider_category.eq("tts"))
+        .into_boxed();
+    if let Some(provider_id) = provider_id {
+        query = query.filter(id.eq(provider_id));
+    }
+    Ok(query
+        .order(id.asc())
+        .select(AiProvider::as_select())
+        .first(&mut conn)
+        .optional()?)
+}
+
+async fn fetch_feed(client: &reqwest::Client, feed: &str) -> Result<String> {
+    if feed.starts_with("http://") || feed.starts_with("https://") {
+        Ok(client
+            .get(feed)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?)
+    } else {
+        let path = feed.strip_prefix("file://").unwrap_or(feed);
+        Ok(tokio::fs::read_to_string(path).await?)
+    }
+}
+
+async fn poll_feeds(state: &AppState, client: &reqwest::Client, settings: &CapSettings) {
+    let mut ingested = Ingested::default();
+    for feed in &settings.feeds {
+        let alerts = match fetch_feed(client, feed)
+            .await
+            .and_then(|xml| parse_alerts(&xml))
+        {
+            Ok(alerts) => alerts,
+            Err(e) => {
+                tracing::warn!("Failed to read CAP feed {}: {}", feed, e);
+                continue;
+            }
+        };
+
+        let result = state
+            .db
+            .get()
+            .map_err(anyhow::Error::from)
+            .and_then(|mut conn| {
+                let now = Utc::now();
+                alerts
+                    .iter()
+                    .try_for_each(|alert| ingest(&mut conn, alert, settings, now, &mut ingested))
+            });
+        if let Err(e) = result {
+            tracing::error!("Failed to store CAP alerts from {}: {}", feed, e);
+        }
+    }
+
+    if let Err(e) = dispatch(state, &ingested, settings).await {
+        tracing::error!("Failed to push CAP alerts: {}", e);
+    }
+}
+
+pub async fn run(state: AppState) {
+    let client = reqwest::Client::new();
+    let mut tick = tokio::time::interval(TICK);
+    let mut last_poll: Option<Instant> = None;
+
+    loop {
+        tick.tick().await;
+
+        let settings = match state.db.get() {
+            Ok(mut conn) => {
+                match expire(&mut conn, Utc::now().naive_utc()) {
+                    Ok(cleared) if !cleared.is_empty() => {
+                        tracing::info!("CAP alerts expired: {:?}", cleared);
+                        let expired = Ingested {
+                            cleared,
+                            ..Default::default()
+                        };
+                        let settings = CapSettings::default();
+                        if let Err(e) = dispatch(&state, &expired, &settings).await {
+                            tracing::error!("Failed to clear expired alerts: {}", e);
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(e) => tracing::error!("Failed to expire CAP alerts: {}", e),
+                }
+                CapSettings::load(&mut conn)
+            }
+            Err(e) => {
+                tracing::error!("CAP alerts: database connection error: {}", e);
+                continue;
+            }
+        };
+
+        let interval = Duration::from_secs(settings.poll_secs.max(MIN_POLL_SECS));
+        if settings.feeds.is_empty() || last_poll.is_some_and(|t| t.elapsed() < interval) {
+            continue;
+        }
+        last_poll = Some(Instant::now());
+        poll_feeds(&state, &client, &settings).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cap_feed() {
+        let feed = r#"<?xml version="1.0" encoding="UTF-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom" xmlns:cap="urn:oasis:names:tc:emergency:cap:1.2">
+  <entry>
+    <cap:alert>
+      <cap:identifier>NWS-1</cap:identifier>
+      <cap:sender>w-nws.webmaster@noaa.gov</cap:sender>
+      <cap:sent>2026-10-17T14:00:00-05:00</cap:sent>
+      <cap:status>Actual</cap:status>
+      <cap:msgType>Update</cap:msgType>
+      <cap:references>w-nws.webmaster@noaa.gov,NWS-0,2026-10-17T13:00:00-05:00</cap:references>
+      <cap:info>
+        <cap:language>es-US</cap:language>
+        <cap:event>Aviso de Tornado</cap:event>
+        <cap:severity>Extreme</cap:severity>
+      </cap:info>
+      <cap:info>
+        <cap:language>en-US</cap:language>
+        <cap:event>Tornado Warning</cap:event>
+        <cap:urgency>Immediate</cap:urgency>
+        <cap:severity>Extreme</cap:severity>
+        <cap:expires>2026-10-17T14:45:00-05:00</cap:expires>
+        <cap:headline>Tornado Warning issued for Tarrant County</cap:headline>
+        <cap:instruction><![CDATA[Take shelter now & stay away from windows.]]></cap:instruction>
+        <cap:area>
+          <cap:areaDesc>Tarrant, TX</cap:areaDesc>
+          <cap:geocode>
+            <cap:valueName>SAME</cap:valueName>
+            <cap:value>048439</cap:value>
+          </cap:geocode>
+        </cap:area>
+      </cap:info>
+    </cap:alert>
+  </entry>
+  <entry>
+    <cap:alert><cap:identifier>broken</cap:identifier></cap:alert>
+  </entry>
+</feed>"#;
+
+        let alerts = parse_alerts(feed).unwrap();
+        assert_eq!(alerts.len(), 1);
+        let alert = &alerts[0];
+        assert_eq!(alert.msg_type, "Update");
+        assert_eq!(
+            alert.references,
+            vec![("w-nws.webmaster@noaa.gov".to_string(), "NWS-0".to_string())]
+        );
+        assert_eq!(alert.sent.to_rfc3339(), "2026-10-17T19:00:00+00:00");
+
+        let info = alert.info.as_ref().unwrap();
+        assert_eq!(info.event, "Tornado Warning");
+        assert_eq!(info.severity, Severity::Extreme);
+        assert_eq!(
+            info.instruction.as_deref(),
+            Some("Take shelter now & stay away from windows.")
+        );
+        assert_eq!(
+            info.areas,
+            vec![CapArea {
+                desc: "Tarrant, TX".to_string(),
+                geocodes: vec!["SAME=048439".to_string()],
+            }]
+        );
+
+        assert!(covers(&[], &info.areas));
+        assert!(covers(&["SAME=048439".to_string()], &info.areas));
+        assert!(covers(&["048439".to_string()], &info.areas));
+        assert!(covers(&["tarrant".to_string()], &info.areas));
+        assert!(!covers(&["Dallas".to_string()], &info.areas));
+
+        assert!(Severity::Extreme > "severe".parse().unwrap());
+        assert!("Catastrophic".parse::<Severity>().is_err());
+    }
+}
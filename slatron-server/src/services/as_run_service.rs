@@ -0,0 +1,379 @@
+//! Persistent as-run log: what each node actually aired, and how that compares to the plan.
+
+use crate::db::DbConnection;
+use crate::models::{AsRunEntry, NewAsRunEntry};
+use crate::services::channel_service::Target;
+use crate::services::schedule_service;
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use diesel::prelude::*;
+use serde::Serialize;
+
+/// Kinds of playback a node reports
+pub const KINDS: [&str; 6] = [
+    "content",
+    "bumper",
+    "dj_voice",
+    "spot_reel_item",
+    "break",
+    "interrupt",
+];
+
+/// Open a row for an item that started airing. Anything of the same kind still open on
+/// the node has been replaced, so it ends here.
+pub fn record_start(
+    conn: &mut DbConnection,
+    node_id: i32,
+    kind: &str,
+    content_id: Option<i32>,
+    title: Option<String>,
+    path: Option<String>,
+    at: NaiveDateTime,
+) -> Result<()> {
+    record_end(conn, node_id, kind, None, at)?;
+
+    diesel::insert_into(crate::schema::as_run::table)
+        .values(&NewAsRunEntry {
+            node_id,
+            kind: kind.to_string(),
+            content_id,
+            title,
+            path,
+            started_at: at,
+        })
+        .execute(conn)?;
+
+    Ok(())
+}
+
+/// Close open rows of `kind` on the node (only those for `content_id`, if given).
+pub fn record_end(
+    conn: &mut DbConnection,
+    node_id: i32,
+    kind: &str,
+    content_id: Option<i32>,
+    at: NaiveDateTime,
+) -> Result<()> {
+    use crate::schema::as_run::dsl;
+
+    let open = dsl::as_run
+        .filter(dsl::node_id.eq(node_id))
+        .filter(dsl::kind.eq(kind))
+        .filter(dsl::ended_at.is_null());
+
+    match content_id {
+        Some(cid) => diesel::update(open.filter(dsl::content_id.eq(cid)))
+            .set(dsl::ended_at.eq(at))
+            .execute(conn)?,
+        None => diesel::update(open)
+            .set(dsl::ended_at.eq(at))
+            .execute(conn)?,
+    };
+
+    Ok(())
+}
+
+/// Close everything still open on a node, e.g. when it drops offline.
+pub fn close_open(conn: &mut DbConnection, node_id: i32, at: NaiveDateTime) -> Result<()> {
+    use crate::schema::as_run::dsl;
+
+    diesel::update(
+        dsl::as_run
+            .filter(dsl::node_id.eq(node_id))
+            .filter(dsl::ended_at.is_null()),
+    )
+    .set(dsl::ended_at.eq(at))
+    .execute(conn)?;
+
+    Ok(())
+}
+
+/// Rows that were on air at any point in `[from, to)`, oldest first.
+pub fn entries_between(
+    conn: &mut DbConnection,
+    node_id: i32,
+    from: NaiveDateTime,
+    to: NaiveDateTime,
+    kind: Option<&str>,
+) -> Result<Vec<AsRunEntry>> {
+    use crate::schema::as_run::dsl;
+
+    let mut query = dsl::as_run
+        .filter(dsl::node_id.eq(node_id))
+        .filter(dsl::started_at.lt(to))
+        .filter(dsl::ended_at.is_null().or(dsl::ended_at.gt(from)))
+        .order(dsl::started_at.asc())
+        .select(AsRunEntry::as_select())
+        .into_boxed();
+
+    if let Some(k) = kind {
+        query = query.filter(dsl::kind.eq(k.to_string()));
+    }
+
+    Ok(query.load(conn)?)
+}
+
+pub fn to_csv(entries: &[AsRunEntry]) -> String {
+    fn field(value: &str) -> String {
+        if value.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", value.replace('"', "\"\""))
+        } else {
+            value.to_string()
+        }
+    }
+
+    let mut csv =
+        String::from("node_id,kind,content_id,title,path,started_at,ended_at,duration_secs\n");
+    for e in entries {
+        let row = [
+            e.node_id.to_string(),
+            e.kind.clone(),
+            e.content_id.map(|c| c.to_string()).unwrap_or_default(),
+            field(e.title.as_deref().unwrap_or_default()),
+            field(e.path.as_deref().unwrap_or_default()),
+            e.started_at.and_utc().to_rfc3339(),
+            e.ended_at
+                .map(|t| t.and_utc().to_rfc3339())
+                .unwrap_or_default(),
+            e.ended_at
+                .map(|t| (t - e.started_at).num_seconds().to_string())
+                .unwrap_or_default(),
+        ];
+        csv.push_str(&row.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffStatus {
+    /// The planned content aired
+    AsPlanned,
+    /// Something else aired instead of the planned content
+    Substituted,
+    /// Content was planned but nothing aired
+    Missed,
+    /// Content aired where nothing was planned
+    Unplanned,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DiffSegment {
+    pub start_at: DateTime<Utc>,
+    pub end_at: DateTime<Utc>,
+    pub planned_content_id: Option<i32>,
+    pub aired_content_id: Option<i32>,
+    pub status: DiffStatus,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct DiffSummary {
+    pub as_planned_secs: i64,
+    pub substituted_secs: i64,
+    pub missed_secs: i64,
+    pub unplanned_secs: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AsRunDiff {
+    pub date: NaiveDate,
+    pub timezone: String,
+    pub window_start: DateTime<Utc>,
+    pub window_end: DateTime<Utc>,
+    pub summary: DiffSummary,
+    pub segments: Vec<DiffSegment>,
+}
+
+/// Compare a node's collapsed schedule for its local `date` with the content it aired.
+pub fn planned_vs_aired(
+    conn: &mut DbConnection,
+    node_id: i32,
+    date: NaiveDate,
+) -> Result<AsRunDiff> {
+    let tz = schedule_service::node_timezone(conn, node_id)?;
+    let (day_start, day_end) = schedule_service::local_day_bounds(&tz, date);
+    let window = (day_start.timestamp(), day_end.timestamp());
+
+    // Read-only: a fill run-down that was never generated mustn't be stored as the plan now
+    let collapsed = schedule_service::preview_collapsed_schedule(
+        conn,
+        Target::Node(node_id),
+        date,
+        Some(tz.name().to_string()),
+    )?;
+    let mut planned = Vec::new();
+    for cb in &collapsed {
+        if cb.items.is_empty() {
+            let start = cb.start_at.timestamp();
+            planned.push((start, start + cb.duration_secs as i64, cb.content_id));
+        } else {
+            for item in &cb.items {
+                let start = item.start_at.timestamp();
+                planned.push((
+                    start,
+                    start + item.duration_secs as i64,
+                    Some(item.content_id),
+                ));
+            }
+        }
+    }
+
+    // Rows still open are on air until now
+    let now = Utc::now().timestamp();
+    let aired: Vec<(i64, i64, Option<i32>)> = entries_between(
+        conn,
+        node_id,
+        day_start.naive_utc(),
+        day_end.naive_utc(),
+        Some("content"),
+    )?
+    .iter()
+    .map(|e| {
+        let end = e.ended_at.map(|t| t.and_utc().timestamp()).unwrap_or(now);
+        (e.started_at.and_utc().timestamp(), end, e.content_id)
+    })
+    .collect();
+
+    let mut summary = DiffSummary::default();
+    let segments = diff_timeline(window, &planned, &aired)
+        .into_iter()
+        .map(
+            |(start, end, planned_content_id, aired_content_id, status)| {
+                let secs = end - start;
+                match status {
+                    DiffStatus::AsPlanned => summary.as_planned_secs += secs,
+                    DiffStatus::Substituted => summary.substituted_secs += secs,
+                    DiffStatus::Missed => summary.missed_secs += secs,
+                    DiffStatus::Unplanned => summary.unplanned_secs += secs,
+                }
+                DiffSegment {
+                    start_at: DateTime::from_timestamp(start, 0).unwrap_or_default(),
+                    end_at: DateTime::from_timestamp(end, 0).unwrap_or_default(),
+                    planned_content_id,
+                    aired_content_id,
+                    status,
+                }
+            },
+        )
+        .collect();
+
+    Ok(AsRunDiff {
+        date,
+        timezone: tz.name().to_string(),
+        window_start: day_start.with_timezone(&Utc),
+        window_end: day_end.with_timezone(&Utc),
+        summary,
+        segments,
+    })
+}
+
+type Span = (i64, i64, Option<i32>);
+/// Start, end, planned content, aired content
+type DiffPiece = (i64, i64, Option<i32>, Option<i32>, DiffStatus);
+
+/// Split `window` at every planned and aired boundary and classify each piece.
+/// Pieces where nothing was planned or aired are left out; adjacent pieces with the
+/// same planned and aired content are merged.
+fn diff_timeline(window: (i64, i64), planned: &[Span], aired: &[Span]) -> Vec<DiffPiece> {
+    let clip = |(s, e, c): &Span| (*s.max(&window.0), *e.min(&window.1), *c);
+    let planned: Vec<Span> = planned.iter().map(clip).filter(|s| s.0 < s.1).collect();
+    let aired: Vec<Span> = aired.iter().map(clip).filter(|s| s.0 < s.1).collect();
+
+    let mut cuts: Vec<i64> = planned
+        .iter()
+        .chain(&aired)
+        .flat_map(|s| [s.0, s.1])
+        .collect();
+    cuts.sort_unstable();
+    cuts.dedup();
+
+    // The latest-starting row wins where aired rows overlap
+    let at = |spans: &[Span], t: i64| {
+        spans
+            .iter()
+            .filter(|s| s.0 <= t && t < s.1)
+            .max_by_key(|s| s.0)
+            .and_then(|s| s.2)
+    };
+
+    let mut segments: Vec<DiffPiece> = Vec::new();
+    for pair in cuts.windows(2) {
+        let (start, end) = (pair[0], pair[1]);
+        let p = at(&planned, start);
+        let a = at(&aired, start);
+        let status = match (p, a) {
+            (None, None) => continue,
+            (Some(_), None) => DiffStatus::Missed,
+            (None, Some(_)) => DiffStatus::Unplanned,
+            (Some(x), Some(y)) if x == y => DiffStatus::AsPlanned,
+            (Some(_), Some(_)) => DiffStatus::Substituted,
+        };
+
+        if let Some(last) = segments.last_mut() {
+            if last.1 == start && last.2 == p && last.3 == a {
+                last.1 = end;
+                continue;
+            }
+        }
+        segments.push((start, end, p, a, status));
+    }
+
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_timeline() {
+        let planned = vec![(0, 100, Some(1)), (100, 200, Some(2)), (200, 300, Some(3))];
+        // 1 aired a little late, 2 was replaced by 9 part-way, 3 never aired, then 4 ran over
+        let aired = vec![
+            (10, 100, Some(1)),
+            (100, 150, Some(2)),
+            (150, 200, Some(9)),
+            (300, 350, Some(4)),
+        ];
+
+        let diff = diff_timeline((0, 400), &planned, &aired);
+        let statuses: Vec<_> = diff.iter().map(|s| (s.0, s.1, s.4)).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                (0, 10, DiffStatus::Missed),
+                (10, 100, DiffStatus::AsPlanned),
+                (100, 150, DiffStatus::AsPlanned),
+                (150, 200, DiffStatus::Substituted),
+                (200, 300, DiffStatus::Missed),
+                (300, 350, DiffStatus::Unplanned),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_csv_quoting() {
+        let ts = NaiveDate::from_ymd_opt(2026, 1, 1)
+            .unwrap()
+            .and_hms_opt(3, 0, 0)
+            .unwrap();
+        let entry = AsRunEntry {
+            id: Some(1),
+            node_id: 2,
+            kind: "content".to_string(),
+            content_id: Some(5),
+            title: Some("News, \"Late\"".to_string()),
+            path: None,
+            started_at: ts,
+            ended_at: Some(ts + chrono::Duration::seconds(90)),
+        };
+
+        let csv = to_csv(&[entry]);
+        let row = csv.lines().nth(1).unwrap();
+        assert_eq!(
+            row,
+            "2,content,5,\"News, \"\"Late\"\"\",,2026-01-01T03:00:00+00:00,2026-01-01T03:01:30+00:00,90"
+        );
+    }
+}
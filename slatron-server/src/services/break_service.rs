@@ -0,0 +1,184 @@
+//! Mid-programme breaks: cue points on content and the break policies of blocks.
+//!
+//! Cue points are set by hand or detected from the chapters of the media. While a
+//! block with a break policy airs, the node pauses its content at each cue point,
+//! plays the policy's spot reel, then resumes where it left off.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+
+/// Chapters starting this close to the top are the programme start, not a break
+const MIN_CHAPTER_CUE_SECS: f64 = 1.0;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CuePoint {
+    /// Position in the content, in seconds
+    pub at_secs: f64,
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub source: CueSource,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CueSource {
+    #[default]
+    Manual,
+    Chapter,
+}
+
+/// Parse a content item's `cue_points`, sorted by position.
+pub fn parse_cue_points(json: &str) -> Result<Vec<CuePoint>> {
+    let mut cues: Vec<CuePoint> =
+        serde_json::from_str(json).map_err(|e| anyhow!("Invalid cue points: {}", e))?;
+    if let Some(cue) = cues
+        .iter()
+        .find(|c| !c.at_secs.is_finite() || c.at_secs < 0.0)
+    {
+        return Err(anyhow!("Invalid cue point position {}", cue.at_secs));
+    }
+    cues.sort_by(|a, b| a.at_secs.total_cmp(&b.at_secs));
+    cues.dedup_by(|a, b| a.at_secs == b.at_secs);
+    Ok(cues)
+}
+
+/// Validate `cue_points` JSON and store it sorted.
+pub fn normalize_cue_points(json: &str) -> Result<String> {
+    Ok(serde_json::to_string(&parse_cue_points(json)?)?)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BreakPolicy {
+    /// Spot reel played at each break
+    pub spot_reel_id: i32,
+    /// Longest a single break runs; the reel is cut off after this
+    #[serde(default = "default_max_secs")]
+    pub max_secs: u32,
+    /// Breaks taken per airing of a content item at most; every cue point if unset
+    #[serde(default)]
+    pub max_breaks: Option<u32>,
+}
+
+fn default_max_secs() -> u32 {
+    180
+}
+
+impl BreakPolicy {
+    pub fn parse(json: &str) -> Result<Self> {
+        let policy: Self =
+            serde_json::from_str(json).map_err(|e| anyhow!("Invalid break policy: {}", e))?;
+        if policy.max_secs == 0 {
+            return Err(anyhow!("Break policy max_secs must be positive"));
+        }
+        Ok(policy)
+    }
+
+    /// Cue points a break is taken at when the content starts playing `from_secs` in.
+    pub fn breaks_from(&self, cues: &[CuePoint], from_secs: f64) -> Vec<f64> {
+        cues.iter()
+            .map(|c| c.at_secs)
+            .filter(|at| *at > from_secs)
+            .take(self.max_breaks.map_or(usize::MAX, |n| n as usize))
+            .collect()
+    }
+}
+
+/// Chapter starts of the media at `path` (a file or URL the server can read) as cue
+/// points, using ffprobe.
+pub fn detect_chapters(path: &str) -> Result<Vec<CuePoint>> {
+    let output = Command::new("ffprobe")
+        .arg("-v")
+        .arg("error")
+        .arg("-print_format")
+        .arg("json")
+        .arg("-show_chapters")
+        .arg(path)
+        .output()?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("ffprobe failed: {}", stderr));
+    }
+
+    chapters_from_ffprobe(&String::from_utf8(output.stdout)?)
+}
+
+fn chapters_from_ffprobe(json: &str) -> Result<Vec<CuePoint>> {
+    #[derive(Deserialize)]
+    struct Probe {
+        #[serde(default)]
+        chapters: Vec<Chapter>,
+    }
+    #[derive(Deserialize)]
+    struct Chapter {
+        start_time: String,
+        #[serde(default)]
+        tags: std::collections::HashMap<String, String>,
+    }
+
+    let probe: Probe = serde_json::from_str(json)?;
+    Ok(probe
+        .chapters
+        .into_iter()
+        .filter_map(|chapter| {
+            let at_secs: f64 = chapter.start_time.parse().ok()?;
+            (at_secs >= MIN_CHAPTER_CUE_SECS).then(|| CuePoint {
+                at_secs,
+                label: chapter.tags.get("title").cloned(),
+                source: CueSource::Chapter,
+            })
+        })
+        .collect())
+}
+
+/// `existing` with its chapter cue points replaced by `chapters`; manual ones are kept.
+pub fn merge_chapters(existing: Vec<CuePoint>, chapters: Vec<CuePoint>) -> Vec<CuePoint> {
+    let mut cues: Vec<CuePoint> = existing
+        .into_iter()
+        .filter(|c| c.source == CueSource::Manual)
+        .chain(chapters)
+        .collect();
+    cues.sort_by(|a, b| a.at_secs.total_cmp(&b.at_secs));
+    cues.dedup_by(|a, b| a.at_secs == b.at_secs);
+    cues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chapters_become_cue_points() {
+        let probe = r#"{"chapters":[
+            {"id":0,"start_time":"0.000000","end_time":"612.500000","tags":{"title":"Act 1"}},
+            {"id":1,"start_time":"612.500000","end_time":"1440.000000","tags":{"title":"Act 2"}},
+            {"id":2,"start_time":"1440.000000","end_time":"1800.000000"}
+        ]}"#;
+        let chapters = chapters_from_ffprobe(probe).unwrap();
+        let manual =
+            parse_cue_points(r#"[{"at_secs":900},{"at_secs":300,"source":"chapter"}]"#).unwrap();
+
+        let cues = merge_chapters(manual, chapters);
+        let summary: Vec<(f64, CueSource)> = cues.iter().map(|c| (c.at_secs, c.source)).collect();
+        assert_eq!(
+            summary,
+            vec![
+                (612.5, CueSource::Chapter),
+                (900.0, CueSource::Manual),
+                (1440.0, CueSource::Chapter),
+            ]
+        );
+        assert_eq!(cues[0].label.as_deref(), Some("Act 2"));
+
+        let policy = BreakPolicy::parse(r#"{"spot_reel_id":7,"max_breaks":2}"#).unwrap();
+        assert_eq!(policy.max_secs, 180);
+        assert_eq!(policy.breaks_from(&cues, 0.0), vec![612.5, 900.0]);
+        // Joining after the first cue skips it
+        assert_eq!(policy.breaks_from(&cues, 700.0), vec![900.0, 1440.0]);
+
+        assert!(parse_cue_points(r#"[{"at_secs":-5}]"#).is_err());
+        assert!(BreakPolicy::parse(r#"{"spot_reel_id":7,"max_secs":0}"#).is_err());
+    }
+}
@@ -177,6 +177,7 @@ impl BumperService {
                 last_rendered_at.eq(Some(chrono::Utc::now().naive_utc())),
             ))
             .execute(&mut conn)?;
+        crate::services::loudness_service::reset_bumper(&mut conn, bumper_id)?;
 
         tracing::info!(
             "Bumper '{}' rendered successfully ({}ms)",
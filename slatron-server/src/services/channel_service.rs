@@ -0,0 +1,292 @@
+//! Channels: groups of nodes scheduled as one.
+//!
+//! A node in a channel airs the channel's lineup merged with its own assignments. A
+//! schedule the node assigns itself takes the node's priority, and an excluded
+//! assignment drops one of the channel's schedules from that node only. Member nodes
+//! without their own timezone or fallback policy use the channel's.
+//!
+//! A channel with `sync_playback` set is a sync group: its members time the schedule
+//! against the server's clock and hold their players to it, so a video wall or rooms of
+//! speakers stay in lockstep.
+
+use crate::db::DbConnection;
+use crate::models::{NewChannelSchedule, NewNodeSchedule, Schedule};
+use crate::websocket::ServerMessage;
+use crate::AppState;
+use anyhow::{anyhow, Result};
+use diesel::prelude::*;
+use serde::Deserialize;
+
+/// What schedules are assigned to, collapsed for and commands sent to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Target {
+    Node(i32),
+    Channel(i32),
+}
+
+/// A schedule in a lineup with the priority it airs at.
+#[derive(Debug, Clone)]
+pub struct Assignment {
+    pub schedule: Schedule,
+    pub priority: i32,
+}
+
+/// The channel a node belongs to, if any.
+pub fn channel_of(conn: &mut DbConnection, node_id: i32) -> Result<Option<i32>> {
+    use crate::schema::nodes::dsl::{channel_id, id, nodes};
+
+    Ok(nodes
+        .filter(id.eq(node_id))
+        .select(channel_id)
+        .first::<Option<i32>>(conn)
+        .optional()?
+        .flatten())
+}
+
+/// Whether a node is in a sync group.
+pub fn syncs_playback(conn: &mut DbConnection, node_id: i32) -> Result<bool> {
+    use crate::schema::{channels, nodes};
+
+    Ok(nodes::table
+        .inner_join(channels::table)
+        .filter(nodes::id.eq(node_id))
+        .select(channels::sync_playback)
+        .first::<bool>(conn)
+        .optional()?
+        .unwrap_or(false))
+}
+
+/// Every node in a channel.
+pub fn members(conn: &mut DbConnection, channel: i32) -> Result<Vec<i32>> {
+    use crate::schema::nodes::dsl::{channel_id, id, nodes};
+
+    let ids: Vec<Option<i32>> = nodes.filter(channel_id.eq(channel)).select(id).load(conn)?;
+    Ok(ids.into_iter().flatten().collect())
+}
+
+/// The nodes a target covers.
+pub fn nodes(conn: &mut DbConnection, target: Target) -> Result<Vec<i32>> {
+    match target {
+        Target::Node(node_id) => Ok(vec![node_id]),
+        Target::Channel(channel) => members(conn, channel),
+    }
+}
+
+/// The schedules `target` airs, highest priority first. A node's lineup is its
+/// channel's with the node's own assignments applied.
+pub fn lineup(conn: &mut DbConnection, target: Target) -> Result<Vec<Assignment>> {
+    use crate::schema::{channel_schedules, node_schedules, schedules};
+
+    let channel = match target {
+        Target::Node(node_id) => channel_of(conn, node_id)?,
+        Target::Channel(channel) => Some(channel),
+    };
+
+    let inherited: Vec<(Schedule, Option<i32>)> = match channel {
+        Some(channel) => channel_schedules::table
+            .inner_join(schedules::table)
+            .filter(channel_schedules::channel_id.eq(channel))
+            .select((Schedule::as_select(), channel_schedules::priority))
+            .load(conn)?,
+        None => Vec::new(),
+    };
+
+    let own: Vec<(Schedule, Option<i32>, bool)> = match target {
+        Target::Node(node_id) => node_schedules::table
+            .inner_join(schedules::table)
+            .filter(node_schedules::node_id.eq(node_id))
+            .select((
+                Schedule::as_select(),
+                node_schedules::priority,
+                node_schedules::excluded,
+            ))
+            .load(conn)?,
+        Target::Channel(_) => Vec::new(),
+    };
+
+    Ok(merge(inherited, own))
+}
+
+/// Apply a node's own assignments to its channel's. On equal priority the node's own
+/// schedules come first.
+fn merge(
+    inherited: Vec<(Schedule, Option<i32>)>,
+    own: Vec<(Schedule, Option<i32>, bool)>,
+) -> Vec<Assignment> {
+    let overridden: Vec<Option<i32>> = own.iter().map(|(s, _, _)| s.id).collect();
+
+    let mut lineup: Vec<Assignment> = own
+        .into_iter()
+        .filter(|(_, _, excluded)| !excluded)
+        .map(|(s, p, _)| (s, p))
+        .chain(
+            inherited
+                .into_iter()
+                .filter(|(s, _)| !overridden.contains(&s.id)),
+        )
+        .map(|(schedule, p_override)| Assignment {
+            priority: p_override.unwrap_or(schedule.priority),
+            schedule,
+        })
+        .collect();
+
+    // Stable, so ties keep the node's own first
+    lineup.sort_by_key(|a| std::cmp::Reverse(a.priority));
+    lineup
+}
+
+/// Replace `target`'s assignments with `schedule_ids`, the first getting the highest
+/// priority. `excluded_ids` drops schedules a node's channel assigns from that node.
+pub fn assign(
+    conn: &mut DbConnection,
+    target: Target,
+    schedule_ids: &[i32],
+    excluded_ids: &[i32],
+) -> Result<()> {
+    if excluded_ids.iter().any(|s| schedule_ids.contains(s)) {
+        return Err(anyhow!("A schedule can't be both assigned and excluded"));
+    }
+    let count = schedule_ids.len();
+    let priorities = schedule_ids
+        .iter()
+        .enumerate()
+        .map(|(i, s_id)| (*s_id, (count - i) as i32));
+
+    conn.transaction::<_, anyhow::Error, _>(|conn| match target {
+        Target::Node(target_node) => {
+            use crate::schema::node_schedules::dsl::*;
+
+            diesel::delete(node_schedules.filter(node_id.eq(target_node))).execute(conn)?;
+            let assignments: Vec<NewNodeSchedule> = priorities
+                .map(|(s_id, priority_val)| NewNodeSchedule {
+                    node_id: target_node,
+                    schedule_id: s_id,
+                    priority: Some(priority_val),
+                    excluded: false,
+                })
+                .chain(excluded_ids.iter().map(|s_id| NewNodeSchedule {
+                    node_id: target_node,
+                    schedule_id: *s_id,
+                    priority: None,
+                    excluded: true,
+                }))
+                .collect();
+            if !assignments.is_empty() {
+                diesel::insert_into(node_schedules)
+                    .values(&assignments)
+                    .execute(conn)?;
+            }
+            Ok(())
+        }
+        Target::Channel(target_channel) => {
+            use crate::schema::channel_schedules::dsl::*;
+
+            if !excluded_ids.is_empty() {
+                return Err(anyhow!("Only nodes exclude schedules"));
+            }
+            diesel::delete(channel_schedules.filter(channel_id.eq(target_channel)))
+                .execute(conn)?;
+            let assignments: Vec<NewChannelSchedule> = priorities
+                .map(|(s_id, priority_val)| NewChannelSchedule {
+                    channel_id: target_channel,
+                    schedule_id: s_id,
+                    priority: Some(priority_val),
+                })
+                .collect();
+            if !assignments.is_empty() {
+                diesel::insert_into(channel_schedules)
+                    .values(&assignments)
+                    .execute(conn)?;
+            }
+            Ok(())
+        }
+    })
+}
+
+/// Nodes airing a schedule, directly or through their channel (unless excluded).
+pub fn schedule_nodes(conn: &mut DbConnection, sched_id: i32) -> Result<Vec<i32>> {
+    use crate::schema::{channel_schedules, node_schedules, nodes};
+
+    let assignments: Vec<(i32, bool)> = node_schedules::table
+        .filter(node_schedules::schedule_id.eq(sched_id))
+        .select((node_schedules::node_id, node_schedules::excluded))
+        .load(conn)?;
+
+    let channels: Vec<i32> = channel_schedules::table
+        .filter(channel_schedules::schedule_id.eq(sched_id))
+        .select(channel_schedules::channel_id)
+        .load(conn)?;
+    let inheriting: Vec<Option<i32>> = nodes::table
+        .filter(nodes::channel_id.eq_any(channels))
+        .select(nodes::id)
+        .load(conn)?;
+
+    let mut node_ids: Vec<i32> = assignments
+        .iter()
+        .filter(|(_, excluded)| !excluded)
+        .map(|(node_id, _)| *node_id)
+        .chain(inheriting.into_iter().flatten().filter(|node_id| {
+            !assignments
+                .iter()
+                .any(|(assigned, excluded)| assigned == node_id && *excluded)
+        }))
+        .collect();
+    node_ids.sort_unstable();
+    node_ids.dedup();
+    Ok(node_ids)
+}
+
+/// Have the connected nodes among `node_ids` refetch their schedule.
+pub async fn notify(state: &AppState, node_ids: &[i32]) {
+    let connected = state.connected_nodes.read().await;
+    for node_id in node_ids {
+        if let Some(tx) = connected.get(node_id) {
+            let _ = tx.send(ServerMessage::ScheduleUpdated {
+                timestamp: chrono::Utc::now().to_rfc3339(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schedule(id: i32, priority: i32) -> Schedule {
+        Schedule {
+            id: Some(id),
+            name: format!("Schedule {}", id),
+            description: None,
+            schedule_type: "weekly".to_string(),
+            priority,
+            is_active: true,
+            created_at: chrono::NaiveDateTime::default(),
+            updated_at: chrono::NaiveDateTime::default(),
+            dj_id: None,
+            published_version_id: None,
+        }
+    }
+
+    #[test]
+    fn test_merge_lineup() {
+        let inherited = vec![
+            (schedule(1, 0), Some(3)),
+            (schedule(2, 0), Some(2)),
+            (schedule(3, 5), None),
+        ];
+        let own = vec![
+            // Raised above the channel's
+            (schedule(2, 0), Some(10), false),
+            (schedule(3, 5), None, true),
+            // Node only, tied with schedule 1
+            (schedule(4, 0), Some(3), false),
+        ];
+
+        let lineup: Vec<(Option<i32>, i32)> = merge(inherited, own)
+            .into_iter()
+            .map(|a| (a.schedule.id, a.priority))
+            .collect();
+        assert_eq!(lineup, vec![(Some(2), 10), (Some(4), 3), (Some(1), 3)]);
+    }
+}
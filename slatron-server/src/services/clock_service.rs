@@ -0,0 +1,180 @@
+//! Node clocks measured against the server's.
+//!
+//! Nodes time their schedule by their own clock, which on hardware without a battery
+//! backed RTC can be hours out after booting offline. Heartbeats report the offset the
+//! node measured in its time-sync exchanges with the server; a node that hasn't
+//! measured one yet is estimated from when it sent the heartbeat, which counts the
+//! network latency as offset. An offset past the `clock` setting's `warn_ms` puts the
+//! node in a clock warning until it's back in line. With `server_time` set, every node
+//! runs its schedule on the server's clock, not just sync groups.
+
+use crate::db::DbConnection;
+use crate::websocket::SyncStatus;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::Deserialize;
+
+/// The `clock` setting.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ClockSettings {
+    /// Offset, beyond the measurement's uncertainty, that raises a clock warning
+    #[serde(default = "default_warn_ms")]
+    pub warn_ms: i64,
+    /// Every node schedules against the server's clock rather than its own
+    #[serde(default)]
+    pub server_time: bool,
+}
+
+fn default_warn_ms() -> i64 {
+    2000
+}
+
+impl Default for ClockSettings {
+    fn default() -> Self {
+        Self {
+            warn_ms: default_warn_ms(),
+            server_time: false,
+        }
+    }
+}
+
+impl ClockSettings {
+    pub fn parse(json: &str) -> Result<Self> {
+        serde_json::from_str(json).map_err(|e| anyhow!("Invalid clock setting: {}", e))
+    }
+
+    /// The setting, or the defaults if unset or invalid.
+    pub fn load(conn: &mut DbConnection) -> Self {
+        use crate::schema::global_settings::dsl::{global_settings, key, value};
+
+        let json: Option<String> = global_settings
+            .filter(key.eq("clock"))
+            .select(value)
+            .first(conn)
+            .optional()
+            .ok()
+            .flatten();
+
+        match json.as_deref().map(Self::parse) {
+            Some(Ok(settings)) => settings,
+            Some(Err(e)) => {
+                tracing::warn!("{}; node clocks use the defaults", e);
+                Self::default()
+            }
+            None => Self::default(),
+        }
+    }
+}
+
+/// A node's clock offset (server minus node).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Measurement {
+    pub offset_ms: i64,
+    /// `None` when estimated from a heartbeat's send time
+    pub rtt_ms: Option<i64>,
+}
+
+impl Measurement {
+    /// From a heartbeat received at `received_at`: the node's own measurement if it has
+    /// one, else estimated from `sent_at`.
+    pub fn from_heartbeat(
+        sync: Option<&SyncStatus>,
+        sent_at: Option<DateTime<Utc>>,
+        received_at: DateTime<Utc>,
+    ) -> Option<Self> {
+        if let Some(offset_ms) = sync.and_then(|s| s.clock_offset_ms) {
+            return Some(Self {
+                offset_ms,
+                rtt_ms: sync.and_then(|s| s.rtt_ms),
+            });
+        }
+        sent_at.map(|sent_at| Self {
+            offset_ms: (received_at - sent_at).num_milliseconds(),
+            rtt_ms: None,
+        })
+    }
+
+    /// Whether the clock is more than `warn_ms` out, allowing for half the round trip.
+    pub fn drifted(&self, warn_ms: i64) -> bool {
+        self.offset_ms.abs() - self.rtt_ms.unwrap_or(0) / 2 > warn_ms
+    }
+}
+
+/// Store a node's clock measurement, raising or clearing its clock warning.
+pub fn record(
+    conn: &mut DbConnection,
+    node_id: i32,
+    measurement: &Measurement,
+    settings: &ClockSettings,
+) -> Result<()> {
+    use crate::schema::nodes::dsl::*;
+
+    let was_warning: bool = nodes
+        .filter(id.eq(node_id))
+        .select(clock_warning)
+        .first(conn)?;
+    let warning = measurement.drifted(settings.warn_ms);
+
+    let clamp = |ms: i64| ms.clamp(i32::MIN as i64, i32::MAX as i64) as i32;
+    diesel::update(nodes.filter(id.eq(node_id)))
+        .set((
+            clock_offset_ms.eq(clamp(measurement.offset_ms)),
+            clock_rtt_ms.eq(measurement.rtt_ms.map(clamp)),
+            clock_warning.eq(warning),
+        ))
+        .execute(conn)?;
+
+    if warning && !was_warning {
+        tracing::warn!(
+            "Node {} clock is {}ms off the server's",
+            node_id,
+            measurement.offset_ms
+        );
+    } else if was_warning && !warning {
+        tracing::info!("Node {} clock is back in line with the server's", node_id);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_measurement_from_heartbeat() {
+        let received_at = DateTime::parse_from_rfc3339("2026-10-17T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let sync = SyncStatus {
+            enabled: false,
+            clock_offset_ms: Some(-2500),
+            rtt_ms: Some(1200),
+            drift_ms: None,
+        };
+
+        // The node's own measurement wins; its round trip leaves it within 2s
+        let measured = Measurement::from_heartbeat(Some(&sync), None, received_at).unwrap();
+        assert_eq!(measured.offset_ms, -2500);
+        assert!(!measured.drifted(2000));
+
+        // Without one, the send time: a node clock an hour slow
+        let unmeasured = SyncStatus {
+            clock_offset_ms: None,
+            ..sync
+        };
+        let sent_at = received_at - chrono::Duration::hours(1);
+        let estimated =
+            Measurement::from_heartbeat(Some(&unmeasured), Some(sent_at), received_at).unwrap();
+        assert_eq!(
+            estimated,
+            Measurement {
+                offset_ms: 3_600_000,
+                rtt_ms: None
+            }
+        );
+        assert!(estimated.drifted(2000));
+
+        assert_eq!(Measurement::from_heartbeat(None, None, received_at), None);
+    }
+}
@@ -0,0 +1,241 @@
+//! What a node plays when no block covers the current time.
+//!
+//! A node's `fallback_policy` (else the global `fallback_policy` setting) is resolved
+//! into a concrete list of content items or a rendered slate and sent along with the
+//! node's schedule, so the node can keep filling gaps while it is offline.
+
+use crate::db::DbConnection;
+use crate::models::{Bumper, ContentItem};
+use crate::services::loudness_service;
+use crate::services::rundown_service::{FillRule, Rotation};
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, NaiveDate};
+use diesel::prelude::*;
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+/// Tag query results are capped so the schedule response stays small
+const MAX_QUERY_ITEMS: usize = 200;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum FallbackPolicy {
+    /// Stop playback (the historical behaviour)
+    #[default]
+    Silence,
+    /// Cycle through these content items in order
+    Playlist { content_ids: Vec<i32> },
+    /// Cycle through library items matching a fill rule
+    TagQuery { rule: FillRule },
+    /// Loop a spot reel
+    SpotReel { spot_reel_id: i32 },
+    /// Loop a rendered bumper as a slate
+    Slate { bumper_id: i32 },
+}
+
+impl FallbackPolicy {
+    pub fn parse(json: &str) -> Result<Self> {
+        let policy: Self =
+            serde_json::from_str(json).map_err(|e| anyhow!("Invalid fallback policy: {}", e))?;
+        if let FallbackPolicy::Playlist { content_ids } = &policy {
+            if content_ids.is_empty() {
+                return Err(anyhow!("Fallback playlist is empty"));
+            }
+        }
+        Ok(policy)
+    }
+
+    pub fn is_silence(&self) -> bool {
+        matches!(self, FallbackPolicy::Silence)
+    }
+}
+
+/// A policy resolved to what the node actually plays.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ResolvedFallback {
+    pub mode: String,
+    /// Content items to cycle through, in play order
+    pub content_ids: Vec<i32>,
+    /// Rendered bumper to loop, relative to the server URL
+    pub slate_path: Option<String>,
+    /// Loudness gain (dB) for the slate
+    pub slate_gain_db: Option<f64>,
+}
+
+impl ResolvedFallback {
+    pub fn silence() -> Self {
+        Self {
+            mode: "silence".to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+/// The policy for a node: its own, else its channel's, else the global setting, else
+/// silence. Unparseable policies are logged and treated as silence.
+pub fn node_policy(conn: &mut DbConnection, node_id: i32) -> Result<FallbackPolicy> {
+    use crate::schema::{channels, nodes};
+
+    let (node_json, channel_json): (Option<String>, Option<String>) = nodes::table
+        .left_join(channels::table)
+        .filter(nodes::id.eq(node_id))
+        .select((nodes::fallback_policy, channels::fallback_policy.nullable()))
+        .first(conn)
+        .optional()?
+        .unwrap_or_default();
+
+    let json = match node_json.or(channel_json) {
+        Some(json) => Some(json),
+        None => {
+            use crate::schema::global_settings::dsl::{global_settings, key, value};
+            global_settings
+                .filter(key.eq("fallback_policy"))
+                .select(value)
+                .first(conn)
+                .optional()?
+        }
+    };
+
+    Ok(match json.as_deref().map(FallbackPolicy::parse) {
+        Some(Ok(policy)) => policy,
+        Some(Err(e)) => {
+            tracing::warn!("Node {}: {}; using silence", node_id, e);
+            FallbackPolicy::Silence
+        }
+        None => FallbackPolicy::Silence,
+    })
+}
+
+/// Resolve a policy for airing on `date`. Anything that no longer exists resolves to
+/// silence rather than failing the node's schedule.
+pub fn resolve(
+    conn: &mut DbConnection,
+    policy: &FallbackPolicy,
+    date: NaiveDate,
+) -> Result<ResolvedFallback> {
+    use crate::schema::content_items::dsl::{content_items, id, spot_reel_id as reel_col};
+
+    let content_ids: Vec<i32> = match policy {
+        FallbackPolicy::Silence => Vec::new(),
+        FallbackPolicy::Playlist { content_ids } => {
+            let existing: Vec<Option<i32>> = content_items
+                .filter(id.eq_any(content_ids))
+                .select(id)
+                .load(conn)?;
+            content_ids
+                .iter()
+                .copied()
+                .filter(|cid| existing.contains(&Some(*cid)))
+                .collect()
+        }
+        FallbackPolicy::TagQuery { rule } => {
+            let mut pool: Vec<ContentItem> =
+                content_items.select(ContentItem::as_select()).load(conn)?;
+            pool.retain(|item| rule.matches(item));
+            order_pool(pool, rule.rotation, date.num_days_from_ce() as u64)
+        }
+        FallbackPolicy::SpotReel { spot_reel_id } => content_items
+            .filter(reel_col.eq(spot_reel_id))
+            .order(id.asc())
+            .select(id)
+            .first::<Option<i32>>(conn)
+            .optional()?
+            .flatten()
+            .into_iter()
+            .collect(),
+        FallbackPolicy::Slate { bumper_id } => {
+            use crate::schema::bumpers::dsl::{bumpers, id as bumper_col};
+            let bumper: Option<Bumper> = bumpers
+                .filter(bumper_col.eq(bumper_id))
+                .select(Bumper::as_select())
+                .first(conn)
+                .optional()?;
+            return match bumper {
+                Some(Bumper {
+                    rendered_path: Some(path),
+                    loudness_lufs,
+                    true_peak_dbtp,
+                    ..
+                }) => Ok(ResolvedFallback {
+                    mode: "slate".to_string(),
+                    content_ids: Vec::new(),
+                    slate_path: Some(path),
+                    slate_gain_db: loudness_service::stored_gain_db(
+                        loudness_lufs,
+                        true_peak_dbtp,
+                        loudness_service::target_lufs(conn),
+                    ),
+                }),
+                _ => {
+                    tracing::warn!("Fallback slate bumper {} is not rendered", bumper_id);
+                    Ok(ResolvedFallback::silence())
+                }
+            };
+        }
+    };
+
+    if content_ids.is_empty() {
+        if !policy.is_silence() {
+            tracing::warn!("Fallback policy {:?} matched no content", policy);
+        }
+        return Ok(ResolvedFallback::silence());
+    }
+
+    let mode = match policy {
+        FallbackPolicy::SpotReel { .. } => "spot_reel",
+        _ => "playlist",
+    };
+    Ok(ResolvedFallback {
+        mode: mode.to_string(),
+        content_ids,
+        slate_path: None,
+        slate_gain_db: None,
+    })
+}
+
+/// Play order for a tag query. Shuffles are seeded per day so repeated schedule polls
+/// don't reshuffle what the node is cycling through.
+fn order_pool(mut pool: Vec<ContentItem>, rotation: Rotation, seed: u64) -> Vec<i32> {
+    pool.sort_by_key(|item| item.id);
+    match rotation {
+        Rotation::Shuffle => pool.shuffle(&mut StdRng::seed_from_u64(seed)),
+        Rotation::LeastRecentlyPlayed => pool.sort_by_key(|item| (item.last_played_at, item.id)),
+        Rotation::Sequential => {}
+    }
+    pool.into_iter()
+        .filter_map(|item| item.id)
+        .take(MAX_QUERY_ITEMS)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_policies() {
+        assert_eq!(
+            FallbackPolicy::parse(r#"{"mode":"silence"}"#).unwrap(),
+            FallbackPolicy::Silence
+        );
+        assert_eq!(
+            FallbackPolicy::parse(r#"{"mode":"playlist","content_ids":[3,1]}"#).unwrap(),
+            FallbackPolicy::Playlist {
+                content_ids: vec![3, 1]
+            }
+        );
+        let query = FallbackPolicy::parse(
+            r#"{"mode":"tag_query","rule":{"tags":["filler"],"rotation":"sequential"}}"#,
+        )
+        .unwrap();
+        match query {
+            FallbackPolicy::TagQuery { rule } => {
+                assert_eq!(rule.tags, vec!["filler"]);
+                assert_eq!(rule.rotation, Rotation::Sequential);
+            }
+            other => panic!("unexpected policy {:?}", other),
+        }
+        assert!(FallbackPolicy::parse(r#"{"mode":"playlist","content_ids":[]}"#).is_err());
+        assert!(FallbackPolicy::parse(r#"{"mode":"slate"}"#).is_err());
+    }
+}
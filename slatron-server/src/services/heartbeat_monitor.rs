@@ -93,7 +93,6 @@ async fn process_dj_logic(
     use crate::schema::content_items::dsl as c_dsl;
     use crate::schema::dj_profiles::dsl as dj_dsl;
     use crate::schema::nodes::dsl as n_dsl;
-    use crate::schema::schedules::dsl as s_dsl;
 
     // 1. Get ALL online nodes (playing or idle)
     let mut conn = state.db.get().map_err(|e| anyhow::anyhow!(e.to_string()))?;
@@ -118,6 +117,11 @@ async fn process_dj_logic(
             }
         }
 
+        // An interrupted node airs nothing else until the interrupt ends
+        if node.interrupt_status.is_some() {
+            continue;
+        }
+
         let current_content_id_val = node.current_content_id.unwrap_or(0); // 0 for cold start
 
         // Detect Content Change (Unload)
@@ -260,196 +264,70 @@ async fn process_dj_logic(
 
         // --- Common DJ Logic Below ---
 
-        // Try to find active schedule to find specific DJ
-        // 1. Check for Node-Specific Schedule
-        use crate::schema::node_schedules::dsl as ns_dsl;
-
-        // Find all schedules assigned to this node, ordered by priority
-        let node_schedules_all = ns_dsl::node_schedules
-            .filter(ns_dsl::node_id.eq(node_id))
-            .inner_join(s_dsl::schedules)
-            .filter(s_dsl::is_active.eq(true))
-            .order(ns_dsl::priority.desc())
-            .select(crate::models::Schedule::as_select())
-            .load::<crate::models::Schedule>(&mut conn)?;
-
-        // Find all active global schedules as a fallback base
-        let global_schedules = s_dsl::schedules
-            .filter(s_dsl::is_active.eq(true))
-            .order(s_dsl::priority.desc())
-            .load::<crate::models::Schedule>(&mut conn)?;
-
-        // Combine: Node Specific (Higher Priority) -> Global (Lower Priority)
-        let mut candidate_map: HashMap<i32, crate::models::Schedule> = HashMap::new();
-        // Insert globals first
-        for s in global_schedules {
-            if let Some(sid) = s.id {
-                candidate_map.insert(sid, s);
-            }
-        }
-        // Insert node schedules (overwriting if same ID)
-        for s in node_schedules_all {
-            if let Some(sid) = s.id {
-                candidate_map.insert(sid, s);
-            }
-        }
-
-        let mut active_schedules: Vec<crate::models::Schedule> =
-            candidate_map.into_values().collect();
-        // Sort by Priority Descending
-        active_schedules.sort_by(|a, b| b.priority.cmp(&a.priority));
+        // Find the block on air the same way the node's schedule and the simulation do:
+        // the node's assigned schedules, with overrides, exceptions and priorities resolved
+        use crate::services::{fallback_service, simulation_service};
 
         let mut dj_profile_opt: Option<DjProfile> = None;
         let mut active_block_script: Option<String> = None;
         let mut active_block_info: Option<serde_json::Value> = None;
 
-        let mut active_schedule: Option<crate::models::Schedule> = None;
-
-        // Fetch Timezone
-        use crate::schema::global_settings::dsl::{global_settings, key, value};
-        use chrono::{Datelike, Timelike};
-        use chrono_tz::Tz;
-
-        let timezone_setting: Option<String> = global_settings
-            .filter(key.eq("timezone"))
-            .select(value)
-            .first(&mut conn)
-            .optional()
-            .unwrap_or(None);
-
-        let tz: Tz = timezone_setting
-            .as_deref()
-            .and_then(|s| s.parse().ok())
-            .unwrap_or(chrono_tz::UTC);
-
         let now_utc = Utc::now();
-        let now_target = now_utc.with_timezone(&tz);
-        let current_time = now_target.time();
-        let current_dow = now_target.weekday().number_from_monday() as i32 - 1;
-        let current_date = now_target.date_naive();
-
-        // CASCADING SCHEDULE CHECK
-        for schedule in active_schedules {
-            if let Some(sched_id) = schedule.id {
-                use crate::models::{ScheduleBlock, Script};
-                use crate::schema::schedule_blocks::dsl as sb_dsl;
-                use crate::schema::scripts::dsl as sc_dsl;
-
-                let blocks = sb_dsl::schedule_blocks
-                    .filter(sb_dsl::schedule_id.eq(sched_id))
-                    .load::<ScheduleBlock>(&mut conn)
-                    .unwrap_or_default();
-
-                let active_block = blocks.iter().find(|b| {
-                    let matches_day = if let Some(d) = b.specific_date {
-                        d == current_date
-                    } else if let Some(dow) = b.day_of_week {
-                        dow == current_dow
-                    } else {
-                        false
-                    };
-
-                    if !matches_day {
-                        return false;
-                    }
-
-                    let start = b.start_time;
-                    let start_secs = start.num_seconds_from_midnight();
-                    let end_secs = start_secs + (b.duration_minutes as u32 * 60);
-                    let curr_secs = current_time.num_seconds_from_midnight();
-
-                    curr_secs >= start_secs && curr_secs < end_secs
-                });
-
-                if let Some(block) = active_block {
-                    // FOUND ACTIVE BLOCK! This schedule wins.
-                    active_schedule = Some(schedule.clone());
-
-                    // Populate Block Info
-                    let start = block.start_time;
-                    let start_secs = start.num_seconds_from_midnight();
-                    let end_secs = start_secs + (block.duration_minutes as u32 * 60);
-                    let curr_secs = current_time.num_seconds_from_midnight();
-                    let remaining_mins = (end_secs as i64 - curr_secs as i64) / 60;
-
-                    // Upcoming...
-                    let items: Vec<&ScheduleBlock> = blocks
-                        .iter()
-                        .filter(|b| {
-                            let matches_day = if let Some(d) = b.specific_date {
-                                d == current_date
-                            } else if let Some(dow) = b.day_of_week {
-                                dow == current_dow
-                            } else {
-                                false
-                            };
-                            if !matches_day {
-                                return false;
-                            }
-                            b.start_time > block.start_time
-                        })
-                        .collect::<Vec<&ScheduleBlock>>();
-
-                    let mut upcoming_refs = items;
-                    upcoming_refs.sort_by_key(|b| b.start_time);
-                    let upcoming_json: Vec<serde_json::Value> = upcoming_refs
-                        .into_iter()
-                        .take(3)
-                        .map(|b| {
-                            serde_json::json!({
-                               "title": format!("Block {}", b.id.unwrap_or(0)),
-                               "start_time": b.start_time.to_string(),
-                               "content_type": "block"
-                            })
-                        })
-                        .collect();
-
-                    active_block_info = Some(serde_json::json!({
-                        "block": {
-                            "id": block.id,
-                            "name": format!("Block {}", block.id.unwrap_or(0)),
-                            "start_time": block.start_time.to_string(),
-                            "duration": block.duration_minutes,
-                            "dj_id": block.dj_id
-                        },
-                        "time_remaining_minutes": remaining_mins,
-                        "upcoming": upcoming_json
-                    }));
-
-                    // Block DJ Override
-                    if let Some(blk_dj_id) = block.dj_id {
-                        dj_profile_opt = dj_dsl::dj_profiles
-                            .filter(dj_dsl::id.eq(blk_dj_id))
-                            .first::<DjProfile>(&mut conn)
-                            .optional()?;
-                    }
-                    // Block Script
-                    if let Some(sid) = block.script_id {
-                        if let Ok(script) = sc_dsl::scripts
-                            .filter(sc_dsl::id.eq(sid))
-                            .first::<Script>(&mut conn)
-                        {
-                            active_block_script = Some(script.script_content);
-                        }
-                    }
-
-                    // Fallback to Schedule DJ if block didn't specify
-                    if dj_profile_opt.is_none() {
-                        if let Some(sched_dj_id) = schedule.dj_id {
-                            dj_profile_opt = dj_dsl::dj_profiles
-                                .filter(dj_dsl::id.eq(sched_dj_id))
-                                .first::<DjProfile>(&mut conn)
-                                .optional()?;
-                        }
-                    }
-
-                    break; // STOP here, we found our block.
+        let on_air = simulation_service::on_air(&mut conn, node_id, now_utc)?;
+
+        if let Some(on_air) = &on_air {
+            use crate::models::Script;
+            use crate::schema::scripts::dsl as sc_dsl;
+
+            let block = &on_air.block;
+            let block_end = block.start_at + chrono::Duration::seconds(block.duration_secs as i64);
+            let remaining_mins = (block_end - now_utc).num_minutes();
+
+            let upcoming_json: Vec<serde_json::Value> = on_air
+                .upcoming
+                .iter()
+                .take(3)
+                .map(|b| {
+                    serde_json::json!({
+                       "title": format!("Block {}", b.block_id),
+                       "start_time": b.start_time,
+                       "content_type": "block"
+                    })
+                })
+                .collect();
+
+            active_block_info = Some(serde_json::json!({
+                "block": {
+                    "id": block.block_id,
+                    "name": format!("Block {}", block.block_id),
+                    "start_time": block.start_time,
+                    "duration": block.duration_minutes,
+                    "dj_id": block.dj_id
+                },
+                "time_remaining_minutes": remaining_mins,
+                "upcoming": upcoming_json
+            }));
+
+            // The block's DJ, else its schedule's (a replacement's on a replaced date)
+            if let Some(dj_id) = block.dj_id {
+                dj_profile_opt = dj_dsl::dj_profiles
+                    .filter(dj_dsl::id.eq(dj_id))
+                    .first::<DjProfile>(&mut conn)
+                    .optional()?;
+            }
+            // Block Script
+            if let Some(sid) = block.script_id {
+                if let Ok(script) = sc_dsl::scripts
+                    .filter(sc_dsl::id.eq(sid))
+                    .first::<Script>(&mut conn)
+                {
+                    active_block_script = Some(script.script_content);
                 }
             }
         }
 
-        // If after checking ALL active schedules we found nothing...
-        if active_schedule.is_none() {
+        // If no block is on air...
+        if on_air.is_none() {
             // If Trigger was 'Cold Start' but NO active block matches time across ANY active schedule?
             // Then we shouldn't play anything. It's off-air time.
             if node.current_content_id.is_none() {
@@ -457,6 +335,9 @@ async fn process_dj_logic(
                     "DEBUG: Cold start aborted - no active block found in any schedule."
                 );
                 continue;
+            } else if !fallback_service::node_policy(&mut conn, node_id)?.is_silence() {
+                // The node fills the gap from its fallback policy
+                continue;
             } else {
                 // If we are currently playing content, but the block ended/no block matches:
                 // We must STOP/PAUSE the node.
@@ -0,0 +1,378 @@
+//! iCalendar (RFC 5545) export and import of schedules.
+//!
+//! Schedule times are wall-clock times in whatever zone the airing node uses, so
+//! events are written as floating local times (no `Z`, no `TZID`).
+
+use crate::db::DbConnection;
+use crate::models::{ContentItem, Schedule};
+use crate::services::version_service;
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Utc};
+use chrono_tz::Tz;
+use diesel::prelude::*;
+use std::collections::HashMap;
+
+const ICS_WEEKDAYS: [&str; 7] = ["MO", "TU", "WE", "TH", "FR", "SA", "SU"];
+
+/// Render a schedule as a VCALENDAR. Weekly blocks become weekly recurring events
+/// anchored on the first matching day on or after the schedule's creation.
+pub fn export_schedule(conn: &mut DbConnection, schedule: &Schedule) -> Result<String> {
+    let schedule_id = schedule.id.ok_or_else(|| anyhow!("Schedule ID missing"))?;
+
+    // The feed shows what airs, not unpublished edits
+    let mut blocks = version_service::published_blocks(conn, schedule)?;
+    blocks.sort_by_key(|b| b.start_time);
+
+    let content: HashMap<i32, ContentItem> = {
+        use crate::schema::content_items::dsl::*;
+        let ids: Vec<i32> = blocks.iter().filter_map(|b| b.content_id).collect();
+        content_items
+            .filter(id.eq_any(ids))
+            .select(ContentItem::as_select())
+            .load(conn)?
+            .into_iter()
+            .filter_map(|c| c.id.map(|cid| (cid, c)))
+            .collect()
+    };
+
+    let dj_names: HashMap<i32, String> = {
+        use crate::schema::dj_profiles::dsl::*;
+        dj_profiles
+            .select((id, name))
+            .load::<(Option<i32>, String)>(conn)?
+            .into_iter()
+            .filter_map(|(did, dname)| did.map(|d| (d, dname)))
+            .collect()
+    };
+
+    let stamp = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+    let anchor = schedule.created_at.date();
+
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//Slatron//Schedule Export//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+        format!("X-WR-CALNAME:{}", escape_text(&schedule.name)),
+    ];
+
+    for block in &blocks {
+        let Some(block_id) = block.id else { continue };
+
+        let (date, rrule) = match (block.specific_date, &block.rrule, block.day_of_week) {
+            (Some(d), Some(rule), _) => (d, Some(rule.clone())),
+            (Some(d), None, _) => (d, None),
+            (None, _, Some(dow)) if (0..7).contains(&dow) => (
+                next_weekday(anchor, dow as u32),
+                Some(format!("FREQ=WEEKLY;BYDAY={}", ICS_WEEKDAYS[dow as usize])),
+            ),
+            _ => continue,
+        };
+
+        let item = block.content_id.and_then(|cid| content.get(&cid));
+        let dj = block.dj_id.and_then(|did| dj_names.get(&did));
+        let summary = match (item, dj) {
+            (Some(c), _) => c.title.clone(),
+            (None, Some(name)) => name.clone(),
+            (None, None) => "Block".to_string(),
+        };
+
+        let start = date.and_time(block.start_time);
+        let end = start + Duration::seconds(block.total_duration_secs());
+
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.push(format!(
+            "UID:block-{}-schedule-{}@slatron",
+            block_id, schedule_id
+        ));
+        lines.push(format!("DTSTAMP:{}", stamp));
+        lines.push(format!("DTSTART:{}", start.format("%Y%m%dT%H%M%S")));
+        lines.push(format!("DTEND:{}", end.format("%Y%m%dT%H%M%S")));
+        if let Some(rule) = rrule {
+            lines.push(format!("RRULE:{}", rule));
+        }
+        lines.push(format!("SUMMARY:{}", escape_text(&summary)));
+        if let Some(desc) = item.and_then(|c| c.description.as_deref()) {
+            lines.push(format!("DESCRIPTION:{}", escape_text(desc)));
+        }
+        if let Some(cid) = block.content_id {
+            lines.push(format!("X-SLATRON-CONTENT-ID:{}", cid));
+        }
+        lines.push("END:VEVENT".to_string());
+    }
+
+    lines.push("END:VCALENDAR".to_string());
+
+    Ok(lines
+        .iter()
+        .map(|l| fold_line(l))
+        .collect::<Vec<_>>()
+        .join("\r\n")
+        + "\r\n")
+}
+
+/// First date on or after `from` falling on `dow` (0 = Monday).
+fn next_weekday(from: NaiveDate, dow: u32) -> NaiveDate {
+    let current = from.weekday().num_days_from_monday();
+    from + Duration::days(((dow + 7 - current) % 7) as i64)
+}
+
+fn escape_text(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+fn unescape_text(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => out.push('\n'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// Fold content lines longer than 75 octets (RFC 5545 §3.1).
+fn fold_line(line: &str) -> String {
+    let mut out = String::with_capacity(line.len() + line.len() / 74 * 3);
+    let mut width = 0;
+    for c in line.chars() {
+        let len = c.len_utf8();
+        if width + len > 75 {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(c);
+        width += len;
+    }
+    out
+}
+
+/// A VEVENT reduced to what a schedule block can express.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IcsEvent {
+    pub summary: String,
+    /// Local wall-clock start
+    pub start: NaiveDateTime,
+    pub duration_secs: i64,
+    pub rrule: Option<String>,
+}
+
+/// An event that couldn't be turned into a block, and why.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct SkippedEvent {
+    pub summary: String,
+    pub reason: String,
+}
+
+/// Parse the VEVENTs in an iCalendar document.
+///
+/// UTC times (`...Z`) are converted into `local_tz`; times with a `TZID` or floating
+/// times are taken as wall-clock times. All-day events are skipped.
+pub fn parse_events(ics: &str, local_tz: &Tz) -> (Vec<IcsEvent>, Vec<SkippedEvent>) {
+    // Unfold continuation lines
+    let mut lines: Vec<String> = Vec::new();
+    for raw in ics.split('\n') {
+        let raw = raw.strip_suffix('\r').unwrap_or(raw);
+        if let Some(cont) = raw.strip_prefix([' ', '\t']) {
+            if let Some(last) = lines.last_mut() {
+                last.push_str(cont);
+                continue;
+            }
+        }
+        lines.push(raw.to_string());
+    }
+
+    let mut events = Vec::new();
+    let mut skipped = Vec::new();
+    let mut current: Option<HashMap<String, (String, String)>> = None;
+
+    for line in lines {
+        if line.eq_ignore_ascii_case("BEGIN:VEVENT") {
+            current = Some(HashMap::new());
+            continue;
+        }
+        if line.eq_ignore_ascii_case("END:VEVENT") {
+            if let Some(props) = current.take() {
+                match event_from_props(&props, local_tz) {
+                    Ok(event) => events.push(event),
+                    Err(e) => skipped.push(SkippedEvent {
+                        summary: props
+                            .get("SUMMARY")
+                            .map(|(_, v)| unescape_text(v))
+                            .unwrap_or_default(),
+                        reason: e.to_string(),
+                    }),
+                }
+            }
+            continue;
+        }
+
+        let Some(props) = current.as_mut() else {
+            continue;
+        };
+        let Some((head, value)) = line.split_once(':') else {
+            continue;
+        };
+        let (name, params) = head.split_once(';').unwrap_or((head, ""));
+        props
+            .entry(name.to_ascii_uppercase())
+            .or_insert((params.to_ascii_uppercase(), value.to_string()));
+    }
+
+    (events, skipped)
+}
+
+fn event_from_props(props: &HashMap<String, (String, String)>, local_tz: &Tz) -> Result<IcsEvent> {
+    let summary = props
+        .get("SUMMARY")
+        .map(|(_, v)| unescape_text(v))
+        .unwrap_or_default();
+
+    let (params, value) = props
+        .get("DTSTART")
+        .ok_or_else(|| anyhow!("Missing DTSTART"))?;
+    if params.contains("VALUE=DATE") && !params.contains("VALUE=DATE-TIME") {
+        return Err(anyhow!("All-day events can't be scheduled"));
+    }
+    let start = parse_datetime(value, local_tz)?;
+
+    let duration_secs = if let Some((_, end)) = props.get("DTEND") {
+        (parse_datetime(end, local_tz)? - start).num_seconds()
+    } else if let Some((_, dur)) = props.get("DURATION") {
+        parse_duration(dur)?
+    } else {
+        return Err(anyhow!("Missing DTEND or DURATION"));
+    };
+    if duration_secs <= 0 || duration_secs > 86_400 {
+        return Err(anyhow!("Duration must be between 1 second and 24 hours"));
+    }
+
+    Ok(IcsEvent {
+        summary,
+        start,
+        duration_secs,
+        rrule: props.get("RRULE").map(|(_, v)| v.clone()),
+    })
+}
+
+fn parse_datetime(value: &str, local_tz: &Tz) -> Result<NaiveDateTime> {
+    let (value, utc) = match value.strip_suffix(['Z', 'z']) {
+        Some(v) => (v, true),
+        None => (value, false),
+    };
+    let dt = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S")
+        .map_err(|_| anyhow!("Invalid date-time '{}'", value))?;
+
+    Ok(if utc {
+        dt.and_utc().with_timezone(local_tz).naive_local()
+    } else {
+        dt
+    })
+}
+
+/// Parse an RFC 5545 duration such as `PT1H30M` or `P1D`.
+fn parse_duration(value: &str) -> Result<i64> {
+    let invalid = || anyhow!("Invalid duration '{}'", value);
+    let rest = value
+        .trim_start_matches('+')
+        .strip_prefix('P')
+        .ok_or_else(invalid)?;
+
+    let mut secs: i64 = 0;
+    let mut number = String::new();
+    let mut in_time = false;
+    for c in rest.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'T' => in_time = true,
+            unit => {
+                let n: i64 = number.parse().map_err(|_| invalid())?;
+                number.clear();
+                let unit = match (unit, in_time) {
+                    ('W', false) => 7 * 86_400,
+                    ('D', false) => 86_400,
+                    ('H', true) => 3600,
+                    ('M', true) => 60,
+                    ('S', true) => 1,
+                    _ => return Err(invalid()),
+                };
+                secs = n
+                    .checked_mul(unit)
+                    .and_then(|v| secs.checked_add(v))
+                    .ok_or_else(invalid)?;
+            }
+        }
+    }
+    if !number.is_empty() {
+        return Err(invalid());
+    }
+    Ok(secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_events() {
+        let ics = "BEGIN:VCALENDAR\r\n\
+            BEGIN:VEVENT\r\n\
+            SUMMARY:Morning Show\\, Live\r\n\
+            DTSTART;TZID=Europe/London:20260302T070000\r\n\
+            DURATION:PT2H30M\r\n\
+            RRULE:FREQ=WEEKLY;BYDAY=MO,\r\n \
+            TU\r\n\
+            END:VEVENT\r\n\
+            BEGIN:VEVENT\r\n\
+            SUMMARY:News\r\n\
+            DTSTART:20260305T170000Z\r\n\
+            DTEND:20260305T173000Z\r\n\
+            END:VEVENT\r\n\
+            BEGIN:VEVENT\r\n\
+            SUMMARY:Holiday\r\n\
+            DTSTART;VALUE=DATE:20261225\r\n\
+            END:VEVENT\r\n\
+            END:VCALENDAR\r\n";
+
+        let (events, skipped) = parse_events(ics, &chrono_tz::America::New_York);
+        assert_eq!(events.len(), 2);
+
+        assert_eq!(events[0].summary, "Morning Show, Live");
+        assert_eq!(events[0].duration_secs, 9000);
+        assert_eq!(events[0].rrule.as_deref(), Some("FREQ=WEEKLY;BYDAY=MO,TU"));
+        assert_eq!(events[0].start.to_string(), "2026-03-02 07:00:00");
+
+        // UTC times land in the local zone
+        assert_eq!(events[1].start.to_string(), "2026-03-05 12:00:00");
+        assert_eq!(events[1].duration_secs, 1800);
+
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].summary, "Holiday");
+    }
+
+    #[test]
+    fn test_text_round_trip_and_folding() {
+        let text = "Talk; news, and \\ more\nlater";
+        assert_eq!(unescape_text(&escape_text(text)), text);
+
+        let long = format!("SUMMARY:{}", "x".repeat(200));
+        let folded = fold_line(&long);
+        assert!(folded.split("\r\n").all(|l| l.len() <= 75));
+        assert_eq!(folded.replace("\r\n ", ""), long);
+
+        assert_eq!(parse_duration("P1DT1S").unwrap(), 86_401);
+        assert!(parse_duration("PT1X").is_err());
+        assert!(parse_duration("PT9999999999999999H").is_err());
+    }
+}
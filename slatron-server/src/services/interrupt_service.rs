@@ -0,0 +1,153 @@
+//! Emergency interrupts: breaking into nodes' playback with a slate or message.
+//!
+//! An interrupt preempts everything a node airs (schedule, spot reels, breaks and DJ
+//! injections) and loops a slate until it is cleared or its timeout passes. The node
+//! then rejoins the schedule where it would be by then, and reports its interrupt state
+//! in heartbeats meanwhile.
+
+use crate::db::DbConnection;
+use crate::models::Bumper;
+use crate::services::loudness_service;
+use crate::websocket::{NodeCommand, ServerMessage};
+use crate::AppState;
+use anyhow::{anyhow, Result};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Longest timeout accepted; longer interrupts are cleared by hand
+pub const MAX_TIMEOUT_SECS: u32 = 24 * 60 * 60;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct InterruptRequest {
+    /// Text shown on screen for the whole interrupt
+    #[serde(default)]
+    pub message: Option<String>,
+    /// Rendered bumper looped as the slate; a black screen if unset
+    #[serde(default)]
+    pub bumper_id: Option<i32>,
+    /// The schedule resumes by itself after this long; only when cleared if unset
+    #[serde(default)]
+    pub timeout_secs: Option<u32>,
+}
+
+impl InterruptRequest {
+    pub fn validate(&self) -> Result<()> {
+        if let Some(secs) = self.timeout_secs {
+            if !(1..=MAX_TIMEOUT_SECS).contains(&secs) {
+                return Err(anyhow!(
+                    "Interrupt timeout must be between 1 and {} seconds",
+                    MAX_TIMEOUT_SECS
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// The message to show, if it has any text.
+    fn message(&self) -> Option<String> {
+        self.message
+            .as_deref()
+            .map(str::trim)
+            .filter(|m| !m.is_empty())
+            .map(str::to_string)
+    }
+}
+
+/// Interrupt state a node reports in its heartbeats.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InterruptStatus {
+    pub message: Option<String>,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    /// When the node resumes the schedule by itself, if the interrupt has a timeout
+    pub until: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// The command that starts `req` on a node. The slate bumper must be rendered.
+pub fn command(conn: &mut DbConnection, req: &InterruptRequest) -> Result<NodeCommand> {
+    req.validate()?;
+
+    let (slate_path, slate_gain_db) = match req.bumper_id {
+        Some(bumper_id) => {
+            use crate::schema::bumpers::dsl::{bumpers, id};
+            let bumper: Bumper = bumpers
+                .filter(id.eq(bumper_id))
+                .select(Bumper::as_select())
+                .first(conn)
+                .optional()?
+                .ok_or_else(|| anyhow!("Bumper {} not found", bumper_id))?;
+            let path = bumper
+                .rendered_path
+                .ok_or_else(|| anyhow!("Slate bumper {} is not rendered", bumper_id))?;
+            let gain = loudness_service::stored_gain_db(
+                bumper.loudness_lufs,
+                bumper.true_peak_dbtp,
+                loudness_service::target_lufs(conn),
+            );
+            (Some(path), gain)
+        }
+        None => (None, None),
+    };
+
+    Ok(NodeCommand::Interrupt {
+        message: req.message(),
+        slate_path,
+        slate_gain_db,
+        timeout_secs: req.timeout_secs,
+    })
+}
+
+/// Send `command` to the connected nodes among `node_ids` (every connected node if
+/// `None`). Returns the nodes it reached.
+pub async fn send(state: &AppState, node_ids: Option<&[i32]>, command: NodeCommand) -> Vec<i32> {
+    let connected = state.connected_nodes.read().await;
+    let mut reached: Vec<i32> = connected
+        .iter()
+        .filter(|(node_id, _)| node_ids.is_none_or(|ids| ids.contains(node_id)))
+        .filter(|(_, tx)| {
+            tx.send(ServerMessage::Command {
+                command: command.clone(),
+            })
+            .is_ok()
+        })
+        .map(|(node_id, _)| *node_id)
+        .collect();
+    reached.sort_unstable();
+
+    for node_id in node_ids.unwrap_or_default() {
+        if !reached.contains(node_id) {
+            tracing::warn!("Node {} is not connected; command not sent", node_id);
+        }
+    }
+    reached
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_interrupt() {
+        let req = InterruptRequest {
+            message: Some("  Shelter in place  ".to_string()),
+            timeout_secs: Some(600),
+            ..Default::default()
+        };
+        assert!(req.validate().is_ok());
+        assert_eq!(req.message().as_deref(), Some("Shelter in place"));
+
+        let blank = InterruptRequest {
+            message: Some(" ".to_string()),
+            ..Default::default()
+        };
+        assert!(blank.validate().is_ok());
+        assert_eq!(blank.message(), None);
+
+        for timeout_secs in [0, MAX_TIMEOUT_SECS + 1] {
+            let req = InterruptRequest {
+                timeout_secs: Some(timeout_secs),
+                ..Default::default()
+            };
+            assert!(req.validate().is_err());
+        }
+    }
+}
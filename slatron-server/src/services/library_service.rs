@@ -0,0 +1,357 @@
+//! Media library scans.
+//!
+//! Nodes walk their `media_roots` and report every media file with its size,
+//! modification time and what ffprobe found in it. New files become `local_file`
+//! content items, titled from their tags (or file name) and tagged with the folders
+//! they sit in below the root. Known files get their duration refreshed, and their
+//! checksum and loudness measurement dropped if the file changed. Which nodes found
+//! each file is kept per node; an item under a scanned root is marked missing once no
+//! node has its file any more, until one reports it again. A root a scan found nothing
+//! under at all (an unmounted drive, say) is left as it was.
+
+use crate::db::DbConnection;
+use crate::models::NewContentItem;
+use anyhow::Result;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// A media file as a node found it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScannedFile {
+    pub path: String,
+    pub size_bytes: i64,
+    pub modified_at: DateTime<Utc>,
+    /// Absent if ffprobe couldn't read the file
+    pub duration_secs: Option<f64>,
+    /// Container tags, keys lowercased
+    #[serde(default)]
+    pub tags: HashMap<String, String>,
+}
+
+/// What a scan changed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanReport {
+    pub scanned_at: DateTime<Utc>,
+    pub roots: Vec<String>,
+    pub files: usize,
+    pub created: usize,
+    pub updated: usize,
+    /// Items newly marked missing
+    pub missing: usize,
+    /// Items marked missing whose file is back
+    pub restored: usize,
+}
+
+/// The root `path` is under, the deepest if roots nest.
+fn root_of<'a>(roots: &'a [String], path: &str) -> Option<&'a str> {
+    roots
+        .iter()
+        .map(|root| root.trim_end_matches('/'))
+        .filter(|root| {
+            path.strip_prefix(root)
+                .is_some_and(|rest| rest.starts_with('/'))
+        })
+        .max_by_key(|root| root.len())
+}
+
+/// The folders between `root` and the file, as content tags.
+fn folder_tags(root: &str, path: &str) -> Vec<String> {
+    let relative = Path::new(path)
+        .strip_prefix(root)
+        .unwrap_or(Path::new(path));
+    relative
+        .parent()
+        .into_iter()
+        .flat_map(|dir| dir.iter())
+        .map(|folder| {
+            folder
+                .to_string_lossy()
+                .replace(',', " ")
+                .trim()
+                .to_string()
+        })
+        .filter(|folder| !folder.is_empty())
+        .collect()
+}
+
+/// The file's `title` tag, else its name tidied up.
+fn title_for(file: &ScannedFile) -> String {
+    if let Some(title) = file
+        .tags
+        .get("title")
+        .map(|t| t.trim())
+        .filter(|t| !t.is_empty())
+    {
+        return title.to_string();
+    }
+    let stem = Path::new(&file.path)
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| file.path.clone());
+    stem.replace(['_', '.'], " ").trim().to_string()
+}
+
+fn minutes_for(secs: f64) -> i32 {
+    (secs / 60.0).ceil().max(1.0) as i32
+}
+
+/// Rows inserted or ids matched per statement, well under SQLite's bound parameter limit
+const STATEMENT_CHUNK: usize = 1000;
+
+/// Bring the content items under `roots` in line with the files `node` found there.
+pub fn ingest(
+    conn: &mut DbConnection,
+    node: i32,
+    roots: &[String],
+    files: &[ScannedFile],
+    now: NaiveDateTime,
+) -> Result<ScanReport> {
+    use crate::schema::content_items::dsl::*;
+    use crate::schema::node_library_files::dsl as nlf;
+
+    type Known = (
+        Option<i32>,
+        String,
+        Option<i32>,
+        Option<i64>,
+        Option<NaiveDateTime>,
+        Option<NaiveDateTime>,
+    );
+
+    let mut report = ScanReport {
+        scanned_at: now.and_utc(),
+        roots: roots.to_vec(),
+        files: 0,
+        created: 0,
+        updated: 0,
+        missing: 0,
+        restored: 0,
+    };
+
+    conn.transaction::<_, anyhow::Error, _>(|conn| {
+        let known: Vec<Known> = content_items
+            .filter(content_type.eq("local_file"))
+            .select((
+                id,
+                content_path,
+                duration_minutes,
+                file_size_bytes,
+                file_modified_at,
+                missing_since,
+            ))
+            .load(conn)?;
+        let mut known: HashMap<String, Known> = known
+            .into_iter()
+            .filter(|item| root_of(roots, &item.1).is_some())
+            .map(|item| (item.1.clone(), item))
+            .collect();
+        let mut found: Vec<i32> = Vec::with_capacity(files.len());
+        let mut scanned_roots: HashSet<&str> = HashSet::new();
+
+        for file in files {
+            let Some(root) = root_of(roots, &file.path) else {
+                continue;
+            };
+            report.files += 1;
+            scanned_roots.insert(root);
+            let modified = file.modified_at.naive_utc();
+            let minutes = file.duration_secs.map(minutes_for);
+
+            let Some((item_id, _, old_minutes, old_size, old_modified, was_missing)) =
+                known.remove(&file.path)
+            else {
+                let new_item = NewContentItem {
+                    title: title_for(file),
+                    description: file.tags.get("description").cloned(),
+                    content_type: "local_file".to_string(),
+                    content_path: file.path.clone(),
+                    adapter_id: None,
+                    duration_minutes: minutes,
+                    tags: Some(folder_tags(root, &file.path).join(",")).filter(|t| !t.is_empty()),
+                    node_accessibility: None,
+                    transformer_scripts: None,
+                    is_dj_accessible: false,
+                    spot_reel_id: None,
+                    sha256: None,
+                    cue_points: None,
+                    cue_out_secs: None,
+                };
+                let new_id: Option<i32> = diesel::insert_into(content_items)
+                    .values((
+                        &new_item,
+                        file_size_bytes.eq(file.size_bytes),
+                        file_modified_at.eq(modified),
+                    ))
+                    .returning(id)
+                    .get_result(conn)?;
+                found.extend(new_id);
+                report.created += 1;
+                continue;
+            };
+
+            found.extend(item_id);
+
+            let changed = old_size.is_some_and(|size| size != file.size_bytes)
+                || old_modified.is_some_and(|at| at != modified);
+            let refreshed = minutes.is_some() && minutes != old_minutes;
+            let unrecorded = old_size.is_none() || old_modified.is_none();
+            if !(changed || refreshed || unrecorded || was_missing.is_some()) {
+                continue;
+            }
+
+            let target = content_items.filter(id.eq(item_id));
+            diesel::update(target)
+                .set((
+                    duration_minutes.eq(minutes.or(old_minutes)),
+                    file_size_bytes.eq(file.size_bytes),
+                    file_modified_at.eq(modified),
+                    missing_since.eq(None::<NaiveDateTime>),
+                    updated_at.eq(now),
+                ))
+                .execute(conn)?;
+            if changed {
+                // A different file; what was measured of the old one no longer holds
+                diesel::update(target)
+                    .set((
+                        sha256.eq(None::<String>),
+                        loudness_lufs.eq(None::<f32>),
+                        true_peak_dbtp.eq(None::<f32>),
+                        loudness_analyzed_at.eq(None::<NaiveDateTime>),
+                    ))
+                    .execute(conn)?;
+            }
+
+            if was_missing.is_some() {
+                report.restored += 1;
+            } else {
+                report.updated += 1;
+            }
+        }
+
+        // The scan covers everything the node has, so its previous one is replaced whole
+        diesel::delete(nlf::node_library_files.filter(nlf::node_id.eq(node))).execute(conn)?;
+        for chunk in found.chunks(STATEMENT_CHUNK) {
+            let rows: Vec<_> = chunk
+                .iter()
+                .map(|cid| {
+                    (
+                        nlf::node_id.eq(node),
+                        nlf::content_id.eq(*cid),
+                        nlf::seen_at.eq(now),
+                    )
+                })
+                .collect();
+            diesel::insert_into(nlf::node_library_files)
+                .values(&rows)
+                .execute(conn)?;
+        }
+
+        // Whatever is left under the roots wasn't found here; it's gone if no other node
+        // has it either. A root with no files at all is more likely unmounted than
+        // emptied, so nothing under it is marked.
+        let unfound: Vec<i32> = known
+            .into_values()
+            .filter(|item| item.5.is_none())
+            .filter(|item| root_of(roots, &item.1).is_some_and(|r| scanned_roots.contains(r)))
+            .filter_map(|item| item.0)
+            .collect();
+        for chunk in unfound.chunks(STATEMENT_CHUNK) {
+            let elsewhere: HashSet<i32> = nlf::node_library_files
+                .filter(nlf::content_id.eq_any(chunk))
+                .select(nlf::content_id)
+                .load::<i32>(conn)?
+                .into_iter()
+                .collect();
+            let gone: Vec<i32> = chunk
+                .iter()
+                .copied()
+                .filter(|cid| !elsewhere.contains(cid))
+                .collect();
+            report.missing += diesel::update(content_items.filter(id.eq_any(gone)))
+                .set(missing_since.eq(Some(now)))
+                .execute(conn)?;
+        }
+
+        Ok(())
+    })?;
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scanned_file_metadata() {
+        let roots = vec!["/media".to_string(), "/media/shows/".to_string()];
+        let path = "/media/shows/Seinfeld/Season 1/the_stake_out.mkv";
+
+        assert_eq!(root_of(&roots, path), Some("/media/shows"));
+        assert_eq!(root_of(&roots, "/mediaextra/a.mkv"), None);
+        assert_eq!(
+            folder_tags("/media/shows", path),
+            vec!["Seinfeld", "Season 1"]
+        );
+
+        let mut file = ScannedFile {
+            path: path.to_string(),
+            size_bytes: 1024,
+            modified_at: Utc::now(),
+            duration_secs: Some(1381.2),
+            tags: HashMap::new(),
+        };
+        assert_eq!(title_for(&file), "the stake out");
+        file.tags
+            .insert("title".to_string(), " The Stake Out ".to_string());
+        assert_eq!(title_for(&file), "The Stake Out");
+        assert_eq!(minutes_for(1381.2), 24);
+    }
+
+    #[test]
+    fn test_missing_files_and_empty_roots() {
+        use diesel::connection::SimpleConnection;
+
+        let mut conn = crate::db::test_connection();
+        conn.batch_execute(
+            "INSERT INTO nodes (id, name, secret_key, status) VALUES (1, 'Node', 'secret', 'online')",
+        )
+        .unwrap();
+        let roots = vec!["/media/a".to_string(), "/media/b".to_string()];
+        let file = |path: &str| ScannedFile {
+            path: path.to_string(),
+            size_bytes: 1024,
+            modified_at: Utc::now(),
+            duration_secs: Some(60.0),
+            tags: HashMap::new(),
+        };
+        let missing = |conn: &mut DbConnection| {
+            use crate::schema::content_items::dsl::*;
+            content_items
+                .filter(missing_since.is_not_null())
+                .select(content_path)
+                .order(content_path)
+                .load::<String>(conn)
+                .unwrap()
+        };
+        let now = Utc::now().naive_utc();
+
+        let files = [file("/media/a/x.mp4"), file("/media/b/y.mp4")];
+        let report = ingest(&mut conn, 1, &roots, &files, now).unwrap();
+        assert_eq!(report.created, 2);
+
+        // x is gone from a root that was scanned; b came back empty, as an unmounted
+        // drive would, so y is left alone
+        let report = ingest(&mut conn, 1, &roots, &[file("/media/a/z.mp4")], now).unwrap();
+        assert_eq!(report.missing, 1);
+        assert_eq!(missing(&mut conn), vec!["/media/a/x.mp4"]);
+
+        // A scan that finds nothing anywhere marks nothing
+        let report = ingest(&mut conn, 1, &roots, &[], now).unwrap();
+        assert_eq!(report.missing, 0);
+        assert_eq!(missing(&mut conn), vec!["/media/a/x.mp4"]);
+    }
+}
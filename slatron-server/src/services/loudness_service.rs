@@ -0,0 +1,267 @@
+//! EBU R128 loudness analysis of content items and rendered bumpers.
+//!
+//! A background job measures integrated loudness and true peak with ffmpeg's `ebur128`
+//! filter for anything not analysed yet. Nodes are sent a per-item gain that brings
+//! each item to the `loudness_target_lufs` setting without pushing its true peak over
+//! the ceiling.
+
+use crate::db::{DbConnection, DbPool};
+use anyhow::{anyhow, Result};
+use diesel::prelude::*;
+use std::process::Command;
+use std::time::Duration;
+
+const ANALYSIS_INTERVAL: Duration = Duration::from_secs(60);
+/// Items measured per table per pass, so a large library is worked through gradually
+const BATCH_SIZE: i64 = 10;
+/// Gain is limited so the true peak stays below this
+const TRUE_PEAK_CEILING_DBTP: f64 = -1.0;
+/// Quiet items are boosted by this much at most
+const MAX_BOOST_DB: f64 = 12.0;
+/// ebur128 reports fully gated (silent) media at this level
+const SILENCE_LUFS: f64 = -70.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Loudness {
+    pub integrated_lufs: f64,
+    pub true_peak_dbtp: f64,
+}
+
+/// Measure the media at `path` (a file or URL the server can read) with ffmpeg.
+pub fn measure(path: &str) -> Result<Loudness> {
+    let output = Command::new("ffmpeg")
+        .arg("-hide_banner")
+        .arg("-nostats")
+        .arg("-i")
+        .arg(path)
+        .arg("-vn")
+        .arg("-af")
+        .arg("ebur128=peak=true")
+        .arg("-f")
+        .arg("null")
+        .arg("-")
+        .output()?;
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() {
+        return Err(anyhow!("ffmpeg failed: {}", stderr.trim()));
+    }
+    parse_summary(&stderr)
+}
+
+/// Read the summary ebur128 logs at the end of a run.
+fn parse_summary(log: &str) -> Result<Loudness> {
+    let summary = log
+        .rfind("Summary:")
+        .map(|at| &log[at..])
+        .ok_or_else(|| anyhow!("No loudness summary in ffmpeg output (no audio?)"))?;
+
+    let value = |label: &str| -> Result<f64> {
+        summary
+            .lines()
+            .find_map(|line| line.trim().strip_prefix(label))
+            .and_then(|rest| rest.split_whitespace().next())
+            .and_then(|v| v.parse().ok())
+            .ok_or_else(|| anyhow!("Missing {} in loudness summary", label))
+    };
+
+    Ok(Loudness {
+        integrated_lufs: value("I:")?,
+        true_peak_dbtp: value("Peak:")?,
+    })
+}
+
+/// Gain (dB) that brings media measured at `lufs` / `peak_dbtp` to `target_lufs`,
+/// to the nearest 0.1 dB. `None` for silent media, which has no loudness to match.
+pub fn gain_db(lufs: f64, peak_dbtp: f64, target_lufs: f64) -> Option<f64> {
+    if !lufs.is_finite() || lufs <= SILENCE_LUFS {
+        return None;
+    }
+    let gain = (target_lufs - lufs)
+        .min(MAX_BOOST_DB)
+        .min(TRUE_PEAK_CEILING_DBTP - peak_dbtp);
+    Some((gain * 10.0).round() / 10.0)
+}
+
+/// The `loudness_target_lufs` setting; `None` (normalisation off) if unset or invalid.
+pub fn target_lufs(conn: &mut DbConnection) -> Option<f64> {
+    use crate::schema::global_settings::dsl::{global_settings, key, value};
+
+    let setting: Option<String> = global_settings
+        .filter(key.eq("loudness_target_lufs"))
+        .select(value)
+        .first(conn)
+        .optional()
+        .ok()
+        .flatten();
+
+    setting.and_then(|v| v.trim().parse::<f64>().ok().filter(|t| t.is_finite()))
+}
+
+/// Gain for a stored measurement, if there is one and normalisation is on.
+pub fn stored_gain_db(
+    lufs: Option<f32>,
+    peak_dbtp: Option<f32>,
+    target_lufs: Option<f64>,
+) -> Option<f64> {
+    gain_db(lufs? as f64, peak_dbtp? as f64, target_lufs?)
+}
+
+/// Forget a content item's measurement so the job analyses its (changed) media again.
+pub fn reset_content(conn: &mut DbConnection, item_id: i32) -> QueryResult<usize> {
+    use crate::schema::content_items::dsl::*;
+
+    diesel::update(content_items.filter(id.eq(item_id)))
+        .set((
+            loudness_lufs.eq(None::<f32>),
+            true_peak_dbtp.eq(None::<f32>),
+            loudness_analyzed_at.eq(None::<chrono::NaiveDateTime>),
+        ))
+        .execute(conn)
+}
+
+/// Forget a bumper's measurement so the job analyses its new rendering.
+pub fn reset_bumper(conn: &mut DbConnection, bumper_id: i32) -> QueryResult<usize> {
+    use crate::schema::bumpers::dsl::*;
+
+    diesel::update(bumpers.filter(id.eq(bumper_id)))
+        .set((
+            loudness_lufs.eq(None::<f32>),
+            true_peak_dbtp.eq(None::<f32>),
+            loudness_analyzed_at.eq(None::<chrono::NaiveDateTime>),
+        ))
+        .execute(conn)
+}
+
+pub async fn run(db: DbPool) {
+    let mut tick = tokio::time::interval(ANALYSIS_INTERVAL);
+
+    loop {
+        tick.tick().await;
+
+        let pool = db.clone();
+        // ffmpeg decodes the whole file, so keep it off the async runtime
+        let result = tokio::task::spawn_blocking(move || {
+            let mut conn = pool.get()?;
+            analyze_content(&mut conn)?;
+            analyze_bumpers(&mut conn)
+        })
+        .await;
+
+        match result {
+            Ok(Err(e)) => tracing::error!("Loudness analysis failed: {}", e),
+            Err(e) => tracing::error!("Loudness analysis task panic: {}", e),
+            Ok(Ok(())) => {}
+        }
+    }
+}
+
+fn analyze_content(conn: &mut DbConnection) -> Result<()> {
+    use crate::schema::content_items::dsl::*;
+
+    let pending: Vec<(Option<i32>, String)> = content_items
+        .filter(loudness_analyzed_at.is_null())
+        .filter(content_type.ne("spot_reel"))
+        .select((id, content_path))
+        .limit(BATCH_SIZE)
+        .load(conn)?;
+
+    for (item_id, path) in pending {
+        let measured = measure_logged(&format!("content {}", item_id.unwrap_or(0)), &path);
+        diesel::update(content_items.filter(id.eq(item_id)))
+            .set((
+                loudness_lufs.eq(measured.map(|l| l.integrated_lufs as f32)),
+                true_peak_dbtp.eq(measured.map(|l| l.true_peak_dbtp as f32)),
+                loudness_analyzed_at.eq(Some(chrono::Utc::now().naive_utc())),
+            ))
+            .execute(conn)?;
+    }
+    Ok(())
+}
+
+fn analyze_bumpers(conn: &mut DbConnection) -> Result<()> {
+    use crate::schema::bumpers::dsl::*;
+
+    let pending: Vec<(Option<i32>, Option<String>)> = bumpers
+        .filter(loudness_analyzed_at.is_null())
+        .filter(rendered_path.is_not_null())
+        .select((id, rendered_path))
+        .limit(BATCH_SIZE)
+        .load(conn)?;
+
+    for (bumper_id, path) in pending {
+        let Some(path) = path else {
+            continue;
+        };
+        // Rendered bumpers are served from the static directory
+        let measured = measure_logged(
+            &format!("bumper {}", bumper_id.unwrap_or(0)),
+            &format!("static/{}", path),
+        );
+        diesel::update(bumpers.filter(id.eq(bumper_id)))
+            .set((
+                loudness_lufs.eq(measured.map(|l| l.integrated_lufs as f32)),
+                true_peak_dbtp.eq(measured.map(|l| l.true_peak_dbtp as f32)),
+                loudness_analyzed_at.eq(Some(chrono::Utc::now().naive_utc())),
+            ))
+            .execute(conn)?;
+    }
+    Ok(())
+}
+
+fn measure_logged(what: &str, path: &str) -> Option<Loudness> {
+    match measure(path) {
+        Ok(loudness) => {
+            tracing::info!(
+                "Measured {}: {:.1} LUFS, {:.1} dBTP",
+                what,
+                loudness.integrated_lufs,
+                loudness.true_peak_dbtp
+            );
+            Some(loudness)
+        }
+        Err(e) => {
+            tracing::warn!("Could not measure loudness of {} ({}): {}", what, path, e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_and_gain() {
+        let log =
+            "[Parsed_ebur128_0 @ 0x5581] t: 9.9 TARGET:-23 LUFS M: -18.1 S: -18.6 I: -18.9 LUFS\n\
+            [Parsed_ebur128_0 @ 0x5581] Summary:\n\
+            \n\
+            \x20 Integrated loudness:\n\
+            \x20   I:         -16.4 LUFS\n\
+            \x20   Threshold: -26.6 LUFS\n\
+            \n\
+            \x20 Loudness range:\n\
+            \x20   LRA:         4.2 LU\n\
+            \n\
+            \x20 True peak:\n\
+            \x20   Peak:       -0.3 dBFS\n";
+        let loudness = parse_summary(log).unwrap();
+        assert_eq!(
+            loudness,
+            Loudness {
+                integrated_lufs: -16.4,
+                true_peak_dbtp: -0.3
+            }
+        );
+        assert!(parse_summary("Stream #0:0: Video: h264").is_err());
+
+        // Loud items are turned down to the target
+        assert_eq!(gain_db(-16.4, -0.3, -23.0), Some(-6.6));
+        // Quiet ones are turned up only as far as the true peak allows
+        assert_eq!(gain_db(-30.0, -4.0, -23.0), Some(3.0));
+        assert_eq!(gain_db(-50.0, -30.0, -23.0), Some(12.0));
+        assert_eq!(gain_db(-70.0, f64::NEG_INFINITY, -23.0), None);
+        assert_eq!(stored_gain_db(Some(-20.0), Some(-3.0), None), None);
+    }
+}
@@ -1,8 +1,25 @@
 pub mod ai;
+pub mod alert_service;
+pub mod as_run_service;
+pub mod break_service;
 pub mod bumper_service;
+pub mod channel_service;
 pub mod cleaning_service;
+pub mod clock_service;
 pub mod dj_dialogue_service;
+pub mod fallback_service;
 pub mod heartbeat_monitor;
+pub mod ical_service;
+pub mod interrupt_service;
+pub mod library_service;
+pub mod loudness_service;
+pub mod recurrence;
+pub mod rundown_service;
 pub mod schedule_service;
 pub mod script_service;
+pub mod simulation_service;
+pub mod transition_service;
 pub mod tts;
+pub mod validation_service;
+pub mod version_service;
+pub mod xmltv_service;
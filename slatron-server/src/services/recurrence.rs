@@ -0,0 +1,463 @@
+//! Minimal RFC 5545 RRULE support for `recurring` schedule blocks.
+//!
+//! Blocks carry their own `start_time`, so only the date-level parts of a rule
+//! are interpreted here: FREQ (DAILY/WEEKLY/MONTHLY/YEARLY), INTERVAL, COUNT,
+//! UNTIL, BYDAY (with optional ordinals such as `2TU` or `-1FR`), BYMONTHDAY,
+//! BYMONTH, BYSETPOS and WKST. The series start (DTSTART) is the block's
+//! `specific_date`.
+
+use anyhow::{anyhow, bail, Result};
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+
+// Upper bound on the number of periods walked when expanding a rule, so a rule
+// that never produces a date (e.g. BYMONTHDAY=31;BYMONTH=2) can't spin forever.
+const MAX_PERIODS: u32 = 20_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecurrenceRule {
+    pub freq: Frequency,
+    pub interval: u32,
+    pub count: Option<u32>,
+    pub until: Option<NaiveDate>,
+    /// (ordinal, weekday) pairs, e.g. `-1FR` => (Some(-1), Fri)
+    pub by_day: Vec<(Option<i32>, Weekday)>,
+    pub by_month_day: Vec<i32>,
+    pub by_month: Vec<u32>,
+    pub by_set_pos: Vec<i32>,
+    pub week_start: Weekday,
+}
+
+impl RecurrenceRule {
+    /// Parse an RRULE value, with or without the leading `RRULE:` name.
+    pub fn parse(input: &str) -> Result<Self> {
+        let body = input.trim();
+        let body = body
+            .strip_prefix("RRULE:")
+            .or_else(|| body.strip_prefix("rrule:"))
+            .unwrap_or(body);
+
+        let mut freq = None;
+        let mut rule = RecurrenceRule {
+            freq: Frequency::Daily,
+            interval: 1,
+            count: None,
+            until: None,
+            by_day: Vec::new(),
+            by_month_day: Vec::new(),
+            by_month: Vec::new(),
+            by_set_pos: Vec::new(),
+            week_start: Weekday::Mon,
+        };
+
+        for part in body.split(';').filter(|p| !p.trim().is_empty()) {
+            let (name, value) = part
+                .split_once('=')
+                .ok_or_else(|| anyhow!("Malformed RRULE part '{}'", part))?;
+            let value = value.trim();
+
+            match name.trim().to_ascii_uppercase().as_str() {
+                "FREQ" => {
+                    freq = Some(match value.to_ascii_uppercase().as_str() {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        "YEARLY" => Frequency::Yearly,
+                        other => bail!("Unsupported FREQ '{}'", other),
+                    })
+                }
+                "INTERVAL" => {
+                    rule.interval = value.parse()?;
+                    if rule.interval == 0 {
+                        bail!("INTERVAL must be at least 1");
+                    }
+                }
+                "COUNT" => rule.count = Some(value.parse()?),
+                "UNTIL" => rule.until = Some(parse_until(value)?),
+                "BYDAY" => {
+                    rule.by_day = value
+                        .split(',')
+                        .map(parse_by_day)
+                        .collect::<Result<Vec<_>>>()?
+                }
+                "BYMONTHDAY" => {
+                    rule.by_month_day = parse_int_list(value)?;
+                    if rule.by_month_day.iter().any(|d| *d == 0 || d.abs() > 31) {
+                        bail!("BYMONTHDAY values must be within 1..=31 or -31..=-1");
+                    }
+                }
+                "BYMONTH" => {
+                    rule.by_month = parse_int_list(value)?
+                        .into_iter()
+                        .map(|m| m as u32)
+                        .collect();
+                    if rule.by_month.iter().any(|m| !(1..=12).contains(m)) {
+                        bail!("BYMONTH values must be within 1..=12");
+                    }
+                }
+                "BYSETPOS" => {
+                    rule.by_set_pos = parse_int_list(value)?;
+                    if rule.by_set_pos.contains(&0) {
+                        bail!("BYSETPOS values must be non-zero");
+                    }
+                }
+                "WKST" => rule.week_start = parse_weekday(value)?,
+                other => bail!("Unsupported RRULE part '{}'", other),
+            }
+        }
+
+        rule.freq = freq.ok_or_else(|| anyhow!("RRULE is missing FREQ"))?;
+
+        if rule.count.is_some() && rule.until.is_some() {
+            bail!("RRULE must not contain both COUNT and UNTIL");
+        }
+        if rule.by_day.iter().any(|(ord, _)| ord.is_some())
+            && matches!(rule.freq, Frequency::Daily | Frequency::Weekly)
+        {
+            bail!("Ordinal BYDAY values are only valid for MONTHLY or YEARLY rules");
+        }
+
+        Ok(rule)
+    }
+
+    /// All occurrence dates within `[from, to]` for a series starting on `dtstart`.
+    pub fn occurrences_between(
+        &self,
+        dtstart: NaiveDate,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Vec<NaiveDate> {
+        let mut result = Vec::new();
+        let mut produced: u32 = 0;
+
+        for k in 0..MAX_PERIODS {
+            let Some((period_start, candidates)) = self.period(dtstart, k) else {
+                break;
+            };
+            if period_start > to || self.until.is_some_and(|u| period_start > u) {
+                break;
+            }
+
+            for date in candidates {
+                if date < dtstart {
+                    continue;
+                }
+                if self.until.is_some_and(|u| date > u) || date > to {
+                    return result;
+                }
+                if let Some(count) = self.count {
+                    if produced >= count {
+                        return result;
+                    }
+                }
+                produced += 1;
+                if date >= from {
+                    result.push(date);
+                }
+            }
+        }
+
+        result
+    }
+
+    pub fn occurs_on(&self, dtstart: NaiveDate, date: NaiveDate) -> bool {
+        !self.occurrences_between(dtstart, date, date).is_empty()
+    }
+
+    /// Start date and sorted candidate dates of the k-th period of the series.
+    fn period(&self, dtstart: NaiveDate, k: u32) -> Option<(NaiveDate, Vec<NaiveDate>)> {
+        let step = k.checked_mul(self.interval)?;
+
+        let (start, mut dates) = match self.freq {
+            Frequency::Daily => {
+                let day = dtstart.checked_add_signed(Duration::days(step as i64))?;
+                let keep = (self.by_month.is_empty() || self.by_month.contains(&day.month()))
+                    && (self.by_month_day.is_empty()
+                        || self
+                            .by_month_day
+                            .iter()
+                            .any(|d| resolve_month_day(day.year(), day.month(), *d) == Some(day)))
+                    && (self.by_day.is_empty()
+                        || self.by_day.iter().any(|(_, wd)| *wd == day.weekday()));
+                (day, if keep { vec![day] } else { vec![] })
+            }
+            Frequency::Weekly => {
+                let offset = (7 + dtstart.weekday().num_days_from_monday()
+                    - self.week_start.num_days_from_monday())
+                    % 7;
+                let week = dtstart
+                    .checked_sub_signed(Duration::days(offset as i64))?
+                    .checked_add_signed(Duration::weeks(step as i64))?;
+                let weekdays: Vec<Weekday> = if self.by_day.is_empty() {
+                    vec![dtstart.weekday()]
+                } else {
+                    self.by_day.iter().map(|(_, wd)| *wd).collect()
+                };
+                let dates = weekdays
+                    .into_iter()
+                    .filter_map(|wd| {
+                        let delta = (7 + wd.num_days_from_monday()
+                            - self.week_start.num_days_from_monday())
+                            % 7;
+                        week.checked_add_signed(Duration::days(delta as i64))
+                    })
+                    .filter(|d| self.by_month.is_empty() || self.by_month.contains(&d.month()))
+                    .collect();
+                (week, dates)
+            }
+            Frequency::Monthly => {
+                let months = dtstart.month0() as i64 + step as i64;
+                let year = dtstart.year() as i64 + months.div_euclid(12);
+                let month = months.rem_euclid(12) as u32 + 1;
+                let year = i32::try_from(year).ok()?;
+                let start = NaiveDate::from_ymd_opt(year, month, 1)?;
+                let dates = if self.by_month.is_empty() || self.by_month.contains(&month) {
+                    self.month_candidates(year, month, dtstart)
+                } else {
+                    vec![]
+                };
+                (start, dates)
+            }
+            Frequency::Yearly => {
+                let year = dtstart.year().checked_add(i32::try_from(step).ok()?)?;
+                let start = NaiveDate::from_ymd_opt(year, 1, 1)?;
+                let dates = if !self.by_month.is_empty() {
+                    self.by_month
+                        .iter()
+                        .flat_map(|m| self.month_candidates(year, *m, dtstart))
+                        .collect()
+                } else if !self.by_day.is_empty() && self.by_month_day.is_empty() {
+                    self.year_weekday_candidates(year)
+                } else if !self.by_month_day.is_empty() {
+                    (1..=12)
+                        .flat_map(|m| self.month_candidates(year, m, dtstart))
+                        .collect()
+                } else {
+                    NaiveDate::from_ymd_opt(year, dtstart.month(), dtstart.day())
+                        .into_iter()
+                        .collect()
+                };
+                (start, dates)
+            }
+        };
+
+        dates.sort();
+        dates.dedup();
+
+        if !self.by_set_pos.is_empty() && !dates.is_empty() {
+            let len = dates.len() as i32;
+            let mut picked: Vec<NaiveDate> = self
+                .by_set_pos
+                .iter()
+                .filter_map(|pos| {
+                    let idx = if *pos > 0 { pos - 1 } else { len + pos };
+                    (0..len).contains(&idx).then(|| dates[idx as usize])
+                })
+                .collect();
+            picked.sort();
+            picked.dedup();
+            dates = picked;
+        }
+
+        Some((start, dates))
+    }
+
+    fn month_candidates(&self, year: i32, month: u32, dtstart: NaiveDate) -> Vec<NaiveDate> {
+        let by_month_day: Vec<NaiveDate> = self
+            .by_month_day
+            .iter()
+            .filter_map(|d| resolve_month_day(year, month, *d))
+            .collect();
+
+        let by_day: Vec<NaiveDate> = self
+            .by_day
+            .iter()
+            .flat_map(|(ord, wd)| {
+                let all: Vec<NaiveDate> = month_days(year, month)
+                    .filter(|d| d.weekday() == *wd)
+                    .collect();
+                pick_ordinal(all, *ord)
+            })
+            .collect();
+
+        match (self.by_month_day.is_empty(), self.by_day.is_empty()) {
+            (false, false) => by_month_day
+                .into_iter()
+                .filter(|d| by_day.contains(d))
+                .collect(),
+            (false, true) => by_month_day,
+            (true, false) => by_day,
+            (true, true) => NaiveDate::from_ymd_opt(year, month, dtstart.day())
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    fn year_weekday_candidates(&self, year: i32) -> Vec<NaiveDate> {
+        self.by_day
+            .iter()
+            .flat_map(|(ord, wd)| {
+                let all: Vec<NaiveDate> = (1..=12)
+                    .flat_map(|m| month_days(year, m))
+                    .filter(|d| d.weekday() == *wd)
+                    .collect();
+                pick_ordinal(all, *ord)
+            })
+            .collect()
+    }
+}
+
+fn pick_ordinal(all: Vec<NaiveDate>, ordinal: Option<i32>) -> Vec<NaiveDate> {
+    match ordinal {
+        None => all,
+        Some(n) => {
+            let len = all.len() as i32;
+            let idx = if n > 0 { n - 1 } else { len + n };
+            if (0..len).contains(&idx) {
+                vec![all[idx as usize]]
+            } else {
+                vec![]
+            }
+        }
+    }
+}
+
+fn month_days(year: i32, month: u32) -> impl Iterator<Item = NaiveDate> {
+    (1..=days_in_month(year, month)).filter_map(move |d| NaiveDate::from_ymd_opt(year, month, d))
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    let (next_year, next_month) = if month == 12 {
+        (year + 1, 1)
+    } else {
+        (year, month + 1)
+    };
+    NaiveDate::from_ymd_opt(next_year, next_month, 1)
+        .and_then(|d| d.pred_opt())
+        .map(|d| d.day())
+        .unwrap_or(28)
+}
+
+fn resolve_month_day(year: i32, month: u32, day: i32) -> Option<NaiveDate> {
+    let len = days_in_month(year, month) as i32;
+    let resolved = if day > 0 { day } else { len + day + 1 };
+    if resolved < 1 || resolved > len {
+        return None;
+    }
+    NaiveDate::from_ymd_opt(year, month, resolved as u32)
+}
+
+fn parse_until(value: &str) -> Result<NaiveDate> {
+    let date_part = value.get(..8).unwrap_or(value);
+    NaiveDate::parse_from_str(date_part, "%Y%m%d")
+        .map_err(|e| anyhow!("Invalid UNTIL '{}': {}", value, e))
+}
+
+fn parse_int_list(value: &str) -> Result<Vec<i32>> {
+    value
+        .split(',')
+        .map(|v| {
+            v.trim()
+                .parse::<i32>()
+                .map_err(|e| anyhow!("Invalid number '{}': {}", v, e))
+        })
+        .collect()
+}
+
+fn parse_weekday(value: &str) -> Result<Weekday> {
+    Ok(match value.trim().to_ascii_uppercase().as_str() {
+        "MO" => Weekday::Mon,
+        "TU" => Weekday::Tue,
+        "WE" => Weekday::Wed,
+        "TH" => Weekday::Thu,
+        "FR" => Weekday::Fri,
+        "SA" => Weekday::Sat,
+        "SU" => Weekday::Sun,
+        other => bail!("Invalid weekday '{}'", other),
+    })
+}
+
+fn parse_by_day(value: &str) -> Result<(Option<i32>, Weekday)> {
+    let value = value.trim();
+    if value.len() < 2 || !value.is_char_boundary(value.len() - 2) {
+        bail!("Invalid BYDAY value '{}'", value);
+    }
+    let (ordinal, day) = value.split_at(value.len() - 2);
+    let weekday = parse_weekday(day)?;
+    if ordinal.is_empty() {
+        return Ok((None, weekday));
+    }
+    let n: i32 = ordinal
+        .parse()
+        .map_err(|_| anyhow!("Invalid BYDAY ordinal '{}'", value))?;
+    if n == 0 || n.abs() > 53 {
+        bail!("Invalid BYDAY ordinal '{}'", value);
+    }
+    Ok((Some(n), weekday))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn d(y: i32, m: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, day).unwrap()
+    }
+
+    #[test]
+    fn test_monthly_ordinal_weekdays() {
+        // Every 2nd Tuesday
+        let rule = RecurrenceRule::parse("FREQ=MONTHLY;BYDAY=2TU").unwrap();
+        let dates = rule.occurrences_between(d(2026, 1, 1), d(2026, 1, 1), d(2026, 3, 31));
+        assert_eq!(dates, vec![d(2026, 1, 13), d(2026, 2, 10), d(2026, 3, 10)]);
+
+        // Last Friday of the month
+        let rule = RecurrenceRule::parse("RRULE:FREQ=MONTHLY;BYDAY=-1FR").unwrap();
+        let dates = rule.occurrences_between(d(2026, 1, 1), d(2026, 1, 1), d(2026, 2, 28));
+        assert_eq!(dates, vec![d(2026, 1, 30), d(2026, 2, 27)]);
+    }
+
+    #[test]
+    fn test_weekdays_in_december_with_until() {
+        let rule =
+            RecurrenceRule::parse("FREQ=DAILY;BYMONTH=12;BYDAY=MO,TU,WE,TH,FR;UNTIL=20261204")
+                .unwrap();
+        let dates = rule.occurrences_between(d(2026, 11, 1), d(2026, 11, 1), d(2026, 12, 31));
+        assert_eq!(
+            dates,
+            vec![
+                d(2026, 12, 1),
+                d(2026, 12, 2),
+                d(2026, 12, 3),
+                d(2026, 12, 4)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_biweekly_with_count() {
+        let rule = RecurrenceRule::parse("FREQ=WEEKLY;INTERVAL=2;BYDAY=TU;COUNT=3").unwrap();
+        // 2026-01-06 is a Tuesday
+        let dates = rule.occurrences_between(d(2026, 1, 6), d(2026, 1, 1), d(2026, 12, 31));
+        assert_eq!(dates, vec![d(2026, 1, 6), d(2026, 1, 20), d(2026, 2, 3)]);
+        assert!(rule.occurs_on(d(2026, 1, 6), d(2026, 1, 20)));
+        assert!(!rule.occurs_on(d(2026, 1, 6), d(2026, 1, 13)));
+        assert!(!rule.occurs_on(d(2026, 1, 6), d(2026, 2, 17)));
+    }
+
+    #[test]
+    fn test_rejects_invalid_rules() {
+        assert!(RecurrenceRule::parse("INTERVAL=2").is_err());
+        assert!(RecurrenceRule::parse("FREQ=HOURLY").is_err());
+        assert!(RecurrenceRule::parse("FREQ=WEEKLY;BYDAY=2TU").is_err());
+        assert!(RecurrenceRule::parse("FREQ=WEEKLY;BYDAY=1€").is_err());
+        assert!(RecurrenceRule::parse("FREQ=DAILY;COUNT=2;UNTIL=20260101").is_err());
+    }
+}
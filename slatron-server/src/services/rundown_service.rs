@@ -0,0 +1,474 @@
+//! Expands smart fill blocks into a concrete, gap-free run-down.
+//!
+//! A fill block carries a JSON `FillRule` (content query plus rotation policy)
+//! instead of a single `content_id`. The run-down for each airing is generated
+//! once and stored in `block_rundowns`, so nodes polling the schedule see the
+//! same sequence every time. Least-recently-played rotations count the items of
+//! run-downs already generated for earlier upcoming airings as played then, so days
+//! generated ahead of time don't all repeat the same picks.
+
+use crate::db::DbConnection;
+use crate::models::{BlockRundown, ContentItem, NewBlockRundown, ScheduleBlock};
+use crate::services::schedule_service;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use chrono_tz::Tz;
+use diesel::prelude::*;
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rotation {
+    /// Shuffled per airing (deterministic for a given block and date)
+    #[default]
+    Shuffle,
+    /// Items that haven't played for the longest time first
+    LeastRecentlyPlayed,
+    /// In library order, continuing where the previous airing stopped
+    Sequential,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FillRule {
+    /// Matches items carrying any of these tags (case-insensitive)
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub content_type: Option<String>,
+    #[serde(default)]
+    pub min_duration_minutes: Option<i32>,
+    #[serde(default)]
+    pub max_duration_minutes: Option<i32>,
+    #[serde(default)]
+    pub rotation: Rotation,
+}
+
+impl FillRule {
+    pub fn parse(json: &str) -> Result<Self> {
+        serde_json::from_str(json).map_err(|e| anyhow!("Invalid fill rule: {}", e))
+    }
+
+    pub fn matches(&self, item: &ContentItem) -> bool {
+        // No node has the file any more
+        if item.missing_since.is_some() {
+            return false;
+        }
+        // Items without a known duration can't be placed in a gap-free run-down
+        let Some(duration) = item.duration_minutes.filter(|d| *d > 0) else {
+            return false;
+        };
+
+        if let Some(ct) = &self.content_type {
+            if &item.content_type != ct {
+                return false;
+            }
+        }
+        if self.min_duration_minutes.is_some_and(|min| duration < min)
+            || self.max_duration_minutes.is_some_and(|max| duration > max)
+        {
+            return false;
+        }
+
+        if self.tags.is_empty() {
+            return true;
+        }
+        let item_tags: Vec<String> = item
+            .tags
+            .as_deref()
+            .unwrap_or_default()
+            .split(',')
+            .map(|t| t.trim().to_lowercase())
+            .filter(|t| !t.is_empty())
+            .collect();
+        self.tags
+            .iter()
+            .any(|t| item_tags.contains(&t.trim().to_lowercase()))
+    }
+}
+
+/// One item of a run-down, relative to the start of the block's airing.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RundownItem {
+    pub content_id: i32,
+    pub offset_secs: i64,
+    pub duration_secs: i64,
+}
+
+/// Run-down for the airing of `block` on `date`, generating and storing it on first use.
+/// A stored run-down is regenerated if the block's rule or duration changed since.
+pub fn rundown_for_occurrence(
+    conn: &mut DbConnection,
+    block: &ScheduleBlock,
+    rule_json: &str,
+    date: NaiveDate,
+) -> Result<Vec<RundownItem>> {
+    occurrence_rundown(conn, block, rule_json, date, true)
+}
+
+/// Like [`rundown_for_occurrence`], but a run-down that isn't stored yet is generated
+/// without storing it. Sequential rotations continue from the last stored airing.
+pub fn preview_rundown(
+    conn: &mut DbConnection,
+    block: &ScheduleBlock,
+    rule_json: &str,
+    date: NaiveDate,
+) -> Result<Vec<RundownItem>> {
+    occurrence_rundown(conn, block, rule_json, date, false)
+}
+
+fn occurrence_rundown(
+    conn: &mut DbConnection,
+    block: &ScheduleBlock,
+    rule_json: &str,
+    date: NaiveDate,
+    store: bool,
+) -> Result<Vec<RundownItem>> {
+    use crate::schema::block_rundowns::dsl as br_dsl;
+
+    let block_id = block.id.ok_or_else(|| anyhow!("Block ID missing"))?;
+    let duration_secs = block.total_duration_secs();
+
+    let existing: Option<BlockRundown> = br_dsl::block_rundowns
+        .filter(br_dsl::block_id.eq(block_id))
+        .filter(br_dsl::occurrence_date.eq(date))
+        .select(BlockRundown::as_select())
+        .first(conn)
+        .optional()?;
+
+    if let Some(rundown) = &existing {
+        if rundown.fill_rule == rule_json && rundown.duration_secs as i64 == duration_secs {
+            return Ok(serde_json::from_str(&rundown.items)?);
+        }
+    }
+
+    let rule = FillRule::parse(rule_json)?;
+
+    let mut pool: Vec<ContentItem> = {
+        use crate::schema::content_items::dsl::*;
+        content_items.select(ContentItem::as_select()).load(conn)?
+    };
+    pool.retain(|item| rule.matches(item));
+    pool.sort_by_key(|item| item.id);
+
+    if rule.rotation == Rotation::LeastRecentlyPlayed {
+        use crate::schema::schedule_blocks::dsl as sb_dsl;
+
+        // Occurrence dates are local to each block's schedule, which may still be a day
+        // behind UTC. Narrowed down to each schedule's own today below.
+        let now = Utc::now();
+        let upcoming: Vec<(i32, NaiveDate, NaiveTime, String)> = br_dsl::block_rundowns
+            .inner_join(sb_dsl::schedule_blocks)
+            .filter(br_dsl::occurrence_date.ge(now.date_naive() - Duration::days(1)))
+            .filter(br_dsl::occurrence_date.lt(date))
+            .select((
+                sb_dsl::schedule_id,
+                br_dsl::occurrence_date,
+                sb_dsl::start_time,
+                br_dsl::items,
+            ))
+            .load(conn)?;
+
+        // last_played_at is UTC, so compare airings in UTC too
+        let mut timezones: HashMap<i32, Tz> = HashMap::new();
+        let mut airings: Vec<(NaiveDateTime, Vec<RundownItem>)> = Vec::new();
+        for (sched_id, day, start, json) in upcoming {
+            let tz = match timezones.get(&sched_id) {
+                Some(tz) => *tz,
+                None => {
+                    let tz = schedule_service::schedule_timezone(conn, sched_id)?;
+                    timezones.insert(sched_id, tz);
+                    tz
+                }
+            };
+            let Some(start) = upcoming_airing(&tz, now, day, start) else {
+                continue;
+            };
+            if let Ok(items) = serde_json::from_str(&json) {
+                airings.push((start, items));
+            }
+        }
+        count_scheduled_plays(&mut pool, &airings);
+    }
+
+    // Sequential rotation picks up after the last item of the previous airing
+    let start_after = if rule.rotation == Rotation::Sequential {
+        let previous: Option<String> = br_dsl::block_rundowns
+            .filter(br_dsl::block_id.eq(block_id))
+            .filter(br_dsl::occurrence_date.lt(date))
+            .order(br_dsl::occurrence_date.desc())
+            .select(br_dsl::items)
+            .first(conn)
+            .optional()?;
+        previous
+            .and_then(|json| serde_json::from_str::<Vec<RundownItem>>(&json).ok())
+            .and_then(|items| items.last().map(|i| i.content_id))
+    } else {
+        None
+    };
+
+    let seed = ((block_id as u64) << 32) ^ date.num_days_from_ce() as u64;
+    let items = build_rundown(&pool, rule.rotation, seed, start_after, duration_secs);
+    if !store {
+        return Ok(items);
+    }
+
+    diesel::delete(
+        br_dsl::block_rundowns
+            .filter(br_dsl::block_id.eq(block_id))
+            .filter(br_dsl::occurrence_date.eq(date)),
+    )
+    .execute(conn)?;
+
+    diesel::insert_into(br_dsl::block_rundowns)
+        .values(&NewBlockRundown {
+            block_id,
+            occurrence_date: date,
+            fill_rule: rule_json.to_string(),
+            duration_secs: duration_secs as i32,
+            items: serde_json::to_string(&items)?,
+        })
+        .execute(conn)?;
+
+    Ok(items)
+}
+
+/// UTC start of an airing on the local `day` at `start` in `tz`, unless the day is
+/// already over there.
+fn upcoming_airing(
+    tz: &Tz,
+    now: DateTime<Utc>,
+    day: NaiveDate,
+    start: NaiveTime,
+) -> Option<NaiveDateTime> {
+    if day < now.with_timezone(tz).date_naive() {
+        return None;
+    }
+    Some(schedule_service::resolve_local(tz, day.and_time(start)).naive_utc())
+}
+
+/// Treat the items of `airings` (UTC start, run-down) as played when they air.
+fn count_scheduled_plays(pool: &mut [ContentItem], airings: &[(NaiveDateTime, Vec<RundownItem>)]) {
+    for (start, items) in airings {
+        for scheduled in items {
+            let played = *start + Duration::seconds(scheduled.offset_secs);
+            if let Some(item) = pool
+                .iter_mut()
+                .find(|item| item.id == Some(scheduled.content_id))
+            {
+                item.last_played_at = item.last_played_at.max(Some(played));
+            }
+        }
+    }
+}
+
+/// Fill `duration_secs` back to back from `pool` (already filtered, in library order),
+/// cycling through it as often as needed. Each item holds the air until its cue-out
+/// point, if it has one. The last item is cut at the block end.
+fn build_rundown(
+    pool: &[ContentItem],
+    rotation: Rotation,
+    seed: u64,
+    start_after: Option<i32>,
+    duration_secs: i64,
+) -> Vec<RundownItem> {
+    if pool.is_empty() || duration_secs <= 0 {
+        return Vec::new();
+    }
+
+    let mut base: Vec<&ContentItem> = pool.iter().collect();
+    match rotation {
+        Rotation::Shuffle => {}
+        Rotation::LeastRecentlyPlayed => {
+            // Never-played items (None) sort first
+            base.sort_by_key(|item| (item.last_played_at, item.id));
+        }
+        Rotation::Sequential => {
+            if let Some(pos) =
+                start_after.and_then(|cid| base.iter().position(|i| i.id == Some(cid)))
+            {
+                base.rotate_left(pos + 1);
+            }
+        }
+    }
+
+    let mut items = Vec::new();
+    let mut offset = 0;
+    let mut cycle: u64 = 0;
+    while offset < duration_secs {
+        let mut order = base.clone();
+        if rotation == Rotation::Shuffle {
+            order.shuffle(&mut StdRng::seed_from_u64(seed.wrapping_add(cycle)));
+        }
+
+        for item in order {
+            if offset >= duration_secs {
+                break;
+            }
+            let item_secs = airtime_secs(item);
+            items.push(RundownItem {
+                content_id: item.id.unwrap_or_default(),
+                offset_secs: offset,
+                duration_secs: item_secs.min(duration_secs - offset),
+            });
+            offset += item_secs;
+        }
+        cycle += 1;
+    }
+
+    items
+}
+
+/// Seconds before the next item starts: the cue-out point, else the whole item.
+fn airtime_secs(item: &ContentItem) -> i64 {
+    let full = item.duration_minutes.unwrap_or(0) as i64 * 60;
+    item.cue_out_secs
+        .filter(|secs| *secs >= 1.0)
+        .map_or(full, |secs| (secs as i64).min(full))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn item(id: i32, minutes: i32, tags: &str, last_played_day: Option<u32>) -> ContentItem {
+        let ts = |day| {
+            NaiveDate::from_ymd_opt(2026, 1, day)
+                .unwrap()
+                .and_time(chrono::NaiveTime::MIN)
+        };
+        ContentItem {
+            id: Some(id),
+            title: format!("Item {}", id),
+            description: None,
+            content_type: "local_file".to_string(),
+            content_path: format!("/media/{}.mp3", id),
+            adapter_id: None,
+            duration_minutes: Some(minutes),
+            tags: Some(tags.to_string()),
+            node_accessibility: None,
+            created_at: ts(1),
+            updated_at: ts(1),
+            transformer_scripts: None,
+            is_dj_accessible: false,
+            spot_reel_id: None,
+            last_played_at: last_played_day.map(ts),
+            sha256: None,
+            cue_points: None,
+            loudness_lufs: None,
+            true_peak_dbtp: None,
+            loudness_analyzed_at: None,
+            cue_out_secs: None,
+            file_size_bytes: None,
+            file_modified_at: None,
+            missing_since: None,
+        }
+    }
+
+    #[test]
+    fn test_fill_is_gap_free_and_cut_at_block_end() {
+        let pool = vec![item(1, 4, "music", None), item(2, 3, "music", None)];
+        let items = build_rundown(&pool, Rotation::Sequential, 0, None, 10 * 60);
+
+        let ids: Vec<i32> = items.iter().map(|i| i.content_id).collect();
+        assert_eq!(ids, vec![1, 2, 1]);
+        assert_eq!(items[2].offset_secs, 7 * 60);
+        assert_eq!(items[2].duration_secs, 3 * 60);
+        assert_eq!(items.iter().map(|i| i.duration_secs).sum::<i64>(), 10 * 60);
+
+        // The next item starts at a cue-out point, over the outro
+        let mut outro = item(3, 4, "music", None);
+        outro.cue_out_secs = Some(225.5);
+        let items = build_rundown(
+            &[outro, item(4, 3, "music", None)],
+            Rotation::Sequential,
+            0,
+            None,
+            6 * 60,
+        );
+        assert_eq!(items[1].offset_secs, 225);
+    }
+
+    #[test]
+    fn test_rotation_policies() {
+        let pool = vec![
+            item(1, 5, "music", Some(3)),
+            item(2, 5, "music", None),
+            item(3, 5, "music", Some(1)),
+        ];
+
+        let lru = build_rundown(&pool, Rotation::LeastRecentlyPlayed, 0, None, 15 * 60);
+        let ids: Vec<i32> = lru.iter().map(|i| i.content_id).collect();
+        assert_eq!(ids, vec![2, 3, 1]);
+
+        let sequential = build_rundown(&pool, Rotation::Sequential, 0, Some(2), 10 * 60);
+        let ids: Vec<i32> = sequential.iter().map(|i| i.content_id).collect();
+        assert_eq!(ids, vec![3, 1]);
+
+        // Shuffle is stable for the same seed
+        let a = build_rundown(&pool, Rotation::Shuffle, 42, None, 30 * 60);
+        let b = build_rundown(&pool, Rotation::Shuffle, 42, None, 30 * 60);
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn test_least_recently_played_across_days() {
+        let mut pool = vec![
+            item(1, 5, "music", Some(2)),
+            item(2, 5, "music", Some(1)),
+            item(3, 5, "music", Some(3)),
+            item(4, 5, "music", Some(4)),
+        ];
+        let monday = NaiveDate::from_ymd_opt(2026, 2, 2)
+            .unwrap()
+            .and_hms_opt(9, 0, 0)
+            .unwrap();
+
+        let first = build_rundown(&pool, Rotation::LeastRecentlyPlayed, 0, None, 10 * 60);
+        let ids: Vec<i32> = first.iter().map(|i| i.content_id).collect();
+        assert_eq!(ids, vec![2, 1]);
+
+        // Generated ahead, the next day's airing follows on rather than repeating
+        count_scheduled_plays(&mut pool, &[(monday, first)]);
+        let second = build_rundown(&pool, Rotation::LeastRecentlyPlayed, 0, None, 10 * 60);
+        let ids: Vec<i32> = second.iter().map(|i| i.content_id).collect();
+        assert_eq!(ids, vec![3, 4]);
+    }
+
+    #[test]
+    fn test_upcoming_airing_in_utc() {
+        let tz: Tz = "Asia/Tokyo".parse().unwrap();
+        // 20:00 UTC on the 1st is already the 2nd in Tokyo
+        let now = Utc.with_ymd_and_hms(2026, 2, 1, 20, 0, 0).unwrap();
+        let nine = NaiveTime::from_hms_opt(9, 0, 0).unwrap();
+
+        assert_eq!(
+            upcoming_airing(&tz, now, NaiveDate::from_ymd_opt(2026, 2, 1).unwrap(), nine),
+            None
+        );
+        // 09:00 JST is 00:00 UTC, the same scale as last_played_at
+        assert_eq!(
+            upcoming_airing(&tz, now, NaiveDate::from_ymd_opt(2026, 2, 2).unwrap(), nine),
+            NaiveDate::from_ymd_opt(2026, 2, 2)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+        );
+    }
+
+    #[test]
+    fn test_rule_matching() {
+        let rule = FillRule::parse(
+            r#"{"tags": ["Jazz"], "max_duration_minutes": 6, "rotation": "sequential"}"#,
+        )
+        .unwrap();
+        assert!(rule.matches(&item(1, 5, "music, jazz", None)));
+        assert!(!rule.matches(&item(2, 7, "jazz", None)));
+        assert!(!rule.matches(&item(3, 5, "rock", None)));
+        let mut missing = item(4, 5, "jazz", None);
+        missing.missing_since = Some(missing.created_at);
+        assert!(!rule.matches(&missing));
+        assert!(FillRule::parse(r#"{"rotation": "random"}"#).is_err());
+    }
+}
@@ -1,49 +1,57 @@
-use crate::api::schedules_api::CollapsedBlock;
+use crate::api::schedules_api::{CollapsedBlock, CollapsedItem};
 use crate::db::DbConnection;
-use crate::models::{Schedule, ScheduleBlock};
+use crate::models::{Schedule, ScheduleBlock, ScheduleException};
+use crate::services::break_service::BreakPolicy;
+use crate::services::channel_service::{self, Assignment, Target};
+use crate::services::recurrence::RecurrenceRule;
+use crate::services::rundown_service;
+use crate::services::transition_service::Transition;
+use crate::services::version_service;
 use anyhow::Result;
-use chrono::{Datelike, NaiveDate, Timelike};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
 use diesel::prelude::*;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 pub fn calculate_collapsed_schedule(
     conn: &mut DbConnection,
-    node_id: i32,
+    target: Target,
     date: NaiveDate,
-    _timezone_str: Option<String>,
+    timezone_str: Option<String>,
 ) -> Result<Vec<CollapsedBlock>> {
-    use crate::schema::{node_schedules, schedules};
-
-    // 1. Get all schedules assigned to this node along with assignment priority
-    let assigned_data: Vec<(Schedule, Option<i32>)> = node_schedules::table
-        .inner_join(schedules::table)
-        .filter(node_schedules::node_id.eq(node_id))
-        .filter(schedules::is_active.eq(true))
-        .select((Schedule::as_select(), node_schedules::priority))
-        .load(conn)?;
+    collapse_day(conn, target, date, timezone_str, true)
+}
 
-    struct EffectiveSchedule {
-        schedule: Schedule,
-        effective_priority: i32,
-    }
+/// Like [`calculate_collapsed_schedule`], but fill run-downs that haven't been generated
+/// yet are previewed instead of stored, so nothing is written.
+pub fn preview_collapsed_schedule(
+    conn: &mut DbConnection,
+    target: Target,
+    date: NaiveDate,
+    timezone_str: Option<String>,
+) -> Result<Vec<CollapsedBlock>> {
+    collapse_day(conn, target, date, timezone_str, false)
+}
 
-    let mut effective_schedules: Vec<EffectiveSchedule> = assigned_data
+fn collapse_day(
+    conn: &mut DbConnection,
+    target: Target,
+    date: NaiveDate,
+    timezone_str: Option<String>,
+    store_rundowns: bool,
+) -> Result<Vec<CollapsedBlock>> {
+    // 1. Get the active schedules in the lineup (the node's channel's and its own),
+    //    highest priority first
+    let effective_schedules: Vec<Assignment> = channel_service::lineup(conn, target)?
         .into_iter()
-        .map(|(s, p_override)| {
-            let eff = p_override.unwrap_or(s.priority);
-            EffectiveSchedule {
-                schedule: s,
-                effective_priority: eff,
-            }
-        })
+        .filter(|a| a.schedule.is_active)
         .collect();
 
-    // Sort by priority (descending - higher priority first)
-    effective_schedules.sort_by(|a, b| b.effective_priority.cmp(&a.effective_priority));
-
-    // 2. Parse Timezone (Not strictly used here anymore as we rely on 'date' being localized,
-    // but good to validate string if needed, or remove)
-    // let tz: chrono_tz::Tz = timezone_str ... (Removed as unused)
+    // 2. Resolve the timezone the local day is laid out in (explicit, else the target's own)
+    let tz: Tz = match timezone_str.as_deref().and_then(|s| s.parse().ok()) {
+        Some(tz) => tz,
+        None => target_timezone(conn, target)?,
+    };
 
     // 3. Pre-fetch blocks for relevant dates (Yesterday, Today, Tomorrow)
     //    because local time might shift across midnight relative to UTC.
@@ -53,11 +61,18 @@ pub fn calculate_collapsed_schedule(
 
     // Map: (ScheduleID, Date) -> Vec<ScheduleBlock>
     let mut schedule_blocks_cache: HashMap<(i32, NaiveDate), Vec<ScheduleBlock>> = HashMap::new();
+    // Map: (ScheduleID, Date) -> DJ of the schedule airing in its place (a replacement's)
+    let mut schedule_dj_cache: HashMap<(i32, NaiveDate), i32> = HashMap::new();
 
     for item in &effective_schedules {
+        let published = version_service::published_blocks(conn, &item.schedule)?;
+        let published = DatedBlocks::new(&item.schedule.schedule_type, published);
         for d in valid_dates {
-            let blocks = get_blocks_for_date(conn, &item.schedule, d)?;
+            let blocks = blocks_with_exceptions(conn, &item.schedule, &published, d)?;
             schedule_blocks_cache.insert((item.schedule.id.unwrap(), d), blocks);
+            if let Some(did) = effective_schedule(conn, &item.schedule, d)?.and_then(|s| s.dj_id) {
+                schedule_dj_cache.insert((item.schedule.id.unwrap(), d), did);
+            }
         }
     }
 
@@ -74,6 +89,7 @@ pub fn calculate_collapsed_schedule(
             }
         }
     }
+    all_dj_ids.extend(schedule_dj_cache.values().copied());
     all_dj_ids.sort();
     all_dj_ids.dedup();
 
@@ -90,60 +106,91 @@ pub fn calculate_collapsed_schedule(
         }
     }
 
-    // 4. Create a 1440-minute timeline (24 hours * 60 minutes) representing LOCAL DAY
-    let mut timeline: Vec<Option<TimelineSlot>> = vec![None; 1440];
-
-    // 5. Fill Timeline
-    //    Iterate 0..1440 (LOCAL minutes).
-    //    Find matching block in Highest Priority Schedule.
-    for local_minute in 0..1440 {
-        // Find highest priority schedule that has a block at this local time
-        for item in &effective_schedules {
-            let schedule_id = item.schedule.id.unwrap();
-
-            // We use the requested 'date' as the LOCAL date.
-            if let Some(blocks) = schedule_blocks_cache.get(&(schedule_id, date)) {
-                // Check if any block covers this local_time
-                let mut match_found = false;
-                for block in blocks {
-                    let start = block.start_time;
-                    let end_secs = start.hour() * 3600
-                        + start.minute() * 60
-                        + start.second()
-                        + (block.duration_minutes as u32 * 60);
-                    // let end = chrono::NaiveTime::from_num_seconds_from_midnight_opt(end_secs, 0);
-
-                    let local_secs = local_minute * 60;
-                    let start_secs_val = start.hour() * 3600 + start.minute() * 60 + start.second();
-
-                    if local_secs >= start_secs_val && local_secs < end_secs {
-                        // Found a match!
-                        let d_name = block
-                            .dj_id
-                            .and_then(|did| dj_names_cache.get(&did).cloned());
-                        timeline[local_minute as usize] = Some(TimelineSlot {
-                            content_id: block.content_id,
-                            script_id: block.script_id,
-                            priority: item.effective_priority,
-                            schedule_name: item.schedule.name.clone(),
-                            schedule_id: schedule_id,
-                            block_id: block.id.expect("Block ID missing"),
-                            dj_id: block.dj_id,
-                            dj_name: d_name,
-                        });
-                        match_found = true;
-                        break;
-                    }
-                }
-                if match_found {
-                    break; // Stop checking lower priority schedules for this minute
-                }
+    // 4. The LOCAL DAY in real seconds. Around DST changes it is 23 or 25 hours long.
+    let (day_start, day_end) = local_day_bounds(&tz, date);
+    let day_secs = (day_end - day_start).num_seconds().max(0);
+
+    // 5. Place every block on the real clock as a layer, highest priority schedule first.
+    //    Blocks from the previous day that run past midnight spill into the start of this
+    //    day; within a schedule, same-day blocks come first.
+    let prev_date = date.pred_opt().unwrap();
+    let mut layers: Vec<Interval> = Vec::new();
+    for item in &effective_schedules {
+        let schedule_id = item.schedule.id.unwrap();
+        for d in [date, prev_date] {
+            let Some(blocks) = schedule_blocks_cache.get(&(schedule_id, d)) else {
+                continue;
+            };
+            // Blocks without a DJ of their own are presented by the schedule's
+            let schedule_dj = schedule_dj_cache.get(&(schedule_id, d)).copied();
+            for block in blocks {
+                let (start, end) = block_interval(&tz, d, block);
+                let dj_id = block.dj_id.or(schedule_dj);
+                let break_policy = block.break_policy.as_deref().and_then(|json| {
+                    BreakPolicy::parse(json)
+                        .map_err(|e| tracing::warn!("Block {:?}: {}", block.id, e))
+                        .ok()
+                });
+                let transition = block.transition.as_deref().and_then(|json| {
+                    Transition::parse(json)
+                        .map_err(|e| tracing::warn!("Block {:?}: {}", block.id, e))
+                        .ok()
+                });
+                layers.push(Interval {
+                    start: (start - day_start).num_seconds(),
+                    end: (end - day_start).num_seconds(),
+                    slot: TimelineSlot {
+                        content_id: block.content_id,
+                        script_id: block.script_id,
+                        priority: item.priority,
+                        schedule_name: item.schedule.name.clone(),
+                        schedule_id,
+                        block_id: block.id.expect("Block ID missing"),
+                        start: (start - day_start).num_seconds(),
+                        dj_id,
+                        dj_name: dj_id.and_then(|did| dj_names_cache.get(&did).cloned()),
+                        fill: block.fill_rule.as_ref().map(|rule| FillOccurrence {
+                            block: block.clone(),
+                            rule: rule.clone(),
+                            date: d,
+                            start: (start - day_start).num_seconds(),
+                        }),
+                        break_policy,
+                        transition,
+                    },
+                });
             }
         }
     }
 
-    // Collapse adjacent identical blocks
-    let collapsed = collapse_timeline(timeline);
+    // 6. Resolve layering and emit one collapsed block per resolved interval.
+    //    Fill blocks carry the part of their run-down that survived the layering.
+    let mut collapsed = Vec::new();
+    for interval in resolve_intervals(day_secs, layers) {
+        let mut block = create_collapsed_block(day_start, &interval);
+        if let Some(fill) = &interval.slot.fill {
+            let rundown = if store_rundowns {
+                rundown_service::rundown_for_occurrence(conn, &fill.block, &fill.rule, fill.date)?
+            } else {
+                rundown_service::preview_rundown(conn, &fill.block, &fill.rule, fill.date)?
+            };
+            block.items = rundown
+                .iter()
+                .filter_map(|item| {
+                    let item_start = fill.start + item.offset_secs;
+                    let start = item_start.max(interval.start);
+                    let end = (item_start + item.duration_secs).min(interval.end);
+                    (start < end).then(|| CollapsedItem {
+                        content_id: item.content_id,
+                        start_at: (day_start + Duration::seconds(start)).with_timezone(&Utc),
+                        duration_secs: (end - start) as i32,
+                        offset_secs: (start - item_start) as i32,
+                    })
+                })
+                .collect();
+        }
+        collapsed.push(block);
+    }
 
     Ok(collapsed)
 }
@@ -156,8 +203,22 @@ struct TimelineSlot {
     schedule_name: String,
     schedule_id: i32,
     block_id: i32,
+    /// Start of the whole block occurrence, in seconds from the local day start
+    start: i64,
     dj_id: Option<i32>,
     dj_name: Option<String>,
+    fill: Option<FillOccurrence>,
+    break_policy: Option<BreakPolicy>,
+    transition: Option<Transition>,
+}
+
+/// The airing of a fill block a slot came from; `start` is in seconds from the local day start.
+#[derive(Clone, Debug)]
+struct FillOccurrence {
+    block: ScheduleBlock,
+    rule: String,
+    date: NaiveDate,
+    start: i64,
 }
 
 impl PartialEq for TimelineSlot {
@@ -165,110 +226,425 @@ impl PartialEq for TimelineSlot {
         self.content_id == other.content_id
             && self.script_id == other.script_id
             && self.block_id == other.block_id
+            && self.start == other.start
     }
 }
 
-fn get_blocks_for_date(
+/// Blocks a schedule airs on `date`, taken from its published version, with its
+/// exceptions applied.
+pub fn get_blocks_for_date(
+    conn: &mut DbConnection,
+    schedule: &Schedule,
+    date: NaiveDate,
+) -> Result<Vec<ScheduleBlock>> {
+    let blocks = version_service::published_blocks(conn, schedule)?;
+    let blocks = DatedBlocks::new(&schedule.schedule_type, blocks);
+    blocks_with_exceptions(conn, schedule, &blocks, date)
+}
+
+/// The blocks from `blocks` that `schedule` airs on `date` once its exceptions are
+/// applied: none on a skipped date, the replacement's published blocks on a replaced one.
+pub fn blocks_with_exceptions(
     conn: &mut DbConnection,
     schedule: &Schedule,
+    blocks: &DatedBlocks,
     date: NaiveDate,
 ) -> Result<Vec<ScheduleBlock>> {
-    use crate::schema::schedule_blocks::dsl;
-
-    let blocks = match schedule.schedule_type.as_str() {
-        "weekly" => {
-            // Monday = 0, Tuesday = 1, etc. (To match Frontend array indices)
-            let day_of_week = date.weekday().num_days_from_monday() as i32;
-
-            dsl::schedule_blocks
-                .filter(dsl::schedule_id.eq(schedule.id.expect("Schedule ID missing")))
-                .filter(dsl::day_of_week.eq(day_of_week))
-                .select(ScheduleBlock::as_select())
-                .load(conn)?
+    match effective_schedule(conn, schedule, date)? {
+        None => Ok(Vec::new()),
+        Some(airing) if airing.id == schedule.id => Ok(blocks.on_date(date)),
+        Some(replacement) => {
+            let blocks = version_service::published_blocks(conn, &replacement)?;
+            Ok(DatedBlocks::new(&replacement.schedule_type, blocks).on_date(date))
         }
-        "one_off" => dsl::schedule_blocks
-            .filter(dsl::schedule_id.eq(schedule.id.expect("Schedule ID missing")))
-            .filter(dsl::specific_date.eq(date))
-            .select(ScheduleBlock::as_select())
-            .load(conn)?,
-        _ => vec![],
+    }
+}
+
+/// The exception covering `date` for a schedule, if any. The newest wins where they overlap.
+pub fn exception_on(
+    conn: &mut DbConnection,
+    sched_id: i32,
+    date: NaiveDate,
+) -> Result<Option<ScheduleException>> {
+    use crate::schema::schedule_exceptions::dsl::*;
+
+    Ok(schedule_exceptions
+        .filter(schedule_id.eq(sched_id))
+        .filter(start_date.le(date))
+        .filter(end_date.ge(date))
+        .order(id.desc())
+        .select(ScheduleException::as_select())
+        .first(conn)
+        .optional()?)
+}
+
+/// The schedule that airs in `schedule`'s place on `date`: itself, its replacement, or
+/// nothing when the date is skipped. Replacements air as published, without applying
+/// their own exceptions.
+pub fn effective_schedule(
+    conn: &mut DbConnection,
+    schedule: &Schedule,
+    date: NaiveDate,
+) -> Result<Option<Schedule>> {
+    let Some(sched_id) = schedule.id else {
+        return Ok(Some(schedule.clone()));
+    };
+    let Some(exception) = exception_on(conn, sched_id, date)? else {
+        return Ok(Some(schedule.clone()));
+    };
+    let Some(replacement_id) = exception.replacement_schedule_id else {
+        return Ok(None);
     };
 
-    Ok(blocks)
+    use crate::schema::schedules::dsl::*;
+    let replacement = schedules
+        .filter(id.eq(replacement_id))
+        .select(Schedule::as_select())
+        .first(conn)
+        .optional()?;
+    if replacement.is_none() {
+        tracing::warn!(
+            "Replacement schedule {} for schedule {} on {} no longer exists",
+            replacement_id,
+            sched_id,
+            date
+        );
+    }
+    Ok(replacement)
 }
 
-fn collapse_timeline(timeline: Vec<Option<TimelineSlot>>) -> Vec<CollapsedBlock> {
-    let mut collapsed = Vec::new();
-    let mut current_block: Option<(usize, TimelineSlot)> = None;
-
-    for (minute, slot_opt) in timeline.iter().enumerate() {
-        match (slot_opt, &current_block) {
-            (Some(slot), Some((start_min, current_slot))) => {
-                // Check if this continues the current block
-                if slot == current_slot {
-                    // Continue the current block
-                    continue;
-                } else {
-                    // Different block, save the current one
-                    let duration = minute - *start_min;
-                    collapsed.push(create_collapsed_block(
-                        *start_min,
-                        duration as i32,
-                        current_slot,
-                    ));
-
-                    // Start a new block
-                    current_block = Some((minute, slot.clone()));
+/// The blocks of a schedule with their RRULEs parsed once, so resolving many dates
+/// doesn't re-parse them for every date.
+pub struct DatedBlocks {
+    schedule_type: String,
+    blocks: Vec<(ScheduleBlock, Option<RecurrenceRule>)>,
+}
+
+impl DatedBlocks {
+    /// Blocks of a schedule of `schedule_type`. Recurring blocks with an invalid RRULE
+    /// never air.
+    pub fn new(schedule_type: &str, blocks: Vec<ScheduleBlock>) -> Self {
+        let blocks = blocks
+            .into_iter()
+            .filter_map(|b| match b.rrule.as_deref() {
+                Some(rule_str) if schedule_type == "recurring" => {
+                    match RecurrenceRule::parse(rule_str) {
+                        Ok(rule) => Some((b, Some(rule))),
+                        Err(e) => {
+                            tracing::warn!(
+                                "Ignoring block with invalid RRULE '{}': {}",
+                                rule_str,
+                                e
+                            );
+                            None
+                        }
+                    }
                 }
+                _ => Some((b, None)),
+            })
+            .collect();
+        DatedBlocks {
+            schedule_type: schedule_type.to_string(),
+            blocks,
+        }
+    }
+
+    /// The blocks that air on `date`.
+    pub fn on_date(&self, date: NaiveDate) -> Vec<ScheduleBlock> {
+        // Monday = 0, Tuesday = 1, etc. (To match Frontend array indices)
+        let day_of_week = date.weekday().num_days_from_monday() as i32;
+
+        self.blocks
+            .iter()
+            .filter(|(b, rule)| match self.schedule_type.as_str() {
+                "weekly" => b.day_of_week == Some(day_of_week),
+                "one_off" => b.specific_date == Some(date),
+                "recurring" => match rule {
+                    Some(rule) => b.specific_date.is_some_and(|dt| rule.occurs_on(dt, date)),
+                    None => block_occurs_on(b.day_of_week, b.specific_date, None, date),
+                },
+                _ => false,
+            })
+            .map(|(b, _)| b.clone())
+            .collect()
+    }
+}
+
+/// Whether a block with the given recurrence fields airs on `date`.
+///
+/// Blocks with an RRULE expand from `specific_date` (DTSTART); otherwise
+/// `specific_date` is a single airing and `day_of_week` a weekly one.
+pub fn block_occurs_on(
+    day_of_week: Option<i32>,
+    specific_date: Option<NaiveDate>,
+    rrule: Option<&str>,
+    date: NaiveDate,
+) -> bool {
+    if let Some(rule_str) = rrule {
+        let Some(dtstart) = specific_date else {
+            return false;
+        };
+        return match RecurrenceRule::parse(rule_str) {
+            Ok(rule) => rule.occurs_on(dtstart, date),
+            Err(e) => {
+                tracing::warn!("Ignoring block with invalid RRULE '{}': {}", rule_str, e);
+                false
+            }
+        };
+    }
+
+    if let Some(d) = specific_date {
+        return d == date;
+    }
+
+    day_of_week == Some(date.weekday().num_days_from_monday() as i32)
+}
+
+/// Dates within `[from, to]` a block with the given recurrence fields airs on. Like
+/// [`block_occurs_on`] for every date in the range, but an RRULE is parsed and expanded once.
+pub fn block_airings_between(
+    day_of_week: Option<i32>,
+    specific_date: Option<NaiveDate>,
+    rrule: Option<&str>,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> HashSet<NaiveDate> {
+    if let Some(rule_str) = rrule {
+        let Some(dtstart) = specific_date else {
+            return HashSet::new();
+        };
+        return match RecurrenceRule::parse(rule_str) {
+            Ok(rule) => rule
+                .occurrences_between(dtstart, from, to)
+                .into_iter()
+                .collect(),
+            Err(e) => {
+                tracing::warn!("Ignoring block with invalid RRULE '{}': {}", rule_str, e);
+                HashSet::new()
             }
-            (Some(slot), None) => {
-                // Start a new block
-                current_block = Some((minute, slot.clone()));
+        };
+    }
+
+    from.iter_days()
+        .take_while(|d| *d <= to)
+        .filter(|d| block_occurs_on(day_of_week, specific_date, None, *d))
+        .collect()
+}
+
+/// Timezone a node's schedule is resolved in: its own `timezone`, else its channel's,
+/// else the global `timezone` setting, else UTC.
+pub fn node_timezone(conn: &mut DbConnection, node_id: i32) -> Result<Tz> {
+    use crate::schema::nodes::dsl::{channel_id, id, nodes, timezone};
+
+    let (node_tz, node_channel): (Option<String>, Option<i32>) = nodes
+        .filter(id.eq(node_id))
+        .select((timezone, channel_id))
+        .first(conn)
+        .optional()?
+        .unwrap_or_default();
+
+    match node_tz.and_then(|s| s.parse().ok()) {
+        Some(tz) => Ok(tz),
+        None => match node_channel {
+            Some(channel) => channel_timezone(conn, channel),
+            None => global_timezone(conn),
+        },
+    }
+}
+
+/// Timezone a channel's schedule is resolved in: its own `timezone`, else the global
+/// `timezone` setting, else UTC.
+pub fn channel_timezone(conn: &mut DbConnection, channel: i32) -> Result<Tz> {
+    use crate::schema::channels::dsl::{channels, id, timezone};
+
+    let channel_tz: Option<Tz> = channels
+        .filter(id.eq(channel))
+        .select(timezone)
+        .first::<Option<String>>(conn)
+        .optional()?
+        .flatten()
+        .and_then(|s| s.parse().ok());
+
+    match channel_tz {
+        Some(tz) => Ok(tz),
+        None => global_timezone(conn),
+    }
+}
+
+pub fn target_timezone(conn: &mut DbConnection, target: Target) -> Result<Tz> {
+    match target {
+        Target::Node(node_id) => node_timezone(conn, node_id),
+        Target::Channel(channel) => channel_timezone(conn, channel),
+    }
+}
+
+/// Timezone a schedule's blocks air in: that of the first node airing it, else of the
+/// first channel it's assigned to, else the global `timezone` setting.
+pub fn schedule_timezone(conn: &mut DbConnection, sched_id: i32) -> Result<Tz> {
+    if let Some(node_id) = channel_service::schedule_nodes(conn, sched_id)?.first() {
+        return node_timezone(conn, *node_id);
+    }
+
+    use crate::schema::channel_schedules::dsl::{channel_id, channel_schedules, schedule_id};
+    let channel: Option<i32> = channel_schedules
+        .filter(schedule_id.eq(sched_id))
+        .order(channel_id)
+        .select(channel_id)
+        .first(conn)
+        .optional()?;
+    match channel {
+        Some(channel) => channel_timezone(conn, channel),
+        None => global_timezone(conn),
+    }
+}
+
+/// The global `timezone` setting, else UTC.
+pub fn global_timezone(conn: &mut DbConnection) -> Result<Tz> {
+    use crate::schema::global_settings::dsl::{global_settings, key, value};
+
+    let tz_name: Option<String> = global_settings
+        .filter(key.eq("timezone"))
+        .select(value)
+        .first(conn)
+        .optional()?;
+
+    Ok(tz_name
+        .as_deref()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(chrono_tz::UTC))
+}
+
+/// Resolve a local wall-clock time in `tz` to an instant, handling DST explicitly.
+///
+/// In a fall-back overlap the earlier of the two instants is used. Times inside a
+/// spring-forward gap don't exist and resolve to the end of the gap, so a block at
+/// 02:30 on spring-forward day starts when clocks jump to 03:00 and keeps its
+/// wall-clock end; a block lying entirely inside the gap doesn't air.
+pub fn resolve_local(tz: &Tz, local: NaiveDateTime) -> DateTime<Tz> {
+    if let Some(dt) = tz.from_local_datetime(&local).earliest() {
+        return dt;
+    }
+
+    // Walk forward to the first wall-clock minute that exists again
+    let mut probe = local - Duration::seconds(local.and_utc().timestamp().rem_euclid(60));
+    for _ in 0..(24 * 60) {
+        probe += Duration::minutes(1);
+        if let Some(dt) = tz.from_local_datetime(&probe).earliest() {
+            return dt;
+        }
+    }
+
+    tz.from_utc_datetime(&local)
+}
+
+/// Real instants bounding the local day `date` in `tz` (23h/24h/25h long).
+pub fn local_day_bounds(tz: &Tz, date: NaiveDate) -> (DateTime<Tz>, DateTime<Tz>) {
+    let next = date.succ_opt().unwrap_or(date);
+    (
+        resolve_local(tz, date.and_time(chrono::NaiveTime::MIN)),
+        resolve_local(tz, next.and_time(chrono::NaiveTime::MIN)),
+    )
+}
+
+/// Real interval a block airs in when it occurs on local date `block_date`.
+/// Both ends are wall-clock times, so a block spanning a DST change runs shorter or longer.
+pub fn block_interval(
+    tz: &Tz,
+    block_date: NaiveDate,
+    block: &ScheduleBlock,
+) -> (DateTime<Tz>, DateTime<Tz>) {
+    let local_start = block_date.and_time(block.start_time);
+    let local_end = local_start + Duration::seconds(block.total_duration_secs());
+    (resolve_local(tz, local_start), resolve_local(tz, local_end))
+}
+
+/// A timeline slot placed at `[start, end)`, in seconds from the start of the local day.
+#[derive(Clone, Debug)]
+struct Interval {
+    start: i64,
+    end: i64,
+    slot: TimelineSlot,
+}
+
+/// Resolve priority layering at second precision.
+///
+/// `layers` must be ordered highest priority first. Each layer only claims the parts
+/// of `[0, day_secs)` that no earlier layer covers, so a lower-priority block is split
+/// around higher-priority ones. Touching pieces of the same slot are merged.
+fn resolve_intervals(day_secs: i64, layers: Vec<Interval>) -> Vec<Interval> {
+    let mut free: Vec<(i64, i64)> = vec![(0, day_secs)];
+    let mut resolved: Vec<Interval> = Vec::new();
+
+    for layer in layers {
+        let start = layer.start.max(0);
+        let end = layer.end.min(day_secs);
+        if start >= end {
+            continue;
+        }
+
+        let mut still_free = Vec::with_capacity(free.len() + 1);
+        for (free_start, free_end) in free {
+            if free_end <= start || free_start >= end {
+                still_free.push((free_start, free_end));
+                continue;
             }
-            (None, Some((start_min, current_slot))) => {
-                // End of current block
-                let duration = minute - *start_min;
-                collapsed.push(create_collapsed_block(
-                    *start_min,
-                    duration as i32,
-                    current_slot,
-                ));
-                current_block = None;
+
+            let cut_start = free_start.max(start);
+            let cut_end = free_end.min(end);
+            if free_start < cut_start {
+                still_free.push((free_start, cut_start));
             }
-            (None, None) => {
-                // No block continues
+            resolved.push(Interval {
+                start: cut_start,
+                end: cut_end,
+                slot: layer.slot.clone(),
+            });
+            if cut_end < free_end {
+                still_free.push((cut_end, free_end));
             }
         }
+        free = still_free;
     }
 
-    // Handle any remaining block at end of day
-    if let Some((start_min, current_slot)) = current_block {
-        let duration = 1440 - start_min;
-        collapsed.push(create_collapsed_block(
-            start_min,
-            duration as i32,
-            &current_slot,
-        ));
+    resolved.sort_by_key(|i| i.start);
+
+    let mut merged: Vec<Interval> = Vec::with_capacity(resolved.len());
+    for interval in resolved {
+        if let Some(last) = merged.last_mut() {
+            if last.end == interval.start && last.slot == interval.slot {
+                last.end = interval.end;
+                continue;
+            }
+        }
+        merged.push(interval);
     }
 
-    collapsed
+    merged
 }
 
-fn create_collapsed_block(start_min: usize, duration: i32, slot: &TimelineSlot) -> CollapsedBlock {
-    let hours = start_min / 60;
-    let minutes = start_min % 60;
-    let start_time = format!("{:02}:{:02}:00", hours, minutes);
+fn create_collapsed_block(day_start: DateTime<Tz>, interval: &Interval) -> CollapsedBlock {
+    // Offsets are real elapsed seconds, so the wall-clock label comes from the instant
+    let start_dt = day_start + Duration::seconds(interval.start);
+    let duration_secs = interval.end - interval.start;
+    let slot = &interval.slot;
 
     CollapsedBlock {
-        start_time,
-        duration_minutes: duration,
+        start_time: start_dt.format("%H:%M:%S").to_string(),
+        start_at: start_dt.with_timezone(&Utc),
+        duration_minutes: (duration_secs / 60) as i32,
+        duration_secs: duration_secs as i32,
         content_id: slot.content_id,
         script_id: slot.script_id,
         priority: slot.priority,
         schedule_name: slot.schedule_name.clone(),
         schedule_id: slot.schedule_id,
+        block_id: slot.block_id,
         dj_id: slot.dj_id,
         dj_name: slot.dj_name.clone(),
+        offset_secs: (interval.start - slot.start) as i32,
+        items: Vec::new(),
+        break_policy: slot.break_policy.clone(),
+        transition: slot.transition,
     }
 }
 
@@ -276,84 +652,65 @@ fn create_collapsed_block(start_min: usize, duration: i32, slot: &TimelineSlot)
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_overlap_clipping_logic() {
-        // Simulate the painting logic
-        let mut timeline: Vec<Option<TimelineSlot>> = vec![None; 1440];
-
-        // 1. High Priority (Priority 10): 1:30 - 2:00 (90m - 120m)
-        let hp_start = 90;
-        let hp_end = 120;
-        let hp_priority = 10;
-
-        for minute in hp_start..hp_end {
-            timeline[minute] = Some(TimelineSlot {
-                content_id: Some(2),
-                script_id: None,
-                priority: hp_priority,
-                schedule_name: "High Pri".to_string(),
-                schedule_id: 2,
-                block_id: 200,
-                dj_id: None,
-                dj_name: None,
-            });
+    fn slot(block_id: i32, priority: i32, schedule_name: &str) -> TimelineSlot {
+        TimelineSlot {
+            content_id: Some(block_id),
+            script_id: None,
+            priority,
+            schedule_name: schedule_name.to_string(),
+            schedule_id: priority,
+            block_id,
+            start: 0,
+            dj_id: None,
+            dj_name: None,
+            fill: None,
+            break_policy: None,
+            transition: None,
         }
+    }
 
-        // 2. Low Priority (Priority 1): 1:00 - 3:00 (60m - 180m)
-        let lp_start = 60;
-        let lp_end = 180;
-        let lp_priority = 1;
-
-        // Apply Low Priority (Gap Filling)
-        for minute in lp_start..lp_end {
-            // Logic: Fill if empty or existing is lower priority (which shouldn't happen if sorted High->Low)
-            // But here we simulate the High->Low iteration order.
-            // Since HP is already there with prio 10, and we have prio 1, we only fill if None.
-            if timeline[minute].is_none() {
-                timeline[minute] = Some(TimelineSlot {
-                    content_id: Some(1),
-                    script_id: None,
-                    priority: lp_priority,
-                    schedule_name: "Low Pri".to_string(),
-                    schedule_id: 1,
-                    block_id: 100,
-                    dj_id: None,
-                    dj_name: None,
-                });
-            }
-        }
+    fn utc_day_start() -> DateTime<Tz> {
+        resolve_local(
+            &chrono_tz::UTC,
+            NaiveDate::from_ymd_opt(2026, 1, 1)
+                .unwrap()
+                .and_time(chrono::NaiveTime::MIN),
+        )
+    }
 
-        // 3. Verify Timeline
-        // 60-90: Should be Low Pri
-        for i in 60..90 {
-            assert_eq!(
-                timeline[i].as_ref().unwrap().priority,
-                1,
-                "Minute {} should be Low Pri",
-                i
-            );
-        }
-        // 90-120: Should be High Pri
-        for i in 90..120 {
-            assert_eq!(
-                timeline[i].as_ref().unwrap().priority,
-                10,
-                "Minute {} should be High Pri",
-                i
-            );
-        }
-        // 120-180: Should be Low Pri
-        for i in 120..180 {
-            assert_eq!(
-                timeline[i].as_ref().unwrap().priority,
-                1,
-                "Minute {} should be Low Pri",
-                i
-            );
-        }
+    #[test]
+    fn test_overlap_clipping_logic() {
+        // Layers are resolved High->Low, like schedules sorted by priority
+        let layers = vec![
+            // 1. High Priority (Priority 10): 1:30 - 2:00 (90m - 120m)
+            Interval {
+                start: 90 * 60,
+                end: 120 * 60,
+                slot: slot(200, 10, "High Pri"),
+            },
+            // 2. Low Priority (Priority 1): 1:00 - 3:00 (60m - 180m), only fills gaps
+            Interval {
+                start: 60 * 60,
+                end: 180 * 60,
+                slot: slot(100, 1, "Low Pri"),
+            },
+        ];
+
+        let resolved = resolve_intervals(86400, layers);
+
+        // 3. Verify Timeline: Low 60-90, High 90-120, Low 120-180
+        let spans: Vec<(i64, i64, i32)> = resolved
+            .iter()
+            .map(|i| (i.start / 60, i.end / 60, i.slot.priority))
+            .collect();
+        assert_eq!(spans, vec![(60, 90, 1), (90, 120, 10), (120, 180, 1)]);
 
         // 4. Verify Collapse
-        let collapsed = collapse_timeline(timeline);
+        let day_start = utc_day_start();
+        let collapsed: Vec<CollapsedBlock> = resolved
+            .iter()
+            .map(|i| create_collapsed_block(day_start, i))
+            .collect();
 
         // Expecting 3 blocks: Low (30m), High (30m), Low (60m)
         assert_eq!(collapsed.len(), 3);
@@ -367,4 +724,292 @@ mod tests {
         assert_eq!(collapsed[2].schedule_name, "Low Pri");
         assert_eq!(collapsed[2].duration_minutes, 60);
     }
+
+    #[test]
+    fn test_second_precision_interstitial() {
+        // A 45s interstitial at 12:00:30 splits an hour-long show
+        let layers = vec![
+            Interval {
+                start: 12 * 3600 + 30,
+                end: 12 * 3600 + 75,
+                slot: TimelineSlot {
+                    start: 12 * 3600 + 30,
+                    ..slot(2, 10, "Interstitials")
+                },
+            },
+            Interval {
+                start: 12 * 3600,
+                end: 13 * 3600,
+                slot: TimelineSlot {
+                    start: 12 * 3600,
+                    ..slot(1, 1, "Shows")
+                },
+            },
+        ];
+
+        let collapsed: Vec<CollapsedBlock> = resolve_intervals(86400, layers)
+            .iter()
+            .map(|i| create_collapsed_block(utc_day_start(), i))
+            .collect();
+
+        let summary: Vec<(&str, i32)> = collapsed
+            .iter()
+            .map(|b| (b.start_time.as_str(), b.duration_secs))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![("12:00:00", 30), ("12:00:30", 45), ("12:01:15", 3525)]
+        );
+
+        // The show picks up where the interstitial cut in
+        let offsets: Vec<i32> = collapsed.iter().map(|b| b.offset_secs).collect();
+        assert_eq!(offsets, vec![0, 0, 75]);
+    }
+
+    /// The former 1440-minute grid: paint minute by minute, first covering layer wins,
+    /// then collapse runs of the same slot into (start_min, duration_min, block_id).
+    fn reference_grid(layers: &[Interval]) -> Vec<(i64, i64, i32)> {
+        let mut timeline: Vec<Option<&TimelineSlot>> = vec![None; 1440];
+        for (minute, cell) in timeline.iter_mut().enumerate() {
+            let secs = minute as i64 * 60;
+            *cell = layers
+                .iter()
+                .find(|l| secs >= l.start && secs < l.end)
+                .map(|l| &l.slot);
+        }
+
+        let mut collapsed: Vec<(i64, i64, i32)> = Vec::new();
+        let mut current: Option<(usize, &TimelineSlot)> = None;
+        for (minute, cell) in timeline.iter().chain(std::iter::once(&None)).enumerate() {
+            match (cell, current) {
+                (Some(s), Some((_, c))) if *s == c => {}
+                _ => {
+                    if let Some((start, c)) = current {
+                        collapsed.push((start as i64, (minute - start) as i64, c.block_id));
+                    }
+                    current = cell.map(|s| (minute, s));
+                }
+            }
+        }
+        collapsed
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn prop_matches_minute_grid_for_minute_aligned_input(
+            blocks in proptest::collection::vec((0i64..1440, 1i64..600, 0usize..4), 0..12)
+        ) {
+            // Each tuple is (start minute, duration minutes, schedule rank); lower rank
+            // means higher priority, and layers are ordered like the real resolver's.
+            let mut ranked: Vec<(usize, Interval)> = blocks
+                .iter()
+                .enumerate()
+                .map(|(idx, (start, duration, rank))| {
+                    (
+                        *rank,
+                        Interval {
+                            start: start * 60,
+                            end: (start + duration) * 60,
+                            slot: slot(idx as i32 + 1, 10 - *rank as i32, "Generated"),
+                        },
+                    )
+                })
+                .collect();
+            ranked.sort_by_key(|(rank, _)| *rank);
+            let layers: Vec<Interval> = ranked.into_iter().map(|(_, l)| l).collect();
+
+            let expected = reference_grid(&layers);
+            let actual: Vec<(i64, i64, i32)> = resolve_intervals(86400, layers)
+                .iter()
+                .map(|i| (i.start / 60, (i.end - i.start) / 60, i.slot.block_id))
+                .collect();
+
+            proptest::prop_assert_eq!(actual, expected);
+        }
+    }
+
+    #[test]
+    fn test_dst_resolution() {
+        let tz: Tz = "America/New_York".parse().unwrap();
+        let local = |y, m, d, h, min| {
+            NaiveDate::from_ymd_opt(y, m, d)
+                .unwrap()
+                .and_hms_opt(h, min, 0)
+                .unwrap()
+        };
+
+        // Spring forward (2026-03-08): 02:30 doesn't exist and starts at 03:00 EDT
+        let gap = resolve_local(&tz, local(2026, 3, 8, 2, 30));
+        assert_eq!(
+            gap.with_timezone(&Utc),
+            Utc.with_ymd_and_hms(2026, 3, 8, 7, 0, 0).unwrap()
+        );
+
+        // Fall back (2026-11-01): 01:30 happens twice, the first (EDT) one wins
+        let overlap = resolve_local(&tz, local(2026, 11, 1, 1, 30));
+        assert_eq!(
+            overlap.with_timezone(&Utc),
+            Utc.with_ymd_and_hms(2026, 11, 1, 5, 30, 0).unwrap()
+        );
+
+        let day_len = |d| {
+            let (start, end) = local_day_bounds(&tz, d);
+            (end - start).num_minutes()
+        };
+        assert_eq!(
+            day_len(NaiveDate::from_ymd_opt(2026, 3, 8).unwrap()),
+            23 * 60
+        );
+        assert_eq!(
+            day_len(NaiveDate::from_ymd_opt(2026, 11, 1).unwrap()),
+            25 * 60
+        );
+        assert_eq!(
+            day_len(NaiveDate::from_ymd_opt(2026, 6, 1).unwrap()),
+            24 * 60
+        );
+    }
+
+    #[test]
+    fn test_dated_blocks_parse_rrule_once() {
+        let ts = NaiveDate::from_ymd_opt(2026, 1, 1)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+        let block = |id: i32, rrule: &str| ScheduleBlock {
+            id: Some(id),
+            schedule_id: 1,
+            content_id: Some(id),
+            day_of_week: None,
+            specific_date: NaiveDate::from_ymd_opt(2026, 1, 1),
+            start_time: chrono::NaiveTime::from_hms_opt(18, 0, 0).unwrap(),
+            duration_minutes: 30,
+            script_id: None,
+            created_at: ts,
+            updated_at: ts,
+            dj_id: None,
+            rrule: Some(rrule.to_string()),
+            duration_secs: None,
+            fill_rule: None,
+            break_policy: None,
+            transition: None,
+        };
+        let blocks = DatedBlocks::new(
+            "recurring",
+            vec![block(1, "FREQ=MONTHLY;BYDAY=2TU"), block(2, "FREQ=HOURLY")],
+        );
+        let ids = |d| {
+            blocks
+                .on_date(d)
+                .iter()
+                .map(|b| b.id.unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        // The invalid rule never airs; the valid one airs on the second Tuesday only
+        assert_eq!(ids(NaiveDate::from_ymd_opt(2026, 2, 10).unwrap()), vec![1]);
+        assert!(ids(NaiveDate::from_ymd_opt(2026, 2, 3).unwrap()).is_empty());
+
+        // Weekly schedules ignore the RRULE field altogether
+        let weekly = DatedBlocks::new("weekly", vec![block(3, "FREQ=HOURLY")]);
+        assert!(weekly
+            .on_date(NaiveDate::from_ymd_opt(2026, 1, 1).unwrap())
+            .is_empty());
+    }
+
+    #[test]
+    fn test_block_airings_between() {
+        let d = |m, day| NaiveDate::from_ymd_opt(2026, m, day).unwrap();
+
+        // A daily series from long ago is expanded once over the window only
+        let daily = block_airings_between(
+            None,
+            Some(NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()),
+            Some("FREQ=DAILY;INTERVAL=2"),
+            d(3, 1),
+            d(3, 6),
+        );
+        assert_eq!(daily, HashSet::from([d(3, 1), d(3, 3), d(3, 5)]));
+
+        // Weekly and one-off blocks match like block_occurs_on
+        let mondays = block_airings_between(Some(0), None, None, d(3, 1), d(3, 16));
+        assert_eq!(mondays, HashSet::from([d(3, 2), d(3, 9), d(3, 16)]));
+        let once = block_airings_between(None, Some(d(3, 4)), None, d(3, 1), d(3, 6));
+        assert_eq!(once, HashSet::from([d(3, 4)]));
+
+        assert!(
+            block_airings_between(None, Some(d(3, 1)), Some("FREQ=HOURLY"), d(3, 1), d(3, 6))
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_skip_and_replace_exceptions() {
+        use diesel::connection::SimpleConnection;
+        let mut conn = crate::db::test_connection();
+
+        // Daily noon hours: the main schedule (id 1) over a two-hour background one (id 3);
+        // schedule 2 is only ever a replacement
+        conn.batch_execute(
+            "INSERT INTO content_items (id, title, content_type, content_path) VALUES
+                (100, 'Main', 'local_file', '/media/main.mp4'),
+                (200, 'Holiday', 'local_file', '/media/holiday.mp4'),
+                (300, 'Background', 'local_file', '/media/background.mp4');
+            INSERT INTO schedules (id, name, schedule_type, priority, is_active) VALUES
+                (1, 'Main', 'recurring', 10, 1),
+                (2, 'Holiday', 'recurring', 0, 1),
+                (3, 'Background', 'recurring', 5, 1);
+            INSERT INTO schedule_blocks
+                (schedule_id, content_id, specific_date, start_time, duration_minutes, rrule)
+            VALUES
+                (1, 100, '2026-01-01', '12:00:00', 60, 'FREQ=DAILY'),
+                (2, 200, '2026-01-01', '12:00:00', 60, 'FREQ=DAILY'),
+                (3, 300, '2026-01-01', '12:00:00', 120, 'FREQ=DAILY');
+            INSERT INTO nodes (id, name, secret_key, status) VALUES (1, 'Node', 'secret', 'online');
+            INSERT INTO node_schedules (node_id, schedule_id) VALUES (1, 1), (1, 3);
+            INSERT INTO schedule_exceptions
+                (schedule_id, start_date, end_date, action, replacement_schedule_id)
+            VALUES
+                (1, '2026-03-02', '2026-03-03', 'skip', NULL),
+                (1, '2026-03-05', '2026-03-05', 'replace', 2);",
+        )
+        .unwrap();
+        for id in 1..=3 {
+            version_service::publish(&mut conn, id, None, None).unwrap();
+        }
+
+        let aired = |conn: &mut DbConnection, day| {
+            let date = NaiveDate::from_ymd_opt(2026, 3, day).unwrap();
+            preview_collapsed_schedule(conn, Target::Node(1), date, None)
+                .unwrap()
+                .iter()
+                .map(|b| (b.start_time.clone(), b.content_id.unwrap(), b.priority))
+                .collect::<Vec<_>>()
+        };
+        let regular = vec![
+            ("12:00:00".to_string(), 100, 10),
+            ("13:00:00".to_string(), 300, 5),
+        ];
+
+        // The day before the range and the day after it air as usual
+        assert_eq!(aired(&mut conn, 1), regular);
+        assert_eq!(aired(&mut conn, 4), regular);
+
+        // Skipped on every day of the inclusive range, so the background shows through
+        for day in [2, 3] {
+            assert_eq!(
+                aired(&mut conn, day),
+                vec![("12:00:00".to_string(), 300, 5)]
+            );
+        }
+
+        // The replacement's published blocks air at the main schedule's priority
+        assert_eq!(
+            aired(&mut conn, 5),
+            vec![
+                ("12:00:00".to_string(), 200, 10),
+                ("13:00:00".to_string(), 300, 5),
+            ]
+        );
+    }
 }
@@ -661,6 +661,16 @@ impl ScriptService {
             transformer_scripts: None,
             is_dj_accessible: true,
             spot_reel_id: None,
+            last_played_at: None,
+            sha256: None,
+            cue_points: None,
+            loudness_lufs: None,
+            true_peak_dbtp: None,
+            loudness_analyzed_at: None,
+            cue_out_secs: None,
+            file_size_bytes: None,
+            file_modified_at: None,
+            missing_since: None,
         };
         let item_dynamic: Dynamic = rhai::serde::to_dynamic(mock_item)?;
         scope.push("content_item", item_dynamic);
@@ -0,0 +1,921 @@
+//! What a node will air over a span of time, worked out without touching live state.
+//!
+//! The node's collapsed schedule (priorities, schedule overrides, exceptions and fill
+//! run-downs) is laid on the clock and gaps are filled from its fallback policy. At each
+//! content change the scripts the node would run are executed in a sandbox, which yields
+//! the transform settings and any injected bumpers. The heartbeat monitor asks the same
+//! engine what is on air, so DJ decisions match the preview.
+
+use crate::api::schedules_api::CollapsedBlock;
+use crate::db::DbConnection;
+use crate::models::{Bumper, ContentItem, Script};
+use crate::services::break_service::{self, BreakPolicy};
+use crate::services::channel_service::Target;
+use crate::services::fallback_service::{self, ResolvedFallback};
+use crate::services::schedule_service;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, Utc};
+use chrono_tz::Tz;
+use diesel::prelude::*;
+use serde::Serialize;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+/// Longest span a single simulation covers
+pub const MAX_SIMULATION_DAYS: i64 = 7;
+
+/// Transform settings that change how the node plays content
+const PLAYBACK_SETTINGS: [&str; 4] = ["loop", "volume", "start_time", "end_time"];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Segment {
+    pub start_at: DateTime<Utc>,
+    pub end_at: DateTime<Utc>,
+    /// `block`, `fallback`, `bumper`, `break` or `silence`
+    pub source: String,
+    pub content_id: Option<i32>,
+    /// Content title, bumper name, spot reel title or slate path
+    pub title: Option<String>,
+    /// Seconds into the content at `start_at`
+    pub offset_secs: i64,
+    pub schedule_id: Option<i32>,
+    pub schedule_name: Option<String>,
+    pub block_id: Option<i32>,
+    pub dj_id: Option<i32>,
+    pub dj_name: Option<String>,
+    /// Block script the DJ is briefed with
+    pub block_script: Option<String>,
+    /// Global and transformer scripts the node runs for this content
+    pub scripts: Vec<String>,
+    /// Playback settings the transformer scripts set
+    pub settings: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Simulation {
+    pub node_id: i32,
+    /// IANA timezone the schedule was resolved in
+    pub timezone: String,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    /// Back to back, covering `from` to `to`
+    pub segments: Vec<Segment>,
+    /// Scripts that failed and bumpers that couldn't be found while simulating
+    pub warnings: Vec<String>,
+}
+
+/// The block airing on a node at some instant.
+pub struct OnAir {
+    pub block: CollapsedBlock,
+    /// Later blocks of the same local day
+    pub upcoming: Vec<CollapsedBlock>,
+}
+
+/// The collapsed block airing on `node_id` at `at`, if any. Fill run-downs are stored
+/// like when the node fetches its schedule.
+pub fn on_air(conn: &mut DbConnection, node_id: i32, at: DateTime<Utc>) -> Result<Option<OnAir>> {
+    let tz = schedule_service::node_timezone(conn, node_id)?;
+    let date = at.with_timezone(&tz).date_naive();
+    let mut blocks = schedule_service::calculate_collapsed_schedule(
+        conn,
+        Target::Node(node_id),
+        date,
+        Some(tz.name().to_string()),
+    )?;
+
+    let Some(idx) = blocks
+        .iter()
+        .position(|b| b.start_at <= at && at < block_end(b))
+    else {
+        return Ok(None);
+    };
+    let upcoming = blocks.split_off(idx + 1);
+    Ok(blocks.pop().map(|block| OnAir { block, upcoming }))
+}
+
+fn block_end(block: &CollapsedBlock) -> DateTime<Utc> {
+    block.start_at + Duration::seconds(block.duration_secs as i64)
+}
+
+/// The block a planned span belongs to.
+#[derive(Debug, Clone, PartialEq)]
+struct BlockRef {
+    schedule_id: i32,
+    schedule_name: String,
+    block_id: i32,
+    dj_id: Option<i32>,
+    dj_name: Option<String>,
+    script_id: Option<i32>,
+    break_policy: Option<BreakPolicy>,
+}
+
+/// Something planned to air, before scripts and bumpers are applied.
+#[derive(Debug, Clone)]
+struct Planned {
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    source: &'static str,
+    content_id: Option<i32>,
+    /// When the content started from the top; the node only restarts playback when the
+    /// content or this changes
+    content_start: DateTime<Utc>,
+    offset_secs: i64,
+    title: Option<String>,
+    block: Option<BlockRef>,
+}
+
+impl Planned {
+    fn playback_key(&self) -> Option<(i32, DateTime<Utc>)> {
+        self.content_id.map(|cid| (cid, self.content_start))
+    }
+}
+
+/// Simulate what `node_id` airs from `from` to `to`. Nothing is written: run-downs that
+/// haven't been generated yet are previewed, and scripts run sandboxed.
+///
+/// The node is assumed to join at `from` with an empty bumper queue and its fallback
+/// rotation at the first item.
+pub fn simulate(
+    conn: &mut DbConnection,
+    node_id: i32,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Simulation> {
+    if from >= to {
+        return Err(anyhow!("Range ends before it starts"));
+    }
+    if to - from > Duration::days(MAX_SIMULATION_DAYS) {
+        return Err(anyhow!("Range is longer than {} days", MAX_SIMULATION_DAYS));
+    }
+
+    let tz = schedule_service::node_timezone(conn, node_id)?;
+
+    // 1. The collapsed schedule of every local day the range touches
+    let mut planned = Vec::new();
+    let first_day = from.with_timezone(&tz).date_naive();
+    let last_day = to.with_timezone(&tz).date_naive();
+    for date in first_day.iter_days().take_while(|d| *d <= last_day) {
+        let blocks = schedule_service::preview_collapsed_schedule(
+            conn,
+            Target::Node(node_id),
+            date,
+            Some(tz.name().to_string()),
+        )?;
+        planned.extend(blocks.iter().flat_map(plan_block));
+    }
+    let planned = clip(planned, from, to);
+
+    // 2. Fill the gaps from the fallback policy
+    let policy = fallback_service::node_policy(conn, node_id)?;
+    let mut fallbacks: HashMap<chrono::NaiveDate, ResolvedFallback> = HashMap::new();
+    let mut rotation = 0;
+    let mut durations = ContentDurations::default();
+    let mut filled = Vec::new();
+    let mut cursor = from;
+    for span in planned.into_iter().map(Some).chain(std::iter::once(None)) {
+        let gap_end = span.as_ref().map_or(to, |s| s.start);
+        if cursor < gap_end {
+            let date = cursor.with_timezone(&tz).date_naive();
+            let fallback = match fallbacks.entry(date) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => {
+                    entry.insert(fallback_service::resolve(conn, &policy, date)?)
+                }
+            };
+            durations.load(conn, &fallback.content_ids)?;
+            filled.extend(plan_fallback(
+                fallback,
+                cursor,
+                gap_end,
+                &mut rotation,
+                &durations.0,
+            ));
+        }
+        if let Some(span) = span {
+            cursor = span.end;
+            filled.push(span);
+        }
+    }
+
+    // 3. Play it through the node's script hooks
+    let mut player = Player::new(conn, tz)?;
+    for span in &filled {
+        player.air(conn, span)?;
+    }
+
+    Ok(Simulation {
+        node_id,
+        timezone: tz.name().to_string(),
+        from,
+        to,
+        segments: merge_segments(player.segments),
+        warnings: player.warnings,
+    })
+}
+
+/// The spans a collapsed block airs as: its content, or its run-down items with
+/// whatever the run-down doesn't cover left to the block's own content.
+fn plan_block(block: &CollapsedBlock) -> Vec<Planned> {
+    let block_ref = BlockRef {
+        schedule_id: block.schedule_id,
+        schedule_name: block.schedule_name.clone(),
+        block_id: block.block_id,
+        dj_id: block.dj_id,
+        dj_name: block.dj_name.clone(),
+        script_id: block.script_id,
+        break_policy: block.break_policy.clone(),
+    };
+    let span = |start: DateTime<Utc>, end: DateTime<Utc>, content_id, offset_secs: i64| Planned {
+        start,
+        end,
+        source: "block",
+        content_id,
+        content_start: start - Duration::seconds(offset_secs),
+        offset_secs,
+        title: None,
+        block: Some(block_ref.clone()),
+    };
+
+    let start = block.start_at;
+    let end = block_end(block);
+    if block.items.is_empty() {
+        return vec![span(start, end, block.content_id, block.offset_secs as i64)];
+    }
+
+    let mut spans = Vec::new();
+    let mut cursor = start;
+    for item in &block.items {
+        let item_end = item.start_at + Duration::seconds(item.duration_secs as i64);
+        if cursor < item.start_at {
+            let offset = (cursor - start).num_seconds() + block.offset_secs as i64;
+            spans.push(span(cursor, item.start_at, block.content_id, offset));
+        }
+        spans.push(span(
+            item.start_at,
+            item_end,
+            Some(item.content_id),
+            item.offset_secs as i64,
+        ));
+        cursor = cursor.max(item_end);
+    }
+    if cursor < end {
+        let offset = (cursor - start).num_seconds() + block.offset_secs as i64;
+        spans.push(span(cursor, end, block.content_id, offset));
+    }
+    spans
+}
+
+/// Spans overlapping `[from, to)`, cut to it.
+fn clip(spans: Vec<Planned>, from: DateTime<Utc>, to: DateTime<Utc>) -> Vec<Planned> {
+    spans
+        .into_iter()
+        .filter(|s| s.start < to && s.end > from)
+        .map(|mut s| {
+            if s.start < from {
+                s.offset_secs += (from - s.start).num_seconds();
+                s.start = from;
+            }
+            s.end = s.end.min(to);
+            s
+        })
+        .collect()
+}
+
+/// Fallback playback for the gap `[start, end)`. Playlists continue from `rotation`
+/// across gaps; items of unknown length are assumed to last the rest of the gap.
+fn plan_fallback(
+    fallback: &ResolvedFallback,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    rotation: &mut usize,
+    durations: &HashMap<i32, i64>,
+) -> Vec<Planned> {
+    let span = |start, end, source, content_id, title| Planned {
+        start,
+        end,
+        source,
+        content_id,
+        content_start: start,
+        offset_secs: 0,
+        title,
+        block: None,
+    };
+
+    match fallback.mode.as_str() {
+        "playlist" if !fallback.content_ids.is_empty() => {
+            let ids = &fallback.content_ids;
+            let mut spans = Vec::new();
+            let mut cursor = start;
+            while cursor < end {
+                let content_id = ids[*rotation % ids.len()];
+                *rotation = (*rotation + 1) % ids.len();
+                let item_end = match durations.get(&content_id) {
+                    Some(secs) if *secs > 0 => (cursor + Duration::seconds(*secs)).min(end),
+                    _ => end,
+                };
+                spans.push(span(cursor, item_end, "fallback", Some(content_id), None));
+                cursor = item_end;
+            }
+            spans
+        }
+        "spot_reel" if !fallback.content_ids.is_empty() => vec![span(
+            start,
+            end,
+            "fallback",
+            Some(fallback.content_ids[0]),
+            None,
+        )],
+        "slate" => vec![span(
+            start,
+            end,
+            "fallback",
+            None,
+            fallback.slate_path.clone(),
+        )],
+        _ => vec![span(start, end, "silence", None, None)],
+    }
+}
+
+/// Content lengths in seconds, loaded as needed.
+#[derive(Default)]
+struct ContentDurations(HashMap<i32, i64>);
+
+impl ContentDurations {
+    fn load(&mut self, conn: &mut DbConnection, ids: &[i32]) -> Result<()> {
+        use crate::schema::content_items::dsl::{content_items, duration_minutes, id};
+
+        let missing: Vec<i32> = ids
+            .iter()
+            .copied()
+            .filter(|cid| !self.0.contains_key(cid))
+            .collect();
+        if missing.is_empty() {
+            return Ok(());
+        }
+        let rows: Vec<(Option<i32>, Option<i32>)> = content_items
+            .filter(id.eq_any(&missing))
+            .select((id, duration_minutes))
+            .load(conn)?;
+        for (cid, minutes) in rows {
+            if let Some(cid) = cid {
+                self.0.insert(cid, minutes.unwrap_or(0) as i64 * 60);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A script the node runs for the content it plays.
+#[derive(Clone)]
+struct ScriptRun {
+    name: String,
+    content: String,
+    args: rhai::Map,
+}
+
+/// Replays planned spans the way the node's playback loop handles them.
+struct Player {
+    tz: Tz,
+    scripts: HashMap<i32, Script>,
+    global_scripts: Vec<ScriptRun>,
+    global_settings: rhai::Map,
+    content: HashMap<i32, ContentItem>,
+    bumpers: Option<Vec<Bumper>>,
+    /// Spot reel id -> (title, length in seconds)
+    reels: HashMap<i32, (String, i64)>,
+    /// What the node is playing, as (content, when it started from the top)
+    playing: Option<(i32, DateTime<Utc>)>,
+    active_scripts: Vec<ScriptRun>,
+    active_settings: rhai::Map,
+    /// Added to the offset of the playing content by its `start_time` setting
+    start_time_secs: i64,
+    /// Break policy of the block the playing content started in
+    break_policy: Option<BreakPolicy>,
+    /// Cue points of the playing content still to break at, in seconds of the content
+    pending_cues: Vec<f64>,
+    segments: Vec<Segment>,
+    warnings: Vec<String>,
+}
+
+impl Player {
+    fn new(conn: &mut DbConnection, tz: Tz) -> Result<Self> {
+        let scripts: HashMap<i32, Script> = {
+            use crate::schema::scripts::dsl::scripts;
+            scripts
+                .select(Script::as_select())
+                .load(conn)?
+                .into_iter()
+                .filter_map(|s| s.id.map(|sid| (sid, s)))
+                .collect()
+        };
+
+        let settings: Vec<(String, String)> = {
+            use crate::schema::global_settings::dsl::{global_settings, key, value};
+            global_settings.select((key, value)).load(conn)?
+        };
+        let mut global_settings = rhai::Map::new();
+        let mut global_script_ids = Vec::new();
+        for (k, v) in settings {
+            if k == "global_active_scripts" {
+                global_script_ids = parse_global_scripts(&v, &scripts);
+            }
+            global_settings.insert(k.into(), v.into());
+        }
+
+        let global_scripts = global_script_ids
+            .into_iter()
+            .filter_map(|sid| scripts.get(&sid))
+            .map(|s| ScriptRun {
+                name: s.name.clone(),
+                content: s.script_content.clone(),
+                args: rhai::Map::new(),
+            })
+            .collect();
+
+        Ok(Self {
+            tz,
+            scripts,
+            global_scripts,
+            global_settings,
+            content: HashMap::new(),
+            bumpers: None,
+            reels: HashMap::new(),
+            playing: None,
+            active_scripts: Vec::new(),
+            active_settings: rhai::Map::new(),
+            start_time_secs: 0,
+            break_policy: None,
+            pending_cues: Vec::new(),
+            segments: Vec::new(),
+            warnings: Vec::new(),
+        })
+    }
+
+    fn air(&mut self, conn: &mut DbConnection, span: &Planned) -> Result<()> {
+        let key = span.playback_key();
+        if key.is_some() && key == self.playing {
+            return self.play_content(conn, span, span.start);
+        }
+
+        let item = match span.content_id {
+            Some(cid) => self.content_item(conn, cid)?,
+            None => None,
+        };
+        let at = span.start.with_timezone(&self.tz);
+        let mut injected = Vec::new();
+
+        // Spot reels start their own player and leave the scripts loaded
+        let is_spot_reel = item
+            .as_ref()
+            .is_some_and(|i| i.content_type == "spot_reel" && i.spot_reel_id.is_some());
+        if !is_spot_reel {
+            let previous = std::mem::take(&mut self.active_scripts);
+            let mut settings = std::mem::take(&mut self.active_settings);
+            for script in &previous {
+                injected.extend(self.run(script, "on_unload", &mut settings, at));
+            }
+            self.start_time_secs = 0;
+
+            if let Some(item) = &item {
+                let scripts: Vec<ScriptRun> = self
+                    .global_scripts
+                    .iter()
+                    .cloned()
+                    .chain(transformer_scripts(item, &self.scripts))
+                    .collect();
+
+                let mut settings = self.global_settings.clone();
+                for script in &scripts {
+                    self.run(script, "transform", &mut settings, at);
+                }
+                for script in &scripts {
+                    let mut settings_for_load = settings.clone();
+                    injected.extend(self.run(script, "on_load", &mut settings_for_load, at));
+                }
+
+                let looped = settings
+                    .get("loop")
+                    .and_then(|v| v.as_bool().ok())
+                    .unwrap_or(false);
+                if !looped {
+                    self.start_time_secs = settings
+                        .get("start_time")
+                        .and_then(|v| v.as_float().ok().or(v.as_int().ok().map(|i| i as f64)))
+                        .map_or(0, |secs| secs as i64);
+                }
+                self.active_scripts = scripts;
+                self.active_settings = settings;
+            }
+        }
+        self.playing = key;
+
+        // Breaks are planned when the content starts, from the block it started in
+        self.break_policy = span
+            .block
+            .as_ref()
+            .and_then(|b| b.break_policy.clone())
+            .filter(|_| !is_spot_reel);
+        self.pending_cues = match (&self.break_policy, &item) {
+            (Some(policy), Some(item)) => {
+                let cues = item
+                    .cue_points
+                    .as_deref()
+                    .and_then(|json| break_service::parse_cue_points(json).ok())
+                    .unwrap_or_default();
+                policy.breaks_from(&cues, (span.offset_secs + self.start_time_secs) as f64)
+            }
+            _ => Vec::new(),
+        };
+
+        // Queued bumpers cut in right after the change; the content resumes afterwards
+        let mut cursor = span.start;
+        for name in injected {
+            if cursor >= span.end {
+                break;
+            }
+            let Some(bumper) = self.bumper(conn, &name)? else {
+                self.warnings
+                    .push(format!("Bumper '{}' injected at {} not found", name, at));
+                continue;
+            };
+            let end = (cursor + Duration::milliseconds(bumper.duration_ms.unwrap_or(0) as i64))
+                .min(span.end);
+            self.segments
+                .push(interstitial(cursor, end, "bumper", bumper.name));
+            cursor = end;
+        }
+
+        self.play_content(conn, span, cursor)
+    }
+
+    /// Air `span` from `start` on, taking any breaks due in it. After a bumper or break
+    /// the content resumes where it was cut off.
+    fn play_content(
+        &mut self,
+        conn: &mut DbConnection,
+        span: &Planned,
+        start: DateTime<Utc>,
+    ) -> Result<()> {
+        let mut cursor = start;
+        let mut position = span.offset_secs as f64;
+        while cursor < span.end {
+            let next_break = self
+                .break_policy
+                .clone()
+                .zip(self.pending_cues.first().copied());
+            let Some((policy, cue)) = next_break else {
+                break;
+            };
+            let cue_at = cursor
+                + Duration::milliseconds(
+                    ((cue - self.start_time_secs as f64 - position).max(0.0) * 1000.0) as i64,
+                );
+            if cue_at >= span.end {
+                break;
+            }
+            self.pending_cues.remove(0);
+
+            if cursor < cue_at {
+                let segment = self.segment(span, cursor, cue_at, position as i64);
+                self.segments.push(segment);
+            }
+            let (title, reel_secs) = self.reel(conn, policy.spot_reel_id)?;
+            let break_end =
+                (cue_at + Duration::seconds(reel_secs.min(policy.max_secs as i64))).min(span.end);
+            self.segments
+                .push(interstitial(cue_at, break_end, "break", title));
+            cursor = break_end;
+            position = cue - self.start_time_secs as f64;
+        }
+
+        if cursor < span.end {
+            let segment = self.segment(span, cursor, span.end, position as i64);
+            self.segments.push(segment);
+        }
+        Ok(())
+    }
+
+    fn segment(
+        &self,
+        span: &Planned,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        offset_secs: i64,
+    ) -> Segment {
+        let block = span.block.as_ref();
+        let title = span.title.clone().or_else(|| {
+            span.content_id
+                .and_then(|cid| self.content.get(&cid))
+                .map(|i| i.title.clone())
+        });
+        let settings = PLAYBACK_SETTINGS
+            .iter()
+            .filter_map(|k| {
+                let value = self.active_settings.get(*k)?;
+                Some((k.to_string(), serde_json::to_value(value).ok()?))
+            })
+            .collect();
+
+        Segment {
+            start_at: start,
+            end_at: end,
+            source: span.source.to_string(),
+            content_id: span.content_id,
+            title,
+            offset_secs: offset_secs + self.start_time_secs,
+            schedule_id: block.map(|b| b.schedule_id),
+            schedule_name: block.map(|b| b.schedule_name.clone()),
+            block_id: block.map(|b| b.block_id),
+            dj_id: block.and_then(|b| b.dj_id),
+            dj_name: block.and_then(|b| b.dj_name.clone()),
+            block_script: block
+                .and_then(|b| b.script_id)
+                .and_then(|sid| self.scripts.get(&sid))
+                .map(|s| s.name.clone()),
+            scripts: self.active_scripts.iter().map(|s| s.name.clone()).collect(),
+            settings,
+        }
+    }
+
+    /// Run one script hook, returning the bumpers it injected. Failures become warnings.
+    fn run(
+        &mut self,
+        script: &ScriptRun,
+        fn_name: &str,
+        settings: &mut rhai::Map,
+        at: DateTime<Tz>,
+    ) -> Vec<String> {
+        match crate::rhai_engine::simulate_script_function(
+            &script.content,
+            fn_name,
+            settings,
+            script.args.clone(),
+            at,
+        ) {
+            Ok(bumpers) => bumpers,
+            Err(e) => {
+                self.warnings.push(format!(
+                    "Script '{}' {} at {}: {}",
+                    script.name, fn_name, at, e
+                ));
+                Vec::new()
+            }
+        }
+    }
+
+    fn content_item(&mut self, conn: &mut DbConnection, cid: i32) -> Result<Option<ContentItem>> {
+        if let Some(item) = self.content.get(&cid) {
+            return Ok(Some(item.clone()));
+        }
+
+        use crate::schema::content_items::dsl::{content_items, id};
+        let item: Option<ContentItem> = content_items
+            .filter(id.eq(cid))
+            .select(ContentItem::as_select())
+            .first(conn)
+            .optional()?;
+        if let Some(item) = &item {
+            self.content.insert(cid, item.clone());
+        }
+        Ok(item)
+    }
+
+    /// Title and length of a spot reel: the sum of its items' display durations.
+    fn reel(&mut self, conn: &mut DbConnection, reel_id: i32) -> Result<(String, i64)> {
+        if let Some(reel) = self.reels.get(&reel_id) {
+            return Ok(reel.clone());
+        }
+
+        use crate::schema::{spot_reel_items, spot_reels};
+        let title: String = spot_reels::table
+            .filter(spot_reels::id.eq(reel_id))
+            .select(spot_reels::title)
+            .first::<String>(conn)
+            .optional()?
+            .unwrap_or_else(|| format!("Spot reel {}", reel_id));
+        let secs: Vec<i32> = spot_reel_items::table
+            .filter(spot_reel_items::spot_reel_id.eq(reel_id))
+            .select(spot_reel_items::display_duration_secs)
+            .load(conn)?;
+
+        let reel = (title, secs.iter().map(|s| *s as i64).sum());
+        self.reels.insert(reel_id, reel.clone());
+        Ok(reel)
+    }
+
+    /// A bumper by id or name, like the node looks it up. Only rendered bumpers play.
+    fn bumper(&mut self, conn: &mut DbConnection, name_or_id: &str) -> Result<Option<Bumper>> {
+        if self.bumpers.is_none() {
+            use crate::schema::bumpers::dsl::bumpers;
+            self.bumpers = Some(bumpers.select(Bumper::as_select()).load(conn)?);
+        }
+        let by_id = name_or_id.parse::<i32>().ok();
+        Ok(self.bumpers.iter().flatten().find_map(|b| {
+            let matches = match by_id {
+                Some(bid) => b.id == Some(bid),
+                None => b.name == name_or_id,
+            };
+            (matches && b.rendered_path.is_some()).then(|| b.clone())
+        }))
+    }
+}
+
+/// A bumper or break cutting into the schedule.
+fn interstitial(start: DateTime<Utc>, end: DateTime<Utc>, source: &str, title: String) -> Segment {
+    Segment {
+        start_at: start,
+        end_at: end,
+        source: source.to_string(),
+        content_id: None,
+        title: Some(title),
+        offset_secs: 0,
+        schedule_id: None,
+        schedule_name: None,
+        block_id: None,
+        dj_id: None,
+        dj_name: None,
+        block_script: None,
+        scripts: Vec::new(),
+        settings: serde_json::Map::new(),
+    }
+}
+
+/// `global_active_scripts` holds script names, ids, or a single id.
+fn parse_global_scripts(json: &str, scripts: &HashMap<i32, Script>) -> Vec<i32> {
+    if let Ok(names) = serde_json::from_str::<Vec<String>>(json) {
+        names
+            .iter()
+            .filter_map(|name| {
+                scripts
+                    .iter()
+                    .find(|(_, s)| &s.name == name)
+                    .map(|(sid, _)| *sid)
+            })
+            .collect()
+    } else if let Ok(ids) = serde_json::from_str::<Vec<i32>>(json) {
+        ids
+    } else {
+        json.parse::<i32>().ok().into_iter().collect()
+    }
+}
+
+/// Transformer scripts of a content item: script ids, or objects with an `id` (or
+/// `script_id`) and `args`.
+fn transformer_scripts(item: &ContentItem, scripts: &HashMap<i32, Script>) -> Vec<ScriptRun> {
+    let Some(entries) = item
+        .transformer_scripts
+        .as_deref()
+        .and_then(|json| serde_json::from_str::<Vec<serde_json::Value>>(json).ok())
+    else {
+        return Vec::new();
+    };
+
+    entries
+        .iter()
+        .filter_map(|entry| {
+            let (sid, args) = match entry {
+                serde_json::Value::Object(obj) => {
+                    let sid = obj.get("id").or(obj.get("script_id"))?.as_i64()?;
+                    let mut args = rhai::Map::new();
+                    if let Some(serde_json::Value::Object(arg_obj)) = obj.get("args") {
+                        for (k, v) in arg_obj {
+                            let value: rhai::Dynamic = match v {
+                                serde_json::Value::String(s) => s.clone().into(),
+                                serde_json::Value::Bool(b) => (*b).into(),
+                                serde_json::Value::Number(n) => match n.as_i64() {
+                                    Some(i) => i.into(),
+                                    None => n.as_f64().unwrap_or_default().into(),
+                                },
+                                _ => continue,
+                            };
+                            args.insert(k.as_str().into(), value);
+                        }
+                    }
+                    (sid, args)
+                }
+                other => (other.as_i64()?, rhai::Map::new()),
+            };
+            let script = scripts.get(&(sid as i32))?;
+            Some(ScriptRun {
+                name: script.name.clone(),
+                content: script.script_content.clone(),
+                args,
+            })
+        })
+        .collect()
+}
+
+/// Join consecutive segments that are one continuous airing, e.g. a block split at
+/// local midnight.
+fn merge_segments(segments: Vec<Segment>) -> Vec<Segment> {
+    let mut merged: Vec<Segment> = Vec::with_capacity(segments.len());
+    for segment in segments {
+        if let Some(last) = merged.last_mut() {
+            let continues = last.end_at == segment.start_at
+                && last.source == segment.source
+                && last.content_id == segment.content_id
+                && last.block_id == segment.block_id
+                && last.title == segment.title
+                && last.offset_secs + (last.end_at - last.start_at).num_seconds()
+                    == segment.offset_secs;
+            if continues && !matches!(segment.source.as_str(), "bumper" | "break") {
+                last.end_at = segment.end_at;
+                continue;
+            }
+        }
+        merged.push(segment);
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::schedules_api::CollapsedItem;
+    use chrono::TimeZone;
+
+    fn at(h: u32, m: u32, s: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 1, 1, h, m, s).unwrap()
+    }
+
+    #[test]
+    fn test_fill_block_and_fallback_spans() {
+        // A fill block whose run-down leaves its last ten minutes uncovered
+        let block = CollapsedBlock {
+            start_time: "10:00:00".to_string(),
+            start_at: at(10, 0, 0),
+            duration_minutes: 60,
+            duration_secs: 3600,
+            content_id: None,
+            script_id: None,
+            priority: 1,
+            schedule_name: "Music".to_string(),
+            schedule_id: 1,
+            block_id: 7,
+            dj_id: None,
+            dj_name: None,
+            offset_secs: 0,
+            break_policy: None,
+            transition: None,
+            items: vec![
+                CollapsedItem {
+                    content_id: 1,
+                    start_at: at(10, 0, 0),
+                    duration_secs: 1800,
+                    offset_secs: 0,
+                },
+                CollapsedItem {
+                    content_id: 2,
+                    start_at: at(10, 30, 0),
+                    duration_secs: 1200,
+                    offset_secs: 0,
+                },
+            ],
+        };
+        let spans = clip(plan_block(&block), at(10, 15, 0), at(12, 0, 0));
+        let summary: Vec<(DateTime<Utc>, Option<i32>, i64)> = spans
+            .iter()
+            .map(|s| (s.start, s.content_id, s.offset_secs))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (at(10, 15, 0), Some(1), 900),
+                (at(10, 30, 0), Some(2), 0),
+                (at(10, 50, 0), None, 3000),
+            ]
+        );
+        // Joining mid-item doesn't make it a new playback
+        assert_eq!(spans[0].content_start, at(10, 0, 0));
+
+        // A 25 minute gap filled from a playlist of 10 and 20 minute items
+        let fallback = ResolvedFallback {
+            mode: "playlist".to_string(),
+            content_ids: vec![3, 4],
+            slate_path: None,
+            slate_gain_db: None,
+        };
+        let durations = HashMap::from([(3, 600), (4, 1200)]);
+        let mut rotation = 0;
+        let filled = plan_fallback(
+            &fallback,
+            at(11, 0, 0),
+            at(11, 25, 0),
+            &mut rotation,
+            &durations,
+        );
+        let summary: Vec<(DateTime<Utc>, DateTime<Utc>, Option<i32>)> = filled
+            .iter()
+            .map(|s| (s.start, s.end, s.content_id))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (at(11, 0, 0), at(11, 10, 0), Some(3)),
+                (at(11, 10, 0), at(11, 25, 0), Some(4)),
+            ]
+        );
+        // The next gap carries on with the rotation
+        assert_eq!(rotation, 0);
+    }
+}
@@ -0,0 +1,56 @@
+//! Segues between consecutive items on nodes.
+//!
+//! The global `transition` setting (overridden per block) sets how nodes switch items:
+//! the next item starts `audio_crossfade_secs` early while the outgoing audio fades out
+//! underneath it, and the picture fades through black over `video_fade_secs`. Both zero
+//! is a hard cut. Fill block run-downs also start the next item at an item's
+//! `cue_out_secs`, so outros overlap what follows.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+/// Longest crossfade or fade accepted
+pub const MAX_TRANSITION_SECS: f64 = 10.0;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Transition {
+    #[serde(default)]
+    pub audio_crossfade_secs: f64,
+    #[serde(default)]
+    pub video_fade_secs: f64,
+}
+
+impl Transition {
+    pub fn parse(json: &str) -> Result<Self> {
+        let transition: Self =
+            serde_json::from_str(json).map_err(|e| anyhow!("Invalid transition: {}", e))?;
+        for secs in [transition.audio_crossfade_secs, transition.video_fade_secs] {
+            if !(0.0..=MAX_TRANSITION_SECS).contains(&secs) {
+                return Err(anyhow!(
+                    "Transition lengths must be between 0 and {} seconds",
+                    MAX_TRANSITION_SECS
+                ));
+            }
+        }
+        Ok(transition)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_transition() {
+        assert_eq!(
+            Transition::parse(r#"{"audio_crossfade_secs": 2.5}"#).unwrap(),
+            Transition {
+                audio_crossfade_secs: 2.5,
+                video_fade_secs: 0.0
+            }
+        );
+        assert_eq!(Transition::parse("{}").unwrap(), Transition::default());
+        assert!(Transition::parse(r#"{"video_fade_secs": -1}"#).is_err());
+        assert!(Transition::parse(r#"{"audio_crossfade_secs": 30}"#).is_err());
+    }
+}
@@ -0,0 +1,661 @@
+//! Static checks over schedules, so editors catch problems before a node goes dark.
+
+use crate::db::DbConnection;
+use crate::models::{ContentItem, Node, Schedule, ScheduleBlock};
+use crate::services::channel_service::{self, Target};
+use crate::services::fallback_service;
+use crate::services::rundown_service::FillRule;
+use crate::services::schedule_service;
+use crate::services::version_service;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, NaiveDate, Timelike, Utc};
+use chrono_tz::Tz;
+use diesel::prelude::*;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// Longest date range a single report may cover
+pub const MAX_RANGE_DAYS: i64 = 31;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Error,
+    Warning,
+    /// Worth knowing, but nothing goes wrong on air
+    Info,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueKind {
+    Overlap,
+    ContentTooLong,
+    MissingContent,
+    ContentUnavailable,
+    MissingScript,
+    MissingDj,
+    DeadAir,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ValidationIssue {
+    pub kind: IssueKind,
+    pub severity: Severity,
+    pub message: String,
+    /// First date in the range the problem shows up on
+    pub date: NaiveDate,
+    pub schedule_id: Option<i32>,
+    pub block_id: Option<i32>,
+    /// The other block of an overlap
+    pub other_block_id: Option<i32>,
+    pub content_id: Option<i32>,
+    /// Exact span for dead air
+    pub start_at: Option<DateTime<Utc>>,
+    pub duration_secs: Option<i64>,
+}
+
+impl ValidationIssue {
+    fn new(kind: IssueKind, severity: Severity, date: NaiveDate, message: String) -> Self {
+        Self {
+            kind,
+            severity,
+            message,
+            date,
+            schedule_id: None,
+            block_id: None,
+            other_block_id: None,
+            content_id: None,
+            start_at: None,
+            duration_secs: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ValidationReport {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub node_id: Option<i32>,
+    pub errors: usize,
+    pub warnings: usize,
+    pub issues: Vec<ValidationIssue>,
+}
+
+/// Library and reference data shared by all checks in a report
+struct Lookups {
+    content: HashMap<i32, ContentItem>,
+    script_ids: HashSet<i32>,
+    dj_ids: HashSet<i32>,
+}
+
+impl Lookups {
+    fn load(conn: &mut DbConnection) -> Result<Self> {
+        use crate::schema::{content_items, dj_profiles, scripts};
+
+        let content = content_items::table
+            .select(ContentItem::as_select())
+            .load(conn)?
+            .into_iter()
+            .filter_map(|c| c.id.map(|cid| (cid, c)))
+            .collect();
+        let script_ids = scripts::table
+            .select(scripts::id)
+            .load::<Option<i32>>(conn)?
+            .into_iter()
+            .flatten()
+            .collect();
+        let dj_ids = dj_profiles::table
+            .select(dj_profiles::id)
+            .load::<Option<i32>>(conn)?
+            .into_iter()
+            .flatten()
+            .collect();
+
+        Ok(Self {
+            content,
+            script_ids,
+            dj_ids,
+        })
+    }
+}
+
+/// Kind, schedule, block, other block and content an issue is about
+type IssueKey = (
+    IssueKind,
+    Option<i32>,
+    Option<i32>,
+    Option<i32>,
+    Option<i32>,
+);
+
+/// Collects issues, reporting each problem once even if it recurs on many dates
+#[derive(Default)]
+struct Collector {
+    issues: Vec<ValidationIssue>,
+    seen: HashSet<IssueKey>,
+}
+
+impl Collector {
+    fn push(&mut self, issue: ValidationIssue) {
+        let key = (
+            issue.kind,
+            issue.schedule_id,
+            issue.block_id,
+            issue.other_block_id,
+            issue.content_id,
+        );
+        if self.seen.insert(key) {
+            self.issues.push(issue);
+        }
+    }
+
+    fn into_report(self, from: NaiveDate, to: NaiveDate, node_id: Option<i32>) -> ValidationReport {
+        let mut issues = self.issues;
+        issues.sort_by_key(|i| (i.date, i.start_at, i.schedule_id, i.block_id));
+        let count = |severity| issues.iter().filter(|i| i.severity == severity).count();
+        let (errors, warnings) = (count(Severity::Error), count(Severity::Warning));
+
+        ValidationReport {
+            from,
+            to,
+            node_id,
+            errors,
+            warnings,
+            issues,
+        }
+    }
+}
+
+/// Check a schedule's draft over `[from, to]`, optionally against the node it will air on,
+/// so problems surface before it is published.
+pub fn validate_schedule(
+    conn: &mut DbConnection,
+    schedule_id: i32,
+    from: NaiveDate,
+    to: NaiveDate,
+    node_id: Option<i32>,
+) -> Result<ValidationReport> {
+    let schedule: Schedule = {
+        use crate::schema::schedules::dsl::*;
+        schedules
+            .filter(id.eq(schedule_id))
+            .select(Schedule::as_select())
+            .first(conn)?
+    };
+    let node = node_id.map(|nid| load_node(conn, nid)).transpose()?;
+
+    let blocks = version_service::draft_blocks(conn, schedule_id)?;
+
+    let lookups = Lookups::load(conn)?;
+    let mut collector = Collector::default();
+    check_schedule(
+        conn,
+        &schedule,
+        &blocks,
+        from,
+        to,
+        node.as_ref(),
+        &lookups,
+        &mut collector,
+    )?;
+
+    Ok(collector.into_report(from, to, node_id))
+}
+
+/// Check the published version of every active schedule assigned to a node, plus dead air
+/// in its effective schedule.
+pub fn validate_node(
+    conn: &mut DbConnection,
+    node_id: i32,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<ValidationReport> {
+    let node = load_node(conn, node_id)?;
+    let tz = schedule_service::node_timezone(conn, node_id)?;
+    let assigned: Vec<Schedule> = channel_service::lineup(conn, Target::Node(node_id))?
+        .into_iter()
+        .map(|a| a.schedule)
+        .filter(|s| s.is_active)
+        .collect();
+
+    let lookups = Lookups::load(conn)?;
+    let mut collector = Collector::default();
+    for schedule in &assigned {
+        let blocks = version_service::published_blocks(conn, schedule)?;
+        check_schedule(
+            conn,
+            schedule,
+            &blocks,
+            from,
+            to,
+            Some(&node),
+            &lookups,
+            &mut collector,
+        )?;
+    }
+
+    // Gaps the node's fallback fills aren't silent on air
+    let fallback = fallback_service::node_policy(conn, node_id)?;
+    let (severity, note) = if fallback.is_silence() {
+        (Severity::Error, "")
+    } else {
+        (Severity::Info, " (covered by the fallback policy)")
+    };
+    for date in dates(from, to) {
+        for (start_at, secs) in dead_air(conn, &tz, &assigned, date)? {
+            let message = format!(
+                "Nothing scheduled for {} from {}{}",
+                format_secs(secs),
+                start_at.with_timezone(&tz).format("%Y-%m-%d %H:%M:%S"),
+                note
+            );
+            collector.issues.push(ValidationIssue {
+                start_at: Some(start_at),
+                duration_secs: Some(secs),
+                ..ValidationIssue::new(IssueKind::DeadAir, severity, date, message)
+            });
+        }
+    }
+
+    Ok(collector.into_report(from, to, Some(node_id)))
+}
+
+fn load_node(conn: &mut DbConnection, node_id: i32) -> Result<Node> {
+    use crate::schema::nodes::dsl::*;
+    Ok(nodes
+        .filter(id.eq(node_id))
+        .select(Node::as_select())
+        .first(conn)?)
+}
+
+/// Resolve an optional `[from, to]` query range, defaulting to today (UTC).
+pub fn resolve_range(
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> Result<(NaiveDate, NaiveDate)> {
+    let from = from.unwrap_or_else(|| Utc::now().date_naive());
+    let to = to.unwrap_or(from);
+    if to < from {
+        return Err(anyhow!("Range ends before it starts"));
+    }
+    if (to - from).num_days() >= MAX_RANGE_DAYS {
+        return Err(anyhow!("Range is longer than {} days", MAX_RANGE_DAYS));
+    }
+    Ok((from, to))
+}
+
+fn dates(from: NaiveDate, to: NaiveDate) -> impl Iterator<Item = NaiveDate> {
+    from.iter_days().take_while(move |d| *d <= to)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn check_schedule(
+    conn: &mut DbConnection,
+    schedule: &Schedule,
+    schedule_blocks: &[ScheduleBlock],
+    from: NaiveDate,
+    to: NaiveDate,
+    node: Option<&Node>,
+    lookups: &Lookups,
+    collector: &mut Collector,
+) -> Result<()> {
+    let schedule_blocks =
+        schedule_service::DatedBlocks::new(&schedule.schedule_type, schedule_blocks.to_vec());
+    let mut on_date =
+        |date| schedule_service::blocks_with_exceptions(conn, schedule, &schedule_blocks, date);
+    let mut prev_blocks = on_date(from.pred_opt().unwrap_or(from))?;
+    for date in dates(from, to) {
+        let blocks = on_date(date)?;
+
+        // Wall-clock seconds from this date's midnight; yesterday's blocks may spill in
+        let spans = |offset: i64, list: &[ScheduleBlock]| -> Vec<(i32, i64, i64)> {
+            list.iter()
+                .filter_map(|b| {
+                    let start = offset + b.start_time.num_seconds_from_midnight() as i64;
+                    Some((b.id?, start, start + b.total_duration_secs()))
+                })
+                .collect()
+        };
+        let (carried, today) = (spans(-86_400, &prev_blocks), spans(0, &blocks));
+        for (a, b) in overlapping_pairs(&carried, &today) {
+            let (first, second) = (a.min(b), a.max(b));
+            let message = format!(
+                "Blocks {} and {} in '{}' overlap on {}",
+                first, second, schedule.name, date
+            );
+            collector.push(ValidationIssue {
+                schedule_id: schedule.id,
+                block_id: Some(first),
+                other_block_id: Some(second),
+                ..ValidationIssue::new(IssueKind::Overlap, Severity::Error, date, message)
+            });
+        }
+
+        for block in &blocks {
+            check_block(block, schedule, date, node, lookups, collector);
+        }
+
+        prev_blocks = blocks;
+    }
+
+    Ok(())
+}
+
+fn check_block(
+    block: &ScheduleBlock,
+    schedule: &Schedule,
+    date: NaiveDate,
+    node: Option<&Node>,
+    lookups: &Lookups,
+    collector: &mut Collector,
+) {
+    let label = format!("Block at {} in '{}'", block.start_time, schedule.name);
+    let issue = |kind, severity, content_id, message| ValidationIssue {
+        schedule_id: schedule.id,
+        block_id: block.id,
+        content_id,
+        ..ValidationIssue::new(kind, severity, date, message)
+    };
+
+    if let Some(cid) = block.content_id {
+        match lookups.content.get(&cid) {
+            None => collector.push(issue(
+                IssueKind::MissingContent,
+                Severity::Error,
+                Some(cid),
+                format!("{} references deleted content {}", label, cid),
+            )),
+            Some(item) => {
+                let content_secs = item.duration_minutes.unwrap_or(0) as i64 * 60;
+                if content_secs > block.total_duration_secs() {
+                    collector.push(issue(
+                        IssueKind::ContentTooLong,
+                        Severity::Warning,
+                        Some(cid),
+                        format!(
+                            "{}: '{}' runs {} but the block is {}",
+                            label,
+                            item.title,
+                            format_secs(content_secs),
+                            format_secs(block.total_duration_secs())
+                        ),
+                    ));
+                }
+                if let Some(reason) = node.and_then(|n| unavailable_reason(item, n)) {
+                    collector.push(issue(
+                        IssueKind::ContentUnavailable,
+                        Severity::Error,
+                        Some(cid),
+                        format!("{}: '{}' {}", label, item.title, reason),
+                    ));
+                }
+            }
+        }
+    }
+
+    if let Some(rule) = block
+        .fill_rule
+        .as_deref()
+        .and_then(|r| FillRule::parse(r).ok())
+    {
+        let playable = lookups
+            .content
+            .values()
+            .filter(|item| rule.matches(item))
+            .any(|item| node.is_none_or(|n| unavailable_reason(item, n).is_none()));
+        if !playable {
+            collector.push(issue(
+                IssueKind::DeadAir,
+                Severity::Error,
+                None,
+                format!("{}: fill rule matches no playable content", label),
+            ));
+        }
+    }
+
+    if let Some(sid) = block.script_id.filter(|s| !lookups.script_ids.contains(s)) {
+        collector.push(issue(
+            IssueKind::MissingScript,
+            Severity::Error,
+            None,
+            format!("{} references deleted script {}", label, sid),
+        ));
+    }
+
+    if let Some(did) = block.dj_id.filter(|d| !lookups.dj_ids.contains(d)) {
+        collector.push(issue(
+            IssueKind::MissingDj,
+            Severity::Error,
+            None,
+            format!("{} references deleted DJ {}", label, did),
+        ));
+    }
+}
+
+/// Why `item` can't play on `node`, if it can't.
+///
+/// `node_accessibility` is either `public` (or unset) or a comma-separated list of
+/// node names/IDs. Local files must live under one of the node's reported paths;
+/// nodes that haven't reported any paths aren't checked.
+fn unavailable_reason(item: &ContentItem, node: &Node) -> Option<String> {
+    if let Some(since) = item.missing_since {
+        return Some(format!("has been missing from the library since {}", since));
+    }
+
+    if let Some(access) = item.node_accessibility.as_deref().map(str::trim) {
+        if !access.is_empty() && !access.eq_ignore_ascii_case("public") {
+            let node_id = node.id.map(|i| i.to_string());
+            let allowed = access
+                .split(',')
+                .map(str::trim)
+                .any(|a| a == node.name || Some(a) == node_id.as_deref());
+            if !allowed {
+                return Some(format!("is restricted to nodes '{}'", access));
+            }
+        }
+    }
+
+    if item.content_type == "local_file" {
+        let paths: Vec<String> = node
+            .available_paths
+            .as_deref()
+            .and_then(|p| serde_json::from_str(p).ok())
+            .unwrap_or_default();
+        if !paths.is_empty()
+            && !paths
+                .iter()
+                .any(|p| Path::new(&item.content_path).starts_with(p))
+        {
+            return Some(format!(
+                "is at {}, outside the node's available paths",
+                item.content_path
+            ));
+        }
+    }
+
+    None
+}
+
+/// Pairs of span IDs whose `[start, end)` ranges intersect, among `spans` and between
+/// `carried` (spilling in from the day before) and `spans`, but not within `carried`.
+fn overlapping_pairs(carried: &[(i32, i64, i64)], spans: &[(i32, i64, i64)]) -> Vec<(i32, i32)> {
+    let overlap = |a: &(i32, i64, i64), b: &(i32, i64, i64)| a.0 != b.0 && a.1 < b.2 && b.1 < a.2;
+    let mut pairs = Vec::new();
+    for a in carried {
+        for b in spans {
+            if overlap(a, b) {
+                pairs.push((a.0, b.0));
+            }
+        }
+    }
+    for (i, a) in spans.iter().enumerate() {
+        for b in &spans[i + 1..] {
+            if overlap(a, b) {
+                pairs.push((a.0, b.0));
+            }
+        }
+    }
+    pairs
+}
+
+/// Gaps in `[0, day_secs)` that none of `spans` cover.
+fn uncovered(day_secs: i64, mut spans: Vec<(i64, i64)>) -> Vec<(i64, i64)> {
+    spans.sort();
+    let mut gaps = Vec::new();
+    let mut cursor = 0;
+    for (start, end) in spans {
+        if start > cursor {
+            gaps.push((cursor, start.min(day_secs)));
+        }
+        cursor = cursor.max(end);
+        if cursor >= day_secs {
+            break;
+        }
+    }
+    if cursor < day_secs {
+        gaps.push((cursor, day_secs));
+    }
+    gaps.retain(|(s, e)| s < e);
+    gaps
+}
+
+/// Stretches of the local day `date` in which no assigned schedule has a block.
+fn dead_air(
+    conn: &mut DbConnection,
+    tz: &Tz,
+    schedules: &[Schedule],
+    date: NaiveDate,
+) -> Result<Vec<(DateTime<Utc>, i64)>> {
+    let (day_start, day_end) = schedule_service::local_day_bounds(tz, date);
+    let day_secs = (day_end - day_start).num_seconds();
+
+    let mut spans = Vec::new();
+    for schedule in schedules {
+        for d in [date.pred_opt().unwrap_or(date), date] {
+            for block in schedule_service::get_blocks_for_date(conn, schedule, d)? {
+                let (start, end) = schedule_service::block_interval(tz, d, &block);
+                spans.push((
+                    (start - day_start).num_seconds(),
+                    (end - day_start).num_seconds(),
+                ));
+            }
+        }
+    }
+
+    Ok(uncovered(day_secs, spans)
+        .into_iter()
+        .map(|(s, e)| {
+            (
+                (day_start + Duration::seconds(s)).with_timezone(&Utc),
+                e - s,
+            )
+        })
+        .collect())
+}
+
+fn format_secs(secs: i64) -> String {
+    format!("{}:{:02}:{:02}", secs / 3600, secs % 3600 / 60, secs % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(available_paths: Option<&str>) -> Node {
+        let ts = NaiveDate::from_ymd_opt(2026, 1, 1)
+            .unwrap()
+            .and_time(chrono::NaiveTime::MIN);
+        Node {
+            id: Some(7),
+            name: "studio-a".to_string(),
+            secret_key: String::new(),
+            ip_address: None,
+            status: "online".to_string(),
+            last_heartbeat: None,
+            available_paths: available_paths.map(str::to_string),
+            created_at: ts,
+            updated_at: ts,
+            current_content_id: None,
+            playback_position_secs: None,
+            playback_duration_secs: None,
+            script_context: None,
+            timezone: None,
+            fallback_policy: None,
+            cache_status: None,
+            interrupt_status: None,
+            alert_areas: None,
+            channel_id: None,
+            sync_status: None,
+            clock_offset_ms: None,
+            clock_rtt_ms: None,
+            clock_warning: false,
+            library_scan: None,
+        }
+    }
+
+    fn content(path: &str, access: Option<&str>) -> ContentItem {
+        let ts = NaiveDate::from_ymd_opt(2026, 1, 1)
+            .unwrap()
+            .and_time(chrono::NaiveTime::MIN);
+        ContentItem {
+            id: Some(1),
+            title: "Item".to_string(),
+            description: None,
+            content_type: "local_file".to_string(),
+            content_path: path.to_string(),
+            adapter_id: None,
+            duration_minutes: Some(30),
+            tags: None,
+            node_accessibility: access.map(str::to_string),
+            created_at: ts,
+            updated_at: ts,
+            transformer_scripts: None,
+            is_dj_accessible: false,
+            spot_reel_id: None,
+            last_played_at: None,
+            sha256: None,
+            cue_points: None,
+            loudness_lufs: None,
+            true_peak_dbtp: None,
+            loudness_analyzed_at: None,
+            cue_out_secs: None,
+            file_size_bytes: None,
+            file_modified_at: None,
+            missing_since: None,
+        }
+    }
+
+    #[test]
+    fn test_content_availability() {
+        let n = node(Some(r#"["/media/shows"]"#));
+        assert!(unavailable_reason(&content("/media/shows/a.mp4", None), &n).is_none());
+        assert!(unavailable_reason(&content("/other/a.mp4", None), &n).is_some());
+        assert!(unavailable_reason(&content("/media/shows2/x.mp4", None), &n).is_some());
+        assert!(unavailable_reason(&content("/media/shows/a.mp4", Some("studio-a")), &n).is_none());
+        assert!(unavailable_reason(&content("/media/shows/a.mp4", Some("7, 9")), &n).is_none());
+        assert!(unavailable_reason(&content("/media/shows/a.mp4", Some("studio-b")), &n).is_some());
+
+        // Nodes that never reported paths aren't path-checked
+        assert!(
+            unavailable_reason(&content("/other/a.mp4", Some("public")), &node(None)).is_none()
+        );
+    }
+
+    #[test]
+    fn test_overlaps_and_gaps() {
+        // Block 3 spills from the previous day into block 1
+        let carried = vec![(3, -600, 300)];
+        let spans = vec![(1, 0, 1800), (2, 1800, 3600), (4, 3000, 3300)];
+        assert_eq!(overlapping_pairs(&carried, &spans), vec![(3, 1), (2, 4)]);
+
+        // Yesterday's own overlaps were reported yesterday
+        let carried = vec![(5, -3600, 600), (6, -1800, 900)];
+        assert_eq!(overlapping_pairs(&carried, &[(1, 1200, 1800)]), vec![]);
+
+        let gaps = uncovered(86_400, vec![(3600, 7200), (-600, 300), (7000, 9000)]);
+        assert_eq!(gaps, vec![(300, 3600), (9000, 86_400)]);
+        assert_eq!(uncovered(100, vec![(0, 200)]), vec![]);
+    }
+}
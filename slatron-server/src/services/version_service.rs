@@ -0,0 +1,292 @@
+//! Draft and published revisions of schedules.
+//!
+//! `schedule_blocks` holds the editable draft. Publishing snapshots the draft into an
+//! immutable `schedule_versions` row and points the schedule at it; everything that airs
+//! reads the published snapshot, so unfinished edits never reach nodes.
+
+use crate::db::DbConnection;
+use crate::models::{NewScheduleVersion, Schedule, ScheduleBlock, ScheduleVersion};
+use anyhow::Result;
+use diesel::prelude::*;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// The draft blocks of a schedule, in creation order.
+pub fn draft_blocks(conn: &mut DbConnection, schedule_id: i32) -> Result<Vec<ScheduleBlock>> {
+    use crate::schema::schedule_blocks::dsl;
+
+    Ok(dsl::schedule_blocks
+        .filter(dsl::schedule_id.eq(schedule_id))
+        .order(dsl::id.asc())
+        .select(ScheduleBlock::as_select())
+        .load(conn)?)
+}
+
+/// The blocks nodes air for a schedule; empty until it is first published.
+pub fn published_blocks(
+    conn: &mut DbConnection,
+    schedule: &Schedule,
+) -> Result<Vec<ScheduleBlock>> {
+    let Some(version_id) = schedule.published_version_id else {
+        return Ok(Vec::new());
+    };
+
+    use crate::schema::schedule_versions::dsl::*;
+    let published: ScheduleVersion = schedule_versions
+        .filter(id.eq(version_id))
+        .select(ScheduleVersion::as_select())
+        .first(conn)?;
+    version_blocks(&published)
+}
+
+pub fn version_blocks(version: &ScheduleVersion) -> Result<Vec<ScheduleBlock>> {
+    Ok(serde_json::from_str(&version.blocks)?)
+}
+
+/// Versions of a schedule, newest first.
+pub fn list_versions(conn: &mut DbConnection, sched_id: i32) -> Result<Vec<ScheduleVersion>> {
+    use crate::schema::schedule_versions::dsl::*;
+
+    Ok(schedule_versions
+        .filter(schedule_id.eq(sched_id))
+        .order(version.desc())
+        .select(ScheduleVersion::as_select())
+        .load(conn)?)
+}
+
+pub fn get_version(
+    conn: &mut DbConnection,
+    sched_id: i32,
+    version_number: i32,
+) -> Result<ScheduleVersion> {
+    use crate::schema::schedule_versions::dsl::*;
+
+    Ok(schedule_versions
+        .filter(schedule_id.eq(sched_id))
+        .filter(version.eq(version_number))
+        .select(ScheduleVersion::as_select())
+        .first(conn)?)
+}
+
+/// Snapshot the draft as the next version and make it the one nodes air.
+pub fn publish(
+    conn: &mut DbConnection,
+    schedule_id: i32,
+    user_id: Option<i32>,
+    note: Option<String>,
+) -> Result<ScheduleVersion> {
+    conn.transaction(|conn| publish_draft(conn, schedule_id, user_id, note))
+}
+
+/// Restore a version's blocks into the draft, discarding unpublished edits, and publish
+/// them again as a new version. History is never rewritten.
+pub fn rollback(
+    conn: &mut DbConnection,
+    sched_id: i32,
+    version_number: i32,
+    user_id: Option<i32>,
+) -> Result<ScheduleVersion> {
+    conn.transaction(|conn| {
+        let target = get_version(conn, sched_id, version_number)?;
+        let blocks = version_blocks(&target)?;
+
+        {
+            use crate::schema::schedule_blocks::dsl::*;
+            diesel::delete(schedule_blocks.filter(schedule_id.eq(sched_id))).execute(conn)?;
+            // Blocks keep their ids, so fill run-downs carry on where they left off
+            diesel::insert_into(schedule_blocks)
+                .values(&blocks)
+                .execute(conn)?;
+        }
+
+        publish_draft(
+            conn,
+            sched_id,
+            user_id,
+            Some(format!("Rollback to version {}", version_number)),
+        )
+    })
+}
+
+fn publish_draft(
+    conn: &mut DbConnection,
+    sched_id: i32,
+    user_id: Option<i32>,
+    note: Option<String>,
+) -> Result<ScheduleVersion> {
+    let blocks = serde_json::to_string(&draft_blocks(conn, sched_id)?)?;
+
+    let latest: Option<i32> = {
+        use crate::schema::schedule_versions::dsl::*;
+        schedule_versions
+            .filter(schedule_id.eq(sched_id))
+            .select(diesel::dsl::max(version))
+            .first(conn)?
+    };
+
+    let published = diesel::insert_into(crate::schema::schedule_versions::table)
+        .values(&NewScheduleVersion {
+            schedule_id: sched_id,
+            version: latest.unwrap_or(0) + 1,
+            blocks,
+            note,
+            published_by: user_id,
+        })
+        .returning(ScheduleVersion::as_select())
+        .get_result(conn)?;
+
+    {
+        use crate::schema::schedules::dsl::*;
+        diesel::update(schedules.filter(id.eq(sched_id)))
+            .set((
+                published_version_id.eq(published.id),
+                updated_at.eq(chrono::Utc::now().naive_utc()),
+            ))
+            .execute(conn)?;
+    }
+
+    Ok(published)
+}
+
+#[derive(Debug, Serialize)]
+pub struct BlockChange {
+    pub before: ScheduleBlock,
+    pub after: ScheduleBlock,
+    /// Names of the fields that differ
+    pub fields: Vec<&'static str>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct VersionDiff {
+    pub added: Vec<ScheduleBlock>,
+    pub removed: Vec<ScheduleBlock>,
+    pub changed: Vec<BlockChange>,
+}
+
+impl VersionDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Compare two block sets by block id. Timestamps are ignored.
+pub fn diff_blocks(from: &[ScheduleBlock], to: &[ScheduleBlock]) -> VersionDiff {
+    let before: HashMap<Option<i32>, &ScheduleBlock> = from.iter().map(|b| (b.id, b)).collect();
+    let after: HashMap<Option<i32>, &ScheduleBlock> = to.iter().map(|b| (b.id, b)).collect();
+
+    let mut diff = VersionDiff::default();
+    for block in to {
+        match before.get(&block.id) {
+            None => diff.added.push(block.clone()),
+            Some(old) => {
+                let fields = changed_fields(old, block);
+                if !fields.is_empty() {
+                    diff.changed.push(BlockChange {
+                        before: (*old).clone(),
+                        after: block.clone(),
+                        fields,
+                    });
+                }
+            }
+        }
+    }
+    diff.removed = from
+        .iter()
+        .filter(|b| !after.contains_key(&b.id))
+        .cloned()
+        .collect();
+
+    diff
+}
+
+fn changed_fields(a: &ScheduleBlock, b: &ScheduleBlock) -> Vec<&'static str> {
+    let checks = [
+        ("content_id", a.content_id != b.content_id),
+        ("day_of_week", a.day_of_week != b.day_of_week),
+        ("specific_date", a.specific_date != b.specific_date),
+        ("start_time", a.start_time != b.start_time),
+        (
+            "duration",
+            a.total_duration_secs() != b.total_duration_secs(),
+        ),
+        ("script_id", a.script_id != b.script_id),
+        ("dj_id", a.dj_id != b.dj_id),
+        ("rrule", a.rrule != b.rrule),
+        ("fill_rule", a.fill_rule != b.fill_rule),
+        ("break_policy", a.break_policy != b.break_policy),
+        ("transition", a.transition != b.transition),
+    ];
+
+    checks
+        .into_iter()
+        .filter(|(_, changed)| *changed)
+        .map(|(name, _)| name)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveDate, NaiveTime};
+
+    fn block(id: i32, hour: u32, content_id: Option<i32>) -> ScheduleBlock {
+        let ts = NaiveDate::from_ymd_opt(2026, 1, 1)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+        ScheduleBlock {
+            id: Some(id),
+            schedule_id: 1,
+            content_id,
+            day_of_week: Some(0),
+            specific_date: None,
+            start_time: NaiveTime::from_hms_opt(hour, 0, 0).unwrap(),
+            duration_minutes: 30,
+            script_id: None,
+            created_at: ts,
+            updated_at: ts,
+            dj_id: None,
+            rrule: None,
+            duration_secs: None,
+            fill_rule: None,
+            break_policy: None,
+            transition: None,
+        }
+    }
+
+    #[test]
+    fn test_diff_blocks() {
+        let published = vec![block(1, 18, Some(5)), block(2, 19, Some(6))];
+
+        let mut moved = block(1, 20, Some(5));
+        moved.updated_at += chrono::Duration::hours(1);
+        // Same airing, only the legacy minutes field was replaced by seconds
+        let mut same = block(2, 19, Some(6));
+        same.duration_secs = Some(1800);
+        let draft = vec![moved, same, block(3, 21, None)];
+
+        let diff = diff_blocks(&published, &draft);
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(diff.added[0].id, Some(3));
+        assert!(diff.removed.is_empty());
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].fields, vec!["start_time"]);
+
+        let diff = diff_blocks(&draft, &published);
+        assert_eq!(diff.removed.len(), 1);
+        assert!(!diff.is_empty());
+        assert!(diff_blocks(&published, &published).is_empty());
+    }
+
+    #[test]
+    fn test_snapshot_from_migration_parses() {
+        // Shape written by the SQL that seeds version 1 for existing schedules
+        let json = r#"[{"id":1,"schedule_id":1,"content_id":null,"day_of_week":2,"specific_date":null,"start_time":"18:00:00","duration_minutes":30,"script_id":null,"created_at":"2026-10-17T00:01:34","updated_at":"2026-10-17T00:01:34","dj_id":null,"rrule":null,"duration_secs":null,"fill_rule":null}]"#;
+        let blocks: Vec<ScheduleBlock> = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            blocks[0].start_time,
+            NaiveTime::from_hms_opt(18, 0, 0).unwrap()
+        );
+        assert_eq!(blocks[0].total_duration_secs(), 1800);
+    }
+}
@@ -0,0 +1,223 @@
+//! XMLTV programme guide built from nodes' collapsed schedules.
+
+use crate::db::DbConnection;
+use crate::models::{ContentItem, Node};
+use crate::services::channel_service::Target;
+use crate::services::schedule_service;
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use diesel::prelude::*;
+use quick_xml::events::{BytesDecl, BytesText, Event};
+use quick_xml::Writer;
+use std::collections::HashMap;
+use std::io::Cursor;
+
+/// Longest guide that can be requested, in days
+pub const MAX_GUIDE_DAYS: i64 = 14;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Programme {
+    pub start: DateTime<Utc>,
+    pub stop: DateTime<Utc>,
+    pub title: String,
+    pub description: Option<String>,
+    pub categories: Vec<String>,
+    pub presenter: Option<String>,
+}
+
+pub struct ChannelGuide {
+    pub node_id: i32,
+    pub name: String,
+    pub programmes: Vec<Programme>,
+}
+
+/// Guide for a node covering `days` local days, starting today in the node's timezone.
+/// Served without authentication, so fill run-downs not generated yet are previewed
+/// rather than stored.
+pub fn node_guide(conn: &mut DbConnection, node_id: i32, days: i64) -> Result<ChannelGuide> {
+    let node: Node = {
+        use crate::schema::nodes::dsl::*;
+        nodes
+            .filter(id.eq(node_id))
+            .select(Node::as_select())
+            .first(conn)?
+    };
+    let tz = schedule_service::node_timezone(conn, node_id)?;
+    let today = Utc::now().with_timezone(&tz).date_naive();
+
+    let mut blocks = Vec::new();
+    for offset in 0..days {
+        let date = today + Duration::days(offset);
+        blocks.extend(schedule_service::preview_collapsed_schedule(
+            conn,
+            Target::Node(node_id),
+            date,
+            Some(tz.name().to_string()),
+        )?);
+    }
+
+    let mut content_ids: Vec<i32> = blocks
+        .iter()
+        .flat_map(|b| {
+            b.content_id
+                .into_iter()
+                .chain(b.items.iter().map(|i| i.content_id))
+        })
+        .collect();
+    content_ids.sort_unstable();
+    content_ids.dedup();
+    let content: HashMap<i32, ContentItem> = {
+        use crate::schema::content_items::dsl::*;
+        content_items
+            .filter(id.eq_any(content_ids))
+            .select(ContentItem::as_select())
+            .load(conn)?
+            .into_iter()
+            .filter_map(|c| c.id.map(|cid| (cid, c)))
+            .collect()
+    };
+
+    let mut programmes = Vec::new();
+    for block in &blocks {
+        let presenter = block.dj_name.clone();
+        let mut entries: Vec<(DateTime<Utc>, i32, Option<i32>)> = block
+            .items
+            .iter()
+            .map(|i| (i.start_at, i.duration_secs, Some(i.content_id)))
+            .collect();
+        if entries.is_empty() {
+            entries.push((block.start_at, block.duration_secs, block.content_id));
+        }
+
+        for (start, secs, content_id) in entries {
+            let item = content_id.and_then(|cid| content.get(&cid));
+            // Blocks without content (e.g. live DJ shows) are listed under the DJ or schedule
+            let title = match (item, &presenter) {
+                (Some(c), _) => c.title.clone(),
+                (None, Some(dj)) => dj.clone(),
+                (None, None) => block.schedule_name.clone(),
+            };
+
+            programmes.push(Programme {
+                start,
+                stop: start + Duration::seconds(secs as i64),
+                title,
+                description: item.and_then(|c| c.description.clone()),
+                categories: item
+                    .and_then(|c| c.tags.as_deref())
+                    .map(|t| {
+                        t.split(',')
+                            .map(|s| s.trim().to_string())
+                            .filter(|s| !s.is_empty())
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+                presenter: presenter.clone(),
+            });
+        }
+    }
+
+    Ok(ChannelGuide {
+        node_id,
+        name: node.name,
+        programmes,
+    })
+}
+
+pub fn channel_id(node_id: i32) -> String {
+    format!("node-{}.slatron", node_id)
+}
+
+fn xmltv_time(t: DateTime<Utc>) -> String {
+    t.format("%Y%m%d%H%M%S +0000").to_string()
+}
+
+/// Render guides as an XMLTV document, one channel per node.
+pub fn render(guides: &[ChannelGuide]) -> Result<String> {
+    let mut writer = Writer::new_with_indent(Cursor::new(Vec::new()), b' ', 2);
+    writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+
+    writer
+        .create_element("tv")
+        .with_attribute(("generator-info-name", "Slatron"))
+        .write_inner_content::<_, anyhow::Error>(|w| {
+            for guide in guides {
+                w.create_element("channel")
+                    .with_attribute(("id", channel_id(guide.node_id).as_str()))
+                    .write_inner_content::<_, anyhow::Error>(|w| {
+                        w.create_element("display-name")
+                            .write_text_content(BytesText::new(&guide.name))?;
+                        Ok(())
+                    })?;
+            }
+
+            for guide in guides {
+                let channel = channel_id(guide.node_id);
+                for p in &guide.programmes {
+                    let start = xmltv_time(p.start);
+                    let stop = xmltv_time(p.stop);
+                    w.create_element("programme")
+                        .with_attribute(("start", start.as_str()))
+                        .with_attribute(("stop", stop.as_str()))
+                        .with_attribute(("channel", channel.as_str()))
+                        .write_inner_content::<_, anyhow::Error>(|w| {
+                            w.create_element("title")
+                                .write_text_content(BytesText::new(&p.title))?;
+                            if let Some(desc) = &p.description {
+                                w.create_element("desc")
+                                    .write_text_content(BytesText::new(desc))?;
+                            }
+                            if let Some(dj) = &p.presenter {
+                                w.create_element("credits")
+                                    .write_inner_content::<_, anyhow::Error>(|w| {
+                                        w.create_element("presenter")
+                                            .write_text_content(BytesText::new(dj))?;
+                                        Ok(())
+                                    })?;
+                            }
+                            for category in &p.categories {
+                                w.create_element("category")
+                                    .write_text_content(BytesText::new(category))?;
+                            }
+                            Ok(())
+                        })?;
+                }
+            }
+            Ok(())
+        })?;
+
+    Ok(String::from_utf8(writer.into_inner().into_inner())?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_render_xmltv() {
+        let start = Utc.with_ymd_and_hms(2026, 3, 1, 18, 0, 0).unwrap();
+        let guide = ChannelGuide {
+            node_id: 3,
+            name: "Lobby & Bar".to_string(),
+            programmes: vec![Programme {
+                start,
+                stop: start + Duration::minutes(30),
+                title: "Evening <News>".to_string(),
+                description: Some("Headlines".to_string()),
+                categories: vec!["news".to_string()],
+                presenter: Some("DJ Nova".to_string()),
+            }],
+        };
+
+        let xml = render(&[guide]).unwrap();
+        assert!(xml.contains(r#"<channel id="node-3.slatron">"#));
+        assert!(xml.contains("<display-name>Lobby &amp; Bar</display-name>"));
+        assert!(xml.contains(
+            r#"<programme start="20260301180000 +0000" stop="20260301183000 +0000" channel="node-3.slatron">"#
+        ));
+        assert!(xml.contains("<title>Evening &lt;News&gt;</title>"));
+        assert!(xml.contains("<presenter>DJ Nova</presenter>"));
+        assert!(xml.contains("<category>news</category>"));
+    }
+}
@@ -1,3 +1,8 @@
+use crate::services::alert_service;
+use crate::services::as_run_service;
+use crate::services::clock_service::{self, ClockSettings, Measurement};
+use crate::services::interrupt_service::InterruptStatus;
+use crate::services::library_service::{self, ScannedFile};
 use crate::AppState;
 use axum::{
     extract::{
@@ -31,11 +36,18 @@ pub enum ServerMessage {
     Command { command: NodeCommand },
     #[serde(rename = "heartbeat_ack")]
     HeartbeatAck,
+    /// Reply to a node's `time_sync`, for it to work out its clock offset and round trip
+    #[serde(rename = "time_sync")]
+    TimeSync {
+        client_sent: chrono::DateTime<Utc>,
+        server_received: chrono::DateTime<Utc>,
+        server_sent: chrono::DateTime<Utc>,
+    },
 }
 
 // ...
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "action")]
 pub enum NodeCommand {
     #[serde(rename = "play")]
@@ -60,6 +72,31 @@ pub enum NodeCommand {
     Shutdown,
     #[serde(rename = "inject_audio")]
     InjectAudio { url: String, mix: bool },
+    /// Preempt all playback with a slate and/or message (see `interrupt_service`)
+    #[serde(rename = "interrupt")]
+    Interrupt {
+        message: Option<String>,
+        /// Rendered bumper to loop, relative to the server URL
+        slate_path: Option<String>,
+        slate_gain_db: Option<f64>,
+        timeout_secs: Option<u32>,
+    },
+    /// End an interrupt and resume the schedule
+    #[serde(rename = "clear_interrupt")]
+    ClearInterrupt,
+    /// Crawl a CAP alert along the bottom of the screen (see `alert_service`)
+    #[serde(rename = "show_alert")]
+    ShowAlert {
+        alert_id: i32,
+        text: String,
+        severity: String,
+        expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    },
+    #[serde(rename = "clear_alert")]
+    ClearAlert { alert_id: i32 },
+    /// Walk the media roots and report the files found (see `library_service`)
+    #[serde(rename = "scan_library")]
+    ScanLibrary,
 }
 
 // Node → Server messages
@@ -80,11 +117,29 @@ pub enum NodeMessage {
         cpu_usage_percent: f64,
         memory_usage_mb: f64,
         errors: Vec<String>,
+        /// Absent on nodes without an asset cache
+        #[serde(default)]
+        cache: Option<CacheStatus>,
+        /// Present while the node is interrupted
+        #[serde(default)]
+        interrupt: Option<InterruptStatus>,
+        /// Absent on nodes that don't sync their clock
+        #[serde(default)]
+        sync: Option<SyncStatus>,
+        /// The node's clock when it sent the heartbeat
+        #[serde(default)]
+        sent_at: Option<chrono::DateTime<Utc>>,
     },
     #[serde(rename = "request_schedule")]
     RequestSchedule,
     #[serde(rename = "report_paths")]
     ReportPaths { available_paths: Vec<String> },
+    /// Every media file under the node's media roots
+    #[serde(rename = "library_scan")]
+    LibraryScan {
+        roots: Vec<String>,
+        files: Vec<ScannedFile>,
+    },
     #[serde(rename = "content_error")]
     ContentError { content_id: i32, error: String },
     #[serde(rename = "log")]
@@ -96,6 +151,50 @@ pub enum NodeMessage {
     },
     #[serde(rename = "screenshot")]
     Screenshot { image_base64: String },
+    /// Clock reference request, answered straight away with the server's time
+    #[serde(rename = "time_sync")]
+    TimeSync { client_sent: chrono::DateTime<Utc> },
+    #[serde(rename = "playback_event")]
+    PlaybackEvent {
+        /// One of `as_run_service::KINDS`
+        kind: String,
+        phase: PlaybackPhase,
+        content_id: Option<i32>,
+        title: Option<String>,
+        path: Option<String>,
+        at: chrono::DateTime<Utc>,
+    },
+}
+
+/// Summary of a node's prefetched asset cache.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheStatus {
+    pub items: usize,
+    pub used_bytes: u64,
+    pub budget_bytes: u64,
+    /// Upcoming items not downloaded yet
+    pub pending: usize,
+    /// Upcoming items whose download or checksum verification failed
+    pub failed: usize,
+}
+
+/// A node's clock and playback sync as measured against the server.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncStatus {
+    /// Whether the node is in a sync group and plays against the server's clock
+    pub enabled: bool,
+    /// Server clock minus the node's, from the best recent exchange
+    pub clock_offset_ms: Option<i64>,
+    pub rtt_ms: Option<i64>,
+    /// Content position minus where the schedule has it, while a sync group airs content
+    pub drift_ms: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlaybackPhase {
+    Start,
+    End,
 }
 
 pub async fn ws_handler(State(state): State<AppState>, ws: WebSocketUpgrade) -> Response {
@@ -155,6 +254,26 @@ async fn handle_socket(socket: WebSocket, state: AppState) {
                                         let mut nodes = state_clone.connected_nodes.write().await;
                                         nodes.insert(id, tx.clone());
                                     }
+
+                                    // Put back the alerts it should be showing
+                                    let alerts = match state_clone.db.get() {
+                                        Ok(mut conn) => {
+                                            alert_service::active_commands(&mut conn, id)
+                                        }
+                                        Err(e) => Err(e.into()),
+                                    };
+                                    match alerts {
+                                        Ok(commands) => {
+                                            for command in commands {
+                                                let _ = tx.send(ServerMessage::Command { command });
+                                            }
+                                        }
+                                        Err(e) => tracing::error!(
+                                            "Failed to load alerts for node {}: {}",
+                                            id,
+                                            e
+                                        ),
+                                    }
                                 }
                                 Err(e) => {
                                     let _ = tx.send(ServerMessage::AuthResponse {
@@ -173,7 +292,12 @@ async fn handle_socket(socket: WebSocket, state: AppState) {
                             cpu_usage_percent,
                             memory_usage_mb,
                             errors,
+                            cache,
+                            interrupt,
+                            sync,
+                            sent_at,
                         } => {
+                            let received_at = Utc::now();
                             if authenticated {
                                 if let Some(id) = node_id {
                                     if let Err(e) = update_node_status(
@@ -196,6 +320,51 @@ async fn handle_socket(socket: WebSocket, state: AppState) {
                                         tracing::error!("Failed to update node status: {}", e);
                                     }
 
+                                    if let Some(cache) = &cache {
+                                        if let Err(e) =
+                                            update_node_cache_status(&state_clone, id, cache)
+                                        {
+                                            tracing::error!(
+                                                "Failed to update node cache status: {}",
+                                                e
+                                            );
+                                        }
+                                    }
+
+                                    if let Err(e) = update_node_interrupt_status(
+                                        &state_clone,
+                                        id,
+                                        interrupt.as_ref(),
+                                    ) {
+                                        tracing::error!(
+                                            "Failed to update node interrupt status: {}",
+                                            e
+                                        );
+                                    }
+
+                                    if let Some(sync) = &sync {
+                                        if let Err(e) =
+                                            update_node_sync_status(&state_clone, id, sync)
+                                        {
+                                            tracing::error!(
+                                                "Failed to update node sync status: {}",
+                                                e
+                                            );
+                                        }
+                                    }
+
+                                    if let Some(measurement) = Measurement::from_heartbeat(
+                                        sync.as_ref(),
+                                        sent_at,
+                                        received_at,
+                                    ) {
+                                        if let Err(e) =
+                                            update_node_clock(&state_clone, id, &measurement)
+                                        {
+                                            tracing::error!("Failed to update node clock: {}", e);
+                                        }
+                                    }
+
                                     let _ = tx.send(ServerMessage::HeartbeatAck);
 
                                     tracing::debug!(
@@ -211,6 +380,16 @@ async fn handle_socket(socket: WebSocket, state: AppState) {
                                 }
                             }
                         }
+                        NodeMessage::TimeSync { client_sent } => {
+                            if authenticated {
+                                let server_received = Utc::now();
+                                let _ = tx.send(ServerMessage::TimeSync {
+                                    client_sent,
+                                    server_received,
+                                    server_sent: Utc::now(),
+                                });
+                            }
+                        }
                         NodeMessage::RequestSchedule => {
                             if authenticated && node_id.is_some() {
                                 // Send schedule update notification
@@ -227,6 +406,19 @@ async fn handle_socket(socket: WebSocket, state: AppState) {
                                 }
                             }
                         }
+                        NodeMessage::LibraryScan { roots, files } => {
+                            if authenticated {
+                                if let Some(id) = node_id {
+                                    // The roots are where the node's local files live
+                                    let _ = update_node_paths(&state_clone, id, &roots).await;
+                                    if let Err(e) =
+                                        record_library_scan(&state_clone, id, &roots, &files)
+                                    {
+                                        tracing::error!("Failed to ingest library scan: {}", e);
+                                    }
+                                }
+                            }
+                        }
                         NodeMessage::ContentError { content_id, error } => {
                             if authenticated {
                                 tracing::error!(
@@ -300,6 +492,31 @@ async fn handle_socket(socket: WebSocket, state: AppState) {
                                 }
                             }
                         }
+                        NodeMessage::PlaybackEvent {
+                            kind,
+                            phase,
+                            content_id,
+                            title,
+                            path,
+                            at,
+                        } => {
+                            if authenticated {
+                                if let Some(id) = node_id {
+                                    if let Err(e) = record_playback_event(
+                                        &state_clone,
+                                        id,
+                                        &kind,
+                                        phase,
+                                        content_id,
+                                        title,
+                                        path,
+                                        at,
+                                    ) {
+                                        tracing::error!("Failed to record as-run event: {}", e);
+                                    }
+                                }
+                            }
+                        }
                     }
                 }
             }
@@ -377,6 +594,12 @@ async fn update_node_status(
         .get()
         .map_err(|_| "Database connection error".to_string())?;
 
+    let previous_content_id: Option<i32> = n_dsl::nodes
+        .filter(n_dsl::id.eq(node_id))
+        .select(n_dsl::current_content_id)
+        .first(&mut conn)
+        .map_err(|e| format!("Failed to load node: {}", e))?;
+
     diesel::update(n_dsl::nodes.filter(n_dsl::id.eq(node_id)))
         .set((
             n_dsl::status.eq(status),
@@ -388,6 +611,15 @@ async fn update_node_status(
         .execute(&mut conn)
         .map_err(|e| format!("Failed to update node status: {}", e))?;
 
+    // Track plays for least-recently-played fill rotation
+    if let Some(cid) = current_content_id.filter(|cid| previous_content_id != Some(*cid)) {
+        use crate::schema::content_items::dsl as c_dsl;
+        diesel::update(c_dsl::content_items.filter(c_dsl::id.eq(cid)))
+            .set(c_dsl::last_played_at.eq(Utc::now().naive_utc()))
+            .execute(&mut conn)
+            .map_err(|e| format!("Failed to update last played time: {}", e))?;
+    }
+
     Ok(())
 }
 
@@ -410,6 +642,158 @@ async fn update_node_paths(state: &AppState, node_id: i32, paths: &[String]) ->
     Ok(())
 }
 
+fn update_node_cache_status(
+    state: &AppState,
+    node_id: i32,
+    cache: &CacheStatus,
+) -> Result<(), String> {
+    use crate::schema::nodes::dsl;
+
+    let mut conn = state
+        .db
+        .get()
+        .map_err(|_| "Database connection error".to_string())?;
+
+    let cache_json =
+        serde_json::to_string(cache).map_err(|_| "Failed to serialize cache status".to_string())?;
+
+    diesel::update(dsl::nodes.filter(dsl::id.eq(node_id)))
+        .set(dsl::cache_status.eq(cache_json))
+        .execute(&mut conn)
+        .map_err(|_| "Failed to update node cache status".to_string())?;
+
+    Ok(())
+}
+
+fn update_node_interrupt_status(
+    state: &AppState,
+    node_id: i32,
+    interrupt: Option<&InterruptStatus>,
+) -> Result<(), String> {
+    use crate::schema::nodes::dsl;
+
+    let mut conn = state
+        .db
+        .get()
+        .map_err(|_| "Database connection error".to_string())?;
+
+    let interrupt_json = interrupt
+        .map(serde_json::to_string)
+        .transpose()
+        .map_err(|_| "Failed to serialize interrupt status".to_string())?;
+
+    diesel::update(dsl::nodes.filter(dsl::id.eq(node_id)))
+        .set(dsl::interrupt_status.eq(interrupt_json))
+        .execute(&mut conn)
+        .map_err(|_| "Failed to update node interrupt status".to_string())?;
+
+    Ok(())
+}
+
+fn update_node_sync_status(
+    state: &AppState,
+    node_id: i32,
+    sync: &SyncStatus,
+) -> Result<(), String> {
+    use crate::schema::nodes::dsl;
+
+    let mut conn = state
+        .db
+        .get()
+        .map_err(|_| "Database connection error".to_string())?;
+
+    let sync_json =
+        serde_json::to_string(sync).map_err(|_| "Failed to serialize sync status".to_string())?;
+
+    diesel::update(dsl::nodes.filter(dsl::id.eq(node_id)))
+        .set(dsl::sync_status.eq(sync_json))
+        .execute(&mut conn)
+        .map_err(|_| "Failed to update node sync status".to_string())?;
+
+    Ok(())
+}
+
+fn record_library_scan(
+    state: &AppState,
+    node_id: i32,
+    roots: &[String],
+    files: &[ScannedFile],
+) -> Result<(), String> {
+    use crate::schema::nodes::dsl;
+
+    let mut conn = state
+        .db
+        .get()
+        .map_err(|_| "Database connection error".to_string())?;
+
+    let report = library_service::ingest(&mut conn, node_id, roots, files, Utc::now().naive_utc())
+        .map_err(|e| format!("Failed to update content items: {}", e))?;
+    tracing::info!(
+        "Node {} library scan: {} files, {} new, {} updated, {} missing, {} restored",
+        node_id,
+        report.files,
+        report.created,
+        report.updated,
+        report.missing,
+        report.restored
+    );
+
+    let report_json = serde_json::to_string(&report)
+        .map_err(|_| "Failed to serialize library scan".to_string())?;
+
+    diesel::update(dsl::nodes.filter(dsl::id.eq(node_id)))
+        .set(dsl::library_scan.eq(report_json))
+        .execute(&mut conn)
+        .map_err(|_| "Failed to update node library scan".to_string())?;
+
+    Ok(())
+}
+
+fn update_node_clock(
+    state: &AppState,
+    node_id: i32,
+    measurement: &Measurement,
+) -> Result<(), String> {
+    let mut conn = state
+        .db
+        .get()
+        .map_err(|_| "Database connection error".to_string())?;
+
+    let settings = ClockSettings::load(&mut conn);
+    clock_service::record(&mut conn, node_id, measurement, &settings)
+        .map_err(|e| format!("Failed to record node clock: {}", e))
+}
+
+#[allow(clippy::too_many_arguments)]
+fn record_playback_event(
+    state: &AppState,
+    node_id: i32,
+    kind: &str,
+    phase: PlaybackPhase,
+    content_id: Option<i32>,
+    title: Option<String>,
+    path: Option<String>,
+    at: chrono::DateTime<Utc>,
+) -> Result<(), String> {
+    if !as_run_service::KINDS.contains(&kind) {
+        return Err(format!("Unknown playback kind '{}'", kind));
+    }
+
+    let mut conn = state
+        .db
+        .get()
+        .map_err(|_| "Database connection error".to_string())?;
+
+    let at = at.naive_utc();
+    match phase {
+        PlaybackPhase::Start => {
+            as_run_service::record_start(&mut conn, node_id, kind, content_id, title, path, at)
+        }
+        PlaybackPhase::End => as_run_service::record_end(&mut conn, node_id, kind, content_id, at),
+    }
+    .map_err(|e| format!("Failed to write as-run log: {}", e))
+}
+
 async fn mark_node_offline(state: &AppState, node_id: i32) -> Result<(), String> {
     use crate::schema::nodes::dsl;
 
@@ -423,5 +807,9 @@ async fn mark_node_offline(state: &AppState, node_id: i32) -> Result<(), String>
         .execute(&mut conn)
         .map_err(|_| "Failed to mark node offline".to_string())?;
 
+    // Whatever it was airing is no longer known to be on air
+    as_run_service::close_open(&mut conn, node_id, Utc::now().naive_utc())
+        .map_err(|_| "Failed to close as-run entries".to_string())?;
+
     Ok(())
 }
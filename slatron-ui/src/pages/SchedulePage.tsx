@@ -36,7 +36,7 @@ export default function SchedulePage() {
   const isEditor = user?.role === 'admin' || user?.role === 'editor'
   const { id } = useParams()
   const navigate = useNavigate()
-  const { fetchSchedules, fetchBlocks, selectedScheduleId, createBlock, updateBlock, deleteSchedule, updateSchedule, setSelectedSchedule, schedules } = useScheduleStore()
+  const { fetchSchedules, fetchBlocks, selectedScheduleId, createBlock, updateBlock, deleteSchedule, publishSchedule, updateSchedule, setSelectedSchedule, schedules } = useScheduleStore()
   const { fetchContent } = useContentStore()
   const { fetchDjs } = useDjStore()
   const [zoomLevel, setZoomLevel] = useState(2)
@@ -134,6 +134,23 @@ export default function SchedulePage() {
     }
   }
 
+  const handlePublishSchedule = async () => {
+    if (!isEditor) return
+    if (!selectedScheduleId) return
+    const note = window.prompt('Publish these changes to all nodes? Optional note:')
+    if (note === null) return
+    try {
+      await publishSchedule(selectedScheduleId, note)
+    } catch (e: any) {
+      if (e.response?.status === 409) {
+        alert("There are no unpublished changes")
+      } else {
+        console.error("Failed to publish schedule", e)
+        alert("Failed to publish schedule")
+      }
+    }
+  }
+
   const sensors = useSensors(
     useSensor(PointerSensor, {
       activationConstraint: {
@@ -316,6 +333,20 @@ export default function SchedulePage() {
             </div>
           )}
 
+          {isEditor && (
+            <button
+              onClick={handlePublishSchedule}
+              className="px-4 py-2 bg-indigo-500/10 text-indigo-400 border border-indigo-500/20 rounded-lg hover:bg-indigo-500/20 transition-all duration-200 flex items-center gap-2"
+              disabled={!selectedScheduleId}
+              title="Edits only reach nodes once published"
+            >
+              <svg className="w-4 h-4" fill="none" viewBox="0 0 24 24" stroke="currentColor">
+                <path strokeLinecap="round" strokeLinejoin="round" strokeWidth={2} d="M4 16v1a3 3 0 003 3h10a3 3 0 003-3v-1m-4-8l-4-4m0 0L8 8m4-4v12" />
+              </svg>
+              Publish
+            </button>
+          )}
+
           {isEditor && (
             <button
               onClick={handleDeleteSchedule}
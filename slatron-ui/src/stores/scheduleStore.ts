@@ -10,6 +10,7 @@ interface Schedule {
   is_active: boolean
   created_at: string
   updated_at: string
+  published_version_id: number | null
 }
 
 interface ScheduleBlock {
@@ -35,6 +36,7 @@ interface ScheduleStore {
   createSchedule: (data: Partial<Schedule>) => Promise<void>
   updateSchedule: (id: number, data: Partial<Schedule>) => Promise<void>
   deleteSchedule: (id: number) => Promise<void>
+  publishSchedule: (id: number, note?: string) => Promise<void>
 
   createBlock: (scheduleId: number, blockData: Partial<ScheduleBlock>) => Promise<ScheduleBlock>
   updateBlock: (scheduleId: number, blockId: number, data: Partial<ScheduleBlock>) => Promise<void>
@@ -84,6 +86,11 @@ export const useScheduleStore = create<ScheduleStore>((set, get) => ({
     })
   },
 
+  publishSchedule: async (id, note) => {
+    await apiClient.post(`/api/schedules/${id}/publish`, { note: note || null })
+    await get().fetchSchedules()
+  },
+
   createBlock: async (scheduleId, data) => {
     const payload = { ...data, schedule_id: scheduleId };
     const response = await apiClient.post<ScheduleBlock>(`/api/schedules/${scheduleId}/blocks`, payload)